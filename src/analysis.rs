@@ -0,0 +1,437 @@
+use std::{
+    path::Path,
+    process::Command,
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+
+use kira::{sound::static_sound::StaticSoundData, Frame};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    beatgrid,
+    loudness::Biquad,
+    musical_key::{self, Key},
+    overview::Overview,
+    spectrogram::Spectrogram,
+};
+
+/// Length in seconds of the blocks the level is measured on
+const BLOCK_SECONDS: f64 = 0.1;
+/// Level in dBFS under which a block is silent
+const SILENCE_DB: f64 = -48.0;
+/// Length in seconds of the moving average finding the intro and outro, so
+/// that a single hit does not end an intro
+const SMOOTHING_SECONDS: f64 = 2.0;
+/// Quantile of the sounding blocks taken as the level of the body of the
+/// track
+const BODY_QUANTILE: f64 = 0.8;
+/// Part of the body level under which the start and end of a track count as
+/// a low energy intro or outro
+const LOW_ENERGY_RATIO: f64 = 0.5;
+/// Body level in dBFS scored as no loudness, 0 dBFS scoring full loudness
+const QUIET_DB: f64 = -30.0;
+/// Rises of the level per second, in body levels, scored as full flux. A
+/// four on the floor kick at 120 BPM rises about twice a second
+const FULL_FLUX: f64 = 4.0;
+/// Version of the analysis, tracks analyzed by an older one are analyzed
+/// again when loaded
+pub const ANALYSIS_VERSION: u32 = 4;
+/// Center in Hz and quality of the band pass keeping the voice range
+const VOICE_CENTER: f64 = 1_000.0;
+const VOICE_Q: f64 = 0.5;
+/// Smallest part of the power of the center of the stereo image in the
+/// voice range for a block to sound like a voice
+const VOICE_SHARE: f64 = 0.4;
+/// Smallest ratio between the power in the voice range of the center and of
+/// the sides for a block to sound like a voice, as vocals are mixed centered
+const VOICE_CENTERING: f64 = 4.0;
+/// Smallest part of the sounding blocks sounding like a voice for a track
+/// to be tagged as vocal
+const VOCAL_SCORE: f64 = 0.3;
+
+/// Regions of a track found by its analysis, in seconds, and its energy
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrackAnalysis {
+    pub version: u32,
+    /// end of the leading silence
+    pub sound_start: f64,
+    /// start of the trailing silence
+    pub sound_end: f64,
+    /// end of the low energy intro, where the body of the track starts
+    pub intro_end: f64,
+    /// start of the low energy outro, where the body of the track ends
+    pub outro_start: f64,
+    /// from 0 to 10, half from the loudness of the body of the track and
+    /// half from how often its level rises, e.g. on drum hits
+    pub energy: f64,
+    /// part of the track sounding like a voice, from 0 to 1
+    pub vocal_score: f64,
+    /// vocal-heavy rather than instrumental
+    pub vocal: bool,
+    /// detected tempo, given to the track unless its BPM was set by hand
+    pub bpm: Option<f64>,
+    /// detected first downbeat, anchoring the beat grid with `bpm`
+    pub first_beat: f64,
+    /// detected key, given to the track unless its key was set by hand
+    pub key: Option<Key>,
+}
+
+enum Request {
+    Track(String, TrackRequest),
+    Spectrogram(String),
+}
+
+/// What to compute for a track, all from a single decode
+#[derive(Debug, Default)]
+pub struct TrackRequest {
+    /// find the regions, energy, vocals, beat grid and key of the track
+    pub analysis: bool,
+    /// command detecting the vocals instead of the built-in heuristic
+    pub vocal_detector: Option<String>,
+    /// levels drawn under the deck
+    pub overview: bool,
+    /// the track is longer than the streaming threshold, so it is not
+    /// decoded into memory: it is not analyzed and its overview is read
+    /// packet by packet
+    pub streamed: bool,
+}
+
+enum Done {
+    Analysis(String, TrackAnalysis),
+    Spectrogram(String, Spectrogram),
+    Overview(String, Overview),
+}
+
+/// Analyzes the loaded tracks on a background thread, as decoding a whole
+/// track takes too long for a frame
+pub struct Analyzer {
+    requests: Sender<Request>,
+    done: Receiver<Done>,
+    /// finished while polling the other kind of results
+    analyzed: Vec<(String, TrackAnalysis)>,
+    spectrograms: Vec<(String, Spectrogram)>,
+    overviews: Vec<(String, Overview)>,
+}
+
+impl Analyzer {
+    pub fn new() -> Self {
+        let (requests, pending) = mpsc::channel::<Request>();
+        let (sender, done) = mpsc::channel();
+
+        thread::spawn(move || {
+            for request in pending {
+                let done = match request {
+                    Request::Track(path, request) => track(path, request),
+                    Request::Spectrogram(path) => decode(&path)
+                        .map(|sound_data| {
+                            let spectrogram =
+                                Spectrogram::compute(&sound_data.frames, sound_data.sample_rate);
+                            vec![Done::Spectrogram(path, spectrogram)]
+                        })
+                        .unwrap_or_default(),
+                };
+                if done.into_iter().any(|done| sender.send(done).is_err()) {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            requests,
+            done,
+            analyzed: Vec::new(),
+            spectrograms: Vec::new(),
+            overviews: Vec::new(),
+        }
+    }
+
+    /// Queue the track at `path` for analysis, its vocals being detected by
+    /// `vocal_detector` if set instead of the built-in heuristic
+    pub fn request(&self, path: &str, vocal_detector: Option<&str>) {
+        self.request_track(
+            path,
+            TrackRequest {
+                analysis: true,
+                vocal_detector: vocal_detector.map(str::to_string),
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Queue the analysis and the overview of the track at `path`, as
+    /// requested
+    pub fn request_track(&self, path: &str, request: TrackRequest) {
+        if request.analysis || request.overview {
+            let _ = self
+                .requests
+                .send(Request::Track(path.to_string(), request));
+        }
+    }
+
+    /// Queue the spectrogram of the track at `path`, which is not kept in
+    /// the library
+    pub fn request_spectrogram(&self, path: &str) {
+        let _ = self.requests.send(Request::Spectrogram(path.to_string()));
+    }
+
+    fn receive(&mut self) {
+        for done in self.done.try_iter() {
+            match done {
+                Done::Analysis(path, analysis) => self.analyzed.push((path, analysis)),
+                Done::Spectrogram(path, spectrogram) => self.spectrograms.push((path, spectrogram)),
+                Done::Overview(path, overview) => self.overviews.push((path, overview)),
+            }
+        }
+    }
+
+    /// Analyses finished since the last call
+    pub fn poll(&mut self) -> Vec<(String, TrackAnalysis)> {
+        self.receive();
+        std::mem::take(&mut self.analyzed)
+    }
+
+    /// Spectrograms finished since the last call
+    pub fn poll_spectrograms(&mut self) -> Vec<(String, Spectrogram)> {
+        self.receive();
+        std::mem::take(&mut self.spectrograms)
+    }
+
+    /// Overviews finished since the last call
+    pub fn poll_overviews(&mut self) -> Vec<(String, Overview)> {
+        self.receive();
+        std::mem::take(&mut self.overviews)
+    }
+}
+
+/// Analysis and overview of a track, as requested
+fn track(path: String, request: TrackRequest) -> Vec<Done> {
+    if request.streamed {
+        return match request.overview.then(|| Overview::read(Path::new(&path))) {
+            Some(Ok(overview)) => vec![Done::Overview(path, overview)],
+            Some(Err(e)) => {
+                log::error!("Cannot read the overview of '{}': {}", path, e);
+                Vec::new()
+            }
+            None => Vec::new(),
+        };
+    }
+    let Some(sound_data) = decode(&path) else {
+        return Vec::new();
+    };
+
+    let mut done = Vec::new();
+    if request.analysis {
+        let mut analysis = analyze(&sound_data.frames, sound_data.sample_rate);
+        if let Some(vocal) = request
+            .vocal_detector
+            .and_then(|command| detect_vocals(&command, &path))
+        {
+            analysis.vocal = vocal;
+            analysis.vocal_score = if vocal { 1.0 } else { 0.0 };
+        }
+        done.push(Done::Analysis(path.clone(), analysis));
+    }
+    if request.overview {
+        let overview = Overview::compute(&sound_data.frames, sound_data.sample_rate);
+        done.push(Done::Overview(path, overview));
+    }
+    done
+}
+
+fn decode(path: &str) -> Option<StaticSoundData> {
+    StaticSoundData::from_file(Path::new(path))
+        .inspect_err(|e| log::error!("Cannot analyze '{}': {}", path, e))
+        .ok()
+}
+
+/// Tag a track with an external program, given its path and printing
+/// `vocal` or `instrumental`, e.g. wrapping a source separation model
+fn detect_vocals(command: &str, path: &str) -> Option<bool> {
+    let output = match Command::new(command).arg(path).output() {
+        Ok(output) => output,
+        Err(e) => {
+            log::error!("Cannot run vocal detector '{}': {}", command, e);
+            return None;
+        }
+    };
+
+    match String::from_utf8_lossy(&output.stdout).trim() {
+        "vocal" => Some(true),
+        "instrumental" => Some(false),
+        other => {
+            log::error!(
+                "Unexpected vocal detector output for '{}': '{}'",
+                path,
+                other
+            );
+            None
+        }
+    }
+}
+
+/// Find the silences, the low energy intro and outro, the energy, the
+/// vocals, the beat grid and the key of a track
+pub fn analyze(frames: &[Frame], sample_rate: u32) -> TrackAnalysis {
+    let block_length = ((sample_rate as f64 * BLOCK_SECONDS) as usize).max(1);
+    let levels: Vec<f64> = frames
+        .chunks(block_length)
+        .map(|block| {
+            let power = block
+                .iter()
+                .map(|frame| {
+                    let mono = (frame.left + frame.right) as f64 / 2.0;
+                    mono * mono
+                })
+                .sum::<f64>()
+                / block.len() as f64;
+            power.sqrt()
+        })
+        .collect();
+    let duration = frames.len() as f64 / sample_rate as f64;
+    let time = |block: usize| ((block * block_length) as f64 / sample_rate as f64).min(duration);
+
+    let silence = 10f64.powf(SILENCE_DB / 20.0);
+    let (Some(first), Some(last)) = (
+        levels.iter().position(|level| *level > silence),
+        levels.iter().rposition(|level| *level > silence),
+    ) else {
+        return TrackAnalysis {
+            version: ANALYSIS_VERSION,
+            ..Default::default()
+        };
+    };
+
+    let smoothing = (SMOOTHING_SECONDS / BLOCK_SECONDS) as usize / 2;
+    let smoothed: Vec<f64> = (0..levels.len())
+        .map(|block| {
+            let window =
+                &levels[block.saturating_sub(smoothing)..(block + smoothing + 1).min(levels.len())];
+            window.iter().sum::<f64>() / window.len() as f64
+        })
+        .collect();
+
+    let mut sounding = levels[first..=last].to_vec();
+    sounding.sort_by(f64::total_cmp);
+    let body = sounding[((sounding.len() - 1) as f64 * BODY_QUANTILE) as usize];
+    let threshold = body * LOW_ENERGY_RATIO;
+    let intro_end = smoothed[first..=last]
+        .iter()
+        .position(|level| *level >= threshold)
+        .map_or(first, |block| first + block);
+    let outro_start = smoothed[first..=last]
+        .iter()
+        .rposition(|level| *level >= threshold)
+        .map_or(last, |block| first + block + 1);
+
+    // spectral flux approximated by the rises of the level between blocks
+    let rises: f64 = levels[first..=last]
+        .windows(2)
+        .map(|pair| (pair[1] - pair[0]).max(0.0))
+        .sum();
+    let sounding_seconds = time(last + 1) - time(first);
+    let flux = rises / body.max(f64::EPSILON) / sounding_seconds.max(BLOCK_SECONDS);
+    let loudness = (1.0 - 20.0 * body.log10() / QUIET_DB).clamp(0.0, 1.0);
+    let energy = 5.0 * loudness + 5.0 * (flux / FULL_FLUX).min(1.0);
+
+    let vocal_score = vocal_score(&frames[first * block_length..], sample_rate, block_length);
+    let grid = beatgrid::detect(frames, sample_rate);
+
+    TrackAnalysis {
+        version: ANALYSIS_VERSION,
+        energy,
+        vocal_score,
+        vocal: vocal_score >= VOCAL_SCORE,
+        sound_start: time(first),
+        sound_end: time(last + 1),
+        intro_end: time(intro_end),
+        outro_start: time(outro_start),
+        bpm: grid.map(|(bpm, _)| bpm),
+        first_beat: grid.map_or(0.0, |(_, first_beat)| first_beat),
+        key: musical_key::detect(frames, sample_rate),
+    }
+}
+
+/// Part of the sounding blocks of `frames` whose power is mostly in the
+/// voice range and centered. A rough heuristic: centered leads and pads also
+/// count as voices
+fn vocal_score(frames: &[Frame], sample_rate: u32, block_length: usize) -> f64 {
+    let silence = 10f64.powf(SILENCE_DB / 20.0);
+    let mut center_filter = Biquad::band_pass(sample_rate as f64, VOICE_CENTER, VOICE_Q);
+    let mut side_filter = center_filter;
+    let (mut sounding, mut voiced) = (0, 0);
+
+    for block in frames.chunks(block_length) {
+        let (mut total, mut center, mut side) = (0.0, 0.0, 0.0);
+        for frame in block {
+            let mid = (frame.left + frame.right) as f64 / 2.0;
+            let voice_mid = center_filter.process(mid);
+            let voice_side = side_filter.process((frame.left - frame.right) as f64 / 2.0);
+            total += mid * mid;
+            center += voice_mid * voice_mid;
+            side += voice_side * voice_side;
+        }
+
+        if (total / block.len() as f64).sqrt() <= silence {
+            continue;
+        }
+        sounding += 1;
+        if center >= VOICE_SHARE * total && center >= VOICE_CENTERING * side {
+            voiced += 1;
+        }
+    }
+
+    match sounding {
+        0 => 0.0,
+        _ => voiced as f64 / sounding as f64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analyze() {
+        let sample_rate = 1_000;
+        let section = |seconds: usize, level: f32| vec![Frame::from_mono(level); seconds * 1_000];
+        let frames = [
+            section(1, 0.0),
+            section(4, 0.1),
+            section(10, 0.8),
+            section(4, 0.1),
+            section(1, 0.0),
+        ]
+        .concat();
+
+        let analysis = analyze(&frames, sample_rate);
+        assert_eq!(analysis.sound_start, 1.0);
+        assert_eq!(analysis.sound_end, 19.0);
+        assert!((analysis.intro_end - 5.0).abs() < 0.5);
+        assert!((analysis.outro_start - 15.0).abs() < 0.5);
+        assert_eq!(analyze(&section(1, 0.0), sample_rate).sound_end, 0.0);
+
+        // a hit every half second has more energy than a steady tone
+        let pulse = [vec![Frame::from_mono(0.8); 250], vec![Frame::ZERO; 250]].concat();
+        let steady = analyze(&section(10, 0.8), sample_rate).energy;
+        let pulsing = analyze(&pulse.repeat(20), sample_rate).energy;
+        assert!(steady > 4.0 && steady < 5.0);
+        assert!(pulsing > steady + 2.0);
+
+        let sample_rate = 8_000;
+        let sine = |frequency: f32, left: f32, right: f32| -> Vec<Frame> {
+            (0..2 * sample_rate)
+                .map(|i| {
+                    let value =
+                        (std::f32::consts::TAU * frequency * i as f32 / sample_rate as f32).sin();
+                    Frame::new(left * value, right * value)
+                })
+                .collect()
+        };
+        assert!(analyze(&sine(1_000.0, 0.5, 0.5), sample_rate).vocal);
+        // bass, and a voice panned to a side
+        assert!(!analyze(&sine(60.0, 0.5, 0.5), sample_rate).vocal);
+        assert!(!analyze(&sine(1_000.0, 0.5, 0.0), sample_rate).vocal);
+    }
+}
@@ -1,35 +1,364 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use egui::{Image, Label, Layout, Rounding, ScrollArea, SelectableLabel, Visuals};
+use egui::{
+    Color32, Image, Label, Layout, RichText, Rounding, ScrollArea, SelectableLabel, WidgetInfo,
+    WidgetType,
+};
 use egui_wgpu::ScreenDescriptor;
-use winit::event::{DeviceEvent, ElementState, KeyEvent, Modifiers, WindowEvent};
-use winit::event_loop::{ControlFlow, EventLoop, EventLoopWindowTarget};
-use winit::keyboard::{KeyCode, ModifiersState, PhysicalKey};
-use winit::window::{Window, WindowBuilder};
-
-use crate::controller::{BoothEvent, Controller, TurntableFocus};
+use winit::event::{DeviceEvent, Modifiers, WindowEvent};
+use winit::event_loop::{ControlFlow, DeviceEvents, EventLoop, EventLoopWindowTarget};
+use winit::window::{CursorGrabMode, Window, WindowBuilder, WindowLevel};
+
+use crate::analysis::{Analyzer, TrackAnalysis};
+use crate::automation::{Automation, TRANSITION_SECONDS};
+use crate::backup;
+use crate::beatgrid::GridTaps;
+use crate::browser_filter::{self, BrowserFilter};
+use crate::clap_host::{self, PluginInfo};
+use crate::config::{self, data_dir, ConfigWatcher};
+use crate::controller::{self, BoothEvent, Controller, TurntableFocus};
 use crate::cover_img::CoverImg;
+use crate::cue_preview::{self, CuePreview};
+use crate::diagnostics::{Snapshot, MAX_RECENT_EVENTS};
+use crate::duck_server::DuckServer;
 use crate::file_navigator::FileNavigator;
+use crate::gamepad_controller::GamepadController;
+use crate::generator::{GeneratorBus, Waveform, MAX_FREQUENCY, MIN_FREQUENCY};
+use crate::global_hotkeys::GlobalHotkeys;
 use crate::gpu::Gpu;
 use crate::gui::Gui;
-use crate::mixer::Mixer;
+use crate::hid_controller::HidController;
+use crate::input_source::InputSource;
+use crate::keyboard_controller::KeyboardController;
+use crate::layer::Layer;
+use crate::library::{Library, TrackInfo};
+use crate::limiter::ChannelLimiter;
+use crate::mapping::{GamepadMapping, HidMapping, KeyMap, MidiMapping, TargetGroup};
+use crate::master_tempo::{MasterTempo, TempoSource};
+use crate::midi_controller::MidiController;
+use crate::mixer::{CueMode, Mixer, CENTER_CROSSFADER, CENTER_CUE_MIX, EQ_FLAT_GAIN};
+use crate::morph::Morph;
+use crate::overlay_server::{OverlayServer, OverlayState};
+use crate::overview::Overview;
+use crate::overview_gpu::OverviewCallback;
+use crate::plugin_slot::{self, PluginSlot};
 use crate::processable::Processable;
+use crate::recorder::RecordSource;
+use crate::sampler::{
+    PlayMode, Quantize, Sampler, BEATS_PER_BAR, MAX_BPM, MAX_CAPTURE_BEATS, MIN_BPM, PAD_COUNT,
+};
+use crate::scheduler::{self, Schedule, Scheduler};
+use crate::session::{self, Session};
+use crate::settings::Settings;
+use crate::spectrogram::SpectrogramView;
+use crate::stats::{SessionStats, StatsSample};
+use crate::stems::Stem;
+use crate::suggestions;
+use crate::theme;
+use crate::thumbnails::Thumbnails;
+use crate::timecode::{Timecode, TimecodeSender};
+use crate::toast::Toasts;
+use crate::track_loader::TrackLoader;
+use crate::trackpad_controller::TrackpadController;
+use crate::trainer::{self, Trainer};
+use crate::transform::{Transform, TransformGate};
 use crate::turntable::Turntable;
-use crate::utils::{remap, to_min_sec_millis_str};
+use crate::tutorial::{Tutorial, TutorialArea};
+use crate::utils::{is_image_path, to_hour_min_sec_str, to_min_sec_millis_str};
+use crate::watchdog::Watchdog;
+use crate::window_geometry::WindowGeometry;
+
+/// Frame rate when nothing moves on screen
+const IDLE_FPS: u8 = 4;
+/// Time the full frame rate is kept after an input
+const INPUT_ACTIVITY: Duration = Duration::from_secs(1);
+/// Tracks listed in the suggestions panel
+const MAX_SUGGESTIONS: usize = 10;
+const SPECTROGRAM_HEIGHT: f32 = 320.0;
+const OVERVIEW_HEIGHT: f32 = 40.0;
+/// Closest bar marks on the overview in points, every other one being left
+/// out until they are this far apart
+const MIN_BAR_SPACING: f32 = 4.0;
+/// Seconds the beat grid moves by per click
+const GRID_NUDGE: f64 = 0.01;
+/// Tempo range offered by the browser filter
+const FILTER_MIN_BPM: f64 = 60.0;
+const FILTER_MAX_BPM: f64 = 200.0;
+const CUE_MODE_HELP: &str = "Cue before the EQ and fader (PFL) or after them (AFL)";
 
 pub struct AppData {
     pub fps: u8,
     pub frame_counter: u32,
+    /// whether frames are drawn at [`IDLE_FPS`] instead of `fps`
+    pub idle: bool,
     pub show_debug_panel: bool,
+    pub show_help: bool,
+    pub show_settings: bool,
+    pub settings: Settings,
+    /// set from the UI to quit even in background mode
+    pub quit_requested: bool,
+    pub show_plugins: bool,
+    /// effects found by the last plugin scan
+    pub plugins: Vec<PluginInfo>,
+    /// name typed to save a plugin preset
+    pub plugin_preset_name: String,
+    /// set when a setting affecting the mappings changed
+    pub mappings_outdated: bool,
+    /// sampler pad shown in the slot editor
+    pub sampler_edit_pad: usize,
+    /// name typed to save a sampler bank
+    pub sampler_bank_name: String,
+    /// active keymap, displayed in the help
+    pub keymap: KeyMap,
     pub mixer: Mixer,
+    /// mixer snapshots A and B
+    pub morph: Morph,
+    pub master_tempo: MasterTempo,
+    /// beats tapped to correct the beat grid of a track
+    pub grid_taps: GridTaps,
+    pub transform: Transform,
+    /// recorded knob and fader moves
+    pub automation: Automation,
+    /// memory point played into the cue only
+    pub cue_preview: Option<CuePreview>,
+    /// beatmatching practice rounds
+    pub trainer: Trainer,
+    /// statistics of the running session
+    pub stats: SessionStats,
+    /// dead air warnings
+    pub watchdog: Watchdog,
+    /// playlists played at set times
+    pub scheduler: Scheduler,
     pub turntable_one: Turntable,
     pub turntable_two: Turntable,
+    /// acapellas or loops played on top of the decks
+    pub layer_one: Layer,
+    pub layer_two: Layer,
     pub turntable_focus: TurntableFocus,
     pub modifiers_key: Modifiers,
     pub file_navigator: FileNavigator,
+    pub thumbnails: Thumbnails,
+    /// silence and intro detection of the loaded tracks
+    pub analyzer: Analyzer,
+    /// decoding of the tracks being loaded
+    pub track_loader: TrackLoader,
+    /// start of an entry name typed to jump to it in the browser
+    pub browser_find: String,
+    /// list the analyzed tracks of the browser by decreasing energy
+    pub browser_sort_by_energy: bool,
+    pub show_browser_filters: bool,
+    /// track of the library whose spectrogram is shown
+    pub spectrogram: Option<SpectrogramView>,
+    /// overviews of the tracks loaded on the decks
+    pub overviews: Vec<(String, Overview)>,
+    pub browser_filter: BrowserFilter,
+    /// the browser filter changed since it was last applied
+    pub browser_filter_outdated: bool,
+    /// memory points and other track information
+    pub library: Library,
+    /// tracks put aside while browsing, loaded first in first out
+    pub prepare: Vec<String>,
     pub cover_one: CoverImg,
     pub cover_two: CoverImg,
+    pub input_debug_info: Vec<String>,
+    /// last input events, for diagnostic snapshots
+    pub recent_events: VecDeque<String>,
+    pub pending_recovery: Option<Session>,
+    pub tutorial: Tutorial,
+    pub toasts: Toasts,
+}
+
+impl AppData {
+    /// Booth state with a fresh mixer and empty decks, browsing `root_dir`
+    pub fn new(settings: Settings, keymap: KeyMap, root_dir: &str) -> Self {
+        let mixer = Mixer::new();
+        let audio_manager_clone_one = mixer.get_audio_manager();
+        let audio_manager_clone_two = mixer.get_audio_manager();
+        let ch_one_track_clone = mixer.get_ch_one_track();
+        let ch_two_track_clone = mixer.get_ch_two_track();
+        let layer_one = Layer::new(mixer.get_audio_manager(), mixer.get_ch_one_track());
+        let layer_two = Layer::new(mixer.get_audio_manager(), mixer.get_ch_two_track());
+
+        Self {
+            fps: 24,
+            frame_counter: 0,
+            idle: false,
+            show_debug_panel: true,
+            show_help: false,
+            show_settings: false,
+            settings,
+            quit_requested: false,
+            show_plugins: false,
+            plugins: Vec::new(),
+            plugin_preset_name: String::new(),
+            mappings_outdated: false,
+            sampler_edit_pad: 0,
+            sampler_bank_name: String::new(),
+            keymap,
+            mixer: mixer,
+            morph: Morph::default(),
+            master_tempo: MasterTempo::default(),
+            grid_taps: GridTaps::default(),
+            transform: Transform::default(),
+            automation: Automation::default(),
+            cue_preview: None,
+            trainer: Trainer::default(),
+            stats: SessionStats::default(),
+            watchdog: Watchdog::default(),
+            scheduler: Scheduler::new(),
+            turntable_one: Turntable::new(audio_manager_clone_one, ch_one_track_clone),
+            turntable_two: Turntable::new(audio_manager_clone_two, ch_two_track_clone),
+            layer_one,
+            layer_two,
+            turntable_focus: TurntableFocus::One,
+            modifiers_key: Modifiers::default(),
+            file_navigator: FileNavigator::new(&root_dir.to_string()),
+            thumbnails: Thumbnails::new(),
+            analyzer: Analyzer::new(),
+            track_loader: TrackLoader::new(),
+            browser_find: String::new(),
+            browser_sort_by_energy: false,
+            show_browser_filters: false,
+            spectrogram: None,
+            overviews: Vec::new(),
+            browser_filter: BrowserFilter::default(),
+            browser_filter_outdated: false,
+            library: Library::load(),
+            prepare: Vec::new(),
+            cover_one: CoverImg::default(),
+            cover_two: CoverImg::default(),
+            input_debug_info: Vec::new(),
+            recent_events: VecDeque::new(),
+            pending_recovery: None,
+            tutorial: Tutorial::new(),
+            toasts: Toasts::default(),
+        }
+    }
+
+    /// Tempo of a deck, from the BPM of its track in the library and its
+    /// pitch
+    pub fn deck_bpm(&self, deck: TurntableFocus) -> Option<f64> {
+        let turntable = match deck {
+            TurntableFocus::One => &self.turntable_one,
+            TurntableFocus::Two => &self.turntable_two,
+        };
+        let bpm = self.library.track(&turntable.currently_loaded()?)?.bpm?;
+
+        Some(bpm * turntable.pitch())
+    }
+
+    /// Playback rate taking the layer of a deck to the tempo of the deck,
+    /// or to its pitch when either BPM is unknown
+    pub fn layer_rate(&self, deck: TurntableFocus) -> f64 {
+        let (turntable, layer) = match deck {
+            TurntableFocus::One => (&self.turntable_one, &self.layer_one),
+            TurntableFocus::Two => (&self.turntable_two, &self.layer_two),
+        };
+        let layer_bpm = layer
+            .currently_loaded()
+            .and_then(|path| self.library.track(&path)?.bpm);
+
+        match (self.deck_bpm(deck), layer_bpm) {
+            (Some(deck_bpm), Some(layer_bpm)) => deck_bpm / layer_bpm,
+            _ => turntable.pitch(),
+        }
+    }
+
+    /// Position of a deck within the current beat, from 0.0 on the beat to
+    /// 1.0, given by the beat grid of its track in the library
+    pub fn beat_phase(&self, deck: TurntableFocus) -> Option<f64> {
+        Some(self.deck_beats(deck)?.rem_euclid(1.0))
+    }
+
+    /// Beats played by a deck since the first beat of its track, negative
+    /// before it
+    pub fn deck_beats(&self, deck: TurntableFocus) -> Option<f64> {
+        let turntable = match deck {
+            TurntableFocus::One => &self.turntable_one,
+            TurntableFocus::Two => &self.turntable_two,
+        };
+        let track = self.library.track(&turntable.currently_loaded()?)?;
+
+        Some((turntable.display_position()? - track.first_beat) * track.bpm? / 60.0)
+    }
+
+    /// Overview of the track loaded on a deck, once computed
+    pub fn deck_overview(&self, deck: TurntableFocus) -> Option<&Overview> {
+        let path = match deck {
+            TurntableFocus::One => self.turntable_one.currently_loaded(),
+            TurntableFocus::Two => self.turntable_two.currently_loaded(),
+        }?;
+
+        self.overviews
+            .iter()
+            .find(|(overview_path, _)| *overview_path == path)
+            .map(|(_, overview)| overview)
+    }
+
+    /// Playing deck heard the most on the master
+    pub fn master_deck(&self) -> Option<TurntableFocus> {
+        [
+            (
+                TurntableFocus::One,
+                &self.turntable_one,
+                self.mixer.get_ch_one_volume(),
+            ),
+            (
+                TurntableFocus::Two,
+                &self.turntable_two,
+                self.mixer.get_ch_two_volume(),
+            ),
+        ]
+        .into_iter()
+        .filter(|(deck, turntable, volume)| {
+            turntable.is_playing() && *volume > 0.0 && !self.is_previewed(*deck)
+        })
+        .max_by(|(_, _, a), (_, _, b)| a.total_cmp(b))
+        .map(|(deck, _, _)| deck)
+    }
+
+    /// Deck giving the master tempo, none while set by hand
+    pub fn tempo_master_deck(&self) -> Option<TurntableFocus> {
+        match self.master_tempo.source() {
+            TempoSource::Auto => self.master_deck(),
+            TempoSource::Deck(deck) => Some(deck),
+            TempoSource::Manual => None,
+        }
+    }
+
+    /// Whether `deck` is taken off the master to preview a memory point
+    pub fn is_previewed(&self, deck: TurntableFocus) -> bool {
+        self.cue_preview.is_some_and(|preview| preview.deck == deck)
+    }
+
+    /// What is heard on the master, for the session statistics
+    pub fn stats_sample(&self) -> StatsSample {
+        let volumes = [
+            self.mixer.get_ch_one_volume(),
+            self.mixer.get_ch_two_volume(),
+        ];
+        let audible = |deck: TurntableFocus, turntable: &Turntable, volume: f64| match turntable
+            .is_playing()
+            && volume > 0.0
+            && !self.is_previewed(deck)
+        {
+            true => turntable.currently_loaded(),
+            false => None,
+        };
+
+        StatsSample {
+            audible: [
+                audible(TurntableFocus::One, &self.turntable_one, volumes[0]),
+                audible(TurntableFocus::Two, &self.turntable_two, volumes[1]),
+            ],
+            volumes,
+            master_bpm: self.master_deck().and_then(|deck| self.deck_bpm(deck)),
+        }
+    }
 }
 
 pub struct App {
@@ -39,45 +368,97 @@ pub struct App {
     pub app_data: AppData,
     pub controller: Controller,
     pub delta_timer: Instant,
+    pub input_sources: Vec<Box<dyn InputSource>>,
+    pub config_watcher: ConfigWatcher,
+    last_input: Instant,
+    /// whether the cursor is grabbed for an input source moving a vinyl
+    pointer_grabbed: bool,
+    /// delay after which egui asked to be run again
+    repaint_delay: Duration,
+    /// whether the window is hidden by other windows
+    occluded: bool,
+    timecode_sender: Option<TimecodeSender>,
+    overlay_server: Option<OverlayServer>,
 }
 
 impl App {
     pub fn new(event_loop: &EventLoop<()>) -> Self {
-        let window = WindowBuilder::new()
+        let settings = Settings::load();
+
+        let mut window_builder = WindowBuilder::new()
             .with_title(format!(
                 "{} v{}",
                 env!("CARGO_PKG_NAME"),
                 env!("CARGO_PKG_VERSION")
             ))
-            .build(&event_loop)
-            .unwrap();
+            .with_window_level(window_level(&settings));
+        if let Some(geometry) = WindowGeometry::load() {
+            window_builder = geometry.apply(window_builder, event_loop.available_monitors());
+        }
+        let window = window_builder.build(&event_loop).unwrap();
         let window = Arc::new(window);
 
         let gpu = pollster::block_on(Gpu::new(Arc::clone(&window)));
 
         let gui = Gui::new(&window, &gpu);
 
-        let mixer = Mixer::new();
-        let audio_manager_clone_one = mixer.get_audio_manager();
-        let audio_manager_clone_two = mixer.get_audio_manager();
-        let ch_one_track_clone = mixer.get_ch_one_track();
-        let ch_two_track_clone = mixer.get_ch_two_track();
+        let keymap = KeyMap::load();
 
-        let app_data = AppData {
-            fps: 24,
-            frame_counter: 0,
-            show_debug_panel: true,
-            mixer: mixer,
-            turntable_one: Turntable::new(audio_manager_clone_one, ch_one_track_clone),
-            turntable_two: Turntable::new(audio_manager_clone_two, ch_two_track_clone),
-            turntable_focus: TurntableFocus::One,
-            modifiers_key: Modifiers::default(),
-            file_navigator: FileNavigator::new(
-                &dotenv::var("ROOT_DIR").expect("ROOT_DIR environment variable not present"),
-            ),
-            cover_one: CoverImg::default(),
-            cover_two: CoverImg::default(),
-        };
+        let mut app_data = AppData::new(
+            settings,
+            keymap.clone(),
+            &config::root_dir()
+                .expect("ROOT_DIR environment variable not present")
+                .to_string_lossy(),
+        );
+        app_data.pending_recovery = session::load_recovery();
+
+        // raw keys are only delivered to the focused window by default
+        if !keymap.global.is_empty() {
+            event_loop.listen_device_events(DeviceEvents::Always);
+        }
+
+        let event_loop_proxy = event_loop.create_proxy();
+        let mut input_sources: Vec<Box<dyn InputSource>> = vec![
+            Box::new(KeyboardController::new(keymap.clone())),
+            Box::new(TrackpadController::new(keymap.pointer)),
+            Box::new(GlobalHotkeys::new(keymap.global, window.has_focus())),
+            Box::new(MidiController::new(
+                app_data.settings.midi_preset.as_deref(),
+                move || {
+                    let _ = event_loop_proxy.send_event(());
+                },
+            )),
+        ];
+
+        if let Some(gamepad_controller) = GamepadController::new(GamepadMapping::load()) {
+            input_sources.push(Box::new(gamepad_controller));
+        }
+
+        if let Some(hid_mapping) = HidMapping::load() {
+            let event_loop_proxy = event_loop.create_proxy();
+            if let Some(hid_controller) = HidController::new(hid_mapping, move || {
+                let _ = event_loop_proxy.send_event(());
+            }) {
+                input_sources.push(Box::new(hid_controller));
+            }
+        }
+
+        if let Some(port) = app_data.settings.duck_port {
+            let event_loop_proxy = event_loop.create_proxy();
+            if let Some(duck_server) = DuckServer::new(port, move || {
+                let _ = event_loop_proxy.send_event(());
+            }) {
+                input_sources.push(Box::new(duck_server));
+            }
+        }
+
+        let timecode_sender = app_data
+            .settings
+            .timecode_target
+            .as_deref()
+            .and_then(TimecodeSender::new);
+        let overlay_server = app_data.settings.overlay_port.and_then(OverlayServer::new);
 
         Self {
             window: window,
@@ -86,16 +467,17 @@ impl App {
             app_data: app_data,
             controller: Controller::new(),
             delta_timer: Instant::now(),
+            input_sources,
+            config_watcher: ConfigWatcher::new(),
+            last_input: Instant::now(),
+            pointer_grabbed: false,
+            repaint_delay: Duration::ZERO,
+            occluded: false,
+            timecode_sender,
+            overlay_server,
         }
     }
 
-    fn surface_texture(&self) -> wgpu::SurfaceTexture {
-        self.gpu
-            .surface
-            .get_current_texture()
-            .expect("Failed to acquire next swap chain texture")
-    }
-
     fn surface_view(&self, surface_texture: &wgpu::SurfaceTexture) -> wgpu::TextureView {
         surface_texture
             .texture
@@ -115,313 +497,2680 @@ impl App {
         }
     }
 
-    pub fn on_window_event(&mut self, event: WindowEvent, elwt: &EventLoopWindowTarget<()>) {
-        self.gui.handle_event(&self.window, &event);
+    pub fn on_window_event(&mut self, event: WindowEvent, elwt: &EventLoopWindowTarget<()>) {
+        let consumed = self.gui.handle_event(&self.window, &event);
+
+        // keys typed in a text field are not mapped
+        let typed = consumed && matches!(event, WindowEvent::KeyboardInput { .. });
+        if !typed {
+            for source in self.input_sources.iter_mut() {
+                source.on_window_event(&event);
+            }
+        }
+        self.update_pointer_grab();
+        self.on_input_ready();
+        if event != WindowEvent::RedrawRequested {
+            self.last_input = Instant::now();
+        }
+
+        match event {
+            // the audio keeps running in background mode
+            WindowEvent::CloseRequested if self.app_data.settings.background_mode => {
+                log::info!("The close button was pressed; minimizing");
+                self.window.set_minimized(true);
+            }
+            WindowEvent::CloseRequested => {
+                log::info!("The close button was pressed; stopping");
+                self.exit(elwt);
+            }
+
+            WindowEvent::RedrawRequested => {
+                if !self.is_visible() {
+                    return;
+                }
+                // the frame is skipped, the audio keeps being processed
+                let Some(surface_texture) = self.gpu.surface_texture() else {
+                    return;
+                };
+                self.app_data.frame_counter += 1;
+
+                let mut encoder = self.encoder();
+                let surface_view = self.surface_view(&surface_texture);
+
+                self.repaint_delay = self.gui.draw(
+                    &self.gpu.device,
+                    &self.gpu.queue,
+                    &mut encoder,
+                    &self.window,
+                    &surface_view,
+                    self.screen_descriptor(),
+                    |ctx| run_ui(ctx, &self.window, &mut self.app_data, &mut self.controller),
+                );
+
+                self.gpu.queue.submit(Some(encoder.finish()));
+                surface_texture.present();
+
+                if self.app_data.quit_requested {
+                    log::info!("Quit was requested; stopping");
+                    self.exit(elwt);
+                }
+            }
+            // some platforms minimize to a zero size, which cannot be
+            // configured
+            WindowEvent::Resized(physical_size) => {
+                if physical_size.width > 0 && physical_size.height > 0 {
+                    self.gpu.resize(physical_size);
+                }
+                self.window.request_redraw();
+            }
+            WindowEvent::Occluded(occluded) => {
+                self.occluded = occluded;
+                self.window.request_redraw();
+            }
+            // the window moved to a monitor with another scale factor, egui
+            // picks it up from the event
+            WindowEvent::ScaleFactorChanged { .. } => {
+                self.gpu.resize(self.window.inner_size());
+                self.window.request_redraw();
+            }
+
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.on_modifiers_key_changed(modifiers);
+            }
+
+            _ => (),
+        }
+    }
+
+    fn exit(&self, elwt: &EventLoopWindowTarget<()>) {
+        if let Some(geometry) = WindowGeometry::capture(&self.window) {
+            geometry.save();
+        }
+        elwt.exit();
+    }
+
+    pub fn on_modifiers_key_changed(&mut self, modifiers: Modifiers) {
+        self.app_data.modifiers_key = modifiers;
+    }
+
+    /// Grab the cursor while an input source moves a vinyl with the pointer,
+    /// unless disabled in the settings
+    fn update_pointer_grab(&mut self) {
+        let grab = self
+            .input_sources
+            .iter()
+            .any(|source| source.grabs_pointer());
+        if grab == self.pointer_grabbed {
+            return;
+        }
+
+        self.pointer_grabbed = grab;
+        let mode = match grab && self.app_data.settings.grab_cursor {
+            true => self.grab_cursor(),
+            false => {
+                if let Err(e) = self.window.set_cursor_grab(CursorGrabMode::None) {
+                    log::warn!("Cannot release the cursor: {}", e);
+                }
+                CursorGrabMode::None
+            }
+        };
+        for source in self.input_sources.iter_mut() {
+            source.set_pointer_locked(mode == CursorGrabMode::Locked);
+        }
+    }
+
+    /// Lock the cursor, or confine it to the window where locking is refused,
+    /// e.g. by some Wayland compositors. Returns the mode obtained
+    fn grab_cursor(&self) -> CursorGrabMode {
+        for mode in [CursorGrabMode::Locked, CursorGrabMode::Confined] {
+            match self.window.set_cursor_grab(mode) {
+                Ok(()) => return mode,
+                Err(e) => log::warn!("Cannot grab the cursor in {:?} mode: {}", mode, e),
+            }
+        }
+        CursorGrabMode::None
+    }
+
+    pub fn on_device_event(&mut self, event: DeviceEvent) {
+        for source in self.input_sources.iter_mut() {
+            source.on_device_event(&event);
+        }
+        self.on_input_ready();
+        self.last_input = Instant::now();
+    }
+
+    /// Poll every input source and dispatch the booth events they produced
+    pub fn on_input_ready(&mut self) {
+        let mut events = Vec::new();
+        for source in self.input_sources.iter_mut() {
+            source.poll(&mut events);
+        }
+
+        if !events.is_empty() {
+            self.last_input = Instant::now();
+        }
+        events.extend(self.app_data.automation.poll(Instant::now()));
+        for event in events {
+            let recent_events = &mut self.app_data.recent_events;
+            if recent_events.len() == MAX_RECENT_EVENTS {
+                recent_events.pop_front();
+            }
+            recent_events.push_back(format!("{:?}", event));
+
+            self.controller.handle_event(&mut self.app_data, event);
+        }
+
+        self.app_data.input_debug_info = self
+            .input_sources
+            .iter()
+            .filter_map(|source| source.debug_info())
+            .collect();
+    }
+
+    pub fn on_resume_time_reached(&mut self, elwt: &EventLoopWindowTarget<()>) {
+        self.process(self.delta_timer.elapsed().as_secs_f64());
+        self.delta_timer = Instant::now();
+        self.on_input_ready();
+
+        self.app_data.idle = !self.is_animating();
+        let fps = match self.app_data.idle {
+            true => IDLE_FPS,
+            false => self.app_data.fps,
+        };
+        elwt.set_control_flow(ControlFlow::wait_duration(Duration::from_millis(
+            (1000 as f32 / fps as f32) as u64,
+        )));
+        if self.is_visible() {
+            self.window.request_redraw();
+        }
+    }
+
+    /// Whether the window is shown, nothing being rendered while it is
+    /// minimized or occluded and the audio keeping being processed
+    fn is_visible(&self) -> bool {
+        let size = self.window.inner_size();
+        !self.occluded
+            && self.window.is_minimized() != Some(true)
+            && size.width > 0
+            && size.height > 0
+    }
+
+    /// Whether something moves on screen or input is expected, in which case
+    /// frames are drawn at the full rate
+    fn is_animating(&self) -> bool {
+        let app_data = &self.app_data;
+        let mixer = &app_data.mixer;
+        let frame = Duration::from_secs_f32(1.0 / app_data.fps as f32);
+
+        let decks_moving = [&app_data.turntable_one, &app_data.turntable_two]
+            .iter()
+            .any(|turntable| turntable.is_playing() || turntable.speed().abs() > 1e-3);
+        // toasts, loading spinners, meters, flashing buttons, morph glides and
+        // live inputs
+        let live = !app_data.toasts.is_empty()
+            || app_data.track_loader.is_loading()
+            || app_data.morph.is_gliding()
+            || app_data.automation.is_replaying()
+            || mixer.is_metronome_enabled()
+            || mixer.recorder().is_recording()
+            || mixer.is_sampler_playing()
+            || mixer.is_cue_one_enabled()
+            || mixer.is_cue_two_enabled()
+            || mixer.is_input_one_enabled()
+            || mixer.is_input_two_enabled()
+            || mixer.is_mic_enabled();
+
+        decks_moving
+            || live
+            || self.last_input.elapsed() < INPUT_ACTIVITY
+            || self.repaint_delay <= frame
+            || self
+                .input_sources
+                .iter()
+                .any(|source| source.needs_polling())
+    }
+}
+
+impl Processable for App {
+    fn process(&mut self, delta: f64) {
+        let auto_pause = self.app_data.settings.auto_pause;
+        self.app_data.turntable_one.set_auto_pause(auto_pause);
+        self.app_data.turntable_two.set_auto_pause(auto_pause);
+        let stream_over = match self.app_data.settings.stream_over_minutes {
+            0 => None,
+            minutes => Some(minutes as f64 * 60.0),
+        };
+        self.app_data.turntable_one.set_stream_over(stream_over);
+        self.app_data.turntable_two.set_stream_over(stream_over);
+        let channel_limiter = self.app_data.settings.channel_limiter;
+        self.app_data.mixer.set_limiters_enabled(channel_limiter);
+        cue_preview::process(&mut self.app_data, delta);
+        scheduler::process(&mut self.app_data, &self.controller, delta);
+        self.app_data.turntable_one.process(delta);
+        self.app_data.turntable_two.process(delta);
+        let rate = self.app_data.layer_rate(TurntableFocus::One);
+        self.app_data.layer_one.set_rate(rate);
+        let rate = self.app_data.layer_rate(TurntableFocus::Two);
+        self.app_data.layer_two.set_rate(rate);
+        let deck_bpm = self
+            .app_data
+            .tempo_master_deck()
+            .and_then(|deck| self.app_data.deck_bpm(deck));
+        self.app_data.master_tempo.follow(deck_bpm);
+        let bpm = self.app_data.master_tempo.bpm();
+        if self.app_data.mixer.metronome().follows_tempo() {
+            self.app_data.mixer.metronome().set_bpm(bpm);
+        }
+        let sampler = self.app_data.mixer.sampler();
+        if sampler.follows_tempo() && sampler.bpm() != bpm {
+            sampler.set_bpm(bpm);
+        }
+        self.app_data.transform.process(delta);
+        let gate = self.app_data.settings.transform_gate;
+        let [cut_one, cut_two] = [TurntableFocus::One, TurntableFocus::Two].map(|deck| {
+            let beat_phase = self.app_data.beat_phase(deck);
+            self.app_data.transform.is_cut(deck, gate, beat_phase, bpm)
+        });
+        self.app_data.mixer.set_cut_one(cut_one);
+        self.app_data.mixer.set_cut_two(cut_two);
+        self.app_data.mixer.process(delta);
+
+        if self.app_data.trainer.is_active()
+            && self.app_data.turntable_one.is_playing()
+            && self.app_data.turntable_two.is_playing()
+        {
+            let app_data = &self.app_data;
+            if let (Some(bpm_one), Some(bpm_two), Some(phase_one), Some(phase_two)) = (
+                app_data.deck_bpm(TurntableFocus::One),
+                app_data.deck_bpm(TurntableFocus::Two),
+                app_data.beat_phase(TurntableFocus::One),
+                app_data.beat_phase(TurntableFocus::Two),
+            ) {
+                self.app_data.trainer.update(
+                    (bpm_one - bpm_two).abs(),
+                    trainer::phase_error(phase_one, phase_two),
+                    Instant::now(),
+                );
+            }
+        }
+        self.app_data.morph.process(delta, &mut self.app_data.mixer);
+
+        for deck in self.app_data.track_loader.poll() {
+            self.controller
+                .handle_event(&mut self.app_data, BoothEvent::TrackLoaded(deck));
+        }
+        let next = self.app_data.scheduler.next_track().or(self
+            .app_data
+            .prepare
+            .first()
+            .map(String::as_str));
+        if let (true, Some(next)) = (self.app_data.settings.preload_next, next) {
+            self.app_data
+                .track_loader
+                .preload(Path::new(next), stream_over);
+        }
+
+        let overviews = self.app_data.analyzer.poll_overviews();
+        if !overviews.is_empty() {
+            let app_data = &mut self.app_data;
+            app_data.overviews.extend(overviews);
+            let loaded = [
+                app_data.turntable_one.currently_loaded(),
+                app_data.turntable_two.currently_loaded(),
+            ];
+            app_data
+                .overviews
+                .retain(|(path, _)| loaded.contains(&Some(path.clone())));
+        }
+
+        let analyzed = self.app_data.analyzer.poll();
+        for (path, analysis) in analyzed.iter() {
+            let track = self.app_data.library.track_mut(path);
+            track.analysis = Some(*analysis);
+            if let (None, Some(bpm)) = (track.bpm, analysis.bpm) {
+                track.bpm = Some(bpm);
+                track.first_beat = analysis.first_beat;
+            }
+            if let (None, Some(key)) = (&track.key, analysis.key) {
+                track.key = Some(key.camelot());
+            }
+        }
+        if !analyzed.is_empty() {
+            self.app_data.library.save();
+        }
+        for deck in [TurntableFocus::One, TurntableFocus::Two] {
+            let loaded = match deck {
+                TurntableFocus::One => self.app_data.turntable_one.currently_loaded(),
+                TurntableFocus::Two => self.app_data.turntable_two.currently_loaded(),
+            };
+            if analyzed
+                .iter()
+                .any(|(path, _)| Some(path) == loaded.as_ref())
+            {
+                controller::cue_first_beat(&mut self.app_data, deck);
+            }
+        }
+        let dead_air_limit = self.app_data.settings.dead_air_minutes as f64 * 60.0;
+        let audible = self.app_data.master_deck().is_some();
+        if let Some(dead_air) = self
+            .app_data
+            .watchdog
+            .update(delta, audible, dead_air_limit)
+        {
+            self.controller
+                .handle_event(&mut self.app_data, BoothEvent::DeadAir(dead_air));
+            if let Some(sender) = &self.timecode_sender {
+                sender.send_dead_air(dead_air);
+            }
+        }
+
+        let sample = self.app_data.stats_sample();
+        let heard = self.app_data.stats.tracks().len();
+        self.app_data.stats.update(delta, &sample);
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        for path in self.app_data.stats.tracks()[heard..].iter() {
+            let track = self.app_data.library.track_mut(path);
+            track.play_count += 1;
+            track.last_played = Some(now);
+        }
+        if self.app_data.stats.tracks().len() > heard {
+            self.app_data.library.save();
+        }
+
+        session::update_snapshot(Session::capture(&self.app_data));
+        if let Some(sender) = &self.timecode_sender {
+            sender.send(&Timecode::capture(&self.app_data));
+        }
+        if let Some(server) = &mut self.overlay_server {
+            server.publish(OverlayState::capture(&self.app_data));
+        }
+
+        if self.config_watcher.has_changed() || self.app_data.mappings_outdated {
+            log::info!("Config changed, reloading mappings");
+            self.app_data.mappings_outdated = false;
+            self.app_data.keymap = KeyMap::load();
+            self.app_data.scheduler.schedule = Schedule::load();
+            for source in self.input_sources.iter_mut() {
+                source.reload_mapping(&self.app_data.settings);
+            }
+        }
+
+        for source in self.input_sources.iter_mut() {
+            source.process(delta);
+            source.update_feedback(&self.app_data);
+        }
+    }
+}
+
+/// Name a deck widget for screen readers and tooltips, as its text alone is
+/// ambiguous between the two decks
+fn accessible(response: egui::Response, label: &str, selected: Option<bool>) -> egui::Response {
+    response.widget_info(|| match selected {
+        Some(selected) => WidgetInfo::selected(WidgetType::Button, selected, label),
+        None => WidgetInfo::labeled(WidgetType::Button, label),
+    });
+    response.on_hover_text(label)
+}
+
+/// Lit when a channel went over full scale, e.g. from boosted EQ bands
+fn clip_indicator(ui: &mut egui::Ui, limiter: &ChannelLimiter) {
+    let color = match limiter.is_clipping() {
+        true => ui.visuals().error_fg_color,
+        false => ui.visuals().weak_text_color(),
+    };
+    let help = match limiter.is_enabled() {
+        true => "Channel limited to avoid clipping",
+        false => "Channel clipping",
+    };
+    ui.colored_label(color, "CLIP").on_hover_text(help);
+}
+
+/// Play state of a deck, shown on its START-STOP button
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DeckState {
+    Empty,
+    Paused,
+    /// paused with the channel cued, i.e. being prepared in the headphones
+    Cueing,
+    Playing,
+}
+
+impl DeckState {
+    fn of(turntable: &Turntable, cued: bool) -> Self {
+        match (turntable.currently_loaded(), turntable.is_playing(), cued) {
+            (None, _, _) => DeckState::Empty,
+            (Some(_), true, _) => DeckState::Playing,
+            (Some(_), false, true) => DeckState::Cueing,
+            (Some(_), false, false) => DeckState::Paused,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            DeckState::Empty => "START-STOP",
+            DeckState::Paused => "PAUSED",
+            DeckState::Cueing => "CUEING",
+            DeckState::Playing => "PLAYING",
+        }
+    }
+}
+
+/// START-STOP button lit while playing and flashing while cueing
+fn start_stop_button(ui: &mut egui::Ui, state: DeckState, lit_fill: Color32) -> egui::Response {
+    let flash_on = ui.input(|i| i.time).fract() < 0.5;
+    let button = egui::Button::new(state.label());
+
+    ui.add(match state {
+        DeckState::Playing => button.fill(lit_fill),
+        DeckState::Cueing if flash_on => button.fill(lit_fill),
+        _ => button,
+    })
+}
+
+/// Name of the loaded track in its accent color, dimmed when the channel is
+/// not heard on the master
+fn deck_header(turntable: &Turntable, audible: bool, color: Option<Color32>) -> Label {
+    let name = match turntable.currently_loaded() {
+        Some(path) => path.split('/').next_back().unwrap().to_string(),
+        None => "No Track Loaded".to_string(),
+    };
+
+    Label::new(match (audible, color) {
+        (true, Some(color)) => RichText::new(name).color(color),
+        (true, None) => RichText::new(name),
+        (false, _) => RichText::new(name).weak(),
+    })
+}
+
+/// Key of the track loaded in a deck, detected or set by hand, shown after
+/// its name to mix harmonically
+fn track_key(turntable: &Turntable, library: &Library) -> Option<String> {
+    library.track(&turntable.currently_loaded()?)?.key.clone()
+}
+
+/// Accent color set in the library for the track loaded in a deck
+fn track_color(turntable: &Turntable, library: &Library) -> Option<Color32> {
+    let [r, g, b] = library.track(&turntable.currently_loaded()?)?.color?;
+    Some(Color32::from_rgb(r, g, b))
+}
+
+/// Button picking the accent color of the loaded track
+fn track_color_ui(
+    ui: &mut egui::Ui,
+    deck: TurntableFocus,
+    color: Option<Color32>,
+    app_data: &mut AppData,
+    controller: &mut Controller,
+) {
+    let mut rgb = color.map_or([128; 3], |color| [color.r(), color.g(), color.b()]);
+
+    let loaded = match deck {
+        TurntableFocus::One => app_data.turntable_one.currently_loaded().is_some(),
+        TurntableFocus::Two => app_data.turntable_two.currently_loaded().is_some(),
+    };
+
+    let response = ui.add_enabled_ui(loaded, |ui| {
+        egui::color_picker::color_edit_button_srgb(ui, &mut rgb)
+    });
+    if accessible(response.inner, "Track color", None).changed() {
+        controller.handle_event(app_data, BoothEvent::TrackColorSet(deck, rgb));
+    }
+}
+
+/// Prepared tracks, the first one being loaded by the prepare targets
+fn prepare_list_ui(ui: &mut egui::Ui, app_data: &mut AppData, controller: &mut Controller) {
+    let mut removed = None;
+
+    for (index, file_path) in app_data.prepare.clone().iter().enumerate() {
+        ui.horizontal(|ui| {
+            if ui.small_button("x").on_hover_text("Remove").clicked() {
+                removed = Some(index);
+            }
+            for (label, deck) in [("1", TurntableFocus::One), ("2", TurntableFocus::Two)] {
+                if ui
+                    .small_button(label)
+                    .on_hover_text(format!("Load on deck {}", label))
+                    .clicked()
+                {
+                    // loading takes the first track, so move this one up
+                    let file_path = app_data.prepare.remove(index);
+                    app_data.prepare.insert(0, file_path);
+                    controller.handle_event(app_data, BoothEvent::PrepareLoad(deck));
+                }
+            }
+            ui.label(file_path.split('/').next_back().unwrap_or(file_path));
+        });
+    }
+
+    if let Some(index) = removed {
+        app_data.prepare.remove(index);
+    }
+}
+
+/// Energy found by the analysis of the track at `path`
+fn track_energy(library: &Library, path: &str) -> Option<f64> {
+    Some(library.track(path)?.analysis?.energy)
+}
+
+/// Shade the silences and the low energy intro and outro of a track on its
+/// progress bar
+fn paint_analysis(ui: &egui::Ui, rect: egui::Rect, analysis: &TrackAnalysis, duration: f64) {
+    let x = |time: f64| rect.left() + rect.width() * (time / duration).clamp(0.0, 1.0) as f32;
+    let shade = |from: f64, to: f64, alpha: u8| {
+        let region = egui::Rect::from_x_y_ranges(x(from)..=x(to), rect.y_range());
+        ui.painter()
+            .rect_filled(region, 0.0, Color32::from_black_alpha(alpha));
+    };
+
+    shade(analysis.sound_start, analysis.intro_end, 60);
+    shade(analysis.outro_start, analysis.sound_end, 60);
+    shade(0.0, analysis.sound_start, 140);
+    shade(analysis.sound_end, duration, 140);
+}
+
+/// Levels of the whole track of a deck with the playhead on top, seeking
+/// where clicked
+fn overview_ui(
+    ui: &mut egui::Ui,
+    deck: TurntableFocus,
+    app_data: &mut AppData,
+    controller: &mut Controller,
+) {
+    let Some(overview) = app_data.deck_overview(deck) else {
+        return;
+    };
+    let turntable = match deck {
+        TurntableFocus::One => &app_data.turntable_one,
+        TurntableFocus::Two => &app_data.turntable_two,
+    };
+
+    let (rect, response) = ui.allocate_exact_size(
+        egui::vec2(ui.available_width(), OVERVIEW_HEIGHT),
+        egui::Sense::click(),
+    );
+    let painter = ui.painter_at(rect);
+    let color = track_color(turntable, &app_data.library).unwrap_or(ui.visuals().selection.bg_fill);
+    if let Some(path) = turntable.currently_loaded() {
+        let width = (rect.width() * ui.ctx().pixels_per_point()).round() as u32;
+        painter.add(egui_wgpu::Callback::new_paint_callback(
+            rect,
+            OverviewCallback::new(
+                deck,
+                path,
+                width,
+                [color.gamma_multiply(0.5), color],
+                overview.levels().clone(),
+            ),
+        ));
+    }
+
+    if let Some(track) = turntable
+        .currently_loaded()
+        .and_then(|path| app_data.library.track(&path))
+    {
+        paint_bars(
+            &painter,
+            rect,
+            track,
+            overview.duration,
+            ui.visuals().weak_text_color(),
+        );
+    }
+
+    // on the duration of the decoded frames under the levels, which may
+    // differ slightly from the duration measured for a streamed track
+    if let (Some(position), true) = (turntable.display_position(), overview.duration > 0.0) {
+        let x = rect.left() + rect.width() * (position / overview.duration).clamp(0.0, 1.0) as f32;
+        let stroke = egui::Stroke::new(2.0, ui.visuals().strong_text_color());
+        painter.vline(x, rect.y_range(), stroke);
+    }
+
+    if let Some(pointer) = response.interact_pointer_pos() {
+        let percent = ((pointer.x - rect.left()) / rect.width()).clamp(0.0, 1.0) as f64;
+        let event = match deck {
+            TurntableFocus::One => BoothEvent::SeekOne(percent),
+            TurntableFocus::Two => BoothEvent::SeekTwo(percent),
+        };
+        controller.handle_event(app_data, event);
+    }
+}
+
+/// Mark the bars of the beat grid of a track on its overview, the first of
+/// every 2, 4 or more bars on long tracks
+fn paint_bars(
+    painter: &egui::Painter,
+    rect: egui::Rect,
+    track: &TrackInfo,
+    duration: f64,
+    color: Color32,
+) {
+    let bars: Vec<f64> = track
+        .beats(0.0, duration)
+        .into_iter()
+        .filter_map(|(position, downbeat)| downbeat.then_some(position))
+        .collect();
+    let spacing = match bars.as_slice() {
+        [first, second, ..] => rect.width() * ((second - first) / duration) as f32,
+        _ => return,
+    };
+    let mut step = 1;
+    while spacing * (step as f32) < MIN_BAR_SPACING {
+        step *= 2;
+    }
+
+    let stroke = egui::Stroke::new(1.0, color.gamma_multiply(0.5));
+    for bar in bars.into_iter().step_by(step) {
+        let x = rect.left() + rect.width() * (bar / duration) as f32;
+        painter.vline(x, rect.y_range(), stroke);
+    }
+}
+
+/// Mark the memory points of a track on its progress bar
+fn paint_memory_points(ui: &egui::Ui, rect: egui::Rect, points: &[f64], duration: f64) {
+    let stroke = egui::Stroke::new(2.0, ui.visuals().warn_fg_color);
+
+    for point in points {
+        let x = rect.left() + rect.width() * (point / duration) as f32;
+        ui.painter().vline(x, rect.y_range(), stroke);
+    }
+}
+
+/// Buttons adding memory points to a deck and moving between them
+fn memory_points_ui(
+    ui: &mut egui::Ui,
+    deck: TurntableFocus,
+    app_data: &mut AppData,
+    controller: &mut Controller,
+) {
+    ui.horizontal(|ui| {
+        if accessible(ui.button("<MEM"), "Previous memory point", None).clicked() {
+            controller.handle_event(app_data, BoothEvent::MemoryPointPrevious(deck));
+        }
+        if accessible(ui.button("MEM+"), "Add a memory point", None).clicked() {
+            controller.handle_event(app_data, BoothEvent::MemoryPointAdd(deck));
+        }
+        if accessible(ui.button("MEM>"), "Next memory point", None).clicked() {
+            controller.handle_event(app_data, BoothEvent::MemoryPointNext(deck));
+        }
+    });
+}
+
+/// Shade the loop playing on a deck on its progress bar
+fn paint_loop(ui: &egui::Ui, rect: egui::Rect, (start, end): (f64, f64), duration: f64) {
+    let x = |time: f64| rect.left() + rect.width() * (time / duration).clamp(0.0, 1.0) as f32;
+    ui.painter().rect_filled(
+        egui::Rect::from_x_y_ranges(x(start)..=x(end), rect.y_range()),
+        0.0,
+        ui.visuals().selection.bg_fill.gamma_multiply(0.4),
+    );
+}
+
+/// Mute buttons and levels of the stems of the track of a deck, when it has
+/// some
+fn stems_ui(
+    ui: &mut egui::Ui,
+    deck: TurntableFocus,
+    app_data: &mut AppData,
+    controller: &mut Controller,
+) {
+    let turntable = match deck {
+        TurntableFocus::One => &app_data.turntable_one,
+        TurntableFocus::Two => &app_data.turntable_two,
+    };
+    if !turntable.has_stems() {
+        return;
+    }
+
+    let stems: Vec<(Stem, bool, f64)> = Stem::ALL
+        .into_iter()
+        .filter(|stem| turntable.has_stem(*stem))
+        .map(|stem| {
+            let stems = turntable.stems();
+            (stem, !stems.is_muted(stem), stems.level(stem))
+        })
+        .collect();
+    ui.horizontal(|ui| {
+        for (stem, heard, mut level) in stems {
+            ui.vertical(|ui| {
+                let button = egui::Button::new(stem.label()).fill(match heard {
+                    true => ui.visuals().selection.bg_fill,
+                    false => ui.visuals().widgets.inactive.weak_bg_fill,
+                });
+                if accessible(ui.add(button), "Mute stem", Some(heard)).clicked() {
+                    controller.handle_event(app_data, BoothEvent::ToggleStem(deck, stem));
+                }
+                if ui
+                    .add(egui::Slider::new(&mut level, 0.0..=1.0).show_value(false))
+                    .changed()
+                {
+                    controller
+                        .handle_event(app_data, BoothEvent::StemLevelChanged(deck, stem, level));
+                }
+            });
+        }
+    });
+}
+
+/// Layer of a deck: loading the browser selection, playing it on the beat of
+/// the deck, looping and level, and the BPM of the layer file the tempo is
+/// matched from
+fn layer_ui(
+    ui: &mut egui::Ui,
+    deck: TurntableFocus,
+    app_data: &mut AppData,
+    controller: &mut Controller,
+) {
+    ui.horizontal(|ui| {
+        if accessible(ui.button("LAYER"), "Load the selection as layer", None).clicked() {
+            controller.handle_event(app_data, BoothEvent::LayerLoad(deck));
+        }
+
+        let layer = match deck {
+            TurntableFocus::One => &mut app_data.layer_one,
+            TurntableFocus::Two => &mut app_data.layer_two,
+        };
+        let Some(path) = layer.currently_loaded() else {
+            return;
+        };
+        let playing = layer.is_playing();
+        let mut looping = layer.is_looping();
+        if ui.checkbox(&mut looping, "Loop").changed() {
+            layer.set_looping(looping);
+        }
+        let mut level = layer.level();
+
+        let name = Path::new(&path)
+            .file_stem()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let button = egui::Button::new(name).fill(match playing {
+            true => ui.visuals().selection.bg_fill,
+            false => ui.visuals().widgets.inactive.weak_bg_fill,
+        });
+        if accessible(ui.add(button), "Play layer", Some(playing)).clicked() {
+            controller.handle_event(app_data, BoothEvent::ToggleLayer(deck));
+        }
+        if ui
+            .add(egui::Slider::new(&mut level, 0.0..=1.0).show_value(false))
+            .changed()
+        {
+            controller.handle_event(app_data, BoothEvent::LayerLevelChanged(deck, level));
+        }
+
+        let mut bpm = app_data
+            .library
+            .track(&path)
+            .and_then(|track| track.bpm)
+            .unwrap_or(0.0);
+        let response = ui.add(
+            egui::DragValue::new(&mut bpm)
+                .clamp_range(0.0..=MAX_BPM)
+                .speed(0.1)
+                .custom_formatter(|bpm, _| match bpm {
+                    0.0 => "Set layer BPM".to_string(),
+                    bpm => format!("{:.1} BPM", bpm),
+                }),
+        );
+        if response.changed() {
+            app_data.library.track_mut(&path).bpm = (bpm > 0.0).then_some(bpm);
+        }
+        if response.drag_stopped() || response.lost_focus() {
+            app_data.library.save();
+        }
+    });
+}
+
+/// Manual loop buttons of a deck, then the pads of the loops saved with its
+/// track. Right click a pad to rename or delete it
+fn loops_ui(
+    ui: &mut egui::Ui,
+    deck: TurntableFocus,
+    app_data: &mut AppData,
+    controller: &mut Controller,
+) {
+    let marked = match deck {
+        TurntableFocus::One => app_data.turntable_one.loop_in(),
+        TurntableFocus::Two => app_data.turntable_two.loop_in(),
+    }
+    .is_some();
+    ui.horizontal(|ui| {
+        let button = egui::Button::new("IN").fill(match marked {
+            true => ui.visuals().selection.bg_fill,
+            false => ui.visuals().widgets.inactive.weak_bg_fill,
+        });
+        if accessible(ui.add(button), "Loop in", Some(marked)).clicked() {
+            controller.handle_event(app_data, BoothEvent::LoopIn(deck));
+        }
+        if accessible(ui.button("OUT"), "Loop out", None).clicked() {
+            controller.handle_event(app_data, BoothEvent::LoopOut(deck));
+        }
+        if accessible(ui.button("EXIT"), "Exit loop", None).clicked() {
+            controller.handle_event(app_data, BoothEvent::LoopExit(deck));
+        }
+        if accessible(ui.button("SAVE"), "Save loop", None).clicked() {
+            controller.handle_event(app_data, BoothEvent::LoopSave(deck));
+        }
+        if accessible(ui.button("PHRASE"), "4-bar loop at the next phrase", None).clicked() {
+            controller.handle_event(app_data, BoothEvent::PhraseLoop(deck));
+        }
+    });
+    ui.horizontal(|ui| {
+        if accessible(ui.button("<<"), "Move the loop backward", None).clicked() {
+            controller.handle_event(app_data, BoothEvent::LoopMoveBackward(deck));
+        }
+        if accessible(ui.button("/2"), "Halve the loop", None).clicked() {
+            controller.handle_event(app_data, BoothEvent::LoopHalve(deck));
+        }
+        if accessible(ui.button("x2"), "Double the loop", None).clicked() {
+            controller.handle_event(app_data, BoothEvent::LoopDouble(deck));
+        }
+        if accessible(ui.button(">>"), "Move the loop forward", None).clicked() {
+            controller.handle_event(app_data, BoothEvent::LoopMoveForward(deck));
+        }
+    });
+
+    let turntable = match deck {
+        TurntableFocus::One => &app_data.turntable_one,
+        TurntableFocus::Two => &app_data.turntable_two,
+    };
+    let active = turntable.loop_region();
+    let Some(path) = turntable.currently_loaded() else {
+        return;
+    };
+    let Some(saved_loops) = app_data
+        .library
+        .track(&path)
+        .map(|track| track.saved_loops.clone())
+        .filter(|saved_loops| !saved_loops.is_empty())
+    else {
+        return;
+    };
+
+    ui.horizontal_wrapped(|ui| {
+        for (pad, saved) in saved_loops.into_iter().enumerate() {
+            let playing = active == Some((saved.start, saved.end));
+            let button = egui::Button::new(&saved.name).fill(match playing {
+                true => ui.visuals().selection.bg_fill,
+                false => ui.visuals().widgets.inactive.weak_bg_fill,
+            });
+            let response = accessible(
+                ui.add(button)
+                    .on_hover_text(format!("{:.2}s long", saved.length())),
+                &saved.name,
+                Some(playing),
+            );
+            if response.clicked() {
+                controller.handle_event(app_data, BoothEvent::SavedLoop(deck, pad));
+            }
+
+            response.context_menu(|ui| {
+                let mut name = saved.name.clone();
+                let rename = ui.text_edit_singleline(&mut name);
+                if rename.changed() {
+                    if let Some(saved) = app_data.library.track_mut(&path).saved_loops.get_mut(pad)
+                    {
+                        saved.name = name;
+                    }
+                }
+                if rename.lost_focus() {
+                    app_data.library.save();
+                }
+                if ui.button("Delete").clicked() {
+                    let track = app_data.library.track_mut(&path);
+                    if pad < track.saved_loops.len() {
+                        track.saved_loops.remove(pad);
+                    }
+                    app_data.library.save();
+                    ui.close_menu();
+                }
+            });
+        }
+    });
+}
+
+/// Master tempo and where it comes from, set by hand by typing or tapping
+/// it. The tempo of the decks is hidden while training to beatmatch by ear
+fn master_tempo_ui(ui: &mut egui::Ui, app_data: &mut AppData, controller: &mut Controller) {
+    let tempo = &mut app_data.master_tempo;
+    let mut source = tempo.source();
+    egui::ComboBox::from_id_source("tempo_source")
+        .selected_text(format!("Tempo: {}", source.label()))
+        .show_ui(ui, |ui| {
+            for option in TempoSource::ALL {
+                ui.selectable_value(&mut source, option, option.label());
+            }
+        });
+    if source != tempo.source() {
+        tempo.set_source(source);
+    }
+
+    match app_data.trainer.is_active() && source != TempoSource::Manual {
+        true => {
+            ui.label("--- BPM");
+        }
+        false => {
+            let mut bpm = tempo.bpm();
+            if accessible(
+                ui.add(
+                    egui::DragValue::new(&mut bpm)
+                        .clamp_range(MIN_BPM..=MAX_BPM)
+                        .speed(0.1)
+                        .suffix(" BPM"),
+                ),
+                "Master tempo",
+                None,
+            )
+            .changed()
+            {
+                tempo.set_bpm(bpm);
+            }
+        }
+    }
+
+    if accessible(ui.button("TAP"), "Tap the master tempo", None).clicked() {
+        controller.handle_event(app_data, BoothEvent::TapTempo);
+    }
+}
+
+/// Noise and tone generator, its sound, level and the bus it plays on
+fn generator_ui(
+    ui: &mut egui::Ui,
+    app_data: &mut AppData,
+    controller: &mut Controller,
+    enabled_fill: Color32,
+) {
+    ui.horizontal(|ui| {
+        let enabled = app_data.mixer.generator().is_enabled();
+        if accessible(
+            ui.add(egui::Button::new("GEN").fill(match enabled {
+                true => enabled_fill,
+                false => ui.visuals().widgets.inactive.weak_bg_fill,
+            })),
+            "Noise and tone generator",
+            Some(enabled),
+        )
+        .clicked()
+        {
+            controller.handle_event(app_data, BoothEvent::ToggleGenerator);
+        }
+
+        let generator = app_data.mixer.generator();
+        let mut waveform = generator.waveform();
+        egui::ComboBox::from_id_source("generator_waveform")
+            .selected_text(format!("{:?}", waveform))
+            .show_ui(ui, |ui| {
+                for option in Waveform::ALL {
+                    ui.selectable_value(&mut waveform, option, format!("{:?}", option));
+                }
+            });
+        if waveform != generator.waveform() {
+            generator.set_waveform(waveform);
+        }
+
+        let mut bus = generator.bus();
+        egui::ComboBox::from_id_source("generator_bus")
+            .selected_text(format!("To {:?}", bus))
+            .show_ui(ui, |ui| {
+                for option in GeneratorBus::ALL {
+                    ui.selectable_value(&mut bus, option, format!("{:?}", option));
+                }
+            });
+        if bus != generator.bus() {
+            generator.set_bus(bus);
+        }
+    });
+
+    let generator = app_data.mixer.generator();
+    if generator.waveform() == Waveform::Sine {
+        let mut frequency = generator.frequency();
+        if ui
+            .add(
+                egui::Slider::new(&mut frequency, MIN_FREQUENCY..=MAX_FREQUENCY)
+                    .logarithmic(true)
+                    .suffix(" Hz"),
+            )
+            .changed()
+        {
+            generator.set_frequency(frequency);
+        }
+    }
+
+    let mut level = generator.level();
+    if ui
+        .add(egui::Slider::new(&mut level, 0.0..=1.0).text("Level"))
+        .changed()
+    {
+        generator.set_level(level);
+    }
+}
+
+/// Practice click heard on the cue, at a tempo set by hand or following
+/// the master tempo
+fn metronome_ui(
+    ui: &mut egui::Ui,
+    app_data: &mut AppData,
+    controller: &mut Controller,
+    enabled_fill: Color32,
+) {
+    ui.horizontal(|ui| {
+        let muted = app_data.mixer.is_master_muted();
+        if accessible(
+            ui.add(egui::Button::new("MUTE").fill(match muted {
+                true => enabled_fill,
+                false => ui.visuals().widgets.inactive.weak_bg_fill,
+            })),
+            "Mute master",
+            Some(muted),
+        )
+        .clicked()
+        {
+            controller.handle_event(app_data, BoothEvent::ToggleMuteMaster);
+        }
+
+        let ducked = app_data.mixer.is_ducked();
+        if accessible(
+            ui.add(egui::Button::new("DUCK").fill(match ducked {
+                true => enabled_fill,
+                false => ui.visuals().widgets.inactive.weak_bg_fill,
+            })),
+            "Duck master",
+            Some(ducked),
+        )
+        .clicked()
+        {
+            controller.handle_event(app_data, BoothEvent::DuckChanged(!ducked));
+        }
+
+        let enabled = app_data.mixer.is_metronome_enabled();
+        if accessible(
+            ui.add(egui::Button::new("CLICK").fill(match enabled {
+                true => enabled_fill,
+                false => ui.visuals().widgets.inactive.weak_bg_fill,
+            })),
+            "Metronome on the cue",
+            Some(enabled),
+        )
+        .clicked()
+        {
+            controller.handle_event(app_data, BoothEvent::ToggleMetronome);
+        }
+
+        let training = app_data.trainer.is_active();
+        let metronome = app_data.mixer.metronome();
+        let mut follow_tempo = metronome.follows_tempo();
+        ui.checkbox(&mut follow_tempo, "Follow master tempo");
+        metronome.set_follow_tempo(follow_tempo);
+
+        if !training {
+            let mut bpm = metronome.bpm();
+            ui.add_enabled(
+                !follow_tempo,
+                egui::DragValue::new(&mut bpm)
+                    .clamp_range(MIN_BPM..=MAX_BPM)
+                    .speed(0.1)
+                    .suffix(" BPM"),
+            );
+            metronome.set_bpm(bpm);
+        }
+
+        let mut volume = metronome.volume();
+        ui.add(egui::Slider::new(&mut volume, 0.0..=1.0).text("Volume"));
+        metronome.set_volume(volume);
+    });
+}
+
+/// BPM and beat grid of the loaded track, remembered in the library, and the
+/// resulting tempo of the deck. Hidden while training to beatmatch by ear
+fn track_bpm_ui(ui: &mut egui::Ui, deck: TurntableFocus, app_data: &mut AppData) {
+    let turntable = match deck {
+        TurntableFocus::One => &app_data.turntable_one,
+        TurntableFocus::Two => &app_data.turntable_two,
+    };
+    let (Some(path), false) = (turntable.currently_loaded(), app_data.trainer.is_active()) else {
+        return;
+    };
+    let position = turntable.display_position();
+
+    ui.horizontal(|ui| {
+        let track_bpm = app_data.library.track(&path).and_then(|track| track.bpm);
+        let mut bpm = track_bpm.unwrap_or(0.0);
+        let response = ui.add(
+            egui::DragValue::new(&mut bpm)
+                .clamp_range(0.0..=MAX_BPM)
+                .speed(0.1)
+                .custom_formatter(|bpm, _| match bpm {
+                    0.0 => "Set BPM".to_string(),
+                    bpm => format!("{:.1} BPM", bpm),
+                }),
+        );
+        if response.drag_stopped() || response.lost_focus() {
+            app_data.library.track_mut(&path).bpm = (bpm > 0.0).then_some(bpm);
+            app_data.library.save();
+        } else if response.changed() {
+            app_data.library.track_mut(&path).bpm = (bpm > 0.0).then_some(bpm);
+        }
+
+        if accessible(ui.small_button("GRID"), "Align the beat grid here", None).clicked() {
+            if let Some(position) = position {
+                app_data.library.track_mut(&path).first_beat = position;
+                app_data.library.save();
+            }
+        }
+        if track_bpm.is_some() {
+            let mut edited = false;
+            if accessible(ui.small_button("<"), "Move the beat grid earlier", None).clicked() {
+                app_data.library.track_mut(&path).nudge_grid(-GRID_NUDGE);
+                edited = true;
+            }
+            if accessible(ui.small_button(">"), "Move the beat grid later", None).clicked() {
+                app_data.library.track_mut(&path).nudge_grid(GRID_NUDGE);
+                edited = true;
+            }
+            if accessible(
+                ui.small_button("BAR"),
+                "Move the downbeat to the next beat",
+                None,
+            )
+            .clicked()
+            {
+                app_data.library.track_mut(&path).shift_downbeat(1);
+                edited = true;
+            }
+            if edited {
+                app_data.library.save();
+            }
+        }
+        if accessible(
+            ui.small_button("TAP"),
+            "Tap the beats to correct the beat grid",
+            None,
+        )
+        .clicked()
+        {
+            if let Some(position) = position {
+                let taps = app_data.grid_taps.tap(deck, position).to_vec();
+                app_data.library.track_mut(&path).tap_grid(&taps);
+                app_data.library.save();
+            }
+        }
+        if let Some(beats) = app_data.deck_beats(deck) {
+            let beats = beats.floor() as i64;
+            let bar_length = BEATS_PER_BAR as i64;
+            ui.label(format!(
+                "{}.{}",
+                beats.div_euclid(bar_length) + 1,
+                beats.rem_euclid(bar_length) + 1
+            ))
+            .on_hover_text("Bar and beat on the beat grid");
+        }
+
+        if let Some(tempo) = app_data.deck_bpm(deck) {
+            ui.label(format!("{:.1} BPM at pitch", tempo));
+        }
+
+        let track = app_data.library.track(&path);
+        let mut key = track
+            .and_then(|track| track.key.clone())
+            .unwrap_or_default();
+        let mut genre = track
+            .and_then(|track| track.genre.clone())
+            .unwrap_or_default();
+        let mut rating = track.map_or(0, |track| track.rating);
+        let key_response = ui.add(
+            egui::TextEdit::singleline(&mut key)
+                .hint_text("Key")
+                .desired_width(32.0),
+        );
+        let genre_response = ui.add(
+            egui::TextEdit::singleline(&mut genre)
+                .hint_text("Genre")
+                .desired_width(80.0),
+        );
+        let rating_response = ui.add(
+            egui::DragValue::new(&mut rating)
+                .clamp_range(0..=5)
+                .custom_formatter(|rating, _| match rating as usize {
+                    0 => "Rate".to_string(),
+                    stars => "★".repeat(stars),
+                }),
+        );
+        if key_response.changed() || genre_response.changed() || rating_response.changed() {
+            let track = app_data.library.track_mut(&path);
+            track.key = Some(key.trim().to_uppercase()).filter(|key| !key.is_empty());
+            track.genre = Some(genre.trim().to_string()).filter(|genre| !genre.is_empty());
+            track.rating = rating;
+        }
+        if key_response.lost_focus()
+            || genre_response.lost_focus()
+            || rating_response.drag_stopped()
+            || rating_response.lost_focus()
+        {
+            app_data.library.save();
+        }
+    });
+}
+
+/// Spectrogram of a track of the library with its memory points and the
+/// position of the decks playing it. Clicking adds a memory point
+fn spectrogram_window(ctx: &egui::Context, app_data: &mut AppData) {
+    let spectrograms = app_data.analyzer.poll_spectrograms();
+    let Some(view) = &mut app_data.spectrogram else {
+        return;
+    };
+    for (path, spectrogram) in spectrograms.iter() {
+        if *path == view.path {
+            view.set(ctx, spectrogram);
+        }
+    }
+
+    let mut open = true;
+    let name = view.path.rsplit('/').next().unwrap_or_default().to_string();
+    egui::Window::new("Spectrogram")
+        .open(&mut open)
+        .default_width(800.0)
+        .show(ctx, |ui| {
+            ui.label(&name);
+            let Some((texture, duration)) = view.texture() else {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Computing");
+                });
+                return;
+            };
+
+            let size = egui::vec2(ui.available_width(), SPECTROGRAM_HEIGHT);
+            let response = ui.add(
+                Image::new((texture.id(), size))
+                    .fit_to_exact_size(size)
+                    .sense(egui::Sense::click()),
+            );
+            let rect = response.rect;
+            let time_at =
+                |x: f32| ((x - rect.left()) / rect.width()).clamp(0.0, 1.0) as f64 * duration;
+
+            if let Some(track) = app_data.library.track(&view.path) {
+                paint_memory_points(ui, rect, &track.memory_points, *duration);
+            }
+            for turntable in [&app_data.turntable_one, &app_data.turntable_two] {
+                let position = turntable
+                    .display_position()
+                    .filter(|_| turntable.currently_loaded().as_deref() == Some(&view.path));
+                if let Some(position) = position {
+                    let x = rect.left() + rect.width() * (position / duration) as f32;
+                    ui.painter()
+                        .vline(x, rect.y_range(), egui::Stroke::new(1.0, Color32::WHITE));
+                }
+            }
+
+            if let Some(pointer) = response.hover_pos() {
+                ui.painter().vline(
+                    pointer.x,
+                    rect.y_range(),
+                    ui.visuals().widgets.hovered.fg_stroke,
+                );
+                response.clone().on_hover_text_at_pointer(format!(
+                    "{}, click to add a memory point",
+                    to_min_sec_millis_str(time_at(pointer.x))
+                ));
+            }
+            if let Some(pointer) = response
+                .interact_pointer_pos()
+                .filter(|_| response.clicked())
+            {
+                let position = time_at(pointer.x);
+                app_data
+                    .library
+                    .track_mut(&view.path)
+                    .add_memory_point(position);
+                app_data.library.save();
+            }
+        });
+
+    if !open {
+        app_data.spectrogram = None;
+    }
+}
+
+/// Mix notes of the track loaded on `deck`, edited in place
+fn track_notes_ui(ui: &mut egui::Ui, deck: TurntableFocus, app_data: &mut AppData) {
+    let turntable = match deck {
+        TurntableFocus::One => &app_data.turntable_one,
+        TurntableFocus::Two => &app_data.turntable_two,
+    };
+    let Some(path) = turntable.currently_loaded() else {
+        return;
+    };
+
+    let mut notes = app_data
+        .library
+        .track(&path)
+        .map(|track| track.notes.clone())
+        .unwrap_or_default();
+    let response = ui.add(
+        egui::TextEdit::multiline(&mut notes)
+            .hint_text("Notes, e.g. drop at 1:32")
+            .desired_rows(2),
+    );
+    if response.changed() {
+        app_data.library.track_mut(&path).notes = notes;
+    }
+    if response.lost_focus() {
+        app_data.library.save();
+    }
+}
+
+/// Genre, tempo range and key narrowing the tracks listed in the browser
+fn browser_filter_ui(ui: &mut egui::Ui, app_data: &mut AppData) {
+    let previous = app_data.browser_filter.clone();
+    let filter = &mut app_data.browser_filter;
+    ui.heading("Filters");
+
+    ui.label("Genre");
+    egui::ComboBox::from_id_source("filter_genre")
+        .selected_text(filter.genre.as_deref().unwrap_or("Any"))
+        .show_ui(ui, |ui| {
+            ui.selectable_value(&mut filter.genre, None, "Any");
+            for genre in browser_filter::genres(&app_data.library) {
+                let label = genre.clone();
+                ui.selectable_value(&mut filter.genre, Some(genre), label);
+            }
+        });
+
+    let mut bpm_enabled = filter.bpm.is_some();
+    ui.checkbox(&mut bpm_enabled, "BPM");
+    let (mut min, mut max) = filter.bpm.unwrap_or((FILTER_MIN_BPM, FILTER_MAX_BPM));
+    ui.add_enabled_ui(bpm_enabled, |ui| {
+        ui.horizontal(|ui| {
+            ui.add(egui::DragValue::new(&mut min).clamp_range(FILTER_MIN_BPM..=max));
+            ui.label("to");
+            ui.add(egui::DragValue::new(&mut max).clamp_range(min..=FILTER_MAX_BPM));
+        });
+    });
+    filter.bpm = bpm_enabled.then_some((min, max));
+
+    ui.label("Key");
+    egui::ComboBox::from_id_source("filter_key")
+        .selected_text(filter.key.as_deref().unwrap_or("Any"))
+        .show_ui(ui, |ui| {
+            ui.selectable_value(&mut filter.key, None, "Any");
+            for number in 1..=12 {
+                for mode in ["A", "B"] {
+                    let key = format!("{}{}", number, mode);
+                    let label = key.clone();
+                    ui.selectable_value(&mut filter.key, Some(key), label);
+                }
+            }
+        });
+
+    if ui.button("Clear").clicked() {
+        *filter = BrowserFilter::default();
+    }
+    if app_data.browser_filter != previous {
+        app_data.browser_filter_outdated = true;
+    }
+}
+
+/// Marks a track already heard in this session, so that it is not played
+/// again by accident
+fn played_label(path: &str, track: Option<&TrackInfo>, stats: &SessionStats) -> Label {
+    let play_count = track.map_or(0, |track| track.play_count);
+    match stats.has_played(path) {
+        true => Label::new(RichText::new("PLAYED").strong()),
+        false if play_count > 0 => Label::new(RichText::new(format!("×{}", play_count)).weak()),
+        false => Label::new(""),
+    }
+}
+
+fn play_history(track: Option<&TrackInfo>) -> String {
+    let Some((play_count, last_played)) =
+        track.and_then(|track| Some((track.play_count, track.last_played?)))
+    else {
+        return "Never played".to_string();
+    };
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let days = now.saturating_sub(last_played) / (24 * 60 * 60);
+    format!(
+        "Played in {} sessions, last {}",
+        play_count,
+        match days {
+            0 => "today".to_string(),
+            1 => "yesterday".to_string(),
+            days => format!("{} days ago", days),
+        }
+    )
+}
+
+/// Library tracks mixing well into the track on the master, by tempo and key.
+/// Hidden while training to beatmatch by ear
+fn suggestions_ui(ui: &mut egui::Ui, app_data: &mut AppData, controller: &mut Controller) {
+    if app_data.trainer.is_active() {
+        ui.label("Hidden while training");
+        return;
+    }
+    let Some(deck) = app_data.master_deck() else {
+        ui.label("Play a track to get suggestions");
+        return;
+    };
+    let turntable = match deck {
+        TurntableFocus::One => &app_data.turntable_one,
+        TurntableFocus::Two => &app_data.turntable_two,
+    };
+    let (Some(path), Some(bpm)) = (turntable.currently_loaded(), app_data.deck_bpm(deck)) else {
+        ui.label("Set the BPM of the playing track to get suggestions");
+        return;
+    };
+
+    let key = app_data
+        .library
+        .track(&path)
+        .and_then(|track| track.key.clone());
+    let loaded: Vec<String> = [&app_data.turntable_one, &app_data.turntable_two]
+        .iter()
+        .filter_map(|turntable| turntable.currently_loaded())
+        .collect();
+    let suggestions: Vec<(String, TrackInfo)> =
+        suggestions::suggest(&app_data.library, bpm, key.as_deref(), &loaded)
+            .into_iter()
+            .take(MAX_SUGGESTIONS)
+            .map(|(path, track)| (path.clone(), track.clone()))
+            .collect();
+    if suggestions.is_empty() {
+        ui.label("No compatible track in the library");
+        return;
+    }
+
+    egui::Grid::new("Suggestions").striped(true).show(ui, |ui| {
+        for (path, track) in suggestions.iter() {
+            ui.label(path.rsplit('/').next().unwrap_or(path));
+            ui.label(format!("{:.1}", track.bpm.unwrap_or_default()));
+            ui.label(track.key.as_deref().unwrap_or("-"));
+            ui.label("★".repeat(track.rating as usize));
+            ui.add(played_label(path, Some(track), &app_data.stats))
+                .on_hover_text(play_history(Some(track)));
+            for (label, deck) in [("ONE", TurntableFocus::One), ("TWO", TurntableFocus::Two)] {
+                if ui.small_button(label).clicked() {
+                    let focus = app_data.turntable_focus;
+                    app_data.turntable_focus = deck;
+                    controller.handle_event(app_data, BoothEvent::TrackLoading(Path::new(path)));
+                    app_data.turntable_focus = focus;
+                }
+            }
+            ui.end_row();
+        }
+    });
+}
+
+/// Start of a beatmatching round and the scores
+fn trainer_ui(ui: &mut egui::Ui, app_data: &mut AppData, controller: &mut Controller) {
+    ui.horizontal(|ui| {
+        let active = app_data.trainer.is_active();
+        if accessible(
+            ui.button(match active {
+                true => "GIVE UP",
+                false => "TRAIN",
+            }),
+            "Beatmatch a deck set to a random pitch",
+            Some(active),
+        )
+        .clicked()
+        {
+            controller.handle_event(app_data, BoothEvent::ToggleTrainer);
+        }
+
+        match app_data.trainer.deck() {
+            Some(TurntableFocus::One) => ui.label("Match deck ONE to deck TWO by ear"),
+            Some(TurntableFocus::Two) => ui.label("Match deck TWO to deck ONE by ear"),
+            None => ui.label("Both tracks need a BPM and a beat grid"),
+        };
+    });
+
+    let score = |score: Option<trainer::Score>| match score {
+        Some(score) => format!(
+            "{} points in {:.1} s ({:.2} BPM, {:.0} % of a beat off)",
+            score.points(),
+            score.seconds,
+            score.tempo_error,
+            score.phase_error * 100.0
+        ),
+        None => "-".to_string(),
+    };
+    ui.label(format!("Last: {}", score(app_data.trainer.last_score())));
+    ui.label(format!("Best: {}", score(app_data.trainer.best_score())));
+}
+
+/// Recording and replay of the knob and fader moves
+fn automation_ui(
+    ui: &mut egui::Ui,
+    app_data: &mut AppData,
+    controller: &mut Controller,
+    enabled_fill: Color32,
+) {
+    let automation = &app_data.automation;
+    let (recording, replaying) = (automation.is_recording(), automation.is_replaying());
+    let disabled_fill = ui.visuals().widgets.inactive.weak_bg_fill;
+    let fill = |on: bool| match on {
+        true => enabled_fill,
+        false => disabled_fill,
+    };
+
+    ui.horizontal(|ui| {
+        if accessible(
+            ui.add(egui::Button::new("REC MOVES").fill(fill(recording))),
+            "Record knob and fader moves",
+            Some(recording),
+        )
+        .clicked()
+        {
+            controller.handle_event(app_data, BoothEvent::ToggleAutomationRecording);
+        }
+        if accessible(
+            ui.add(egui::Button::new("REPLAY").fill(fill(replaying))),
+            "Replay the recorded moves",
+            Some(replaying),
+        )
+        .clicked()
+        {
+            controller.handle_event(app_data, BoothEvent::ToggleAutomationReplay);
+        }
+        if accessible(
+            ui.button(format!("LAST {:.0} s", TRANSITION_SECONDS)),
+            "Replay the last transition",
+            None,
+        )
+        .clicked()
+        {
+            controller.handle_event(app_data, BoothEvent::ReplayLastTransition);
+        }
+        ui.label(format!("{} moves", app_data.automation.take().len()));
+    });
+}
+
+/// Figures of the running session and a tempo curve, with an export of
+/// the summary
+fn schedule_ui(ui: &mut egui::Ui, app_data: &mut AppData) {
+    let status = app_data.scheduler.status();
+    let slots = &app_data.scheduler.schedule.slots;
+    if slots.is_empty() {
+        ui.label("No schedule.toml in the config directory");
+        return;
+    }
+
+    egui::Grid::new("Schedule").striped(true).show(ui, |ui| {
+        for (index, slot) in slots.iter().enumerate() {
+            let on_air = status.is_some_and(|(slot, _, _)| slot == index);
+            ui.label(format!("{}-{}", slot.start, slot.end));
+            ui.label(
+                slot.playlist
+                    .file_name()
+                    .unwrap_or(slot.playlist.as_os_str())
+                    .to_string_lossy(),
+            );
+            ui.label(match on_air {
+                true => "ON AIR",
+                false => "",
+            });
+            ui.end_row();
+        }
+    });
+
+    if let Some((_, track, tracks)) = status {
+        ui.horizontal(|ui| {
+            ui.label(format!("Track {}/{}", track, tracks));
+            if ui.button("Stop").clicked() {
+                scheduler::stop(app_data);
+            }
+        });
+    }
+}
+
+fn stats_ui(ui: &mut egui::Ui, app_data: &mut AppData) {
+    let stats = &app_data.stats;
+    ui.label(format!(
+        "Set length: {}",
+        to_hour_min_sec_str(stats.set_length())
+    ));
+    ui.label(format!("Tracks played: {}", stats.tracks().len()));
+    ui.label(match stats.average_bpm() {
+        Some(bpm) => format!("Average tempo: {:.1} BPM", bpm),
+        None => "Average tempo: -".to_string(),
+    });
+    ui.label(format!(
+        "Mixing both decks: {}",
+        to_hour_min_sec_str(stats.mix_seconds())
+    ));
+    ui.label(format!("Fader travel: {:.1} throws", stats.fader_travel()));
+    tempo_timeline_ui(ui, stats);
+
+    if ui.button("Export summary").clicked() {
+        let summary = stats.summary();
+        ui.output_mut(|output| output.copied_text = summary);
+        match stats.save() {
+            Ok(path) => log::info!("Saved session summary '{}'", path.display()),
+            Err(e) => app_data
+                .toasts
+                .error(format!("Cannot save session summary: {}", e)),
+        }
+    }
+}
+
+/// Tempo of the master deck over the session, from its slowest to its
+/// fastest sample, with a mark where each track was first heard. Hovering
+/// shows the tempo at that time
+fn tempo_timeline_ui(ui: &mut egui::Ui, stats: &SessionStats) {
+    let (samples, length) = (stats.bpm_samples(), stats.set_length());
+    let (rect, response) =
+        ui.allocate_exact_size(egui::vec2(ui.available_width(), 60.0), egui::Sense::hover());
+    ui.painter()
+        .rect_filled(rect, 0.0, ui.visuals().extreme_bg_color);
+    if samples.len() < 2 || length <= 0.0 {
+        return;
+    }
+
+    let x = |time: f64| rect.left() + rect.width() * (time / length) as f32;
+    let track_stroke = egui::Stroke::new(1.0, ui.visuals().weak_text_color());
+    for start in stats.track_starts() {
+        ui.painter().vline(x(*start), rect.y_range(), track_stroke);
+    }
+
+    let (min, max) = samples
+        .iter()
+        .fold((f64::MAX, f64::MIN), |(min, max), (_, bpm)| {
+            (min.min(*bpm), max.max(*bpm))
+        });
+    let range = (max - min).max(1.0);
+    let points = samples
+        .iter()
+        .map(|(time, bpm)| {
+            egui::pos2(
+                x(*time),
+                rect.bottom() - rect.height() * ((bpm - min) / range) as f32,
+            )
+        })
+        .collect();
+    ui.painter().add(egui::Shape::line(
+        points,
+        egui::Stroke::new(1.5, ui.visuals().selection.bg_fill),
+    ));
+
+    let font = egui::FontId::proportional(10.0);
+    let text_color = ui.visuals().text_color();
+    ui.painter().text(
+        rect.left_top(),
+        egui::Align2::LEFT_TOP,
+        format!("{:.0}", max),
+        font.clone(),
+        text_color,
+    );
+    ui.painter().text(
+        rect.left_bottom(),
+        egui::Align2::LEFT_BOTTOM,
+        format!("{:.0}", min),
+        font,
+        text_color,
+    );
+
+    if let Some(pointer) = response.hover_pos() {
+        let time = length * ((pointer.x - rect.left()) / rect.width()) as f64;
+        let index = samples.partition_point(|(sample_time, _)| *sample_time <= time);
+        let (sample_time, bpm) = samples[index.saturating_sub(1)];
+        response.on_hover_text(format!(
+            "{} {:.1} BPM",
+            to_hour_min_sec_str(sample_time),
+            bpm
+        ));
+    }
+}
+
+/// Snapshot buttons and the position blending the mixer between them
+fn morph_ui(ui: &mut egui::Ui, app_data: &mut AppData, controller: &mut Controller) {
+    ui.horizontal(|ui| {
+        if accessible(ui.button("STORE A"), "Store the mixer as snapshot A", None).clicked() {
+            controller.handle_event(app_data, BoothEvent::MorphStoreA);
+        }
+        if accessible(ui.button("<A"), "Glide to snapshot A", None).clicked() {
+            controller.handle_event(app_data, BoothEvent::MorphToA);
+        }
+
+        let ready = app_data.morph.has_a() && app_data.morph.has_b();
+        let mut position = app_data.morph.position();
+        let slider = ui.add_enabled(ready, egui::Slider::new(&mut position, 0.0..=1.0));
+        // only moves of the slider apply, so that the mixer can still be
+        // changed by hand between snapshots
+        if slider.changed() {
+            controller.handle_event(app_data, BoothEvent::MorphChanged(position));
+        }
+
+        if accessible(ui.button("B>"), "Glide to snapshot B", None).clicked() {
+            controller.handle_event(app_data, BoothEvent::MorphToB);
+        }
+        if accessible(ui.button("STORE B"), "Store the mixer as snapshot B", None).clicked() {
+            controller.handle_event(app_data, BoothEvent::MorphStoreB);
+        }
+    });
+}
+
+/// Trim, gain, mode, pitch and choke group of a sampler pad
+fn sampler_slot_ui(ui: &mut egui::Ui, sampler: &mut Sampler, pad: usize) {
+    let slot = &sampler.slots()[pad];
+    let Some(duration) = slot.duration() else {
+        ui.label("Empty pad");
+        return;
+    };
+    ui.label(match slot.file().and_then(|file| file.file_name()) {
+        Some(name) => name.to_string_lossy().to_string(),
+        None => "Capture, saved with the bank".to_string(),
+    });
+
+    let mut settings = slot.settings().clone();
+    egui::Grid::new("sampler_slot").show(ui, |ui| {
+        ui.label("Start");
+        ui.add(
+            egui::Slider::new(&mut settings.start, 0.0..=duration)
+                .suffix(" s")
+                .max_decimals(3),
+        );
+        ui.end_row();
+
+        ui.label("End");
+        let mut end = settings.end.unwrap_or(duration);
+        if ui
+            .add(
+                egui::Slider::new(&mut end, 0.0..=duration)
+                    .suffix(" s")
+                    .max_decimals(3),
+            )
+            .changed()
+        {
+            settings.end = Some(end);
+        }
+        ui.end_row();
+
+        ui.label("Gain");
+        ui.add(egui::Slider::new(&mut settings.gain, -24.0..=12.0).suffix(" dB"));
+        ui.end_row();
+
+        ui.label("Pitch");
+        ui.add(egui::Slider::new(&mut settings.pitch, -12.0..=12.0).suffix(" st"));
+        ui.end_row();
+
+        ui.label("Mode");
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut settings.mode, PlayMode::OneShot, "One-shot");
+            ui.radio_value(&mut settings.mode, PlayMode::Loop, "Loop");
+        });
+        ui.end_row();
+
+        ui.label("Choke group");
+        let mut group = settings.choke_group.unwrap_or(0);
+        if ui
+            .add(egui::DragValue::new(&mut group).clamp_range(0..=8))
+            .on_hover_text("0 for none")
+            .changed()
+        {
+            settings.choke_group = (group > 0).then_some(group);
+        }
+        ui.end_row();
+    });
+
+    if settings != *sampler.slots()[pad].settings() {
+        sampler.set_slot_settings(pad, settings);
+    }
+}
+
+/// Insert slots of a channel in processing order, reordered by dragging
+/// their handle or with the arrows, and the chain presets
+fn insert_chain_ui(
+    ui: &mut egui::Ui,
+    label: &str,
+    slots: &mut [PluginSlot],
+    plugins: &[PluginInfo],
+    preset_name: &str,
+) {
+    ui.horizontal(|ui| {
+        ui.strong(label);
+        if ui
+            .add_enabled(!preset_name.is_empty(), egui::Button::new("Save chain"))
+            .clicked()
+        {
+            match plugin_slot::save_chain(slots, preset_name) {
+                Ok(()) => (),
+                Err(e) => log::error!("Cannot save chain '{}': {}", preset_name, e),
+            }
+        }
+        egui::ComboBox::from_id_source((label, "chains"))
+            .selected_text("Load chain")
+            .show_ui(ui, |ui| {
+                for chain in plugin_slot::chains() {
+                    if ui.selectable_label(false, &chain).clicked() {
+                        match plugin_slot::load_chain(slots, &chain) {
+                            Ok(()) => (),
+                            Err(e) => log::error!("Cannot load chain '{}': {}", chain, e),
+                        }
+                    }
+                }
+            });
+    });
+
+    let mut moved = None;
+    for index in 0..slots.len() {
+        let row = ui.horizontal(|ui| {
+            let handle_id = egui::Id::new((label, "insert", index));
+            ui.dnd_drag_source(handle_id, index, |ui| {
+                ui.label(format!("☰ {}", index + 1));
+            });
+            if accessible(
+                ui.add_enabled(index > 0, egui::Button::new("▲").small()),
+                "Move the insert effect earlier",
+                None,
+            )
+            .clicked()
+            {
+                moved = Some((index, index - 1));
+            }
+            if accessible(
+                ui.add_enabled(index + 1 < slots.len(), egui::Button::new("▼").small()),
+                "Move the insert effect later",
+                None,
+            )
+            .clicked()
+            {
+                moved = Some((index, index + 1));
+            }
+        });
+        if let Some(from) = row.response.dnd_release_payload::<usize>() {
+            moved = Some((*from, index));
+        }
+
+        ui.indent((label, index), |ui| {
+            plugin_slot_ui(
+                ui,
+                &format!("{} insert {}", label, index + 1),
+                &mut slots[index],
+                plugins,
+                preset_name,
+            );
+        });
+    }
+
+    if let Some((from, to)) = moved {
+        if let Err(e) = plugin_slot::move_slot(slots, from, to) {
+            log::error!("Cannot move insert effect: {}", e);
+        }
+    }
+}
+
+/// Plugin selection, parameters and presets of a channel insert slot
+fn plugin_slot_ui(
+    ui: &mut egui::Ui,
+    id: &str,
+    slot: &mut PluginSlot,
+    plugins: &[PluginInfo],
+    preset_name: &str,
+) {
+    let current = slot.plugin().map(|plugin| plugin.info().clone());
+
+    ui.horizontal(|ui| {
+        let mut selected = current.clone();
+        egui::ComboBox::from_id_source(id)
+            .selected_text(current.as_ref().map_or("Empty", |info| info.name.as_str()))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut selected, None, "Empty");
+                for info in plugins {
+                    let text = format!("{} ({})", info.name, info.vendor);
+                    ui.selectable_value(&mut selected, Some(info.clone()), text);
+                }
+            });
+
+        if selected != current {
+            match selected {
+                Some(info) => match slot.load(&info) {
+                    Ok(()) => (),
+                    Err(e) => log::error!("Cannot load plugin '{}': {}", info.name, e),
+                },
+                None => slot.unload(),
+            }
+        }
+
+        let mut bypass = slot.is_bypassed();
+        if ui.checkbox(&mut bypass, "Bypass").changed() {
+            slot.set_bypass(bypass);
+        }
+    });
+
+    let Some(plugin) = slot.plugin() else {
+        return;
+    };
+
+    let mut changes = Vec::new();
+    egui::Grid::new(id).show(ui, |ui| {
+        for param in plugin.params() {
+            let Some(mut value) = plugin.param_value(param.id) else {
+                continue;
+            };
+            ui.label(&param.name);
+            if ui
+                .add(egui::Slider::new(&mut value, param.min..=param.max))
+                .changed()
+            {
+                changes.push((param.id, value));
+            }
+            ui.end_row();
+        }
+    });
+    for (param_id, value) in changes {
+        slot.set_param(param_id, value);
+    }
+
+    ui.horizontal(|ui| {
+        if ui
+            .add_enabled(!preset_name.is_empty(), egui::Button::new("Save preset"))
+            .clicked()
+        {
+            match slot.save_preset(preset_name) {
+                Ok(()) => (),
+                Err(e) => log::error!("Cannot save preset '{}': {}", preset_name, e),
+            }
+        }
+        for preset in slot.presets() {
+            if ui.button(&preset).clicked() {
+                match slot.load_preset(&preset) {
+                    Ok(()) => (),
+                    Err(e) => log::error!("Cannot load preset '{}': {}", preset, e),
+                }
+            }
+        }
+    });
+}
+
+/// Scratch a deck by holding and dragging its cover, as with a platter
+fn touch_scratch(
+    platter: &egui::Response,
+    deck: TurntableFocus,
+    app_data: &mut AppData,
+    controller: &mut Controller,
+) {
+    if platter.drag_started() {
+        controller.handle_event(app_data, BoothEvent::FocusChanged(deck));
+        controller.handle_event(app_data, BoothEvent::ScratchBegin);
+    }
+
+    if platter.dragged() {
+        let delta = platter.drag_delta().y as f64;
+        let force = -delta.signum() * delta.abs().powf(0.65);
+        controller.handle_event(app_data, BoothEvent::ForceApplied(force));
+    }
+
+    if platter.drag_stopped() {
+        controller.handle_event(app_data, BoothEvent::ScratchEnd);
+    }
+}
+
+/// Momentary transform button of a channel, cutting it while held
+fn transform_button(
+    ui: &mut egui::Ui,
+    deck: TurntableFocus,
+    enabled_fill: Color32,
+    app_data: &mut AppData,
+    controller: &mut Controller,
+) {
+    let held = app_data.transform.is_held(deck);
+    let label = match deck {
+        TurntableFocus::One => "Transform channel one (cut while held)",
+        TurntableFocus::Two => "Transform channel two (cut while held)",
+    };
+    let button = accessible(
+        ui.add(
+            egui::Button::new("X")
+                .sense(egui::Sense::drag())
+                .fill(match held {
+                    true => enabled_fill,
+                    false => ui.visuals().widgets.inactive.weak_bg_fill,
+                }),
+        ),
+        label,
+        Some(held),
+    );
+
+    if button.drag_started() {
+        controller.handle_event(app_data, BoothEvent::Transform(deck, true));
+    }
+    if button.drag_stopped() {
+        controller.handle_event(app_data, BoothEvent::Transform(deck, false));
+    }
+}
+
+/// Load the first dropped track on the focused deck and put the others in
+/// the prepare list. A dropped image becomes the cover of the focused deck
+fn load_dropped_files(
+    files: &[egui::DroppedFile],
+    app_data: &mut AppData,
+    controller: &mut Controller,
+) {
+    let mut loaded = false;
+    for file in files {
+        let path = match dropped_file_path(file) {
+            Ok(path) => path,
+            Err(e) => {
+                log::error!("Cannot open dropped file '{}': {}", file.name, e);
+                app_data
+                    .toasts
+                    .error(format!("Cannot open dropped file {}: {}", file.name, e));
+                continue;
+            }
+        };
+
+        if backup::is_backup_path(&path) {
+            restore_backup(&path, app_data);
+        } else if is_image_path(&path) {
+            controller.handle_event(
+                app_data,
+                BoothEvent::TrackCoverSet(app_data.turntable_focus, &path),
+            );
+        } else if !loaded {
+            controller.handle_event(app_data, BoothEvent::TrackLoading(&path));
+            loaded = true;
+        } else {
+            let path = path.to_string_lossy().to_string();
+            if !app_data.prepare.contains(&path) {
+                app_data.prepare.push(path);
+            }
+        }
+    }
+}
+
+/// Replace the library, settings and mappings with those of a backup
+fn restore_backup(path: &Path, app_data: &mut AppData) {
+    match backup::restore(path) {
+        Ok(count) => {
+            app_data.library = Library::load();
+            app_data.settings = Settings::load();
+            app_data.mappings_outdated = true;
+            app_data.toasts.info(format!(
+                "Restored {} files, the window layout applies on restart",
+                count
+            ));
+        }
+        Err(e) => {
+            log::error!("Cannot restore backup '{}': {}", path.display(), e);
+            app_data
+                .toasts
+                .error(format!("Cannot restore backup: {}", e));
+        }
+    }
+}
+
+/// Path of a dropped file. Drag sources giving the content rather than a
+/// path have it written to the `dropped` folder of the data directory
+fn dropped_file_path(file: &egui::DroppedFile) -> Result<PathBuf, String> {
+    if let Some(path) = &file.path {
+        return Ok(path.clone());
+    }
+
+    let (Some(bytes), Some(name)) = (&file.bytes, Path::new(&file.name).file_name()) else {
+        return Err("no path given by the drag source".to_string());
+    };
+    let dir = data_dir().join("dropped");
+    let path = dir.join(name);
+    fs::create_dir_all(&dir)
+        .and_then(|_| fs::write(&path, bytes))
+        .map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
+fn window_level(settings: &Settings) -> WindowLevel {
+    match settings.always_on_top {
+        true => WindowLevel::AlwaysOnTop,
+        false => WindowLevel::Normal,
+    }
+}
+
+fn run_ui(
+    ctx: &egui::Context,
+    window: &Arc<Window>,
+    app_data: &mut AppData,
+    controller: &mut Controller,
+) {
+    theme::apply(ctx, &app_data.settings);
+    let theme_visuals = ctx.style().visuals.clone();
+    let enabled_fill = theme::enabled_fill(&app_data.settings);
+    let focus_fill = theme::focus_fill(&app_data.settings);
+
+    let dropped_files = ctx.input(|i| i.raw.dropped_files.clone());
+    if !dropped_files.is_empty() {
+        load_dropped_files(&dropped_files, app_data, controller);
+    }
+
+    if let Some(recovery) = app_data.pending_recovery.clone() {
+        egui::Window::new("Restore previous session?")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label("The app did not close properly last time.");
+                for (name, deck) in [("ONE", &recovery.deck_one), ("TWO", &recovery.deck_two)] {
+                    ui.label(format!(
+                        "{}: {} at {}",
+                        name,
+                        deck.path
+                            .as_deref()
+                            .and_then(|path| path.split('/').next_back())
+                            .unwrap_or("No Track Loaded"),
+                        to_min_sec_millis_str(deck.position.unwrap_or_default())
+                    ));
+                }
+
+                ui.horizontal(|ui| {
+                    if ui.button("Restore").clicked() {
+                        recovery.restore(app_data, controller);
+                        app_data.pending_recovery = None;
+                        session::discard_recovery();
+                    }
+                    if ui.button("Discard").clicked() {
+                        app_data.pending_recovery = None;
+                        session::discard_recovery();
+                    }
+                });
+            });
+    }
+
+    egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Top Panel");
+            if ui.button("Tutorial").clicked() {
+                app_data.tutorial.restart();
+            }
+            if ui.button("Settings").clicked() {
+                app_data.show_settings = !app_data.show_settings;
+            }
+            if app_data.settings.background_mode && ui.button("Quit").clicked() {
+                app_data.quit_requested = true;
+            }
+            if ui.button("Plugins").clicked() {
+                app_data.show_plugins = !app_data.show_plugins;
+            }
+
+            ui.separator();
+            master_tempo_ui(ui, app_data, controller);
+
+            ui.separator();
+
+            let recording = app_data.mixer.recorder().elapsed();
+            if accessible(
+                ui.add(egui::Button::new("REC").fill(match recording {
+                    Some(_) => egui::Color32::from_rgb(220, 40, 40),
+                    None => theme_visuals.widgets.inactive.weak_bg_fill,
+                })),
+                "Start/stop recording",
+                Some(recording.is_some()),
+            )
+            .clicked()
+            {
+                controller.handle_event(app_data, BoothEvent::ToggleRecording);
+            }
+            if let Some(elapsed) = recording {
+                ui.label(to_min_sec_millis_str(elapsed.as_secs_f64()));
+            }
+
+            if app_data.watchdog.is_dead_air() {
+                ui.separator();
+                ui.label(
+                    RichText::new("DEAD AIR")
+                        .strong()
+                        .color(theme_visuals.error_fg_color),
+                )
+                .on_hover_text(format!(
+                    "No deck heard on the master for {} min",
+                    app_data.settings.dead_air_minutes
+                ));
+            }
+        });
+    });
+
+    if app_data.show_browser_filters {
+        egui::SidePanel::left("browser_filters").show(ctx, |ui| {
+            browser_filter_ui(ui, app_data);
+        });
+    }
+
+    egui::CentralPanel::default().show(ctx, |ui| {
+        let mut cue_mix = app_data.mixer.get_cue_mix_value();
+        let cue_mix_slider = ui.add(egui::Slider::new(&mut cue_mix, 0.0..=1.0).text("Cue Mix"));
+        if cue_mix_slider.double_clicked() {
+            cue_mix = CENTER_CUE_MIX;
+        }
+        app_data
+            .tutorial
+            .set_area(TutorialArea::Mixer, cue_mix_slider.rect);
+        controller.handle_event(app_data, BoothEvent::CueMixChanged(cue_mix));
+        let mut crossfader = app_data.mixer.get_crossfader();
+        let crossfader_slider = ui.add(
+            egui::Slider::new(&mut crossfader, 0.0..=1.0)
+                .show_value(false)
+                .text("Crossfader"),
+        );
+        if crossfader_slider.double_clicked() {
+            crossfader = CENTER_CROSSFADER;
+        }
+        controller.handle_event(app_data, BoothEvent::CrossfaderChanged(crossfader));
+        if accessible(
+            ui.button("Reset mixer"),
+            "Flatten EQs and center cue mix",
+            None,
+        )
+        .clicked()
+        {
+            controller.handle_event(app_data, BoothEvent::ResetMixer);
+        }
+
+        egui::CollapsingHeader::new("Morph").show(ui, |ui| {
+            morph_ui(ui, app_data, controller);
+        });
+
+        egui::CollapsingHeader::new("Metronome").show(ui, |ui| {
+            metronome_ui(ui, app_data, controller, enabled_fill);
+        });
+
+        egui::CollapsingHeader::new("Generator").show(ui, |ui| {
+            generator_ui(ui, app_data, controller, enabled_fill);
+        });
+
+        egui::CollapsingHeader::new("Beatmatch trainer").show(ui, |ui| {
+            trainer_ui(ui, app_data, controller);
+        });
+
+        egui::CollapsingHeader::new("Automation").show(ui, |ui| {
+            automation_ui(ui, app_data, controller, enabled_fill);
+        });
+
+        egui::CollapsingHeader::new("Suggestions").show(ui, |ui| {
+            suggestions_ui(ui, app_data, controller);
+        });
+
+        egui::CollapsingHeader::new("Session stats").show(ui, |ui| {
+            stats_ui(ui, app_data);
+        });
+
+        egui::CollapsingHeader::new("Schedule").show(ui, |ui| {
+            schedule_ui(ui, app_data);
+        });
+
+        ui.horizontal(|ui| {
+            let mic = app_data.mixer.is_mic_enabled();
+            if accessible(
+                ui.add(egui::Button::new("MIC").fill(if mic {
+                    enabled_fill
+                } else {
+                    theme_visuals.widgets.inactive.weak_bg_fill
+                })),
+                "Talkover mic",
+                Some(mic),
+            )
+            .clicked()
+            {
+                controller.handle_event(app_data, BoothEvent::ToggleMic);
+            }
+
+            let mut mic_record_level = app_data.mixer.get_mic_record_level();
+            ui.add(egui::Slider::new(&mut mic_record_level, 0.0..=1.0).text("Mic in recording"));
+            controller.handle_event(
+                app_data,
+                BoothEvent::MicRecordLevelChanged(mic_record_level),
+            );
+        });
+
+        egui::CollapsingHeader::new("Loudness (recording)").show(ui, |ui| {
+            let loudness = app_data.mixer.loudness();
+            let lufs = |value: Option<f64>| match value {
+                Some(value) if value.is_finite() => format!("{:.1} LUFS", value),
+                _ => "-inf".to_string(),
+            };
+
+            let (momentary, short_term, integrated) = (
+                lufs(loudness.momentary()),
+                lufs(loudness.short_term()),
+                lufs(loudness.integrated()),
+            );
+            ui.horizontal(|ui| {
+                ui.label(format!("M: {}", momentary));
+                ui.separator();
+                ui.label(format!("S: {}", short_term));
+                ui.separator();
+                ui.label(format!("I: {}", integrated));
+                if ui.button("Reset").clicked() {
+                    controller.handle_event(app_data, BoothEvent::ResetLoudness);
+                }
+            });
+        });
+
+        egui::CollapsingHeader::new("Sampler").show(ui, |ui| {
+            ui.horizontal(|ui| {
+                let sampler = app_data.mixer.sampler();
+                let mut source = sampler.source();
+                egui::ComboBox::from_id_source("sampler_source")
+                    .selected_text(format!("{:?}", source))
+                    .show_ui(ui, |ui| {
+                        for option in RecordSource::ALL {
+                            ui.selectable_value(&mut source, option, format!("{:?}", option));
+                        }
+                    });
+                if source != sampler.source() {
+                    sampler.set_source(source);
+                }
+
+                let mut follow_tempo = sampler.follows_tempo();
+                if ui
+                    .checkbox(&mut follow_tempo, "Follow master tempo")
+                    .changed()
+                {
+                    sampler.set_follow_tempo(follow_tempo);
+                }
+                let mut bpm = sampler.bpm();
+                if ui
+                    .add_enabled(
+                        !follow_tempo,
+                        egui::DragValue::new(&mut bpm)
+                            .clamp_range(MIN_BPM..=MAX_BPM)
+                            .speed(0.1)
+                            .suffix(" BPM"),
+                    )
+                    .changed()
+                {
+                    sampler.set_bpm(bpm);
+                }
+
+                let mut beats = sampler.capture_beats();
+                if ui
+                    .add(
+                        egui::DragValue::new(&mut beats)
+                            .clamp_range(1..=MAX_CAPTURE_BEATS)
+                            .suffix(" beats"),
+                    )
+                    .changed()
+                {
+                    sampler.set_capture_beats(beats);
+                }
+
+                let mut quantize = sampler.quantize();
+                egui::ComboBox::from_id_source("sampler_quantize")
+                    .selected_text(format!("Quantize: {:?}", quantize))
+                    .show_ui(ui, |ui| {
+                        for option in Quantize::ALL {
+                            ui.selectable_value(&mut quantize, option, format!("{:?}", option));
+                        }
+                    });
+                sampler.set_quantize(quantize);
+            });
+
+            // an empty pad captures, a full one toggles its loop
+            ui.horizontal(|ui| {
+                for pad in 0..PAD_COUNT {
+                    let slot = &app_data.mixer.sampler().slots()[pad];
+                    let text = match slot.duration() {
+                        Some(duration) => format!("{}\n{:.1}s", pad + 1, duration),
+                        None => format!("{}\n-", pad + 1),
+                    };
+                    let response = ui
+                        .add(egui::Button::new(text).selected(slot.is_playing()))
+                        .on_hover_text(match slot.is_empty() {
+                            true => "Capture the last beats",
+                            false => "Start/stop the loop, right click to clear",
+                        });
+
+                    if response.clicked() {
+                        controller.handle_event(app_data, BoothEvent::SamplerPad(pad));
+                    }
+                    if response.secondary_clicked() {
+                        app_data.mixer.sampler().clear(pad);
+                    }
+                }
+            });
 
-        match event {
-            WindowEvent::CloseRequested => {
-                println!("The close button was pressed; stopping");
-                elwt.exit();
+            egui::ComboBox::from_label("Edit pad")
+                .selected_text((app_data.sampler_edit_pad + 1).to_string())
+                .show_ui(ui, |ui| {
+                    for pad in 0..PAD_COUNT {
+                        ui.selectable_value(
+                            &mut app_data.sampler_edit_pad,
+                            pad,
+                            (pad + 1).to_string(),
+                        );
+                    }
+                });
+            if let Some(file_path) = app_data.file_navigator.selected_file() {
+                if ui.button("Load selected track into pad").clicked() {
+                    match app_data
+                        .mixer
+                        .sampler()
+                        .load_file(app_data.sampler_edit_pad, Path::new(&file_path))
+                    {
+                        Ok(()) => (),
+                        Err(e) => log::error!("Cannot load '{}' into pad: {}", file_path, e),
+                    }
+                }
             }
+            sampler_slot_ui(ui, app_data.mixer.sampler(), app_data.sampler_edit_pad);
 
-            WindowEvent::RedrawRequested => {
-                self.app_data.frame_counter += 1;
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut app_data.sampler_bank_name)
+                        .hint_text("Bank name")
+                        .desired_width(120.0),
+                );
+                let name = app_data.sampler_bank_name.trim().to_string();
+                if ui
+                    .add_enabled(!name.is_empty(), egui::Button::new("Save bank"))
+                    .clicked()
+                {
+                    match app_data.mixer.sampler().save_bank(&name) {
+                        Ok(()) => log::info!("Saved sampler bank '{}'", name),
+                        Err(e) => log::error!("Cannot save sampler bank '{}': {}", name, e),
+                    }
+                }
+            });
+            ui.horizontal_wrapped(|ui| {
+                for bank in Sampler::banks() {
+                    if ui.button(&bank).clicked() {
+                        match app_data.mixer.sampler().load_saved_bank(&bank) {
+                            Ok(()) => (),
+                            Err(e) => log::error!("Cannot load sampler bank '{}': {}", bank, e),
+                        }
+                    }
+                }
+            });
+        });
 
-                let mut encoder = self.encoder();
-                let surface_texture = self.surface_texture();
-                let surface_view = self.surface_view(&surface_texture);
+        egui::CollapsingHeader::new(format!("Prepare ({})", app_data.prepare.len())).show(
+            ui,
+            |ui| {
+                if ui.button("Add selected track").clicked() {
+                    controller.handle_event(app_data, BoothEvent::PrepareAdd);
+                }
+                prepare_list_ui(ui, app_data, controller);
+            },
+        );
 
-                self.gui.draw(
-                    &self.gpu.device,
-                    &self.gpu.queue,
-                    &mut encoder,
-                    &self.window,
-                    &surface_view,
-                    self.screen_descriptor(),
-                    |ctx| run_ui(ctx, &self.window, &mut self.app_data, &mut self.controller),
-                );
+        ui.separator();
 
-                self.gpu.queue.submit(Some(encoder.finish()));
-                surface_texture.present();
+        ui.horizontal(|ui| {
+            ui.label("Find");
+            let find = ui.add(
+                egui::TextEdit::singleline(&mut app_data.browser_find)
+                    .hint_text("Type the start of a name"),
+            );
+            if find.changed() && !app_data.browser_find.is_empty() {
+                app_data
+                    .file_navigator
+                    .jump_to_prefix(&app_data.browser_find);
             }
-            WindowEvent::Resized(physical_size) => {
-                self.gpu.resize(physical_size);
+            if find.lost_focus() {
+                app_data.browser_find.clear();
             }
-
-            WindowEvent::ModifiersChanged(modifiers) => {
-                self.on_modifiers_key_changed(modifiers);
+            ui.checkbox(&mut app_data.browser_sort_by_energy, "Sort by energy");
+            ui.toggle_value(&mut app_data.show_browser_filters, "Filters");
+            let selected = app_data.file_navigator.selected_file();
+            let spectrogram = ui.add_enabled(selected.is_some(), egui::Button::new("Spectrogram"));
+            if accessible(
+                spectrogram,
+                "Show the spectrogram of the selected track",
+                None,
+            )
+            .clicked()
+            {
+                if let Some(path) = selected {
+                    app_data.analyzer.request_spectrogram(&path);
+                    app_data.spectrogram = Some(SpectrogramView::new(path));
+                }
             }
+        });
 
-            WindowEvent::KeyboardInput {
-                event:
-                    KeyEvent {
-                        physical_key,
-                        state,
-                        repeat,
-                        ..
-                    },
-                ..
-            } => {
-                self.on_key_event(physical_key, state, repeat);
+        // entries are listed again, unfiltered and by name, when entering a
+        // folder
+        let filter_active = app_data.browser_filter.is_active();
+        if app_data.browser_filter_outdated
+            || filter_active != app_data.file_navigator.is_filtered()
+        {
+            app_data.browser_filter_outdated = false;
+            match filter_active {
+                true => {
+                    let (cwd, library, filter) = (
+                        app_data.file_navigator.cwd(),
+                        &app_data.library,
+                        &app_data.browser_filter,
+                    );
+                    app_data.file_navigator.filter(|entry| {
+                        filter.matches(library.track(&format!("{}/{}", cwd, entry)))
+                    });
+                }
+                false => app_data.file_navigator.clear_filter(),
             }
-
-            _ => (),
         }
-    }
-
-    pub fn on_modifiers_key_changed(&mut self, modifiers: Modifiers) {
-        self.app_data.modifiers_key = modifiers;
-
-        match modifiers.state() {
-            ModifiersState::SUPER => self
-                .controller
-                .handle_event(&mut self.app_data, BoothEvent::ScratchBegin),
-            _ => self
-                .controller
-                .handle_event(&mut self.app_data, BoothEvent::ScratchEnd),
-        }
-
-        match modifiers.state() {
-            ModifiersState::ALT | ModifiersState::SUPER => self
-                .window
-                .set_cursor_grab(winit::window::CursorGrabMode::Locked)
-                .unwrap(),
-            _ => self
-                .window
-                .set_cursor_grab(winit::window::CursorGrabMode::None)
-                .unwrap(),
-        };
-    }
-
-    pub fn on_key_event(&mut self, physical_key: PhysicalKey, state: ElementState, repeat: bool) {
         match (
-            physical_key,
-            state,
-            repeat,
-            self.app_data.modifiers_key.state(),
+            app_data.browser_sort_by_energy,
+            app_data.file_navigator.is_sorted_by_key(),
         ) {
-            (
-                PhysicalKey::Code(KeyCode::KeyD),
-                ElementState::Pressed,
-                false,
-                ModifiersState::CONTROL,
-            ) => {
-                self.controller
-                    .handle_event(&mut self.app_data, BoothEvent::ToggleDebug);
-            }
-            (PhysicalKey::Code(KeyCode::ArrowDown), ElementState::Pressed, _, _) => {
-                self.controller
-                    .handle_event(&mut self.app_data, BoothEvent::FileNavigatorDown);
-            }
-            (PhysicalKey::Code(KeyCode::ArrowUp), ElementState::Pressed, _, _) => {
-                self.controller
-                    .handle_event(&mut self.app_data, BoothEvent::FileNavigatorUp);
-            }
-            (PhysicalKey::Code(KeyCode::ArrowRight), ElementState::Pressed, false, _) => {
-                self.controller
-                    .handle_event(&mut self.app_data, BoothEvent::FileNavigatorSelect);
-            }
-            (PhysicalKey::Code(KeyCode::ArrowLeft), ElementState::Pressed, false, _) => {
-                self.controller
-                    .handle_event(&mut self.app_data, BoothEvent::FileNavigatorBack);
-            }
-            (PhysicalKey::Code(KeyCode::KeyD), ElementState::Released, false, _) => {
-                self.controller
-                    .handle_event(&mut self.app_data, BoothEvent::ToggleStartStopOne);
-            }
-            (PhysicalKey::Code(KeyCode::KeyF), ElementState::Released, false, _) => {
-                self.controller
-                    .handle_event(&mut self.app_data, BoothEvent::ToggleStartStopTwo);
-            }
-            _ => (),
-        }
-    }
-
-    pub fn on_device_event(&mut self, event: DeviceEvent) {
-        match (event, self.app_data.modifiers_key.state()) {
-            (DeviceEvent::MouseMotion { delta }, ModifiersState::ALT | ModifiersState::SUPER) => {
-                let dir = delta.1.signum();
-                let mag = delta.1.abs().powf(0.65); // apply pow to compensate for mouse acceleration / non linearity
-
-                self.controller
-                    .handle_event(&mut self.app_data, BoothEvent::ForceApplied(-dir * mag));
+            (true, false) => {
+                let (cwd, library) = (app_data.file_navigator.cwd(), &app_data.library);
+                app_data
+                    .file_navigator
+                    .sort_by_key(|entry| track_energy(library, &format!("{}/{}", cwd, entry)));
             }
+            (false, true) => app_data.file_navigator.sort_by_name(),
             _ => (),
         }
-    }
-
-    pub fn on_resume_time_reached(&mut self, elwt: &EventLoopWindowTarget<()>) {
-        self.process(self.delta_timer.elapsed().as_secs_f64());
-        self.delta_timer = Instant::now();
-
-        elwt.set_control_flow(ControlFlow::wait_duration(Duration::from_millis(
-            (1000 as f32 / self.app_data.fps as f32) as u64,
-        )));
-        self.window.request_redraw();
-    }
-
-    pub fn on_midi_event(&mut self, message: &[u8]) {
-        // hard coded values for my controller here
-        match message {
-            [144, 1, _] => self
-                .controller
-                .handle_event(&mut self.app_data, BoothEvent::ToggleCueOne),
-            [144, 4, _] => self
-                .controller
-                .handle_event(&mut self.app_data, BoothEvent::ToggleCueTwo),
-            [144, 3, _] => self.controller.handle_event(
-                &mut self.app_data,
-                BoothEvent::FocusChanged(TurntableFocus::One),
-            ),
-            [144, 6, _] => self.controller.handle_event(
-                &mut self.app_data,
-                BoothEvent::FocusChanged(TurntableFocus::Two),
-            ),
-            [_, 18, value] => {
-                let value = remap(*value as f64, 0.0, 127.0, 0.0, 1.0);
-                self.controller
-                    .handle_event(&mut self.app_data, BoothEvent::VolumeOneChanged(value))
-            }
-            [_, 22, value] => {
-                let value = remap(*value as f64, 0.0, 127.0, 0.0, 1.0);
-                self.controller
-                    .handle_event(&mut self.app_data, BoothEvent::VolumeTwoChanged(value))
-            }
-            [_, 19, value] => {
-                let value = remap(*value as f64, 0.0, 127.0, 1.06, 0.94);
-                self.controller
-                    .handle_event(&mut self.app_data, BoothEvent::PitchOneChanged(value))
-            }
-            [_, 23, value] => {
-                let value = remap(*value as f64, 0.0, 127.0, 1.06, 0.94);
-                self.controller
-                    .handle_event(&mut self.app_data, BoothEvent::PitchTwoChanged(value))
-            }
-            [_, 17, value] => {
-                let value = remap(
-                    ((*value + 1) as f64).log10() as f64,
-                    0.0,
-                    127.0_f64.log10(),
-                    -24.0,
-                    3.0,
-                );
-                self.controller
-                    .handle_event(&mut self.app_data, BoothEvent::EqLowOneChanged(value))
-            }
-            [_, 16, value] => {
-                let value = remap(
-                    ((*value + 1) as f64).log10() as f64,
-                    0.0,
-                    127.0_f64.log10(),
-                    -24.0,
-                    3.0,
-                );
-                self.controller
-                    .handle_event(&mut self.app_data, BoothEvent::EqHighOneChanged(value))
-            }
-            [_, 21, value] => {
-                let value = remap(
-                    ((*value + 1) as f64).log10() as f64,
-                    0.0,
-                    127.0_f64.log10(),
-                    -24.0,
-                    3.0,
-                );
-                self.controller
-                    .handle_event(&mut self.app_data, BoothEvent::EqLowTwoChanged(value))
-            }
-            [_, 20, value] => {
-                let value = remap(
-                    ((*value + 1) as f64).log10() as f64,
-                    0.0,
-                    127.0_f64.log10(),
-                    -24.0,
-                    3.0,
-                );
-                self.controller
-                    .handle_event(&mut self.app_data, BoothEvent::EqHighTwoChanged(value))
-            }
-            _ => {
-                log::info!("App received unmatched midi message: {:?}", message);
-            }
-        }
-    }
-}
-
-impl Processable for App {
-    fn process(&mut self, delta: f64) {
-        self.app_data.turntable_one.process(delta);
-        self.app_data.turntable_two.process(delta);
-    }
-}
-
-fn run_ui(
-    ctx: &egui::Context,
-    window: &Arc<Window>,
-    app_data: &mut AppData,
-    controller: &mut Controller,
-) {
-    let mut theme_visuals = Visuals::light();
-    theme_visuals.extreme_bg_color = theme_visuals.widgets.inactive.weak_bg_fill;
-    ctx.set_visuals(theme_visuals.clone());
-
-    let dropped_files = ctx.input(|i| i.raw.dropped_files.clone());
-    if !dropped_files.is_empty() {
-        let path = dropped_files[0]
-            .path
-            .as_ref()
-            .expect("Cannot get file path from drag and drop");
-        controller.handle_event(app_data, BoothEvent::TrackLoad(path));
-    }
-
-    egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
-        ui.label("Top Panel");
-    });
-
-    egui::CentralPanel::default().show(ctx, |ui| {
-        let mut cue_mix = app_data.mixer.get_cue_mix_value();
-        ui.add(egui::Slider::new(&mut cue_mix, 0.0..=1.0).text("Cue Mix"));
-        controller.handle_event(app_data, BoothEvent::CueMixChanged(cue_mix));
 
-        ui.separator();
-
-        ScrollArea::vertical()
+        let browser_height = ui.available_height() * 0.3;
+        let row_height = ui.spacing().interact_size.y;
+        let mut browser = ScrollArea::vertical()
             .auto_shrink([false, false])
-            .max_height(ui.available_height() * 0.3)
-            .show(ui, |ui| {
-                if app_data.file_navigator.entries().is_empty() {
-                    ui.add(Label::new("Oops! There is nothing here..."));
-                    return;
-                };
-
-                ui.with_layout(Layout::top_down_justified(egui::Align::LEFT), |ui| {
-                    for entry in app_data.file_navigator.entries().clone().iter() {
-                        ui.add(SelectableLabel::new(
-                            app_data.file_navigator.selected() == Some(entry),
-                            entry,
-                        ));
+            .max_height(browser_height);
+        // center the selected entry when it moved
+        if let Some(index) = app_data.file_navigator.take_scroll_request() {
+            let row_pitch = row_height + ui.spacing().item_spacing.y;
+            browser = browser.vertical_scroll_offset(
+                (index as f32 * row_pitch - (browser_height - row_height) / 2.0).max(0.0),
+            );
+        }
 
-                        // ensure the selected element is visible
-                        if app_data.file_navigator.selected() == Some(entry) {
-                            ui.scroll_to_cursor(Some(egui::Align::Center));
+        // only the rows on screen are laid out, and ask for their thumbnail
+        let entry_count = app_data.file_navigator.entries().len();
+        let browser = browser.show_rows(ui, row_height, entry_count, |ui, rows| {
+            if app_data.file_navigator.entries().is_empty() {
+                ui.add(Label::new("Oops! There is nothing here..."));
+                return;
+            };
+
+            let cwd = app_data.file_navigator.cwd();
+
+            ui.with_layout(Layout::top_down_justified(egui::Align::LEFT), |ui| {
+                for entry in &app_data.file_navigator.entries()[rows] {
+                    ui.horizontal(|ui| {
+                        match app_data.thumbnails.get(ctx, &format!("{}/{}", cwd, entry)) {
+                            Some(texture) => {
+                                ui.add(
+                                    Image::new(texture)
+                                        .fit_to_exact_size(egui::vec2(row_height, row_height)),
+                                );
+                            }
+                            None => ui.add_space(row_height),
                         }
-                    }
-                });
+
+                        let path = format!("{}/{}", cwd, entry);
+                        let track = app_data.library.track(&path);
+                        ui.add_sized(
+                            [2.0 * row_height, row_height],
+                            played_label(&path, track, &app_data.stats),
+                        )
+                        .on_hover_text(play_history(track));
+
+                        let analysis = track.and_then(|track| track.analysis);
+                        let (energy, vocals) = match analysis {
+                            Some(analysis) => (
+                                format!("{:.1}", analysis.energy),
+                                if analysis.vocal { "VOX" } else { "INST" },
+                            ),
+                            None => (String::new(), ""),
+                        };
+                        let key = track
+                            .and_then(|track| track.key.as_deref())
+                            .unwrap_or_default();
+                        ui.add_sized(
+                            [2.0 * row_height, row_height],
+                            Label::new(RichText::new(energy).weak()),
+                        )
+                        .on_hover_text("Energy");
+                        ui.add_sized(
+                            [2.0 * row_height, row_height],
+                            Label::new(RichText::new(vocals).weak()),
+                        )
+                        .on_hover_text("Vocal or instrumental");
+                        ui.add_sized(
+                            [2.0 * row_height, row_height],
+                            Label::new(RichText::new(key).weak()),
+                        )
+                        .on_hover_text("Key");
+
+                        ui.with_layout(Layout::top_down_justified(egui::Align::LEFT), |ui| {
+                            ui.add(SelectableLabel::new(
+                                app_data.file_navigator.selected() == Some(entry),
+                                entry,
+                            ));
+                        });
+                    });
+                }
             });
+        });
+        app_data
+            .tutorial
+            .set_area(TutorialArea::Browser, browser.inner_rect);
 
         ui.separator();
 
         ui.columns(2, |cols| {
-            cols[0].vertical_centered_justified(|ui| {
-                ui.with_layout(Layout::top_down_justified(egui::Align::LEFT), |ui| {
-                    ui.add(match app_data.turntable_one.currently_loaded() {
-                        Some(path) => Label::new(path.split('/').last().unwrap()),
-                        None => Label::new("No Track Loaded"),
-                    })
+            let deck_one = cols[0].vertical_centered_justified(|ui| {
+                let audible = app_data.mixer.get_ch_one_volume() > 0.0;
+                let color = track_color(&app_data.turntable_one, &app_data.library);
+                ui.horizontal(|ui| {
+                    track_color_ui(ui, TurntableFocus::One, color, app_data, controller);
+                    ui.add(deck_header(&app_data.turntable_one, audible, color));
+                    if let Some(key) = track_key(&app_data.turntable_one, &app_data.library) {
+                        ui.label(RichText::new(key).strong()).on_hover_text("Key");
+                    }
+                    if let Some(path) = app_data.track_loader.loading(TurntableFocus::One) {
+                        ui.spinner()
+                            .on_hover_text(format!("Loading {}", path.display()));
+                    }
                 });
 
                 let (position, duration, position_display, duration_display) = match (
-                    app_data.turntable_one.position(),
+                    app_data.turntable_one.display_position(),
                     app_data.turntable_one.duration(),
                 ) {
                     (Some(position), Some(duration)) => (
@@ -436,8 +3185,24 @@ fn run_ui(
                 let progress_bar = ui.add(
                     egui::ProgressBar::new((position / duration) as f32)
                         .text(format!("{} / {}", position_display, duration_display))
-                        .rounding(Rounding::default()),
+                        .rounding(Rounding::default())
+                        .fill(color.unwrap_or(ui.visuals().selection.bg_fill)),
                 );
+                progress_bar
+                    .widget_info(|| WidgetInfo::slider(position / duration, "Position deck one"));
+                if let Some(region) = app_data.turntable_one.loop_region() {
+                    paint_loop(ui, progress_bar.rect, region, duration);
+                }
+                if let Some(track) = app_data
+                    .turntable_one
+                    .currently_loaded()
+                    .and_then(|path| app_data.library.track(&path))
+                {
+                    if let Some(analysis) = &track.analysis {
+                        paint_analysis(ui, progress_bar.rect, analysis, duration);
+                    }
+                    paint_memory_points(ui, progress_bar.rect, &track.memory_points, duration);
+                }
 
                 if let Some(click_position) = progress_bar
                     .interact(egui::Sense::click())
@@ -447,6 +3212,7 @@ fn run_ui(
                     let relative_percent = relative_x / progress_bar.interact_rect.width();
                     controller.handle_event(app_data, BoothEvent::SeekOne(relative_percent as f64));
                 }
+                overview_ui(ui, TurntableFocus::One, app_data, controller);
 
                 ui.horizontal(|ui| {
                     let mut ch_one = app_data.mixer.get_ch_one_volume();
@@ -461,79 +3227,185 @@ fn run_ui(
                     ui.add(
                         egui::Slider::new(&mut pitch_one, 1.08..=0.92)
                             .text("PITCH ONE")
+                            .show_value(!app_data.trainer.is_active())
                             .vertical(),
                     );
                     controller.handle_event(app_data, BoothEvent::PitchOneChanged(pitch_one));
 
                     let mut eq_low_one = app_data.mixer.get_eq_low_one_gain();
-                    ui.add(
-                        egui::Slider::new(&mut eq_low_one, -24.0..=3.0)
-                            .text("LOW ONE")
-                            .vertical(),
-                    );
+                    if ui
+                        .add(
+                            egui::Slider::new(&mut eq_low_one, -24.0..=3.0)
+                                .text("LOW ONE")
+                                .vertical(),
+                        )
+                        .double_clicked()
+                    {
+                        eq_low_one = EQ_FLAT_GAIN;
+                    }
                     controller.handle_event(app_data, BoothEvent::EqLowOneChanged(eq_low_one));
 
                     let mut eq_high_one = app_data.mixer.get_eq_high_one_gain();
-                    ui.add(
-                        egui::Slider::new(&mut eq_high_one, -24.0..=3.0)
-                            .text("HIGH ONE")
-                            .vertical(),
-                    );
+                    if ui
+                        .add(
+                            egui::Slider::new(&mut eq_high_one, -24.0..=3.0)
+                                .text("HIGH ONE")
+                                .vertical(),
+                        )
+                        .double_clicked()
+                    {
+                        eq_high_one = EQ_FLAT_GAIN;
+                    }
                     controller.handle_event(app_data, BoothEvent::EqHighOneChanged(eq_high_one));
 
+                    if accessible(ui.small_button("FLAT"), "Flatten EQ channel one", None).clicked()
+                    {
+                        controller
+                            .handle_event(app_data, BoothEvent::ResetChannel(TurntableFocus::One));
+                    }
+                    clip_indicator(ui, app_data.mixer.limiter_one());
+
                     if app_data.cover_one.create_texture(ctx) {
                         log::info!("Cover one texture created");
                     }
-                    match app_data.cover_one.texture() {
+                    let platter_sense = match app_data.settings.touch_mode {
+                        true => egui::Sense::drag(),
+                        false => egui::Sense::hover(),
+                    };
+                    let platter = match app_data.cover_one.texture() {
                         Some(texture) => ui.add(
                             Image::new((texture.id(), texture.size_vec2()))
                                 .rounding(10.0)
-                                .shrink_to_fit(),
+                                .shrink_to_fit()
+                                .sense(platter_sense),
                         ),
-                        None => ui.add(Label::new("No Cover")),
+                        None => ui.add(Label::new("No Cover").sense(platter_sense)),
                     };
+                    touch_scratch(&platter, TurntableFocus::One, app_data, controller);
                 });
 
                 let cue_one = app_data.mixer.is_cue_one_enabled();
-                if ui
-                    .add(egui::Button::new("Cue").fill(if cue_one {
-                        egui::Color32::LIGHT_BLUE
+                if accessible(
+                    ui.add(egui::Button::new("Cue").fill(if cue_one {
+                        enabled_fill
                     } else {
                         theme_visuals.widgets.inactive.weak_bg_fill
-                    }))
-                    .clicked()
+                    })),
+                    "Cue channel one",
+                    Some(cue_one),
+                )
+                .clicked()
                 {
                     controller.handle_event(app_data, BoothEvent::ToggleCueOne);
                 }
+                let cue_one_mode = app_data.mixer.get_cue_one_mode();
+                if accessible(
+                    ui.button(cue_one_mode.label()),
+                    CUE_MODE_HELP,
+                    Some(cue_one_mode == CueMode::AfterFader),
+                )
+                .clicked()
+                {
+                    app_data.mixer.set_cue_one_mode(cue_one_mode.toggled());
+                }
+                transform_button(ui, TurntableFocus::One, enabled_fill, app_data, controller);
 
-                if ui
-                    .add(
+                let input_one = app_data.mixer.is_input_one_enabled();
+                if accessible(
+                    ui.add(egui::Button::new("LINE IN").fill(if input_one {
+                        enabled_fill
+                    } else {
+                        theme_visuals.widgets.inactive.weak_bg_fill
+                    })),
+                    "Line in on channel one",
+                    Some(input_one),
+                )
+                .clicked()
+                {
+                    controller.handle_event(app_data, BoothEvent::ToggleInputOne);
+                }
+
+                if accessible(
+                    ui.add(
                         egui::Button::new("Focus ChOne").fill(match app_data.turntable_focus {
-                            TurntableFocus::One => egui::Color32::from_rgb(170, 170, 255),
+                            TurntableFocus::One => focus_fill,
                             _ => theme_visuals.widgets.inactive.weak_bg_fill,
                         }),
-                    )
-                    .clicked()
+                    ),
+                    "Focus deck one",
+                    Some(app_data.turntable_focus == TurntableFocus::One),
+                )
+                .clicked()
                 {
                     controller
                         .handle_event(app_data, BoothEvent::FocusChanged(TurntableFocus::One));
                 }
 
-                if ui.add(egui::Button::new("START-STOP")).clicked() {
+                let state =
+                    DeckState::of(&app_data.turntable_one, app_data.mixer.is_cue_one_enabled());
+                if accessible(
+                    start_stop_button(ui, state, enabled_fill),
+                    "Start/stop deck one",
+                    Some(state == DeckState::Playing),
+                )
+                .clicked()
+                {
                     controller.handle_event(app_data, BoothEvent::ToggleStartStopOne);
                 }
+                if accessible(ui.button("RETURN"), "Return to the start of deck one", None)
+                    .clicked()
+                {
+                    controller.handle_event(app_data, BoothEvent::ReturnToStartOne);
+                }
+                if accessible(
+                    ui.button("SYNC"),
+                    "Match the tempo of deck one to the other deck",
+                    None,
+                )
+                .clicked()
+                {
+                    controller.handle_event(app_data, BoothEvent::SyncOne);
+                }
+                if accessible(
+                    ui.button("TEMPO"),
+                    "Match the tempo of deck one to the other deck, without its beats",
+                    None,
+                )
+                .clicked()
+                {
+                    let focus = app_data.turntable_focus;
+                    app_data.turntable_focus = TurntableFocus::One;
+                    controller.handle_event(app_data, BoothEvent::CopyTempo);
+                    app_data.turntable_focus = focus;
+                }
+                memory_points_ui(ui, TurntableFocus::One, app_data, controller);
+                loops_ui(ui, TurntableFocus::One, app_data, controller);
+                stems_ui(ui, TurntableFocus::One, app_data, controller);
+                layer_ui(ui, TurntableFocus::One, app_data, controller);
+                track_bpm_ui(ui, TurntableFocus::One, app_data);
+                track_notes_ui(ui, TurntableFocus::One, app_data);
             });
+            app_data
+                .tutorial
+                .set_area(TutorialArea::DeckOne, deck_one.response.rect);
 
-            cols[1].vertical_centered_justified(|ui| {
-                ui.with_layout(Layout::top_down_justified(egui::Align::LEFT), |ui| {
-                    ui.add(match app_data.turntable_two.currently_loaded() {
-                        Some(path) => Label::new(path.split('/').last().unwrap()),
-                        None => Label::new("No Track Loaded"),
-                    })
+            let deck_two = cols[1].vertical_centered_justified(|ui| {
+                let audible = app_data.mixer.get_ch_two_volume() > 0.0;
+                let color = track_color(&app_data.turntable_two, &app_data.library);
+                ui.horizontal(|ui| {
+                    track_color_ui(ui, TurntableFocus::Two, color, app_data, controller);
+                    ui.add(deck_header(&app_data.turntable_two, audible, color));
+                    if let Some(key) = track_key(&app_data.turntable_two, &app_data.library) {
+                        ui.label(RichText::new(key).strong()).on_hover_text("Key");
+                    }
+                    if let Some(path) = app_data.track_loader.loading(TurntableFocus::Two) {
+                        ui.spinner()
+                            .on_hover_text(format!("Loading {}", path.display()));
+                    }
                 });
 
                 let (position, duration, position_display, duration_display) = match (
-                    app_data.turntable_two.position(),
+                    app_data.turntable_two.display_position(),
                     app_data.turntable_two.duration(),
                 ) {
                     (Some(position), Some(duration)) => (
@@ -548,8 +3420,24 @@ fn run_ui(
                 let progress_bar = ui.add(
                     egui::ProgressBar::new((position / duration) as f32)
                         .text(format!("{} / {}", position_display, duration_display))
-                        .rounding(Rounding::default()),
+                        .rounding(Rounding::default())
+                        .fill(color.unwrap_or(ui.visuals().selection.bg_fill)),
                 );
+                progress_bar
+                    .widget_info(|| WidgetInfo::slider(position / duration, "Position deck two"));
+                if let Some(region) = app_data.turntable_two.loop_region() {
+                    paint_loop(ui, progress_bar.rect, region, duration);
+                }
+                if let Some(track) = app_data
+                    .turntable_two
+                    .currently_loaded()
+                    .and_then(|path| app_data.library.track(&path))
+                {
+                    if let Some(analysis) = &track.analysis {
+                        paint_analysis(ui, progress_bar.rect, analysis, duration);
+                    }
+                    paint_memory_points(ui, progress_bar.rect, &track.memory_points, duration);
+                }
 
                 if let Some(click_position) = progress_bar
                     .interact(egui::Sense::click())
@@ -559,6 +3447,7 @@ fn run_ui(
                     let relative_percent = relative_x / progress_bar.interact_rect.width();
                     controller.handle_event(app_data, BoothEvent::SeekTwo(relative_percent as f64));
                 }
+                overview_ui(ui, TurntableFocus::Two, app_data, controller);
 
                 ui.horizontal(|ui| {
                     let mut ch_two = app_data.mixer.get_ch_two_volume();
@@ -573,68 +3462,167 @@ fn run_ui(
                     ui.add(
                         egui::Slider::new(&mut pitch_two, 1.08..=0.92)
                             .text("PITCH TWO")
+                            .show_value(!app_data.trainer.is_active())
                             .vertical(),
                     );
                     controller.handle_event(app_data, BoothEvent::PitchTwoChanged(pitch_two));
 
                     let mut eq_low_two = app_data.mixer.get_eq_low_two_gain();
-                    ui.add(
-                        egui::Slider::new(&mut eq_low_two, -24.0..=3.0)
-                            .text("LOW TWO")
-                            .vertical(),
-                    );
+                    if ui
+                        .add(
+                            egui::Slider::new(&mut eq_low_two, -24.0..=3.0)
+                                .text("LOW TWO")
+                                .vertical(),
+                        )
+                        .double_clicked()
+                    {
+                        eq_low_two = EQ_FLAT_GAIN;
+                    }
                     controller.handle_event(app_data, BoothEvent::EqLowTwoChanged(eq_low_two));
 
                     let mut eq_high_two = app_data.mixer.get_eq_high_two_gain();
-                    ui.add(
-                        egui::Slider::new(&mut eq_high_two, -24.0..=3.0)
-                            .text("HIGH TWO")
-                            .vertical(),
-                    );
+                    if ui
+                        .add(
+                            egui::Slider::new(&mut eq_high_two, -24.0..=3.0)
+                                .text("HIGH TWO")
+                                .vertical(),
+                        )
+                        .double_clicked()
+                    {
+                        eq_high_two = EQ_FLAT_GAIN;
+                    }
                     controller.handle_event(app_data, BoothEvent::EqHighTwoChanged(eq_high_two));
 
+                    if accessible(ui.small_button("FLAT"), "Flatten EQ channel two", None).clicked()
+                    {
+                        controller
+                            .handle_event(app_data, BoothEvent::ResetChannel(TurntableFocus::Two));
+                    }
+                    clip_indicator(ui, app_data.mixer.limiter_two());
+
                     if app_data.cover_two.create_texture(ctx) {
                         log::info!("Cover two texture created");
                     }
-                    match app_data.cover_two.texture() {
+                    let platter_sense = match app_data.settings.touch_mode {
+                        true => egui::Sense::drag(),
+                        false => egui::Sense::hover(),
+                    };
+                    let platter = match app_data.cover_two.texture() {
                         Some(texture) => ui.add(
                             Image::new((texture.id(), texture.size_vec2()))
                                 .rounding(10.0)
-                                .shrink_to_fit(),
+                                .shrink_to_fit()
+                                .sense(platter_sense),
                         ),
-                        None => ui.add(Label::new("No Cover")),
+                        None => ui.add(Label::new("No Cover").sense(platter_sense)),
                     };
+                    touch_scratch(&platter, TurntableFocus::Two, app_data, controller);
                 });
 
                 let cue_two = app_data.mixer.is_cue_two_enabled();
-                if ui
-                    .add(egui::Button::new("Cue").fill(if cue_two {
-                        egui::Color32::LIGHT_BLUE
+                if accessible(
+                    ui.add(egui::Button::new("Cue").fill(if cue_two {
+                        enabled_fill
                     } else {
                         theme_visuals.widgets.inactive.weak_bg_fill
-                    }))
-                    .clicked()
+                    })),
+                    "Cue channel two",
+                    Some(cue_two),
+                )
+                .clicked()
                 {
                     controller.handle_event(app_data, BoothEvent::ToggleCueTwo);
                 }
+                let cue_two_mode = app_data.mixer.get_cue_two_mode();
+                if accessible(
+                    ui.button(cue_two_mode.label()),
+                    CUE_MODE_HELP,
+                    Some(cue_two_mode == CueMode::AfterFader),
+                )
+                .clicked()
+                {
+                    app_data.mixer.set_cue_two_mode(cue_two_mode.toggled());
+                }
+                transform_button(ui, TurntableFocus::Two, enabled_fill, app_data, controller);
 
-                if ui
-                    .add(
+                let input_two = app_data.mixer.is_input_two_enabled();
+                if accessible(
+                    ui.add(egui::Button::new("LINE IN").fill(if input_two {
+                        enabled_fill
+                    } else {
+                        theme_visuals.widgets.inactive.weak_bg_fill
+                    })),
+                    "Line in on channel two",
+                    Some(input_two),
+                )
+                .clicked()
+                {
+                    controller.handle_event(app_data, BoothEvent::ToggleInputTwo);
+                }
+
+                if accessible(
+                    ui.add(
                         egui::Button::new("Focus ChTwo").fill(match app_data.turntable_focus {
-                            TurntableFocus::Two => egui::Color32::from_rgb(170, 170, 255),
+                            TurntableFocus::Two => focus_fill,
                             _ => theme_visuals.widgets.inactive.weak_bg_fill,
                         }),
-                    )
-                    .clicked()
+                    ),
+                    "Focus deck two",
+                    Some(app_data.turntable_focus == TurntableFocus::Two),
+                )
+                .clicked()
                 {
                     controller
                         .handle_event(app_data, BoothEvent::FocusChanged(TurntableFocus::Two));
                 }
 
-                if ui.add(egui::Button::new("START-STOP")).clicked() {
+                let state =
+                    DeckState::of(&app_data.turntable_two, app_data.mixer.is_cue_two_enabled());
+                if accessible(
+                    start_stop_button(ui, state, enabled_fill),
+                    "Start/stop deck two",
+                    Some(state == DeckState::Playing),
+                )
+                .clicked()
+                {
                     controller.handle_event(app_data, BoothEvent::ToggleStartStopTwo);
                 }
+                if accessible(ui.button("RETURN"), "Return to the start of deck two", None)
+                    .clicked()
+                {
+                    controller.handle_event(app_data, BoothEvent::ReturnToStartTwo);
+                }
+                if accessible(
+                    ui.button("SYNC"),
+                    "Match the tempo of deck two to the other deck",
+                    None,
+                )
+                .clicked()
+                {
+                    controller.handle_event(app_data, BoothEvent::SyncTwo);
+                }
+                if accessible(
+                    ui.button("TEMPO"),
+                    "Match the tempo of deck two to the other deck, without its beats",
+                    None,
+                )
+                .clicked()
+                {
+                    let focus = app_data.turntable_focus;
+                    app_data.turntable_focus = TurntableFocus::Two;
+                    controller.handle_event(app_data, BoothEvent::CopyTempo);
+                    app_data.turntable_focus = focus;
+                }
+                memory_points_ui(ui, TurntableFocus::Two, app_data, controller);
+                loops_ui(ui, TurntableFocus::Two, app_data, controller);
+                stems_ui(ui, TurntableFocus::Two, app_data, controller);
+                layer_ui(ui, TurntableFocus::Two, app_data, controller);
+                track_bpm_ui(ui, TurntableFocus::Two, app_data);
+                track_notes_ui(ui, TurntableFocus::Two, app_data);
             });
+            app_data
+                .tutorial
+                .set_area(TutorialArea::DeckTwo, deck_two.response.rect);
         });
     });
 
@@ -643,9 +3631,335 @@ fn run_ui(
             ui.label("Debug Panel");
             ui.separator();
             ui.label(format!("frame_counter: {}", app_data.frame_counter));
+            ui.label(format!("idle: {}", app_data.idle));
             ui.label(format!("focus: {:?}", app_data.turntable_focus));
+            ui.label(format!(
+                "speed: {:.3} / {:.3}",
+                app_data.turntable_one.speed(),
+                app_data.turntable_two.speed()
+            ));
             ui.label(format!("window_size: {:?}", window.inner_size()));
             ui.label(format!("modifiers_key: {:?}", app_data.modifiers_key));
+            ui.label(format!(
+                "audio_input: {}",
+                app_data.mixer.input_device_name().unwrap_or("None")
+            ));
+            for file in app_data.mixer.recorder().files() {
+                ui.label(format!("recording: {}", file.display()));
+            }
+            for info in app_data.input_debug_info.iter() {
+                ui.label(info);
+            }
+            if ui.button("Copy diagnostic snapshot").clicked() {
+                let snapshot = Snapshot::capture(app_data);
+                ui.output_mut(|output| output.copied_text = snapshot.to_json());
+                match snapshot.save() {
+                    Ok(path) => log::info!("Saved diagnostic snapshot '{}'", path.display()),
+                    Err(e) => log::error!("Cannot save diagnostic snapshot: {}", e),
+                }
+            }
         });
     }
+
+    spectrogram_window(ctx, app_data);
+
+    egui::Window::new("Settings")
+        .open(&mut app_data.show_settings)
+        .collapsible(false)
+        .show(ctx, |ui| {
+            let previous = app_data.settings.clone();
+
+            ui.checkbox(
+                &mut app_data.settings.accessibility,
+                "Accessibility mode (large text, high contrast)",
+            );
+            ui.checkbox(
+                &mut app_data.settings.touch_mode,
+                "Touch mode (large controls, drag the cover to scratch)",
+            );
+            ui.checkbox(
+                &mut app_data.settings.record_stems,
+                "Record each deck to separate files",
+            );
+            ui.checkbox(
+                &mut app_data.settings.exclusive_cue,
+                "Exclusive cue (cueing a channel uncues the other)",
+            );
+            ui.checkbox(
+                &mut app_data.settings.auto_cue,
+                "Cue a channel when loading a track",
+            );
+            ui.checkbox(
+                &mut app_data.settings.cue_first_beat,
+                "Mark and cue the first downbeat of tracks without memory points",
+            );
+            ui.checkbox(
+                &mut app_data.settings.channel_limiter,
+                "Limit the channels after the EQ to avoid clipping",
+            );
+            ui.checkbox(
+                &mut app_data.settings.auto_pause,
+                "Stop decks at the end of their track",
+            );
+            ui.checkbox(
+                &mut app_data.settings.preload_next,
+                "Preload the next prepared or scheduled track",
+            );
+            ui.checkbox(
+                &mut app_data.settings.cue_preview,
+                "Preview memory points of a stopped deck on the cue",
+            );
+            ui.horizontal(|ui| {
+                ui.label("Fader start (the crossfader starts and pauses)");
+                ui.checkbox(&mut app_data.settings.fader_start_one, "Deck one");
+                ui.checkbox(&mut app_data.settings.fader_start_two, "Deck two");
+            });
+            ui.checkbox(
+                &mut app_data.settings.quantize_loops,
+                "Snap moved and resized loops to the beat grid",
+            );
+            egui::ComboBox::from_id_source("transform_gate")
+                .selected_text(format!(
+                    "Transform gate: {:?}",
+                    app_data.settings.transform_gate
+                ))
+                .show_ui(ui, |ui| {
+                    for gate in TransformGate::ALL {
+                        ui.selectable_value(
+                            &mut app_data.settings.transform_gate,
+                            gate,
+                            format!("{:?}", gate),
+                        );
+                    }
+                });
+            ui.checkbox(
+                &mut app_data.settings.grab_cursor,
+                "Lock the cursor while scratching with the pointer",
+            );
+            ui.checkbox(
+                &mut app_data.settings.always_on_top,
+                "Keep the window above other windows",
+            );
+            ui.checkbox(
+                &mut app_data.settings.background_mode,
+                "Keep playing when the window is closed (quit from the top panel)",
+            );
+
+            ui.add(
+                egui::DragValue::new(&mut app_data.settings.ui_scale)
+                    .clamp_range(0.5..=3.0)
+                    .speed(0.01)
+                    .suffix("× UI scale"),
+            );
+            ui.add(
+                egui::DragValue::new(&mut app_data.settings.morph_time)
+                    .clamp_range(0.0..=60.0)
+                    .suffix(" s morph glide"),
+            );
+            ui.add(
+                egui::DragValue::new(&mut app_data.settings.dead_air_minutes)
+                    .clamp_range(0..=120)
+                    .suffix(" min"),
+            )
+            .on_hover_text(
+                "Warn and stop recording when no deck is heard for this long, 0 to disable",
+            );
+            ui.add(
+                egui::DragValue::new(&mut app_data.settings.stream_over_minutes)
+                    .clamp_range(0..=600)
+                    .suffix(" min"),
+            )
+            .on_hover_text(
+                "Tracks longer than this are streamed from their file instead of \
+                 decoded into memory, 0 to always decode them",
+            );
+            ui.horizontal(|ui| {
+                ui.label("Duck");
+                ui.add(
+                    egui::DragValue::new(&mut app_data.settings.duck_gain)
+                        .clamp_range(-60.0..=0.0)
+                        .suffix(" dB"),
+                );
+                ui.add(
+                    egui::DragValue::new(&mut app_data.settings.duck_ramp_ms)
+                        .clamp_range(0..=5000)
+                        .suffix(" ms"),
+                );
+            });
+
+            let mut duck_port = app_data
+                .settings
+                .duck_port
+                .map(|port| port.to_string())
+                .unwrap_or_default();
+            ui.horizontal(|ui| {
+                ui.label("Ducking port");
+                ui.text_edit_singleline(&mut duck_port).on_hover_text(
+                    "Local port ducking the master on requests to /duck and /release, \
+                     empty to disable, applied on restart",
+                );
+            });
+            app_data.settings.duck_port = duck_port.trim().parse().ok();
+
+            let mut timecode_target = app_data
+                .settings
+                .timecode_target
+                .clone()
+                .unwrap_or_default();
+            ui.horizontal(|ui| {
+                ui.label("Timecode target");
+                ui.text_edit_singleline(&mut timecode_target).on_hover_text(
+                    "Address such as 127.0.0.1:7000 receiving the beat clock and deck \
+                     positions as OSC messages, empty to disable, applied on restart",
+                );
+            });
+            app_data.settings.timecode_target =
+                Some(timecode_target).filter(|target| !target.trim().is_empty());
+
+            let mut overlay_port = app_data
+                .settings
+                .overlay_port
+                .map(|port| port.to_string())
+                .unwrap_or_default();
+            ui.horizontal(|ui| {
+                ui.label("Overlay port");
+                ui.text_edit_singleline(&mut overlay_port).on_hover_text(
+                    "Local port serving a now playing page for the browser source of \
+                     streaming software, empty to disable, applied on restart",
+                );
+            });
+            app_data.settings.overlay_port = overlay_port.trim().parse().ok();
+
+            ui.label("Split recordings (0 to disable)");
+            let split = &mut app_data.settings.record_split;
+            ui.add(egui::DragValue::new(&mut split.every_minutes).suffix(" min"));
+            ui.add(egui::DragValue::new(&mut split.after_silence_seconds).suffix(" s of silence"));
+            ui.add(egui::DragValue::new(&mut split.every_tracks).suffix(" tracks"));
+
+            let preset = &mut app_data.settings.midi_preset;
+            egui::ComboBox::from_label("MIDI controller preset")
+                .selected_text(preset.as_deref().unwrap_or("Automatic"))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(preset, None, "Automatic");
+                    for name in MidiMapping::presets().into_iter().filter_map(|p| p.name) {
+                        ui.selectable_value(preset, Some(name.clone()), name);
+                    }
+                });
+
+            let mut vocal_detector = app_data.settings.vocal_detector.clone().unwrap_or_default();
+            ui.horizontal(|ui| {
+                ui.label("Vocal detector");
+                ui.text_edit_singleline(&mut vocal_detector).on_hover_text(
+                    "Program given a track path and printing vocal or instrumental, \
+                     empty for the built-in detection",
+                );
+            });
+            app_data.settings.vocal_detector =
+                Some(vocal_detector).filter(|command| !command.trim().is_empty());
+
+            let export = ui.button("Export backup").on_hover_text(
+                "Save the library, settings and mappings in a single file, \
+                 restored by dropping it on the window",
+            );
+            if export.clicked() {
+                match backup::export() {
+                    Ok(path) => app_data
+                        .toasts
+                        .info(format!("Saved backup '{}'", path.display())),
+                    Err(e) => app_data.toasts.error(format!("Cannot save backup: {}", e)),
+                }
+            }
+
+            if app_data.settings.midi_preset != previous.midi_preset {
+                app_data.mappings_outdated = true;
+            }
+            if app_data.settings.always_on_top != previous.always_on_top {
+                window.set_window_level(window_level(&app_data.settings));
+            }
+            if app_data.settings != previous {
+                app_data.settings.save();
+            }
+        });
+
+    egui::Window::new("Plugins")
+        .open(&mut app_data.show_plugins)
+        .collapsible(false)
+        .show(ctx, |ui| {
+            if ui.button("Scan CLAP plugins").clicked() {
+                app_data.plugins = clap_host::scan();
+            }
+            ui.text_edit_singleline(&mut app_data.plugin_preset_name)
+                .on_hover_text("Preset name");
+
+            for deck in [TurntableFocus::One, TurntableFocus::Two] {
+                let (label, slots) = match deck {
+                    TurntableFocus::One => ("Channel one", app_data.mixer.fx_one()),
+                    TurntableFocus::Two => ("Channel two", app_data.mixer.fx_two()),
+                };
+                ui.separator();
+                insert_chain_ui(
+                    ui,
+                    label,
+                    slots,
+                    &app_data.plugins,
+                    &app_data.plugin_preset_name,
+                );
+            }
+        });
+
+    egui::Window::new("Keyboard shortcuts")
+        .open(&mut app_data.show_help)
+        .collapsible(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(ctx, |ui| {
+            ui.columns(2, |cols| {
+                for (i, group) in TargetGroup::ALL.iter().enumerate() {
+                    let ui = &mut cols[i % 2];
+                    let bindings: Vec<_> = app_data
+                        .keymap
+                        .keys
+                        .iter()
+                        .filter(|binding| binding.target.group() == *group)
+                        .collect();
+                    if bindings.is_empty() {
+                        continue;
+                    }
+
+                    ui.strong(group.label());
+                    egui::Grid::new(group.label()).striped(true).show(ui, |ui| {
+                        for binding in bindings {
+                            ui.monospace(binding.describe());
+                            ui.label(binding.target.label());
+                            ui.end_row();
+                        }
+                    });
+                    ui.add_space(8.0);
+                }
+            });
+
+            let pointer = &app_data.keymap.pointer;
+            ui.strong("Pointer");
+            egui::Grid::new("Pointer").striped(true).show(ui, |ui| {
+                ui.monospace(format!("{} (hold)", pointer.scratch.describe()));
+                ui.label("Scratch by moving the pointer");
+                ui.end_row();
+                ui.monospace(format!("{} (hold)", pointer.seek.describe()));
+                ui.label("Push the vinyl by moving the pointer");
+                ui.end_row();
+            });
+
+            if !app_data.keymap.global.is_empty() {
+                ui.strong("Global");
+                egui::Grid::new("Global").striped(true).show(ui, |ui| {
+                    for binding in app_data.keymap.global.iter() {
+                        ui.monospace(binding.describe());
+                        ui.label(binding.target.label());
+                        ui.end_row();
+                    }
+                });
+            }
+        });
+
+    app_data.tutorial.show(ctx);
+    app_data.toasts.show(ctx);
 }
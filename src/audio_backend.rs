@@ -0,0 +1,78 @@
+use kira::manager::{AudioManager, AudioManagerSettings};
+
+/// Backend the mixer plays through: the audio device, or a mock backend
+/// rendering frames on demand in tests
+#[cfg(not(test))]
+pub type Backend = kira::manager::DefaultBackend;
+#[cfg(test)]
+pub type Backend = kira::manager::backend::mock::MockBackend;
+
+/// Audio manager of the app, running on [`Backend`]
+pub type Manager = AudioManager<Backend>;
+
+pub type BackendError = <Backend as kira::manager::backend::Backend>::Error;
+
+#[cfg(not(test))]
+fn settings() -> AudioManagerSettings<Backend> {
+    AudioManagerSettings::default()
+}
+
+#[cfg(test)]
+fn settings() -> AudioManagerSettings<Backend> {
+    AudioManagerSettings {
+        backend_settings: kira::manager::backend::mock::MockBackendSettings {
+            sample_rate: testing::SAMPLE_RATE,
+        },
+        ..Default::default()
+    }
+}
+
+pub fn new_manager() -> Result<Manager, BackendError> {
+    Manager::new(settings())
+}
+
+/// Helpers to drive the mock backend, so that audio behaviors can be
+/// checked without an audio device
+#[cfg(test)]
+pub mod testing {
+    use std::path::PathBuf;
+
+    use hound::{SampleFormat, WavSpec, WavWriter};
+    use kira::Frame;
+
+    use super::Manager;
+
+    pub const SAMPLE_RATE: u32 = 48_000;
+
+    /// Render `frames` frames of the main output, returning the last one.
+    /// Commands sent since the last render are applied first
+    pub fn render(manager: &mut Manager, frames: usize) -> Frame {
+        let backend = manager.backend_mut();
+        backend.on_start_processing();
+        (0..frames).fold(Frame::ZERO, |_, _| backend.process())
+    }
+
+    /// Write a stereo WAV file of a constant level to the temp directory
+    pub fn constant_wav(name: &str, seconds: f64, level: f32) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "{}-{}-{}.wav",
+            env!("CARGO_PKG_NAME").to_lowercase(),
+            std::process::id(),
+            name
+        ));
+        let spec = WavSpec {
+            channels: 2,
+            sample_rate: SAMPLE_RATE,
+            bits_per_sample: 32,
+            sample_format: SampleFormat::Float,
+        };
+
+        let mut writer = WavWriter::create(&path, spec).unwrap();
+        for _ in 0..(seconds * SAMPLE_RATE as f64) as usize * 2 {
+            writer.write_sample(level).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        path
+    }
+}
@@ -0,0 +1,207 @@
+use core::fmt;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use cpal::{
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+    FromSample, SampleFormat, SizedSample, Stream, StreamConfig,
+};
+use kira::{
+    clock::clock_info::ClockInfoProvider,
+    modulator::value_provider::ModulatorValueProvider,
+    sound::{Sound, SoundData},
+    track::TrackHandle,
+    Frame, OutputDestination,
+};
+use ringbuf::{HeapConsumer, HeapProducer, HeapRb};
+
+use crate::audio_backend::Manager;
+
+/// Maximum delay kept between the input and the output, older frames are
+/// dropped to catch up
+const MAX_BUFFERED_SECONDS: f64 = 0.1;
+
+#[derive(Debug)]
+pub enum AudioInputError {
+    NoDevice,
+    Config(String),
+    Stream(String),
+    Play,
+}
+
+impl fmt::Display for AudioInputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self {
+            AudioInputError::NoDevice => write!(f, "No audio input device found"),
+            AudioInputError::Config(e) => write!(f, "Unsupported audio input config: {}", e),
+            AudioInputError::Stream(e) => write!(f, "Cannot open audio input stream: {}", e),
+            AudioInputError::Play => write!(f, "Cannot play audio input"),
+        }
+    }
+}
+
+/// A hardware audio input (e.g. line or phono through an external preamp)
+/// played directly into a mixer track. The input is captured as long as this
+/// struct is alive
+pub struct AudioInput {
+    _stream: Stream,
+    stopped: Arc<AtomicBool>,
+    device_name: String,
+}
+
+impl AudioInput {
+    /// Start capturing the input device whose name contains `device_name`,
+    /// or the default input device
+    pub fn new(
+        audio_manager: &mut Manager,
+        destination: &TrackHandle,
+        device_name: Option<&str>,
+    ) -> Result<Self, AudioInputError> {
+        let host = cpal::default_host();
+        let device = match device_name {
+            Some(name) => host
+                .input_devices()
+                .map_err(|e| AudioInputError::Config(e.to_string()))?
+                .find(|device| device.name().is_ok_and(|n| n.contains(name))),
+            None => host.default_input_device(),
+        }
+        .ok_or(AudioInputError::NoDevice)?;
+
+        let device_name = device.name().unwrap_or_default();
+        let supported_config = device
+            .default_input_config()
+            .map_err(|e| AudioInputError::Config(e.to_string()))?;
+        let config: StreamConfig = supported_config.clone().into();
+
+        let capacity = (config.sample_rate.0 as f64 * MAX_BUFFERED_SECONDS * 2.0) as usize;
+        let (producer, consumer) = HeapRb::<Frame>::new(capacity).split();
+
+        let stream = match supported_config.sample_format() {
+            SampleFormat::F32 => build_stream::<f32>(&device, &config, producer),
+            SampleFormat::I16 => build_stream::<i16>(&device, &config, producer),
+            SampleFormat::U16 => build_stream::<u16>(&device, &config, producer),
+            SampleFormat::I32 => build_stream::<i32>(&device, &config, producer),
+            format => return Err(AudioInputError::Config(format!("sample format {}", format))),
+        }?;
+        stream
+            .play()
+            .map_err(|e| AudioInputError::Stream(e.to_string()))?;
+
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        audio_manager
+            .play(InputSoundData(InputSound {
+                consumer,
+                destination: destination.into(),
+                input_sample_rate: config.sample_rate.0 as f64,
+                stopped: Arc::clone(&stopped),
+                phase: 0.0,
+                previous: Frame::ZERO,
+                next: Frame::ZERO,
+            }))
+            .map_err(|_| AudioInputError::Play)?;
+
+        log::info!("Audio input '{}' opened", device_name);
+
+        Ok(Self {
+            _stream: stream,
+            stopped,
+            device_name,
+        })
+    }
+
+    pub fn device_name(&self) -> &str {
+        &self.device_name
+    }
+}
+
+impl Drop for AudioInput {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+}
+
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    mut producer: HeapProducer<Frame>,
+) -> Result<Stream, AudioInputError>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    let channels = config.channels as usize;
+
+    device
+        .build_input_stream(
+            config,
+            move |data: &[T], _| {
+                for samples in data.chunks(channels) {
+                    let left: f32 = samples[0].to_sample();
+                    let right = samples.get(1).map_or(left, |s| s.to_sample());
+
+                    // frames are dropped if the output does not keep up
+                    let _ = producer.push(Frame { left, right });
+                }
+            },
+            |e| log::error!("Audio input stream error: {}", e),
+            None,
+        )
+        .map_err(|e| AudioInputError::Stream(e.to_string()))
+}
+
+/// Plays the captured frames, resampled to the output sample rate
+struct InputSound {
+    consumer: HeapConsumer<Frame>,
+    destination: OutputDestination,
+    input_sample_rate: f64,
+    stopped: Arc<AtomicBool>,
+    /// position between the previous and the next input frame
+    phase: f64,
+    previous: Frame,
+    next: Frame,
+}
+
+impl Sound for InputSound {
+    fn output_destination(&mut self) -> OutputDestination {
+        self.destination
+    }
+
+    fn process(
+        &mut self,
+        dt: f64,
+        _clock_info_provider: &ClockInfoProvider,
+        _modulator_value_provider: &ModulatorValueProvider,
+    ) -> Frame {
+        let max_buffered = (MAX_BUFFERED_SECONDS * self.input_sample_rate) as usize;
+        while self.consumer.len() > max_buffered {
+            self.consumer.pop();
+        }
+
+        self.phase += dt * self.input_sample_rate;
+        while self.phase >= 1.0 {
+            self.phase -= 1.0;
+            self.previous = self.next;
+            self.next = self.consumer.pop().unwrap_or(Frame::ZERO);
+        }
+
+        self.previous + (self.next - self.previous) * self.phase as f32
+    }
+
+    fn finished(&self) -> bool {
+        self.stopped.load(Ordering::Relaxed)
+    }
+}
+
+struct InputSoundData(InputSound);
+
+impl SoundData for InputSoundData {
+    type Error = ();
+    type Handle = ();
+
+    fn into_sound(self) -> Result<(Box<dyn Sound>, Self::Handle), Self::Error> {
+        Ok((Box::new(self.0), ()))
+    }
+}
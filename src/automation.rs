@@ -0,0 +1,207 @@
+use std::{
+    fs, io,
+    path::PathBuf,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{config::data_dir, controller::BoothEvent, mapping::Target};
+
+/// Length in seconds of the end of a take replayed as the last transition
+pub const TRANSITION_SECONDS: f64 = 30.0;
+
+/// A knob or fader move of a take
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AutomationMove {
+    /// seconds since the start of the take
+    pub time: f64,
+    pub target: Target,
+    pub value: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Replay {
+    started_at: Instant,
+    /// time in the take the replay started from
+    from: f64,
+    /// index of the next move to send
+    next: usize,
+}
+
+/// Records the knob and fader moves of a set with their time, and replays
+/// them, e.g. to practice a transition again
+#[derive(Debug, Default)]
+pub struct Automation {
+    take: Vec<AutomationMove>,
+    recording_since: Option<Instant>,
+    /// last value of each control, as the UI sends its values every frame
+    last_values: Vec<(Target, f64)>,
+    replay: Option<Replay>,
+}
+
+impl Automation {
+    pub fn is_recording(&self) -> bool {
+        self.recording_since.is_some()
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        self.replay.is_some()
+    }
+
+    /// Moves of the current or last take
+    pub fn take(&self) -> &[AutomationMove] {
+        &self.take
+    }
+
+    /// Start a new take, replacing the previous one
+    pub fn start_recording(&mut self, now: Instant) {
+        self.take.clear();
+        self.last_values.clear();
+        self.replay = None;
+        self.recording_since = Some(now);
+    }
+
+    pub fn stop_recording(&mut self) {
+        self.recording_since = None;
+    }
+
+    /// Add `event` to the take if it moves a control to a new value.
+    /// Replayed moves are not recorded again
+    pub fn observe(&mut self, event: &BoothEvent, now: Instant) {
+        let (Some(since), None) = (self.recording_since, self.replay) else {
+            return;
+        };
+        let Some((target, value)) = continuous_move(event) else {
+            return;
+        };
+
+        match self
+            .last_values
+            .iter_mut()
+            .find(|(last, _)| *last == target)
+        {
+            Some((_, last_value)) if *last_value == value => return,
+            Some((_, last_value)) => *last_value = value,
+            None => self.last_values.push((target, value)),
+        }
+
+        self.take.push(AutomationMove {
+            time: now.duration_since(since).as_secs_f64(),
+            target,
+            value,
+        });
+    }
+
+    /// Replay the take from `from` seconds, stopping the recording
+    pub fn replay(&mut self, from: f64, now: Instant) {
+        self.stop_recording();
+        self.replay = Some(Replay {
+            started_at: now,
+            from,
+            next: self.take.partition_point(|step| step.time < from),
+        });
+    }
+
+    /// Replay the last [`TRANSITION_SECONDS`] of the take
+    pub fn replay_last_transition(&mut self, now: Instant) {
+        let end = self.take.last().map_or(0.0, |step| step.time);
+        self.replay((end - TRANSITION_SECONDS).max(0.0), now);
+    }
+
+    pub fn stop_replay(&mut self) {
+        self.replay = None;
+    }
+
+    /// Moves of the replay due at `now`
+    pub fn poll(&mut self, now: Instant) -> Vec<BoothEvent<'static>> {
+        let Some(replay) = self.replay.as_mut() else {
+            return Vec::new();
+        };
+
+        let time = replay.from + now.duration_since(replay.started_at).as_secs_f64();
+        let due = self.take[replay.next..]
+            .iter()
+            .take_while(|step| step.time <= time)
+            .map(|step| step.target.to_event(step.value))
+            .collect::<Vec<_>>();
+
+        replay.next += due.len();
+        if replay.next == self.take.len() {
+            self.replay = None;
+        }
+        due
+    }
+
+    /// Write the take to the `automation` folder of the data directory, for
+    /// later review
+    pub fn save(&self) -> io::Result<PathBuf> {
+        let dir = data_dir().join("automation");
+        fs::create_dir_all(&dir)?;
+
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let path = dir.join(format!("take-{}.json", time));
+        let content = serde_json::to_string_pretty(&self.take).map_err(io::Error::other)?;
+        fs::write(&path, content)?;
+        Ok(path)
+    }
+}
+
+/// Target and value of the events moving a knob or a fader
+fn continuous_move(event: &BoothEvent) -> Option<(Target, f64)> {
+    match *event {
+        BoothEvent::CueMixChanged(value) => Some((Target::CueMix, value)),
+        BoothEvent::MicRecordLevelChanged(value) => Some((Target::MicRecordLevel, value)),
+        BoothEvent::VolumeOneChanged(value) => Some((Target::VolumeOne, value)),
+        BoothEvent::VolumeTwoChanged(value) => Some((Target::VolumeTwo, value)),
+        BoothEvent::PitchOneChanged(value) => Some((Target::PitchOne, value)),
+        BoothEvent::PitchTwoChanged(value) => Some((Target::PitchTwo, value)),
+        BoothEvent::EqLowOneChanged(value) => Some((Target::EqLowOne, value)),
+        BoothEvent::EqHighOneChanged(value) => Some((Target::EqHighOne, value)),
+        BoothEvent::EqLowTwoChanged(value) => Some((Target::EqLowTwo, value)),
+        BoothEvent::EqHighTwoChanged(value) => Some((Target::EqHighTwo, value)),
+        BoothEvent::MorphChanged(value) => Some((Target::Morph, value)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_record_and_replay() {
+        let start = Instant::now();
+        let at = |seconds: f64| start + Duration::from_secs_f64(seconds);
+        let mut automation = Automation::default();
+
+        automation.start_recording(start);
+        automation.observe(&BoothEvent::VolumeOneChanged(0.0), at(0.0));
+        // repeated values are sent every frame by the UI
+        automation.observe(&BoothEvent::VolumeOneChanged(0.0), at(0.5));
+        automation.observe(&BoothEvent::ToggleHelp, at(0.5));
+        automation.observe(&BoothEvent::VolumeOneChanged(0.5), at(1.0));
+        automation.observe(&BoothEvent::EqLowOneChanged(-6.0), at(40.0));
+        automation.stop_recording();
+        assert_eq!(automation.take().len(), 3);
+
+        automation.replay(0.0, at(100.0));
+        assert_eq!(automation.poll(at(100.0)).len(), 1);
+        assert!(automation.poll(at(100.5)).is_empty());
+        assert!(matches!(
+            automation.poll(at(101.0))[..],
+            [BoothEvent::VolumeOneChanged(volume)] if volume == 0.5
+        ));
+
+        // the last transition starts 30 seconds before the last move
+        automation.replay_last_transition(at(200.0));
+        assert!(automation.poll(at(200.0)).is_empty());
+        assert_eq!(automation.poll(at(230.0)).len(), 1);
+        assert!(!automation.is_replaying());
+    }
+}
@@ -0,0 +1,247 @@
+use std::{
+    fs, io,
+    path::{Component, Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{backups_dir, config_dir, data_dir},
+    utils::{from_base64, to_base64},
+};
+
+pub const BACKUP_EXTENSION: &str = "bousse";
+
+/// Folders of the data directory left out of backups, holding logs, crash
+/// recovery and copies of dropped tracks rather than the user setup
+const EXCLUDED_DATA_DIRS: [&str; 4] = ["logs", "recovery", "diagnostics", "dropped"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+enum Root {
+    Config,
+    Data,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+enum Content {
+    Text(String),
+    /// e.g. plugin presets and sampler captures, in base64 as a JSON array
+    /// of numbers would take about four times their size
+    Binary {
+        base64: String,
+    },
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct BackupFile {
+    root: Root,
+    /// relative to the root, with `/` separators
+    path: String,
+    content: Content,
+}
+
+/// The library, settings, mappings and scripts in a single file, to move
+/// the setup to another machine or recover it
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Backup {
+    version: String,
+    files: Vec<BackupFile>,
+}
+
+impl Backup {
+    fn collect(config: &Path, data: &Path) -> io::Result<Self> {
+        let mut files = Vec::new();
+        collect_dir(Root::Config, config, config, &[], &mut files)?;
+        collect_dir(Root::Data, data, data, &EXCLUDED_DATA_DIRS, &mut files)?;
+
+        Ok(Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            files,
+        })
+    }
+
+    /// Write the files back, replacing the existing ones. Returns the number
+    /// of files written
+    fn unpack(&self, config: &Path, data: &Path) -> io::Result<usize> {
+        for file in &self.files {
+            let relative = Path::new(&file.path);
+            // a crafted archive must not write outside the roots
+            if !relative
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)))
+            {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid path '{}'", file.path),
+                ));
+            }
+        }
+
+        for file in &self.files {
+            let root = match file.root {
+                Root::Config => config,
+                Root::Data => data,
+            };
+            let path = root.join(&file.path);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            match &file.content {
+                Content::Text(text) => fs::write(&path, text)?,
+                Content::Binary { base64 } => {
+                    let bytes = from_base64(base64).ok_or_else(|| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("invalid content of '{}'", file.path),
+                        )
+                    })?;
+                    fs::write(&path, bytes)?
+                }
+            }
+        }
+        Ok(self.files.len())
+    }
+}
+
+fn collect_dir(
+    root: Root,
+    root_dir: &Path,
+    dir: &Path,
+    excluded: &[&str],
+    files: &mut Vec<BackupFile>,
+) -> io::Result<()> {
+    let mut entries: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .collect(),
+        // nothing saved yet
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    entries.sort();
+
+    for path in entries {
+        let Ok(relative) = path.strip_prefix(root_dir) else {
+            continue;
+        };
+        let relative = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        if path.is_dir() {
+            if !excluded.contains(&relative.as_str()) {
+                collect_dir(root, root_dir, &path, excluded, files)?;
+            }
+            continue;
+        }
+
+        let bytes = fs::read(&path)?;
+        let content = match String::from_utf8(bytes) {
+            Ok(text) => Content::Text(text),
+            Err(e) => Content::Binary {
+                base64: to_base64(&e.into_bytes()),
+            },
+        };
+        files.push(BackupFile {
+            root,
+            path: relative,
+            content,
+        });
+    }
+    Ok(())
+}
+
+/// Save a backup of the config and data directories in the backups
+/// directory, returning its path
+pub fn export() -> io::Result<PathBuf> {
+    let backup = Backup::collect(&config_dir(), &data_dir())?;
+    let content = serde_json::to_string(&backup).map_err(io::Error::other)?;
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let dir = backups_dir();
+    let path = dir.join(format!("backup-{}.{}", timestamp, BACKUP_EXTENSION));
+    fs::create_dir_all(&dir)?;
+    fs::write(&path, content)?;
+    Ok(path)
+}
+
+/// Restore the backup at `path` over the config and data directories,
+/// returning the number of files restored
+pub fn restore(path: &Path) -> io::Result<usize> {
+    let content = fs::read_to_string(path)?;
+    let backup: Backup = serde_json::from_str(&content).map_err(io::Error::other)?;
+    log::info!(
+        "Restoring {} files from a backup of version {}",
+        backup.files.len(),
+        backup.version
+    );
+    backup.unpack(&config_dir(), &data_dir())
+}
+
+pub fn is_backup_path(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == BACKUP_EXTENSION)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let dir = std::env::temp_dir().join("bousse-backup-test");
+        let _ = fs::remove_dir_all(&dir);
+        let (config, data) = (dir.join("config"), dir.join("data"));
+        fs::create_dir_all(config.join("presets")).unwrap();
+        fs::create_dir_all(data.join("logs")).unwrap();
+        fs::create_dir_all(data.join("logs2")).unwrap();
+        fs::write(config.join("presets/mixer.toml"), "name = \"mixer\"").unwrap();
+        fs::write(data.join("library.toml"), "[tracks]").unwrap();
+        fs::write(data.join("state.preset"), [0xff, 0x00]).unwrap();
+        fs::write(data.join("logs/bousse.log"), "left out").unwrap();
+        // only the exact names of the excluded folders are left out
+        fs::write(data.join("logs2/x"), "kept").unwrap();
+
+        let backup = Backup::collect(&config, &data).unwrap();
+        let paths: Vec<_> = backup.files.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "presets/mixer.toml",
+                "library.toml",
+                "logs2/x",
+                "state.preset"
+            ]
+        );
+
+        let json = serde_json::to_string(&backup).unwrap();
+        assert!(json.contains(r#"{"base64":"/wA="}"#));
+        let backup: Backup = serde_json::from_str(&json).unwrap();
+        let restored = dir.join("restored");
+        let (config, data) = (restored.join("config"), restored.join("data"));
+        assert_eq!(backup.unpack(&config, &data).unwrap(), 4);
+        assert_eq!(
+            fs::read_to_string(config.join("presets/mixer.toml")).unwrap(),
+            "name = \"mixer\""
+        );
+        assert_eq!(fs::read_to_string(data.join("logs2/x")).unwrap(), "kept");
+        assert_eq!(fs::read(data.join("state.preset")).unwrap(), [0xff, 0x00]);
+
+        let crafted = Backup {
+            version: String::new(),
+            files: vec![BackupFile {
+                root: Root::Data,
+                path: "../outside.toml".to_string(),
+                content: Content::Text(String::new()),
+            }],
+        };
+        assert!(crafted.unpack(&config, &data).is_err());
+    }
+}
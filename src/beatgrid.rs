@@ -0,0 +1,236 @@
+use kira::Frame;
+
+use crate::{controller::TurntableFocus, sampler::BEATS_PER_BAR};
+
+/// Length in seconds of the blocks the onsets are found on
+const BLOCK_SECONDS: f64 = 0.01;
+/// Range of the detected tempo, an octave so that half and double tempos
+/// cannot be confused
+const MIN_DETECTED_BPM: f64 = 88.0;
+const MAX_DETECTED_BPM: f64 = 176.0;
+/// Steps of the tempo search, the coarse one over the whole range then the
+/// fine one around the best coarse tempo
+const COARSE_BPM_STEP: f64 = 0.5;
+const FINE_BPM_STEP: f64 = 0.01;
+/// Steps of the phase search, in blocks
+const COARSE_PHASE_STEP: f64 = 1.0;
+const FINE_PHASE_STEP: f64 = 0.25;
+/// Taps further apart than this in seconds of the track start over
+const TAP_TIMEOUT: f64 = 2.0;
+/// Last taps fitted into the beat grid
+const MAX_TAPS: usize = 16;
+
+/// Find the tempo and the first downbeat in seconds of a track, from the
+/// period and phase best matching the rises of its level. The downbeat is
+/// taken as the strongest beat of the bar, which does not hold for every
+/// track, so that it can be shifted by hand
+pub fn detect(frames: &[Frame], sample_rate: u32) -> Option<(f64, f64)> {
+    let block_length = ((sample_rate as f64 * BLOCK_SECONDS) as usize).max(1);
+    let block_seconds = block_length as f64 / sample_rate as f64;
+    let levels: Vec<f64> = frames
+        .chunks(block_length)
+        .map(|block| {
+            let power = block
+                .iter()
+                .map(|frame| {
+                    let mono = (frame.left + frame.right) as f64 / 2.0;
+                    mono * mono
+                })
+                .sum::<f64>()
+                / block.len() as f64;
+            power.sqrt()
+        })
+        .collect();
+    let onsets: Vec<f64> = levels
+        .windows(2)
+        .map(|pair| (pair[1] - pair[0]).max(0.0))
+        .collect();
+    let beat_blocks = |bpm: f64| 60.0 / bpm / block_seconds;
+    if onsets.len() < 4 * beat_blocks(MIN_DETECTED_BPM) as usize
+        || onsets.iter().all(|onset| *onset == 0.0)
+    {
+        return None;
+    }
+
+    let steps = |from: f64, to: f64, step: f64| {
+        (0..=((to - from) / step).round() as usize).map(move |i| from + i as f64 * step)
+    };
+    let (coarse, _) = best_grid(
+        &onsets,
+        steps(MIN_DETECTED_BPM, MAX_DETECTED_BPM, COARSE_BPM_STEP),
+        COARSE_PHASE_STEP,
+        block_seconds,
+    )?;
+    let (bpm, phase) = best_grid(
+        &onsets,
+        steps(
+            coarse - COARSE_BPM_STEP,
+            coarse + COARSE_BPM_STEP,
+            FINE_BPM_STEP,
+        ),
+        FINE_PHASE_STEP,
+        block_seconds,
+    )?;
+
+    let beat = beat_blocks(bpm);
+    let bar = beat * BEATS_PER_BAR as f64;
+    let downbeat = (0..BEATS_PER_BAR)
+        .map(|beat_of_bar| phase + beat_of_bar as f64 * beat)
+        .max_by(|a, b| comb(&onsets, bar, *a).total_cmp(&comb(&onsets, bar, *b)))?;
+
+    // an onset found between two blocks starts at the second one
+    Some((bpm, (downbeat + 1.0) * block_seconds))
+}
+
+/// Tempo among `bpms` and phase in blocks whose beats fall on the strongest
+/// onsets
+fn best_grid(
+    onsets: &[f64],
+    bpms: impl Iterator<Item = f64>,
+    phase_step: f64,
+    block_seconds: f64,
+) -> Option<(f64, f64)> {
+    bpms.flat_map(|bpm| {
+        let beat = 60.0 / bpm / block_seconds;
+        (0..(beat / phase_step) as usize).map(move |step| {
+            (
+                bpm,
+                step as f64 * phase_step,
+                comb(onsets, beat, step as f64 * phase_step),
+            )
+        })
+    })
+    .max_by(|a, b| a.2.total_cmp(&b.2))
+    .map(|(bpm, phase, _)| (bpm, phase))
+}
+
+/// Mean onset on the beats every `period` blocks from `phase`
+fn comb(onsets: &[f64], period: f64, phase: f64) -> f64 {
+    let beats = ((onsets.len() - 1) as f64 - phase) / period;
+    if beats < 0.0 {
+        return 0.0;
+    }
+
+    let sum: f64 = (0..=beats as usize)
+        .map(|beat| {
+            let position = phase + beat as f64 * period;
+            let (index, fraction) = (position as usize, position.fract());
+            let next = onsets.get(index + 1).copied().unwrap_or_default();
+            onsets[index] * (1.0 - fraction) + next * fraction
+        })
+        .sum();
+    sum / (beats as usize + 1) as f64
+}
+
+/// Beats tapped on a deck, correcting the beat grid of its track
+#[derive(Debug, Default)]
+pub struct GridTaps {
+    deck: Option<TurntableFocus>,
+    /// positions in seconds in the track, in order
+    taps: Vec<f64>,
+}
+
+impl GridTaps {
+    /// Count a tap on the beat at `position` in the track of `deck`,
+    /// returning the taps since the tapping started
+    pub fn tap(&mut self, deck: TurntableFocus, position: f64) -> &[f64] {
+        let same = self.deck == Some(deck)
+            && self
+                .taps
+                .last()
+                .is_some_and(|last| position > *last && position - last <= TAP_TIMEOUT);
+        if !same {
+            self.deck = Some(deck);
+            self.taps.clear();
+        }
+        self.taps.push(position);
+        if self.taps.len() > MAX_TAPS {
+            self.taps.remove(0);
+        }
+
+        &self.taps
+    }
+}
+
+/// Tempo and first beat fitting taps on consecutive beats, by least squares
+pub fn fit_taps(taps: &[f64]) -> Option<(f64, f64)> {
+    if taps.len() < 2 {
+        return None;
+    }
+
+    let count = taps.len() as f64;
+    let mean_index = (count - 1.0) / 2.0;
+    let mean_tap = taps.iter().sum::<f64>() / count;
+    let (covariance, variance) =
+        taps.iter()
+            .enumerate()
+            .fold((0.0, 0.0), |(covariance, variance), (index, tap)| {
+                let index = index as f64 - mean_index;
+                (
+                    covariance + index * (tap - mean_tap),
+                    variance + index * index,
+                )
+            });
+    let beat = covariance / variance;
+    if beat <= 0.0 {
+        return None;
+    }
+
+    Some((60.0 / beat, mean_tap - mean_index * beat))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let sample_rate = 8_000;
+        let (bpm, first_downbeat) = (125.0, 0.3);
+        let beat = 60.0 / bpm;
+        // a louder hit on the downbeats, some off the beat, over a hum
+        let frames: Vec<Frame> = (0..20 * sample_rate)
+            .map(|i| {
+                let time = i as f64 / sample_rate as f64;
+                let beats = (time - first_downbeat) / beat;
+                let since_beat = (beats - beats.floor()) * beat;
+                let since_offbeat = (beats + 0.5 - (beats + 0.5).floor()) * beat;
+                let hit = match (beats < 0.0, beats.floor() as i64 % 4 == 0) {
+                    (true, _) => 0.0,
+                    (false, true) => 0.8,
+                    (false, false) => 0.5,
+                };
+                let level = match (since_beat < 0.03, since_offbeat < 0.01) {
+                    (true, _) => hit,
+                    (false, true) => 0.2,
+                    (false, false) => 0.05,
+                };
+                Frame::from_mono(level as f32)
+            })
+            .collect();
+
+        let (detected_bpm, detected_downbeat) = detect(&frames, sample_rate).unwrap();
+        assert!((detected_bpm - bpm).abs() < 0.1);
+        assert!((detected_downbeat - first_downbeat).abs() < 0.02);
+
+        assert_eq!(
+            detect(&vec![Frame::ZERO; 10 * sample_rate as usize], sample_rate),
+            None
+        );
+    }
+
+    #[test]
+    fn test_tap() {
+        let mut taps = GridTaps::default();
+        assert_eq!(taps.tap(TurntableFocus::One, 10.0), &[10.0]);
+        assert_eq!(taps.tap(TurntableFocus::One, 10.5), &[10.0, 10.5]);
+        // another deck, or going back, starts over
+        assert_eq!(taps.tap(TurntableFocus::Two, 11.0), &[11.0]);
+        assert_eq!(taps.tap(TurntableFocus::Two, 4.0), &[4.0]);
+
+        let (bpm, first) = fit_taps(&[10.01, 10.49, 11.0, 11.5]).unwrap();
+        assert!((bpm - 120.0).abs() < 1.0);
+        assert!((first - 10.0).abs() < 0.02);
+        assert_eq!(fit_taps(&[10.0]), None);
+    }
+}
@@ -0,0 +1,81 @@
+use crate::library::{Library, TrackInfo};
+
+/// Columns narrowing the tracks listed in the browser, combined with each
+/// other. Tracks without the information are hidden while a column is set
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BrowserFilter {
+    pub genre: Option<String>,
+    /// lowest and highest tempo at the original speed
+    pub bpm: Option<(f64, f64)>,
+    /// musical key in Camelot notation
+    pub key: Option<String>,
+}
+
+impl BrowserFilter {
+    pub fn is_active(&self) -> bool {
+        self.genre.is_some() || self.bpm.is_some() || self.key.is_some()
+    }
+
+    pub fn matches(&self, track: Option<&TrackInfo>) -> bool {
+        let genre = self.genre.as_ref().is_none_or(|genre| {
+            track
+                .and_then(|track| track.genre.as_ref())
+                .is_some_and(|track_genre| track_genre.eq_ignore_ascii_case(genre))
+        });
+        let bpm = self.bpm.is_none_or(|(min, max)| {
+            track
+                .and_then(|track| track.bpm)
+                .is_some_and(|bpm| (min..=max).contains(&bpm))
+        });
+        let key = self.key.as_ref().is_none_or(|key| {
+            track
+                .and_then(|track| track.key.as_ref())
+                .is_some_and(|track_key| track_key.eq_ignore_ascii_case(key))
+        });
+
+        genre && bpm && key
+    }
+}
+
+/// Genres of the library, by name and without duplicates
+pub fn genres(library: &Library) -> Vec<String> {
+    let mut genres: Vec<String> = library
+        .tracks()
+        .filter_map(|(_, track)| track.genre.clone())
+        .collect();
+    genres.sort_by_key(|genre| genre.to_lowercase());
+    genres.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+    genres
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        let mut library = Library::default();
+        for (path, genre, bpm, key) in [
+            ("house.mp3", "house", 124.0, "8A"),
+            ("techno.mp3", "Techno", 132.0, "8A"),
+            ("deep.mp3", "House", 120.0, "3B"),
+        ] {
+            let track = library.track_mut(path);
+            track.genre = Some(genre.to_string());
+            track.bpm = Some(bpm);
+            track.key = Some(key.to_string());
+        }
+        assert_eq!(genres(&library), ["House", "Techno"]);
+
+        let filter = BrowserFilter {
+            genre: Some("HOUSE".to_string()),
+            bpm: Some((122.0, 130.0)),
+            key: None,
+        };
+        assert!(filter.matches(library.track("house.mp3")));
+        assert!(!filter.matches(library.track("techno.mp3")));
+        assert!(!filter.matches(library.track("deep.mp3")));
+        assert!(!filter.matches(None));
+        assert!(BrowserFilter::default().matches(None));
+    }
+}
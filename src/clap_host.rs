@@ -0,0 +1,712 @@
+use std::{
+    ffi::{c_char, c_void, CStr, CString},
+    fmt, fs,
+    path::{Path, PathBuf},
+    ptr,
+    sync::{Arc, Mutex, Weak},
+    thread,
+};
+
+use clap_sys::{
+    audio_buffer::clap_audio_buffer,
+    entry::clap_plugin_entry,
+    events::{
+        clap_event_header, clap_event_param_value, clap_input_events, clap_output_events,
+        CLAP_CORE_EVENT_SPACE_ID, CLAP_EVENT_PARAM_VALUE,
+    },
+    ext::{
+        audio_ports::{
+            clap_audio_port_info, clap_plugin_audio_ports, CLAP_AUDIO_PORT_IS_MAIN,
+            CLAP_EXT_AUDIO_PORTS,
+        },
+        params::{
+            clap_param_info, clap_plugin_params, CLAP_EXT_PARAMS, CLAP_PARAM_IS_HIDDEN,
+            CLAP_PARAM_IS_READONLY,
+        },
+        state::{clap_plugin_state, CLAP_EXT_STATE},
+    },
+    factory::plugin_factory::{clap_plugin_factory, CLAP_PLUGIN_FACTORY_ID},
+    host::clap_host,
+    plugin::{clap_plugin, clap_plugin_descriptor},
+    plugin_features::CLAP_PLUGIN_FEATURE_AUDIO_EFFECT,
+    process::{clap_process, CLAP_PROCESS_ERROR},
+    stream::{clap_istream, clap_ostream},
+    version::{clap_version_is_compatible, CLAP_VERSION},
+};
+use kira::Frame;
+use libloading::Library;
+use serde::{Deserialize, Serialize};
+
+/// Frames processed by a plugin at once, which is also the latency it adds
+pub const BLOCK_SIZE: usize = 64;
+/// Parameter changes sent to a plugin per block
+const MAX_PARAM_EVENTS: usize = 64;
+
+/// Plugin files in use, initialized once however many instances they have
+static LIBRARIES: Mutex<Vec<(PathBuf, Weak<PluginLibrary>)>> = Mutex::new(Vec::new());
+
+#[derive(Debug)]
+pub enum PluginError {
+    Library(libloading::Error),
+    NoEntry,
+    IncompatibleVersion,
+    NoFactory,
+    NotFound(String),
+    CreateFailed,
+    InitFailed,
+    NotStereo,
+    ActivateFailed,
+}
+
+impl fmt::Display for PluginError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PluginError::Library(e) => write!(f, "cannot load library: {}", e),
+            PluginError::NoEntry => write!(f, "not a CLAP plugin"),
+            PluginError::IncompatibleVersion => write!(f, "incompatible CLAP version"),
+            PluginError::NoFactory => write!(f, "no plugin factory"),
+            PluginError::NotFound(id) => write!(f, "plugin '{}' not found", id),
+            PluginError::CreateFailed => write!(f, "cannot create plugin"),
+            PluginError::InitFailed => write!(f, "plugin initialization failed"),
+            PluginError::NotStereo => write!(f, "no stereo main audio ports"),
+            PluginError::ActivateFailed => write!(f, "plugin activation failed"),
+        }
+    }
+}
+
+impl From<libloading::Error> for PluginError {
+    fn from(error: libloading::Error) -> Self {
+        PluginError::Library(error)
+    }
+}
+
+/// An effect found by the scanner
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PluginInfo {
+    pub id: String,
+    pub name: String,
+    pub vendor: String,
+    pub path: PathBuf,
+}
+
+/// Folders searched for plugins: the `CLAP_PATH` environment variable, then
+/// the standard locations of the platform
+fn search_paths() -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = match dotenv::var("CLAP_PATH") {
+        Ok(value) => std::env::split_paths(&value).collect(),
+        Err(_) => Vec::new(),
+    };
+
+    if cfg!(target_os = "macos") {
+        paths.extend(dirs::home_dir().map(|dir| dir.join("Library/Audio/Plug-Ins/CLAP")));
+        paths.push(PathBuf::from("/Library/Audio/Plug-Ins/CLAP"));
+    } else if cfg!(target_os = "windows") {
+        paths.extend(dirs::data_local_dir().map(|dir| dir.join("Programs/Common/CLAP")));
+        paths.push(PathBuf::from("C:/Program Files/Common Files/CLAP"));
+    } else {
+        paths.extend(dirs::home_dir().map(|dir| dir.join(".clap")));
+        paths.push(PathBuf::from("/usr/lib/clap"));
+        paths.push(PathBuf::from("/usr/local/lib/clap"));
+    }
+
+    paths
+}
+
+/// `.clap` files and bundles in `dir` and its subfolders
+fn find_plugin_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for path in entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+    {
+        if path.extension().is_some_and(|ext| ext == "clap") {
+            files.push(path);
+        } else if path.is_dir() {
+            find_plugin_files(&path, files);
+        }
+    }
+}
+
+/// Binary of a plugin, inside the bundle on macOS
+fn binary_path(path: &Path) -> PathBuf {
+    match (path.is_dir(), path.file_stem()) {
+        (true, Some(stem)) => path.join("Contents/MacOS").join(stem),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// List the audio effects of the installed CLAP plugins
+pub fn scan() -> Vec<PluginInfo> {
+    let mut files = Vec::new();
+    for dir in search_paths() {
+        find_plugin_files(&dir, &mut files);
+    }
+
+    let mut plugins = Vec::new();
+    for path in files {
+        match PluginLibrary::open(&path).map(|library| library.effects()) {
+            Ok(effects) => plugins.extend(effects),
+            Err(e) => log::warn!("Skipping plugin '{}': {}", path.display(), e),
+        }
+    }
+
+    log::info!("Found {} CLAP effects", plugins.len());
+    plugins
+}
+
+/// Copy a C string owned by a plugin
+unsafe fn to_string(ptr: *const c_char) -> String {
+    if ptr.is_null() {
+        return String::new();
+    }
+    CStr::from_ptr(ptr).to_string_lossy().into_owned()
+}
+
+/// A loaded plugin file, initialized until dropped
+struct PluginLibrary {
+    path: PathBuf,
+    entry: *const clap_plugin_entry,
+    _library: Library,
+}
+
+// the entry only gives access to thread safe functions
+unsafe impl Send for PluginLibrary {}
+unsafe impl Sync for PluginLibrary {}
+
+impl PluginLibrary {
+    /// The library of `path`, shared with the plugins already using it
+    fn open(path: &Path) -> Result<Arc<Self>, PluginError> {
+        loop {
+            let mut libraries = LIBRARIES.lock().unwrap();
+            let Some((_, library)) = libraries.iter().find(|(opened, _)| opened == path) else {
+                let library = Self::load(path)?;
+                libraries.push((path.to_path_buf(), Arc::downgrade(&library)));
+                return Ok(library);
+            };
+            if let Some(library) = library.upgrade() {
+                return Ok(library);
+            }
+            // released meanwhile, opened again once deinitialized
+            drop(libraries);
+            thread::yield_now();
+        }
+    }
+
+    fn load(path: &Path) -> Result<Arc<Self>, PluginError> {
+        let binary = binary_path(path);
+        unsafe {
+            let library = Library::new(&binary)?;
+            let entry = *library
+                .get::<*const clap_plugin_entry>(b"clap_entry\0")
+                .map_err(|_| PluginError::NoEntry)?;
+            if entry.is_null() {
+                return Err(PluginError::NoEntry);
+            }
+            if !clap_version_is_compatible((*entry).clap_version) {
+                return Err(PluginError::IncompatibleVersion);
+            }
+
+            let path_str = CString::new(path.to_string_lossy().as_bytes()).unwrap_or_default();
+            match (*entry).init {
+                Some(init) if init(path_str.as_ptr()) => (),
+                _ => return Err(PluginError::InitFailed),
+            }
+
+            Ok(Arc::new(Self {
+                path: path.to_path_buf(),
+                entry,
+                _library: library,
+            }))
+        }
+    }
+
+    fn factory(&self) -> Result<&clap_plugin_factory, PluginError> {
+        unsafe {
+            let get_factory = (*self.entry).get_factory.ok_or(PluginError::NoFactory)?;
+            let factory =
+                get_factory(CLAP_PLUGIN_FACTORY_ID.as_ptr()) as *const clap_plugin_factory;
+            factory.as_ref().ok_or(PluginError::NoFactory)
+        }
+    }
+
+    fn descriptors(&self) -> Vec<&clap_plugin_descriptor> {
+        let Ok(factory) = self.factory() else {
+            return Vec::new();
+        };
+
+        unsafe {
+            let (Some(count), Some(get)) =
+                (factory.get_plugin_count, factory.get_plugin_descriptor)
+            else {
+                return Vec::new();
+            };
+
+            (0..count(factory))
+                .filter_map(|index| get(factory, index).as_ref())
+                .collect()
+        }
+    }
+
+    fn effects(&self) -> Vec<PluginInfo> {
+        self.descriptors()
+            .into_iter()
+            .filter(|descriptor| unsafe { is_audio_effect(descriptor) })
+            .map(|descriptor| unsafe {
+                PluginInfo {
+                    id: to_string(descriptor.id),
+                    name: to_string(descriptor.name),
+                    vendor: to_string(descriptor.vendor),
+                    path: self.path.clone(),
+                }
+            })
+            .collect()
+    }
+}
+
+unsafe fn is_audio_effect(descriptor: &clap_plugin_descriptor) -> bool {
+    let mut feature = descriptor.features;
+    while !feature.is_null() && !(*feature).is_null() {
+        if CStr::from_ptr(*feature) == CLAP_PLUGIN_FEATURE_AUDIO_EFFECT {
+            return true;
+        }
+        feature = feature.add(1);
+    }
+    false
+}
+
+impl Drop for PluginLibrary {
+    fn drop(&mut self) {
+        let mut libraries = LIBRARIES.lock().unwrap();
+        unsafe {
+            if let Some(deinit) = (*self.entry).deinit {
+                deinit();
+            }
+        }
+        libraries.retain(|(opened, _)| *opened != self.path);
+    }
+}
+
+/// Host callbacks, none of the optional host extensions are provided
+fn new_host() -> Box<clap_host> {
+    unsafe extern "C" fn get_extension(_: *const clap_host, _: *const c_char) -> *const c_void {
+        ptr::null()
+    }
+    unsafe extern "C" fn request(_: *const clap_host) {}
+
+    Box::new(clap_host {
+        clap_version: CLAP_VERSION,
+        host_data: ptr::null_mut(),
+        name: c"Bousse".as_ptr(),
+        vendor: c"leopnt".as_ptr(),
+        url: c"https://github.com/leopnt/bousse".as_ptr(),
+        version: c"0".as_ptr(),
+        get_extension: Some(get_extension),
+        request_restart: Some(request),
+        request_process: Some(request),
+        request_callback: Some(request),
+    })
+}
+
+/// A parameter exposed in the UI
+#[derive(Debug, Clone)]
+pub struct ParamInfo {
+    pub id: u32,
+    pub name: String,
+    pub min: f64,
+    pub max: f64,
+}
+
+/// A created and activated plugin instance, destroyed when dropped
+struct PluginInstance {
+    plugin: *const clap_plugin,
+    activated: bool,
+    _host: Box<clap_host>,
+    _library: Arc<PluginLibrary>,
+}
+
+// CLAP functions document the thread they must be called from, the main
+// thread owns the `Plugin` and the audio thread the `PluginProcessor`
+unsafe impl Send for PluginInstance {}
+unsafe impl Sync for PluginInstance {}
+
+impl PluginInstance {
+    fn extension<T>(&self, id: &CStr) -> Option<&T> {
+        unsafe {
+            let get_extension = (*self.plugin).get_extension?;
+            (get_extension(self.plugin, id.as_ptr()) as *const T).as_ref()
+        }
+    }
+
+    /// Whether the plugin has one input and one output port, both stereo
+    /// main ports, the only layout the processor feeds
+    fn is_stereo(&self) -> bool {
+        let Some(ports) = self.extension::<clap_plugin_audio_ports>(CLAP_EXT_AUDIO_PORTS) else {
+            return false;
+        };
+        let (Some(count), Some(get)) = (ports.count, ports.get) else {
+            return false;
+        };
+
+        [true, false].into_iter().all(|is_input| unsafe {
+            if count(self.plugin, is_input) != 1 {
+                return false;
+            }
+            let mut info: clap_audio_port_info = std::mem::zeroed();
+            get(self.plugin, 0, is_input, &mut info)
+                && info.flags & CLAP_AUDIO_PORT_IS_MAIN != 0
+                && info.channel_count == 2
+        })
+    }
+}
+
+impl Drop for PluginInstance {
+    fn drop(&mut self) {
+        unsafe {
+            if let Some(deactivate) = (*self.plugin).deactivate.filter(|_| self.activated) {
+                deactivate(self.plugin);
+            }
+            if let Some(destroy) = (*self.plugin).destroy {
+                destroy(self.plugin);
+            }
+        }
+    }
+}
+
+/// Main thread side of a plugin: parameters and state
+pub struct Plugin {
+    info: PluginInfo,
+    params: Vec<ParamInfo>,
+    instance: Arc<PluginInstance>,
+}
+
+impl Plugin {
+    /// Create the plugin and activate it for the given sample rate
+    pub fn new(info: &PluginInfo, sample_rate: f64) -> Result<Self, PluginError> {
+        let library = PluginLibrary::open(&info.path)?;
+        let factory = library.factory()?;
+        let host = new_host();
+        let id = CString::new(info.id.as_bytes()).unwrap_or_default();
+
+        let plugin = unsafe {
+            let create = factory.create_plugin.ok_or(PluginError::CreateFailed)?;
+            create(factory, &*host, id.as_ptr())
+        };
+        if plugin.is_null() {
+            return Err(PluginError::NotFound(info.id.clone()));
+        }
+
+        unsafe {
+            let initialized = (*plugin).init.is_some_and(|init| init(plugin));
+            if !initialized {
+                if let Some(destroy) = (*plugin).destroy {
+                    destroy(plugin);
+                }
+                return Err(PluginError::InitFailed);
+            }
+        }
+
+        let mut instance = PluginInstance {
+            plugin,
+            activated: false,
+            _host: host,
+            _library: library,
+        };
+        if !instance.is_stereo() {
+            return Err(PluginError::NotStereo);
+        }
+
+        instance.activated = unsafe {
+            (*plugin)
+                .activate
+                .is_some_and(|activate| activate(plugin, sample_rate, 1, BLOCK_SIZE as u32))
+        };
+        if !instance.activated {
+            return Err(PluginError::ActivateFailed);
+        }
+
+        Ok(Self {
+            info: info.clone(),
+            params: Self::read_params(&instance),
+            instance: Arc::new(instance),
+        })
+    }
+
+    fn read_params(instance: &PluginInstance) -> Vec<ParamInfo> {
+        let Some(params) = instance.extension::<clap_plugin_params>(CLAP_EXT_PARAMS) else {
+            return Vec::new();
+        };
+        let (Some(count), Some(get_info)) = (params.count, params.get_info) else {
+            return Vec::new();
+        };
+
+        unsafe {
+            (0..count(instance.plugin))
+                .filter_map(|index| {
+                    let mut info: clap_param_info = std::mem::zeroed();
+                    if !get_info(instance.plugin, index, &mut info) {
+                        return None;
+                    }
+                    if info.flags & (CLAP_PARAM_IS_HIDDEN | CLAP_PARAM_IS_READONLY) != 0 {
+                        return None;
+                    }
+                    Some(ParamInfo {
+                        id: info.id,
+                        name: to_string(info.name.as_ptr()),
+                        min: info.min_value,
+                        max: info.max_value,
+                    })
+                })
+                .collect()
+        }
+    }
+
+    pub fn info(&self) -> &PluginInfo {
+        &self.info
+    }
+
+    pub fn params(&self) -> &[ParamInfo] {
+        &self.params
+    }
+
+    pub fn param_value(&self, id: u32) -> Option<f64> {
+        let params = self
+            .instance
+            .extension::<clap_plugin_params>(CLAP_EXT_PARAMS)?;
+        let get_value = params.get_value?;
+
+        let mut value = 0.0;
+        unsafe { get_value(self.instance.plugin, id, &mut value) }.then_some(value)
+    }
+
+    /// Whether the audio thread released its side of the plugin
+    pub fn is_released(&self) -> bool {
+        Arc::strong_count(&self.instance) == 1
+    }
+
+    /// Serialized state of the plugin, to be saved as a preset
+    pub fn save_state(&self) -> Option<Vec<u8>> {
+        unsafe extern "C" fn write(
+            stream: *const clap_ostream,
+            buffer: *const c_void,
+            size: u64,
+        ) -> i64 {
+            let data = &mut *((*stream).ctx as *mut Vec<u8>);
+            data.extend_from_slice(std::slice::from_raw_parts(
+                buffer as *const u8,
+                size as usize,
+            ));
+            size as i64
+        }
+
+        let state = self
+            .instance
+            .extension::<clap_plugin_state>(CLAP_EXT_STATE)?;
+        let save = state.save?;
+
+        let mut data: Vec<u8> = Vec::new();
+        let stream = clap_ostream {
+            ctx: &mut data as *mut Vec<u8> as *mut c_void,
+            write: Some(write),
+        };
+        unsafe { save(self.instance.plugin, &stream) }.then_some(data)
+    }
+
+    /// Restore a state previously saved with [`Plugin::save_state`]
+    pub fn load_state(&self, data: &[u8]) -> bool {
+        struct Reader<'a> {
+            data: &'a [u8],
+            position: usize,
+        }
+
+        unsafe extern "C" fn read(
+            stream: *const clap_istream,
+            buffer: *mut c_void,
+            size: u64,
+        ) -> i64 {
+            let reader = &mut *((*stream).ctx as *mut Reader);
+            let remaining = &reader.data[reader.position..];
+            let count = remaining.len().min(size as usize);
+            ptr::copy_nonoverlapping(remaining.as_ptr(), buffer as *mut u8, count);
+            reader.position += count;
+            count as i64
+        }
+
+        let Some(load) = self
+            .instance
+            .extension::<clap_plugin_state>(CLAP_EXT_STATE)
+            .and_then(|state| state.load)
+        else {
+            return false;
+        };
+
+        let mut reader = Reader { data, position: 0 };
+        let stream = clap_istream {
+            ctx: &mut reader as *mut Reader as *mut c_void,
+            read: Some(read),
+        };
+        unsafe { load(self.instance.plugin, &stream) }
+    }
+
+    /// Audio thread side of the plugin
+    pub fn processor(&self) -> PluginProcessor {
+        PluginProcessor {
+            instance: self.instance.clone(),
+            processing: false,
+            input: [[0.0; BLOCK_SIZE]; 2],
+            output: [[0.0; BLOCK_SIZE]; 2],
+            position: 0,
+            steady_time: 0,
+            param_events: Vec::with_capacity(MAX_PARAM_EVENTS),
+        }
+    }
+}
+
+/// Audio thread side of a plugin. Frames are processed by blocks of
+/// [`BLOCK_SIZE`], so the output is delayed by one block
+pub struct PluginProcessor {
+    instance: Arc<PluginInstance>,
+    processing: bool,
+    input: [[f32; BLOCK_SIZE]; 2],
+    output: [[f32; BLOCK_SIZE]; 2],
+    position: usize,
+    steady_time: i64,
+    param_events: Vec<clap_event_param_value>,
+}
+
+impl PluginProcessor {
+    /// Queue a parameter change for the next block
+    pub fn set_param(&mut self, id: u32, value: f64) {
+        if self.param_events.len() == MAX_PARAM_EVENTS {
+            return;
+        }
+
+        self.param_events.push(clap_event_param_value {
+            header: clap_event_header {
+                size: std::mem::size_of::<clap_event_param_value>() as u32,
+                time: 0,
+                space_id: CLAP_CORE_EVENT_SPACE_ID,
+                type_: CLAP_EVENT_PARAM_VALUE,
+                flags: 0,
+            },
+            param_id: id,
+            cookie: ptr::null_mut(),
+            note_id: -1,
+            port_index: -1,
+            channel: -1,
+            key: -1,
+            value,
+        });
+    }
+
+    pub fn process(&mut self, input: Frame) -> Frame {
+        let output = Frame::new(self.output[0][self.position], self.output[1][self.position]);
+        self.input[0][self.position] = input.left;
+        self.input[1][self.position] = input.right;
+
+        self.position += 1;
+        if self.position == BLOCK_SIZE {
+            self.process_block();
+            self.position = 0;
+        }
+
+        output
+    }
+
+    fn process_block(&mut self) {
+        unsafe extern "C" fn size(list: *const clap_input_events) -> u32 {
+            let events = &*((*list).ctx as *const Vec<clap_event_param_value>);
+            events.len() as u32
+        }
+        unsafe extern "C" fn get(
+            list: *const clap_input_events,
+            index: u32,
+        ) -> *const clap_event_header {
+            let events = &*((*list).ctx as *const Vec<clap_event_param_value>);
+            match events.get(index as usize) {
+                Some(event) => &event.header,
+                None => ptr::null(),
+            }
+        }
+        // changes made by the plugin itself are not tracked
+        unsafe extern "C" fn try_push(
+            _: *const clap_output_events,
+            _: *const clap_event_header,
+        ) -> bool {
+            true
+        }
+
+        let plugin = self.instance.plugin;
+
+        unsafe {
+            if !self.processing {
+                self.processing = (*plugin).start_processing.is_none_or(|start| start(plugin));
+            }
+
+            let mut input_channels = [self.input[0].as_mut_ptr(), self.input[1].as_mut_ptr()];
+            let mut output_channels = [self.output[0].as_mut_ptr(), self.output[1].as_mut_ptr()];
+            let audio_input = clap_audio_buffer {
+                data32: input_channels.as_mut_ptr(),
+                data64: ptr::null_mut(),
+                channel_count: 2,
+                latency: 0,
+                constant_mask: 0,
+            };
+            let mut audio_output = clap_audio_buffer {
+                data32: output_channels.as_mut_ptr(),
+                data64: ptr::null_mut(),
+                channel_count: 2,
+                latency: 0,
+                constant_mask: 0,
+            };
+            let in_events = clap_input_events {
+                ctx: &self.param_events as *const Vec<_> as *mut c_void,
+                size: Some(size),
+                get: Some(get),
+            };
+            let out_events = clap_output_events {
+                ctx: ptr::null_mut(),
+                try_push: Some(try_push),
+            };
+
+            let process = clap_process {
+                steady_time: self.steady_time,
+                frames_count: BLOCK_SIZE as u32,
+                transport: ptr::null(),
+                audio_inputs: &audio_input,
+                audio_outputs: &mut audio_output,
+                audio_inputs_count: 1,
+                audio_outputs_count: 1,
+                in_events: &in_events,
+                out_events: &out_events,
+            };
+
+            let status = match ((*plugin).process, self.processing) {
+                (Some(process_fn), true) => process_fn(plugin, &process),
+                _ => CLAP_PROCESS_ERROR,
+            };
+            if status == CLAP_PROCESS_ERROR {
+                self.output = self.input;
+            }
+        }
+
+        self.param_events.clear();
+        self.steady_time += BLOCK_SIZE as i64;
+    }
+
+    /// Stop processing, must be called on the audio thread before the
+    /// processor is handed back to the main thread
+    pub fn stop(&mut self) {
+        let plugin = self.instance.plugin;
+        if self.processing {
+            unsafe {
+                if let Some(stop_processing) = (*plugin).stop_processing {
+                    stop_processing(plugin);
+                }
+            }
+            self.processing = false;
+        }
+    }
+}
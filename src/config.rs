@@ -0,0 +1,203 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver},
+        OnceLock,
+    },
+    time::Duration,
+};
+
+use notify_debouncer_mini::{
+    new_debouncer,
+    notify::{RecommendedWatcher, RecursiveMode},
+    DebounceEventResult, Debouncer,
+};
+
+/// File marking a portable install when next to the executable
+const PORTABLE_MARKER: &str = "portable";
+
+/// Directory of the executable in portable mode, holding the configuration,
+/// the data and usually the music, e.g. on a USB stick.
+///
+/// Portable mode is enabled by a `portable` file next to the executable or
+/// by the `PORTABLE` environment variable
+pub fn portable_dir() -> Option<&'static Path> {
+    static PORTABLE_DIR: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+    PORTABLE_DIR
+        .get_or_init(|| {
+            let dir = env::current_exe().ok()?.parent()?.to_path_buf();
+            let enabled = dotenv::var("PORTABLE")
+                .is_ok_and(|value| value != "0" && value != "false")
+                || dir.join(PORTABLE_MARKER).exists();
+            enabled.then_some(dir)
+        })
+        .as_deref()
+}
+
+/// Directory holding the user configuration files (mappings, ...).
+///
+/// Defaults to the platform config directory, or the `config` folder of the
+/// portable directory, and can be overridden with the `CONFIG_DIR`
+/// environment variable
+pub fn config_dir() -> PathBuf {
+    match (dotenv::var("CONFIG_DIR"), portable_dir()) {
+        (Ok(dir), _) => PathBuf::from(dir),
+        (Err(_), Some(portable)) => portable.join("config"),
+        (Err(_), None) => dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join(env!("CARGO_PKG_NAME").to_lowercase()),
+    }
+}
+
+/// Directory holding the data written by the app (logs, session, ...), the
+/// `data` folder of the portable directory in portable mode
+pub fn data_dir() -> PathBuf {
+    match portable_dir() {
+        Some(portable) => portable.join("data"),
+        None => dirs::data_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join(env!("CARGO_PKG_NAME").to_lowercase()),
+    }
+}
+
+/// Root folder of the file explorer, from the `ROOT_DIR` environment
+/// variable. In portable mode, a relative folder is taken from the portable
+/// directory, which is also the default
+pub fn root_dir() -> Option<PathBuf> {
+    resolve_root_dir(dotenv::var("ROOT_DIR").ok(), portable_dir())
+}
+
+fn resolve_root_dir(root_dir: Option<String>, portable: Option<&Path>) -> Option<PathBuf> {
+    match (root_dir, portable) {
+        (Some(root_dir), Some(portable)) => Some(portable.join(root_dir)),
+        (Some(root_dir), None) => Some(PathBuf::from(root_dir)),
+        (None, portable) => portable.map(Path::to_path_buf),
+    }
+}
+
+/// Directory where recordings are saved.
+///
+/// Defaults to the platform music directory, or the `recordings` folder of
+/// the portable directory, and can be overridden with the `RECORDINGS_DIR`
+/// environment variable
+pub fn recordings_dir() -> PathBuf {
+    match dotenv::var("RECORDINGS_DIR") {
+        Ok(dir) => PathBuf::from(dir),
+        Err(_) => match (portable_dir(), dirs::audio_dir()) {
+            (Some(portable), _) => portable.join("recordings"),
+            (None, Some(dir)) => dir.join(env!("CARGO_PKG_NAME")),
+            (None, None) => data_dir().join("recordings"),
+        },
+    }
+}
+
+/// Directory where backups of the setup are saved.
+///
+/// Defaults to the platform documents directory, away from the files being
+/// backed up, or the `backups` folder of the portable directory, and can be
+/// overridden with the `BACKUPS_DIR` environment variable
+pub fn backups_dir() -> PathBuf {
+    match (dotenv::var("BACKUPS_DIR"), portable_dir()) {
+        (Ok(dir), _) => PathBuf::from(dir),
+        (Err(_), Some(portable)) => portable.join("backups"),
+        (Err(_), None) => match dirs::document_dir() {
+            Some(dir) => dir.join(env!("CARGO_PKG_NAME")).join("backups"),
+            None => data_dir().join("backups"),
+        },
+    }
+}
+
+/// Read a user configuration file from the config directory.
+///
+/// Returns `None` if the file does not exist so that callers can fall back
+/// to the default configuration
+pub fn read_config_file(file_name: &str) -> Option<String> {
+    let path = config_dir().join(file_name);
+
+    match fs::read_to_string(&path) {
+        Ok(content) => {
+            log::info!("Using config file '{}'", path.display());
+            Some(content)
+        }
+        Err(_) => None,
+    }
+}
+
+/// Watches the config directory so that configuration files can be applied
+/// without restarting the app
+pub struct ConfigWatcher {
+    _debouncer: Option<Debouncer<RecommendedWatcher>>,
+    receiver: Receiver<DebounceEventResult>,
+}
+
+impl ConfigWatcher {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let dir = config_dir();
+
+        // editors often write files in several steps, hence the debounce
+        let debouncer =
+            new_debouncer(Duration::from_millis(500), sender).and_then(|mut debouncer| {
+                debouncer.watcher().watch(&dir, RecursiveMode::Recursive)?;
+                Ok(debouncer)
+            });
+
+        let debouncer = match debouncer {
+            Ok(debouncer) => {
+                log::info!("Watching config dir '{}'", dir.display());
+                Some(debouncer)
+            }
+            Err(e) => {
+                log::info!("Config hot reload disabled for '{}': {}", dir.display(), e);
+                None
+            }
+        };
+
+        Self {
+            _debouncer: debouncer,
+            receiver,
+        }
+    }
+
+    /// Whether a config file changed since the last call
+    pub fn has_changed(&self) -> bool {
+        // every pending event is consumed so that a change is reported once
+        self.receiver
+            .try_iter()
+            .filter_map(|result| result.ok())
+            .flatten()
+            .filter(|event| {
+                event
+                    .path
+                    .extension()
+                    .is_some_and(|ext| ext == "toml" || ext == "rhai")
+            })
+            .count()
+            > 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_root_dir() {
+        let stick = Path::new("/media/stick");
+        assert_eq!(resolve_root_dir(None, None), None);
+        assert_eq!(
+            resolve_root_dir(None, Some(stick)),
+            Some(stick.to_path_buf())
+        );
+        assert_eq!(
+            resolve_root_dir(Some("music".to_string()), Some(stick)),
+            Some(PathBuf::from("/media/stick/music"))
+        );
+        assert_eq!(
+            resolve_root_dir(Some("/home/dj/music".to_string()), None),
+            Some(PathBuf::from("/home/dj/music"))
+        );
+    }
+}
@@ -1,6 +1,20 @@
-use std::path::Path;
+use std::{
+    path::Path,
+    time::{Duration, Instant},
+};
 
-use crate::{app::AppData, file_navigator::FileNavigatorSelection, utils::to_cover_path};
+use crate::{
+    analysis::{TrackRequest, ANALYSIS_VERSION},
+    app::AppData,
+    cue_preview,
+    file_navigator::FileNavigatorSelection,
+    layer,
+    library::TrackInfo,
+    preflight, routing,
+    stems::Stem,
+    turntable::{LoadError, MAX_PITCH, MIN_PITCH},
+    utils::to_cover_path,
+};
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum TurntableFocus {
@@ -11,20 +25,124 @@ pub enum TurntableFocus {
 #[derive(Debug)]
 pub enum BoothEvent<'a> {
     FocusChanged(TurntableFocus),
+    /// Load a track into the focused deck at once
     TrackLoad(&'a Path),
+    /// Start decoding a track in the background for the focused deck
+    TrackLoading(&'a Path),
+    /// The track decoding for a deck is ready to be put on it
+    TrackLoaded(TurntableFocus),
+    /// Load the file selected in the file navigator into a deck
+    LoadSelected(TurntableFocus),
+    /// Add the file selected in the file navigator to the prepare list
+    PrepareAdd,
+    /// Load the first track of the prepare list into a deck
+    PrepareLoad(TurntableFocus),
     CueMixChanged(f64),
+    /// Move the crossfader, from 0.0 on channel one to 1.0 on channel two
+    CrossfaderChanged(f64),
+    /// Press or release the transform button of a channel
+    Transform(TurntableFocus, bool),
     ForceApplied(f64),
     ToggleDebug,
+    ToggleHelp,
+    ToggleRecording,
+    /// Start or stop recording the knob and fader moves
+    ToggleAutomationRecording,
+    /// Replay the recorded moves, or stop replaying them
+    ToggleAutomationReplay,
+    /// Replay the end of the recorded moves
+    ReplayLastTransition,
+    ResetLoudness,
+    /// Flatten the EQ of a channel
+    ResetChannel(TurntableFocus),
+    /// Flatten every EQ and center the cue mix
+    ResetMixer,
+    /// Store the mixer as a snapshot to morph from or to
+    MorphStoreA,
+    MorphStoreB,
+    /// Glide between the mixer snapshots over the morph time
+    MorphToA,
+    MorphToB,
+    /// Blend the mixer between snapshot A, at 0.0, and B, at 1.0
+    MorphChanged(f64),
     ScratchBegin,
     ScratchEnd,
     ToggleStartStopOne,
     ToggleStartStopTwo,
+    BrakeOne,
+    BrakeTwo,
+    /// Match the tempo of a deck to the other deck, and its beats when both
+    /// play
+    SyncOne,
+    SyncTwo,
+    /// match the tempo of the focused deck to the other deck, without
+    /// moving its beats
+    CopyTempo,
+    ReturnToStartOne,
+    ReturnToStartTwo,
+    /// Mark the position of a deck in the library
+    MemoryPointAdd(TurntableFocus),
+    MemoryPointNext(TurntableFocus),
+    MemoryPointPrevious(TurntableFocus),
+    /// Mark the start of a loop on a deck
+    LoopIn(TurntableFocus),
+    /// Loop a deck from the marked start to its position
+    LoopOut(TurntableFocus),
+    LoopExit(TurntableFocus),
+    /// Keep the loop of a deck with its track, on the first free pad
+    LoopSave(TurntableFocus),
+    /// Play a saved loop of the track of a deck, or leave it if playing
+    SavedLoop(TurntableFocus, usize),
+    /// Loop 4 bars of a deck from the next phrase of its track
+    PhraseLoop(TurntableFocus),
+    LoopHalve(TurntableFocus),
+    LoopDouble(TurntableFocus),
+    /// Shift the loop of a deck by its own length
+    LoopMoveForward(TurntableFocus),
+    LoopMoveBackward(TurntableFocus),
+    /// Use an image as the cover of the track loaded in a deck
+    TrackCoverSet(TurntableFocus, &'a Path),
+    /// Set the accent color of the track loaded in a deck
+    TrackColorSet(TurntableFocus, [u8; 3]),
     ToggleCueOne,
     ToggleCueTwo,
+    ToggleInputOne,
+    ToggleInputTwo,
+    ToggleMic,
+    /// Start or stop the practice click on the cue
+    ToggleMetronome,
+    /// Tap the master tempo on the beat
+    TapTempo,
+    /// Start or stop the noise and tone generator
+    ToggleGenerator,
+    ToggleMuteMaster,
+    /// Lower the master under an announcement, or bring it back
+    DuckChanged(bool),
+    /// No deck has been heard on the master for the dead air time, or one
+    /// is heard again
+    DeadAir(bool),
+    /// Start a beatmatching round on the focused deck, or give up the
+    /// running one
+    ToggleTrainer,
+    MicRecordLevelChanged(f64),
     VolumeOneChanged(f64),
     VolumeTwoChanged(f64),
     PitchOneChanged(f64),
     PitchTwoChanged(f64),
+    /// Set the pitch of a deck back to 0%
+    PitchReset(TurntableFocus),
+    /// Raise the pitch of a deck by 0.1%
+    PitchUp(TurntableFocus),
+    /// Lower the pitch of a deck by 0.1%
+    PitchDown(TurntableFocus),
+    /// Mute or unmute a stem of the track of a deck
+    ToggleStem(TurntableFocus, Stem),
+    StemLevelChanged(TurntableFocus, Stem, f64),
+    /// Load the file selected in the browser as the layer of a deck
+    LayerLoad(TurntableFocus),
+    /// Play the layer of a deck on its beat, or stop it
+    ToggleLayer(TurntableFocus),
+    LayerLevelChanged(TurntableFocus, f64),
     EqLowOneChanged(f64),
     EqHighOneChanged(f64),
     EqLowTwoChanged(f64),
@@ -35,6 +153,13 @@ pub enum BoothEvent<'a> {
     FileNavigatorUp,
     FileNavigatorSelect,
     FileNavigatorBack,
+    FileNavigatorPageUp,
+    FileNavigatorPageDown,
+    FileNavigatorNextLetter,
+    FileNavigatorPreviousLetter,
+    /// Capture into an empty sampler pad, otherwise start or stop its loop
+    SamplerPad(usize),
+    SamplerBankLoad(&'a Path),
 }
 
 pub struct Controller {}
@@ -45,35 +170,517 @@ impl Controller {
     }
 
     pub fn handle_event(&self, app_data: &mut AppData, event: BoothEvent) {
+        app_data.automation.observe(&event, Instant::now());
+
         match (&event, &mut app_data.turntable_focus) {
             (BoothEvent::FocusChanged(focus), _) => app_data.turntable_focus = *focus,
             (BoothEvent::ToggleDebug, _) => app_data.show_debug_panel = !app_data.show_debug_panel,
+            (BoothEvent::ToggleHelp, _) => app_data.show_help = !app_data.show_help,
+            (BoothEvent::ResetLoudness, _) => app_data.mixer.reset_loudness(),
+            (BoothEvent::ResetChannel(TurntableFocus::One), _) => {
+                app_data.mixer.reset_channel_one()
+            }
+            (BoothEvent::ResetChannel(TurntableFocus::Two), _) => {
+                app_data.mixer.reset_channel_two()
+            }
+            (BoothEvent::ResetMixer, _) => app_data.mixer.reset(),
+            (BoothEvent::MorphStoreA, _) => app_data.morph.store_a(&app_data.mixer),
+            (BoothEvent::MorphStoreB, _) => app_data.morph.store_b(&app_data.mixer),
+            (BoothEvent::MorphToA, _) => {
+                let time = app_data.settings.morph_time;
+                app_data.morph.glide_to(0.0, time, &mut app_data.mixer);
+            }
+            (BoothEvent::MorphToB, _) => {
+                let time = app_data.settings.morph_time;
+                app_data.morph.glide_to(1.0, time, &mut app_data.mixer);
+            }
+            (BoothEvent::MorphChanged(position), _) => {
+                app_data.morph.set_position(*position, &mut app_data.mixer)
+            }
+            (BoothEvent::SamplerPad(pad), _) => app_data.mixer.trigger_sampler_pad(*pad),
+            (BoothEvent::SamplerBankLoad(path), _) => {
+                match app_data.mixer.sampler().load_bank(path) {
+                    Ok(()) => (),
+                    Err(e) => log::error!("Cannot load sampler bank: {}", e),
+                }
+            }
+            (BoothEvent::ToggleRecording, _) => {
+                let result = match app_data.mixer.recorder().is_recording() {
+                    true => app_data.mixer.stop_recording(),
+                    false => app_data.mixer.start_recording(
+                        app_data.settings.record_stems,
+                        &app_data.settings.record_split,
+                    ),
+                };
+                match result {
+                    Ok(()) => (),
+                    Err(e) => log::error!("Cannot toggle recording: {}", e),
+                };
+            }
+            (BoothEvent::ToggleAutomationRecording, _) => {
+                match app_data.automation.is_recording() {
+                    true => {
+                        app_data.automation.stop_recording();
+                        match app_data.automation.save() {
+                            Ok(path) => log::info!("Automation saved to '{}'", path.display()),
+                            Err(e) => log::error!("Cannot save automation: {}", e),
+                        }
+                    }
+                    false => app_data.automation.start_recording(Instant::now()),
+                }
+            }
+            (BoothEvent::ToggleAutomationReplay, _) => match app_data.automation.is_replaying() {
+                true => app_data.automation.stop_replay(),
+                false => app_data.automation.replay(0.0, Instant::now()),
+            },
+            (BoothEvent::ReplayLastTransition, _) => {
+                app_data.automation.replay_last_transition(Instant::now())
+            }
+            (BoothEvent::ToggleMetronome, _) => {
+                let metronome = app_data.mixer.metronome();
+                metronome.set_enabled(!metronome.is_enabled());
+            }
+            (BoothEvent::TapTempo, _) => app_data.master_tempo.tap(Instant::now()),
+            (BoothEvent::ToggleGenerator, _) => {
+                let generator = app_data.mixer.generator();
+                generator.set_enabled(!generator.is_enabled());
+            }
+            (BoothEvent::ToggleMuteMaster, _) => {
+                let muted = app_data.mixer.is_master_muted();
+                app_data.mixer.set_master_muted(!muted);
+            }
+            (BoothEvent::DuckChanged(ducked), _) => {
+                let settings = &app_data.settings;
+                app_data.mixer.set_duck(
+                    ducked.then_some(settings.duck_gain),
+                    Duration::from_millis(settings.duck_ramp_ms),
+                );
+            }
+            (BoothEvent::DeadAir(true), _) => {
+                let minutes = app_data.settings.dead_air_minutes;
+                log::warn!("Dead air, no deck heard for {} min", minutes);
+                app_data
+                    .toasts
+                    .warn(format!("Dead air: no deck heard for {} min", minutes));
+                if app_data.mixer.recorder().is_recording() {
+                    match app_data.mixer.stop_recording() {
+                        Ok(()) => app_data.toasts.warn("Recording stopped on dead air"),
+                        Err(e) => log::error!("Cannot stop recording: {}", e),
+                    }
+                }
+            }
+            (BoothEvent::DeadAir(false), _) => log::info!("A deck is heard again"),
+            (BoothEvent::ToggleTrainer, deck) => {
+                if app_data.trainer.is_active() {
+                    app_data.trainer.stop();
+                    return;
+                }
+
+                let deck = *deck;
+                let other = match deck {
+                    TurntableFocus::One => TurntableFocus::Two,
+                    TurntableFocus::Two => TurntableFocus::One,
+                };
+                let (Some(bpm), Some(other_bpm)) = (
+                    app_data
+                        .deck_bpm(deck)
+                        .map(|bpm| bpm / pitch(app_data, deck)),
+                    app_data.deck_bpm(other),
+                ) else {
+                    app_data
+                        .toasts
+                        .warn("Set the BPM of both tracks to train beatmatching");
+                    return;
+                };
+
+                let pitch = app_data
+                    .trainer
+                    .start(deck, other_bpm / bpm, Instant::now())
+                    .clamp(0.92, 1.08);
+                match deck {
+                    TurntableFocus::One => app_data.turntable_one.set_pitch(pitch),
+                    TurntableFocus::Two => app_data.turntable_two.set_pitch(pitch),
+                }
+            }
             (BoothEvent::CueMixChanged(mix), _) => app_data.mixer.set_cue_mix_value(*mix),
+            (BoothEvent::Transform(deck, held), _) => app_data.transform.set_held(*deck, *held),
+            (BoothEvent::CrossfaderChanged(value), _) => {
+                let before = routing::crossfader_gains(app_data.mixer.get_crossfader());
+                app_data.mixer.set_crossfader(*value);
+                let after = routing::crossfader_gains(app_data.mixer.get_crossfader());
+
+                // fader start: a deck plays while the crossfader lets it through
+                for (enabled, turntable, before, after) in [
+                    (
+                        app_data.settings.fader_start_one,
+                        &mut app_data.turntable_one,
+                        before.0,
+                        after.0,
+                    ),
+                    (
+                        app_data.settings.fader_start_two,
+                        &mut app_data.turntable_two,
+                        before.1,
+                        after.1,
+                    ),
+                ] {
+                    let (opened, closed) =
+                        (before == 0.0 && after > 0.0, before > 0.0 && after == 0.0);
+                    if enabled
+                        && turntable.currently_loaded().is_some()
+                        && ((opened && !turntable.is_playing())
+                            || (closed && turntable.is_playing()))
+                    {
+                        turntable.toggle_start_stop();
+                    }
+                }
+            }
             (BoothEvent::TrackLoad(path), TurntableFocus::One) => {
-                match app_data.turntable_one.load(path) {
-                    Ok(_) => app_data
-                        .cover_one
-                        .load_image_data(&to_cover_path(&path.to_string_lossy().to_string())),
-                    Err(e) => log::error!("Cannot load track: {:?}", e),
+                if !preflight(app_data, path) {
+                    return;
+                }
+                let stream_over = app_data.turntable_one.stream_over();
+                let result = match app_data.track_loader.take_preloaded(path, stream_over) {
+                    Some(track) => app_data.turntable_one.load_decoded(path, track),
+                    None => app_data.turntable_one.load(path),
+                };
+                match result {
+                    Ok(_) => track_loaded(app_data, TurntableFocus::One, path),
+                    Err(e) => {
+                        log::error!("Cannot load track: {:?}", e);
+                        app_data.toasts.error(format!("Cannot load track: {:?}", e));
+                    }
                 };
             }
             (BoothEvent::TrackLoad(path), TurntableFocus::Two) => {
-                match app_data.turntable_two.load(path) {
-                    Ok(_) => app_data
-                        .cover_two
-                        .load_image_data(&to_cover_path(&path.to_string_lossy().to_string())),
-                    Err(e) => log::error!("Cannot load track: {:?}", e),
+                if !preflight(app_data, path) {
+                    return;
+                }
+                let stream_over = app_data.turntable_two.stream_over();
+                let result = match app_data.track_loader.take_preloaded(path, stream_over) {
+                    Some(track) => app_data.turntable_two.load_decoded(path, track),
+                    None => app_data.turntable_two.load(path),
+                };
+                match result {
+                    Ok(_) => track_loaded(app_data, TurntableFocus::Two, path),
+                    Err(e) => {
+                        log::error!("Cannot load track: {:?}", e);
+                        app_data.toasts.error(format!("Cannot load track: {:?}", e));
+                    }
+                };
+            }
+            (BoothEvent::TrackLoading(path), deck) => {
+                let deck = *deck;
+                if !preflight(app_data, path) {
+                    return;
+                }
+                let turntable = match deck {
+                    TurntableFocus::One => &app_data.turntable_one,
+                    TurntableFocus::Two => &app_data.turntable_two,
+                };
+                if turntable.is_playing() {
+                    log::error!("Cannot load track: {:?}", LoadError::IsPlaying);
+                    app_data
+                        .toasts
+                        .error(format!("Cannot load track: {:?}", LoadError::IsPlaying));
+                    return;
+                }
+                let stream_over = turntable.stream_over();
+                if app_data.track_loader.request(deck, path, stream_over) {
+                    self.handle_event(app_data, BoothEvent::TrackLoaded(deck));
+                }
+            }
+            (BoothEvent::TrackLoaded(deck), _) => {
+                let Some((path, track)) = app_data.track_loader.take(*deck) else {
+                    return;
+                };
+                let turntable = match deck {
+                    TurntableFocus::One => &mut app_data.turntable_one,
+                    TurntableFocus::Two => &mut app_data.turntable_two,
+                };
+                match track
+                    .map_err(LoadError::from)
+                    .and_then(|track| turntable.load_decoded(&path, track))
+                {
+                    Ok(()) => track_loaded(app_data, *deck, &path),
+                    Err(e) => {
+                        log::error!("Cannot load track: {:?}", e);
+                        app_data.toasts.error(format!("Cannot load track: {:?}", e));
+                    }
+                }
+            }
+            (BoothEvent::LoadSelected(deck), _) => {
+                if let Some(file_path) = app_data.file_navigator.selected_file() {
+                    let focus = app_data.turntable_focus;
+                    app_data.turntable_focus = *deck;
+                    self.handle_event(app_data, BoothEvent::TrackLoading(Path::new(&file_path)));
+                    app_data.turntable_focus = focus;
+                }
+            }
+            (BoothEvent::PrepareAdd, _) => {
+                if let Some(file_path) = app_data.file_navigator.selected_file() {
+                    if !app_data.prepare.contains(&file_path) {
+                        app_data.prepare.push(file_path);
+                    }
+                }
+            }
+            (BoothEvent::PrepareLoad(deck), _) => {
+                let Some(file_path) = app_data.prepare.first().cloned() else {
+                    return;
+                };
+                let focus = app_data.turntable_focus;
+                app_data.turntable_focus = *deck;
+                self.handle_event(app_data, BoothEvent::TrackLoading(Path::new(&file_path)));
+                app_data.turntable_focus = focus;
+
+                let loaded = match deck {
+                    TurntableFocus::One => app_data.turntable_one.currently_loaded(),
+                    TurntableFocus::Two => app_data.turntable_two.currently_loaded(),
                 };
+                if app_data.track_loader.loading(*deck) == Some(Path::new(&file_path))
+                    || loaded.as_ref() == Some(&file_path)
+                {
+                    app_data.prepare.remove(0);
+                }
+            }
+            // starting a previewed deck puts it live from where it is
+            (BoothEvent::ToggleStartStopOne, _)
+                if app_data
+                    .cue_preview
+                    .is_some_and(|preview| preview.deck == TurntableFocus::One) =>
+            {
+                cue_preview::end(app_data, true)
+            }
+            (BoothEvent::ToggleStartStopTwo, _)
+                if app_data
+                    .cue_preview
+                    .is_some_and(|preview| preview.deck == TurntableFocus::Two) =>
+            {
+                cue_preview::end(app_data, true)
             }
             (BoothEvent::ToggleStartStopOne, _) => app_data.turntable_one.toggle_start_stop(),
             (BoothEvent::ToggleStartStopTwo, _) => app_data.turntable_two.toggle_start_stop(),
+            (BoothEvent::BrakeOne, _) => app_data.turntable_one.brake(),
+            (BoothEvent::BrakeTwo, _) => app_data.turntable_two.brake(),
+            (BoothEvent::SyncOne, _) => sync(app_data, TurntableFocus::One),
+            (BoothEvent::SyncTwo, _) => sync(app_data, TurntableFocus::Two),
+            (BoothEvent::CopyTempo, deck) => {
+                let deck = *deck;
+                copy_tempo(app_data, deck);
+            }
+            (BoothEvent::TrackCoverSet(deck, image_path), _) => {
+                let (turntable, cover) = match deck {
+                    TurntableFocus::One => (&app_data.turntable_one, &mut app_data.cover_one),
+                    TurntableFocus::Two => (&app_data.turntable_two, &mut app_data.cover_two),
+                };
+                if let Some(path) = turntable.currently_loaded() {
+                    let image_path = image_path.to_string_lossy().to_string();
+                    cover.load_image_data(&image_path);
+                    app_data.library.track_mut(&path).cover = Some(image_path);
+                    app_data.library.save();
+                }
+            }
+            (BoothEvent::TrackColorSet(deck, color), _) => {
+                let turntable = match deck {
+                    TurntableFocus::One => &app_data.turntable_one,
+                    TurntableFocus::Two => &app_data.turntable_two,
+                };
+                if let Some(path) = turntable.currently_loaded() {
+                    app_data.library.track_mut(&path).color = Some(*color);
+                    app_data.library.save();
+                }
+            }
+            (BoothEvent::MemoryPointAdd(deck), _) => {
+                let turntable = match deck {
+                    TurntableFocus::One => &app_data.turntable_one,
+                    TurntableFocus::Two => &app_data.turntable_two,
+                };
+                if let (Some(path), Some(position)) =
+                    (turntable.currently_loaded(), turntable.position())
+                {
+                    app_data.library.track_mut(&path).add_memory_point(position);
+                    app_data.library.save();
+                }
+            }
+            (BoothEvent::MemoryPointNext(deck), _) | (BoothEvent::MemoryPointPrevious(deck), _) => {
+                let turntable = match deck {
+                    TurntableFocus::One => &mut app_data.turntable_one,
+                    TurntableFocus::Two => &mut app_data.turntable_two,
+                };
+                let (Some(path), Some(position), Some(duration)) = (
+                    turntable.currently_loaded(),
+                    turntable.position(),
+                    turntable.duration(),
+                ) else {
+                    return;
+                };
+                let Some(track) = app_data.library.track(&path) else {
+                    return;
+                };
+
+                let point = match event {
+                    BoothEvent::MemoryPointNext(_) => track.next_memory_point(position),
+                    _ => track.previous_memory_point(position),
+                };
+                let Some(point) = point else {
+                    return;
+                };
+
+                let previewing = app_data
+                    .cue_preview
+                    .is_some_and(|preview| preview.deck == *deck);
+                if app_data.settings.cue_preview && (!turntable.is_playing() || previewing) {
+                    cue_preview::start(app_data, *deck, point);
+                    return;
+                }
+                match turntable.seek(point / duration) {
+                    Ok(()) => (),
+                    Err(e) => log::error!("Cannot seek to memory point: {:?}", e),
+                };
+            }
+            (BoothEvent::LoopIn(deck), _) => match deck {
+                TurntableFocus::One => app_data.turntable_one.set_loop_in(),
+                TurntableFocus::Two => app_data.turntable_two.set_loop_in(),
+            },
+            (BoothEvent::LoopOut(deck), _) => match deck {
+                TurntableFocus::One => app_data.turntable_one.loop_out(),
+                TurntableFocus::Two => app_data.turntable_two.loop_out(),
+            },
+            (BoothEvent::LoopExit(deck), _) => match deck {
+                TurntableFocus::One => app_data.turntable_one.exit_loop(),
+                TurntableFocus::Two => app_data.turntable_two.exit_loop(),
+            },
+            (BoothEvent::PhraseLoop(deck), _) => {
+                let turntable = match deck {
+                    TurntableFocus::One => &mut app_data.turntable_one,
+                    TurntableFocus::Two => &mut app_data.turntable_two,
+                };
+                let (Some(path), Some(position), Some(duration)) = (
+                    turntable.currently_loaded(),
+                    turntable.position(),
+                    turntable.duration(),
+                ) else {
+                    return;
+                };
+                match app_data
+                    .library
+                    .track(&path)
+                    .and_then(|track| track.phrase_loop(position))
+                {
+                    Some((start, end)) if end <= duration => turntable.queue_loop(start, end),
+                    Some(_) => app_data.toasts.warn("No phrase left to loop"),
+                    None => app_data
+                        .toasts
+                        .warn("Set the BPM of the track to loop on its phrases"),
+                }
+            }
+            (BoothEvent::LoopSave(deck), _) => {
+                let turntable = match deck {
+                    TurntableFocus::One => &app_data.turntable_one,
+                    TurntableFocus::Two => &app_data.turntable_two,
+                };
+                let (Some(path), Some((start, end))) =
+                    (turntable.currently_loaded(), turntable.loop_region())
+                else {
+                    return;
+                };
+                match app_data.library.track_mut(&path).save_loop(start, end) {
+                    Some(_) => app_data.library.save(),
+                    None => app_data.toasts.warn("Every saved loop pad is taken"),
+                }
+            }
+            (BoothEvent::SavedLoop(deck, pad), _) => {
+                let turntable = match deck {
+                    TurntableFocus::One => &mut app_data.turntable_one,
+                    TurntableFocus::Two => &mut app_data.turntable_two,
+                };
+                let Some(saved) = turntable
+                    .currently_loaded()
+                    .and_then(|path| app_data.library.track(&path))
+                    .and_then(|track| track.saved_loops.get(*pad))
+                else {
+                    return;
+                };
+                match turntable.loop_region() == Some((saved.start, saved.end)) {
+                    true => turntable.exit_loop(),
+                    false => turntable.set_loop(saved.start, saved.end),
+                }
+            }
+            (
+                event @ (BoothEvent::LoopHalve(deck)
+                | BoothEvent::LoopDouble(deck)
+                | BoothEvent::LoopMoveForward(deck)
+                | BoothEvent::LoopMoveBackward(deck)),
+                _,
+            ) => {
+                let turntable = match deck {
+                    TurntableFocus::One => &mut app_data.turntable_one,
+                    TurntableFocus::Two => &mut app_data.turntable_two,
+                };
+                let Some((start, end)) = turntable.loop_region() else {
+                    return;
+                };
+                let length = end - start;
+                let (start, end) = match event {
+                    BoothEvent::LoopHalve(_) => (start, start + length / 2.0),
+                    BoothEvent::LoopDouble(_) => (start, end + length),
+                    BoothEvent::LoopMoveForward(_) => (end, end + length),
+                    _ => (start - length, start),
+                };
+                let (start, end) = match turntable
+                    .currently_loaded()
+                    .and_then(|path| app_data.library.track(&path))
+                {
+                    Some(track) if app_data.settings.quantize_loops => {
+                        track.quantize_loop(start, end)
+                    }
+                    _ => (start, end),
+                };
+                turntable.change_loop(start, end);
+            }
+            (BoothEvent::ReturnToStartOne, _) => {
+                match app_data.turntable_one.return_to_start() {
+                    Ok(()) => (),
+                    Err(e) => log::error!("Cannot return to start on deck one: {:?}", e),
+                };
+            }
+            (BoothEvent::ReturnToStartTwo, _) => {
+                match app_data.turntable_two.return_to_start() {
+                    Ok(()) => (),
+                    Err(e) => log::error!("Cannot return to start on deck two: {:?}", e),
+                };
+            }
             (BoothEvent::ToggleCueOne, _) => {
                 let cue = app_data.mixer.is_cue_one_enabled();
-                app_data.mixer.set_cue_one(!cue);
+                set_cue(app_data, TurntableFocus::One, !cue);
             }
             (BoothEvent::ToggleCueTwo, _) => {
                 let cue = app_data.mixer.is_cue_two_enabled();
-                app_data.mixer.set_cue_two(!cue);
+                set_cue(app_data, TurntableFocus::Two, !cue);
+            }
+            (BoothEvent::ToggleInputOne, _) => {
+                let enabled = app_data.mixer.is_input_one_enabled();
+                match app_data.mixer.set_input_one(!enabled) {
+                    Ok(()) => (),
+                    Err(e) => log::error!("Cannot toggle input one: {}", e),
+                };
+            }
+            (BoothEvent::ToggleInputTwo, _) => {
+                let enabled = app_data.mixer.is_input_two_enabled();
+                match app_data.mixer.set_input_two(!enabled) {
+                    Ok(()) => (),
+                    Err(e) => log::error!("Cannot toggle input two: {}", e),
+                };
+            }
+            (BoothEvent::ToggleMic, _) => {
+                let enabled = app_data.mixer.is_mic_enabled();
+                match app_data.mixer.set_mic(!enabled) {
+                    Ok(()) => (),
+                    Err(e) => log::error!("Cannot toggle mic: {}", e),
+                };
+            }
+            (BoothEvent::MicRecordLevelChanged(level), _) => {
+                app_data.mixer.set_mic_record_level(*level);
             }
             (BoothEvent::VolumeOneChanged(volume), _) => {
                 app_data.mixer.set_ch_one_volume(*volume);
@@ -87,6 +694,83 @@ impl Controller {
             (BoothEvent::PitchTwoChanged(pitch), _) => {
                 app_data.turntable_two.set_pitch(*pitch);
             }
+            (BoothEvent::PitchReset(deck), _) => match deck {
+                TurntableFocus::One => app_data.turntable_one.set_pitch(1.0),
+                TurntableFocus::Two => app_data.turntable_two.set_pitch(1.0),
+            },
+            (BoothEvent::PitchUp(deck), _) => match deck {
+                TurntableFocus::One => app_data.turntable_one.step_pitch(1.0),
+                TurntableFocus::Two => app_data.turntable_two.step_pitch(1.0),
+            },
+            (BoothEvent::PitchDown(deck), _) => match deck {
+                TurntableFocus::One => app_data.turntable_one.step_pitch(-1.0),
+                TurntableFocus::Two => app_data.turntable_two.step_pitch(-1.0),
+            },
+            (BoothEvent::ToggleStem(deck, stem), _) => {
+                let stems = match deck {
+                    TurntableFocus::One => app_data.turntable_one.stems_mut(),
+                    TurntableFocus::Two => app_data.turntable_two.stems_mut(),
+                };
+                stems.set_muted(*stem, !stems.is_muted(*stem));
+            }
+            (BoothEvent::LayerLoad(deck), _) => {
+                let Some(file_path) = app_data.file_navigator.selected_file() else {
+                    return;
+                };
+                let path = Path::new(&file_path);
+                let layer = match deck {
+                    TurntableFocus::One => &mut app_data.layer_one,
+                    TurntableFocus::Two => &mut app_data.layer_two,
+                };
+                match layer.load(path) {
+                    Ok(_) => request_analysis(app_data, path),
+                    Err(e) => {
+                        log::error!("Cannot load layer: {:?}", e);
+                        app_data.toasts.error(format!("Cannot load layer: {:?}", e));
+                    }
+                }
+            }
+            (BoothEvent::ToggleLayer(deck), _) => {
+                let layer = match deck {
+                    TurntableFocus::One => &app_data.layer_one,
+                    TurntableFocus::Two => &app_data.layer_two,
+                };
+                let track = layer
+                    .currently_loaded()
+                    .and_then(|path| app_data.library.track(&path));
+                let playing = layer.is_playing();
+
+                // a deck playing on its beat grid starts the layer in phase
+                let deck_playing = match deck {
+                    TurntableFocus::One => app_data.turntable_one.is_playing(),
+                    TurntableFocus::Two => app_data.turntable_two.is_playing(),
+                };
+                let start = match (track, app_data.beat_phase(*deck), deck_playing) {
+                    (Some(track), Some(phase), true) => track
+                        .bpm
+                        .map(|bpm| layer::aligned_start(track.first_beat, bpm, phase)),
+                    _ => None,
+                }
+                .unwrap_or(0.0);
+
+                let layer = match deck {
+                    TurntableFocus::One => &mut app_data.layer_one,
+                    TurntableFocus::Two => &mut app_data.layer_two,
+                };
+                if playing {
+                    layer.stop();
+                } else if let Err(e) = layer.play(start) {
+                    log::error!("Cannot play layer: {:?}", e);
+                }
+            }
+            (BoothEvent::LayerLevelChanged(deck, level), _) => match deck {
+                TurntableFocus::One => app_data.layer_one.set_level(*level),
+                TurntableFocus::Two => app_data.layer_two.set_level(*level),
+            },
+            (BoothEvent::StemLevelChanged(deck, stem, level), _) => match deck {
+                TurntableFocus::One => app_data.turntable_one.stems_mut().set_level(*stem, *level),
+                TurntableFocus::Two => app_data.turntable_two.stems_mut().set_level(*stem, *level),
+            },
             (BoothEvent::EqLowOneChanged(gain), _) => {
                 app_data.mixer.set_eq_low_one_gain(*gain);
             }
@@ -135,6 +819,12 @@ impl Controller {
             (BoothEvent::FileNavigatorDown, _) => {
                 app_data.file_navigator.go_down();
             }
+            (BoothEvent::FileNavigatorPageUp, _) => app_data.file_navigator.page_up(),
+            (BoothEvent::FileNavigatorPageDown, _) => app_data.file_navigator.page_down(),
+            (BoothEvent::FileNavigatorNextLetter, _) => app_data.file_navigator.next_letter(),
+            (BoothEvent::FileNavigatorPreviousLetter, _) => {
+                app_data.file_navigator.previous_letter()
+            }
             (BoothEvent::FileNavigatorBack, _) => match app_data.file_navigator.go_back() {
                 Err(e) => log::error!("{}", e),
                 _ => (),
@@ -142,7 +832,16 @@ impl Controller {
             (BoothEvent::FileNavigatorSelect, TurntableFocus::One) => {
                 match app_data.file_navigator.select() {
                     FileNavigatorSelection::File(file_path) => {
-                        self.handle_event(app_data, BoothEvent::TrackLoad(Path::new(&file_path)));
+                        self.handle_event(
+                            app_data,
+                            BoothEvent::TrackLoading(Path::new(&file_path)),
+                        );
+                    }
+                    FileNavigatorSelection::Bank(file_path) => {
+                        self.handle_event(
+                            app_data,
+                            BoothEvent::SamplerBankLoad(Path::new(&file_path)),
+                        );
                     }
                     _ => (),
                 }
@@ -150,7 +849,16 @@ impl Controller {
             (BoothEvent::FileNavigatorSelect, TurntableFocus::Two) => {
                 match app_data.file_navigator.select() {
                     FileNavigatorSelection::File(file_path) => {
-                        self.handle_event(app_data, BoothEvent::TrackLoad(Path::new(&file_path)));
+                        self.handle_event(
+                            app_data,
+                            BoothEvent::TrackLoading(Path::new(&file_path)),
+                        );
+                    }
+                    FileNavigatorSelection::Bank(file_path) => {
+                        self.handle_event(
+                            app_data,
+                            BoothEvent::SamplerBankLoad(Path::new(&file_path)),
+                        );
                     }
                     _ => (),
                 }
@@ -158,3 +866,566 @@ impl Controller {
         }
     }
 }
+
+/// Check a track before loading it, reporting problems as toasts. Returns
+/// whether the track can be loaded
+fn preflight(app_data: &mut AppData, path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy();
+
+    match preflight::check(path, app_data.mixer.sample_rate()) {
+        Ok(warnings) => {
+            for warning in warnings {
+                log::warn!("'{}': {}", path.display(), warning);
+                app_data.toasts.warn(format!("{}: {}", name, warning));
+            }
+            true
+        }
+        Err(e) => {
+            log::error!("Cannot load '{}': {}", path.display(), e);
+            app_data
+                .toasts
+                .error(format!("Cannot load {}: {}", name, e));
+            false
+        }
+    }
+}
+
+/// Follow up a track loaded on `deck`: cue it if asked, show its cover and
+/// analyze it
+fn track_loaded(app_data: &mut AppData, deck: TurntableFocus, path: &Path) {
+    app_data.mixer.track_loaded();
+    if app_data.settings.auto_cue {
+        set_cue(app_data, deck, true);
+    }
+    let cover_path = cover_path(app_data, path);
+    match deck {
+        TurntableFocus::One => app_data.cover_one.load_image_data(&cover_path),
+        TurntableFocus::Two => app_data.cover_two.load_image_data(&cover_path),
+    }
+    let turntable = match deck {
+        TurntableFocus::One => &app_data.turntable_one,
+        TurntableFocus::Two => &app_data.turntable_two,
+    };
+    let path = path.to_string_lossy();
+    app_data.analyzer.request_track(
+        &path,
+        TrackRequest {
+            analysis: needs_analysis(app_data, &path),
+            vocal_detector: app_data.settings.vocal_detector.clone(),
+            overview: !app_data
+                .overviews
+                .iter()
+                .any(|(overview_path, _)| *overview_path == path),
+            streamed: turntable.is_streaming(),
+        },
+    );
+    cue_first_beat(app_data, deck);
+}
+
+/// Mark the first downbeat of the analyzed track on `deck` when it has no
+/// memory points yet, and cue the deck there if it is stopped
+pub fn cue_first_beat(app_data: &mut AppData, deck: TurntableFocus) {
+    if !app_data.settings.cue_first_beat {
+        return;
+    }
+    let turntable = match deck {
+        TurntableFocus::One => &mut app_data.turntable_one,
+        TurntableFocus::Two => &mut app_data.turntable_two,
+    };
+    let (Some(path), Some(duration)) = (turntable.currently_loaded(), turntable.duration()) else {
+        return;
+    };
+    let Some(point) = app_data
+        .library
+        .track(&path)
+        .filter(|track| track.memory_points.is_empty() && track.analysis.is_some())
+        .and_then(TrackInfo::first_downbeat)
+    else {
+        return;
+    };
+
+    app_data.library.track_mut(&path).add_memory_point(point);
+    app_data.library.save();
+    if !turntable.is_playing() {
+        if let Err(e) = turntable.seek(point / duration) {
+            log::error!("Cannot cue the first downbeat: {:?}", e);
+        }
+    }
+}
+
+/// Cover of a track, the one set in the library or else the `cover.jpg` of
+/// its folder
+pub fn cover_path(app_data: &AppData, path: &Path) -> String {
+    let path = path.to_string_lossy().to_string();
+
+    app_data
+        .library
+        .track(&path)
+        .and_then(|track| track.cover.clone())
+        .unwrap_or_else(|| to_cover_path(&path))
+}
+
+/// Queue a track for analysis, unless the library already has its result
+/// from the current analysis
+fn request_analysis(app_data: &AppData, path: &Path) {
+    let path = path.to_string_lossy();
+
+    if needs_analysis(app_data, &path) {
+        app_data
+            .analyzer
+            .request(&path, app_data.settings.vocal_detector.as_deref());
+    }
+}
+
+/// Whether the library lacks the result of the current analysis of a track
+fn needs_analysis(app_data: &AppData, path: &str) -> bool {
+    app_data
+        .library
+        .track(path)
+        .and_then(|track| track.analysis)
+        .is_none_or(|analysis| analysis.version < ANALYSIS_VERSION)
+}
+
+fn pitch(app_data: &AppData, deck: TurntableFocus) -> f64 {
+    match deck {
+        TurntableFocus::One => app_data.turntable_one.pitch(),
+        TurntableFocus::Two => app_data.turntable_two.pitch(),
+    }
+}
+
+/// Set the pitch of `deck` so that it plays at the tempo of the other deck,
+/// a track at half or double the tempo playing a beat for two. When both
+/// decks play on their beat grid at the same tempo, `deck` also moves onto
+/// the nearest beat of the other one
+fn sync(app_data: &mut AppData, deck: TurntableFocus) {
+    if app_data.trainer.is_active() {
+        app_data
+            .toasts
+            .warn("Sync is off while training beatmatching");
+        return;
+    }
+
+    let other = match deck {
+        TurntableFocus::One => TurntableFocus::Two,
+        TurntableFocus::Two => TurntableFocus::One,
+    };
+    let (Some(bpm), Some(other_bpm)) = (
+        app_data
+            .deck_bpm(deck)
+            .map(|bpm| bpm / pitch(app_data, deck)),
+        app_data.deck_bpm(other),
+    ) else {
+        app_data
+            .toasts
+            .warn("Set the BPM of both tracks to sync them");
+        return;
+    };
+
+    let target = sync_tempo(bpm, other_bpm);
+    if !(MIN_PITCH..=MAX_PITCH).contains(&(target / bpm)) {
+        app_data.toasts.warn(format!(
+            "The tempo of {:.1} BPM is out of pitch range",
+            target
+        ));
+        return;
+    }
+    match deck {
+        TurntableFocus::One => app_data.turntable_one.set_pitch(target / bpm),
+        TurntableFocus::Two => app_data.turntable_two.set_pitch(target / bpm),
+    }
+
+    let playing = app_data.turntable_one.is_playing() && app_data.turntable_two.is_playing();
+    let (true, true, Some(phase), Some(other_phase)) = (
+        playing,
+        target == other_bpm,
+        app_data.beat_phase(deck),
+        app_data.beat_phase(other),
+    ) else {
+        return;
+    };
+    let turntable = match deck {
+        TurntableFocus::One => &mut app_data.turntable_one,
+        TurntableFocus::Two => &mut app_data.turntable_two,
+    };
+    let (None, Some(position), Some(duration)) = (
+        turntable.loop_region(),
+        turntable.position(),
+        turntable.duration(),
+    ) else {
+        return;
+    };
+    let position = position + beat_offset(phase, other_phase) * 60.0 / bpm;
+    if let Err(e) = turntable.seek((position / duration).clamp(0.0, 1.0)) {
+        log::error!("Cannot sync the beats: {:?}", e);
+    }
+}
+
+/// Set the pitch of `deck` so that it plays at the current tempo of the
+/// other deck, once, leaving its beats for the DJ to match by hand
+fn copy_tempo(app_data: &mut AppData, deck: TurntableFocus) {
+    if app_data.trainer.is_active() {
+        app_data
+            .toasts
+            .warn("Copying the tempo is off while training beatmatching");
+        return;
+    }
+
+    let other = match deck {
+        TurntableFocus::One => TurntableFocus::Two,
+        TurntableFocus::Two => TurntableFocus::One,
+    };
+    let (Some(bpm), Some(other_bpm)) = (
+        app_data
+            .deck_bpm(deck)
+            .map(|bpm| bpm / pitch(app_data, deck)),
+        app_data.deck_bpm(other),
+    ) else {
+        app_data
+            .toasts
+            .warn("Set the BPM of both tracks to copy the tempo");
+        return;
+    };
+
+    let pitch = other_bpm / bpm;
+    if !(MIN_PITCH..=MAX_PITCH).contains(&pitch) {
+        app_data.toasts.warn(format!(
+            "The tempo of {:.1} BPM is out of pitch range",
+            other_bpm
+        ));
+        return;
+    }
+    match deck {
+        TurntableFocus::One => app_data.turntable_one.set_pitch(pitch),
+        TurntableFocus::Two => app_data.turntable_two.set_pitch(pitch),
+    }
+}
+
+/// Tempo among the tempo of the other deck, its half and its double, nearest
+/// to `bpm`
+fn sync_tempo(bpm: f64, other_bpm: f64) -> f64 {
+    [other_bpm / 2.0, other_bpm, other_bpm * 2.0]
+        .into_iter()
+        .min_by(|a, b| (a / bpm).ln().abs().total_cmp(&(b / bpm).ln().abs()))
+        .unwrap_or(other_bpm)
+}
+
+/// Beats to move a deck at beat phase `phase` by, forward or back, to land
+/// on the nearest beat at `other_phase`
+fn beat_offset(phase: f64, other_phase: f64) -> f64 {
+    (other_phase - phase + 0.5).rem_euclid(1.0) - 0.5
+}
+
+/// Enable or disable the cue of a channel, disabling the cue of the other
+/// one if cue is exclusive
+fn set_cue(app_data: &mut AppData, channel: TurntableFocus, enabled: bool) {
+    let exclusive = enabled && app_data.settings.exclusive_cue;
+
+    match channel {
+        TurntableFocus::One => {
+            app_data.mixer.set_cue_one(enabled);
+            if exclusive {
+                app_data.mixer.set_cue_two(false);
+            }
+        }
+        TurntableFocus::Two => {
+            app_data.mixer.set_cue_two(enabled);
+            if exclusive {
+                app_data.mixer.set_cue_one(false);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        audio_backend::testing::{constant_wav, render},
+        mapping::KeyMap,
+        processable::Processable,
+        settings::Settings,
+    };
+
+    #[test]
+    fn test_load_and_play() {
+        let path = constant_wav("controller", 1.0, 0.5);
+        let root_dir = std::env::temp_dir().to_string_lossy().to_string();
+        let mut app_data = AppData::new(Settings::default(), KeyMap::load(), &root_dir);
+        let controller = Controller::new();
+
+        controller.handle_event(&mut app_data, BoothEvent::FocusChanged(TurntableFocus::Two));
+        controller.handle_event(&mut app_data, BoothEvent::TrackLoad(&path));
+        assert_eq!(
+            app_data.turntable_two.currently_loaded().as_deref(),
+            path.to_str()
+        );
+        assert!((app_data.turntable_two.duration().unwrap() - 1.0).abs() < 1e-3);
+
+        controller.handle_event(&mut app_data, BoothEvent::CueMixChanged(1.0));
+        controller.handle_event(&mut app_data, BoothEvent::VolumeTwoChanged(1.0));
+        controller.handle_event(&mut app_data, BoothEvent::PitchTwoChanged(1.5));
+        assert_eq!(app_data.turntable_two.pitch(), 1.08);
+        controller.handle_event(&mut app_data, BoothEvent::PitchUp(TurntableFocus::Two));
+        assert_eq!(app_data.turntable_two.pitch(), 1.08);
+        controller.handle_event(&mut app_data, BoothEvent::PitchReset(TurntableFocus::Two));
+        controller.handle_event(&mut app_data, BoothEvent::PitchDown(TurntableFocus::Two));
+        assert_eq!(app_data.turntable_two.pitch(), 0.999);
+        controller.handle_event(&mut app_data, BoothEvent::PitchReset(TurntableFocus::Two));
+        assert_eq!(app_data.turntable_two.pitch(), 1.0);
+
+        controller.handle_event(&mut app_data, BoothEvent::ToggleStartStopTwo);
+        for _ in 0..50 {
+            app_data.turntable_two.process(0.02);
+        }
+        let manager = app_data.mixer.get_audio_manager();
+        let level = render(&mut manager.lock().unwrap(), 4800).left;
+        assert!((level - 0.5).abs() < 1e-3);
+
+        // positions are published at the start of the next render
+        render(&mut manager.lock().unwrap(), 1);
+        assert!(app_data.turntable_two.position().unwrap() > 0.09);
+
+        controller.handle_event(&mut app_data, BoothEvent::ToggleCueTwo);
+        assert!(app_data.mixer.is_cue_two_enabled());
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_load_in_background() {
+        let path = constant_wav("background", 0.5, 0.5);
+        let root_dir = std::env::temp_dir().to_string_lossy().to_string();
+        let mut app_data = AppData::new(Settings::default(), KeyMap::load(), &root_dir);
+        let controller = Controller::new();
+
+        controller.handle_event(&mut app_data, BoothEvent::TrackLoading(&path));
+        assert_eq!(
+            app_data.track_loader.loading(TurntableFocus::One),
+            Some(&*path)
+        );
+        assert!(app_data.turntable_one.currently_loaded().is_none());
+
+        let mut loaded = Vec::new();
+        for _ in 0..500 {
+            loaded = app_data.track_loader.poll();
+            if !loaded.is_empty() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(loaded, vec![TurntableFocus::One]);
+        controller.handle_event(&mut app_data, BoothEvent::TrackLoaded(TurntableFocus::One));
+        assert!(!app_data.track_loader.is_loading());
+        assert_eq!(
+            app_data.turntable_one.currently_loaded().as_deref(),
+            path.to_str()
+        );
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_stems() {
+        let path = constant_wav("stems", 0.5, 0.9);
+        let folder = path.with_extension("stems");
+        std::fs::create_dir_all(&folder).unwrap();
+        for (name, level) in [
+            ("vocals", 0.1),
+            ("drums", 0.2),
+            ("bass", 0.1),
+            ("other", 0.2),
+        ] {
+            let stem = constant_wav(&format!("stems-{}", name), 0.5, level);
+            std::fs::rename(stem, folder.join(format!("{}.wav", name))).unwrap();
+        }
+
+        let root_dir = std::env::temp_dir().to_string_lossy().to_string();
+        let mut app_data = AppData::new(Settings::default(), KeyMap::load(), &root_dir);
+        let controller = Controller::new();
+        controller.handle_event(&mut app_data, BoothEvent::TrackLoad(&path));
+        assert!(app_data.turntable_one.has_stems());
+        controller.handle_event(&mut app_data, BoothEvent::CueMixChanged(1.0));
+        controller.handle_event(&mut app_data, BoothEvent::VolumeOneChanged(1.0));
+        controller.handle_event(&mut app_data, BoothEvent::ToggleStartStopOne);
+        for _ in 0..50 {
+            app_data.turntable_one.process(0.02);
+        }
+        let manager = app_data.mixer.get_audio_manager();
+
+        // the stems are heard instead of the mix
+        let level = render(&mut manager.lock().unwrap(), 4800).left;
+        assert!((level - 0.6).abs() < 1e-3);
+
+        let vocals = BoothEvent::ToggleStem(TurntableFocus::One, Stem::Vocals);
+        controller.handle_event(&mut app_data, vocals);
+        let drums = BoothEvent::StemLevelChanged(TurntableFocus::One, Stem::Drums, 0.5);
+        controller.handle_event(&mut app_data, drums);
+        let level = render(&mut manager.lock().unwrap(), 4800).left;
+        assert!((level - 0.4).abs() < 1e-3);
+
+        let _ = std::fs::remove_dir_all(folder);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_fader_start() {
+        let path = constant_wav("fader_start", 0.5, 0.5);
+        let root_dir = std::env::temp_dir().to_string_lossy().to_string();
+        let settings = Settings {
+            fader_start_one: true,
+            ..Default::default()
+        };
+        let mut app_data = AppData::new(settings, KeyMap::load(), &root_dir);
+        let controller = Controller::new();
+        controller.handle_event(&mut app_data, BoothEvent::TrackLoad(&path));
+
+        controller.handle_event(&mut app_data, BoothEvent::CrossfaderChanged(1.0));
+        assert!(!app_data.turntable_one.is_playing());
+        controller.handle_event(&mut app_data, BoothEvent::CrossfaderChanged(0.9));
+        assert!(app_data.turntable_one.is_playing());
+        // the deck keeps playing while the crossfader lets it through
+        controller.handle_event(&mut app_data, BoothEvent::CrossfaderChanged(0.2));
+        assert!(app_data.turntable_one.is_playing());
+        controller.handle_event(&mut app_data, BoothEvent::CrossfaderChanged(1.0));
+        assert!(!app_data.turntable_one.is_playing());
+
+        // deck two is left alone
+        assert!(!app_data.turntable_two.is_playing());
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_cue_options() {
+        let path = constant_wav("cue", 0.1, 0.5);
+        let root_dir = std::env::temp_dir().to_string_lossy().to_string();
+        let settings = Settings {
+            exclusive_cue: true,
+            auto_cue: true,
+            ..Default::default()
+        };
+        let mut app_data = AppData::new(settings, KeyMap::load(), &root_dir);
+        let controller = Controller::new();
+
+        controller.handle_event(&mut app_data, BoothEvent::ToggleCueOne);
+        assert!(app_data.mixer.is_cue_one_enabled());
+
+        controller.handle_event(&mut app_data, BoothEvent::FocusChanged(TurntableFocus::Two));
+        controller.handle_event(&mut app_data, BoothEvent::TrackLoad(&path));
+        assert!(app_data.mixer.is_cue_two_enabled());
+        assert!(!app_data.mixer.is_cue_one_enabled());
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_sync_tempo() {
+        assert_eq!(sync_tempo(120.0, 126.0), 126.0);
+        // a track at half or double the tempo plays a beat for two
+        assert_eq!(sync_tempo(64.0, 126.0), 63.0);
+        assert_eq!(sync_tempo(250.0, 126.0), 252.0);
+
+        assert!((beat_offset(0.1, 0.3) - 0.2).abs() < 1e-9);
+        assert!((beat_offset(0.3, 0.1) + 0.2).abs() < 1e-9);
+        // across the beat, the nearest way round
+        assert!((beat_offset(0.9, 0.1) - 0.2).abs() < 1e-9);
+        assert!((beat_offset(0.1, 0.9) + 0.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_sync() {
+        let path_one = constant_wav("sync_one", 10.0, 0.5);
+        let path_two = constant_wav("sync_two", 10.0, 0.5);
+        let root_dir = std::env::temp_dir().to_string_lossy().to_string();
+        let mut app_data = AppData::new(Settings::default(), KeyMap::load(), &root_dir);
+        let controller = Controller::new();
+        controller.handle_event(&mut app_data, BoothEvent::TrackLoad(&path_one));
+        controller.handle_event(&mut app_data, BoothEvent::FocusChanged(TurntableFocus::Two));
+        controller.handle_event(&mut app_data, BoothEvent::TrackLoad(&path_two));
+        let set_bpm = |app_data: &mut AppData, path: &Path, bpm: f64| {
+            app_data.library.track_mut(path.to_str().unwrap()).bpm = Some(bpm);
+        };
+
+        // without a BPM nothing moves
+        controller.handle_event(&mut app_data, BoothEvent::SyncOne);
+        assert_eq!(app_data.turntable_one.pitch(), 1.0);
+
+        set_bpm(&mut app_data, &path_one, 120.0);
+        set_bpm(&mut app_data, &path_two, 123.0);
+        controller.handle_event(&mut app_data, BoothEvent::SyncOne);
+        assert!((app_data.turntable_one.pitch() - 1.025).abs() < 1e-9);
+
+        set_bpm(&mut app_data, &path_one, 64.0);
+        controller.handle_event(&mut app_data, BoothEvent::SyncOne);
+        assert!((app_data.turntable_one.pitch() - 61.5 / 64.0).abs() < 1e-9);
+
+        // out of the pitch range the pitch is left alone
+        set_bpm(&mut app_data, &path_one, 100.0);
+        controller.handle_event(&mut app_data, BoothEvent::SyncOne);
+        assert!((app_data.turntable_one.pitch() - 61.5 / 64.0).abs() < 1e-9);
+
+        // playing at the same tempo, deck one moves onto the beats of deck two
+        set_bpm(&mut app_data, &path_one, 120.0);
+        set_bpm(&mut app_data, &path_two, 120.0);
+        controller.handle_event(&mut app_data, BoothEvent::ToggleStartStopOne);
+        controller.handle_event(&mut app_data, BoothEvent::ToggleStartStopTwo);
+        for _ in 0..50 {
+            app_data.turntable_one.process(0.02);
+            app_data.turntable_two.process(0.02);
+        }
+        app_data.turntable_two.seek(0.11).unwrap();
+        let manager = app_data.mixer.get_audio_manager();
+        let phases = |app_data: &AppData| {
+            // positions are published at the start of the next render
+            render(&mut manager.lock().unwrap(), 4800);
+            render(&mut manager.lock().unwrap(), 1);
+            (
+                app_data.beat_phase(TurntableFocus::One).unwrap(),
+                app_data.beat_phase(TurntableFocus::Two).unwrap(),
+            )
+        };
+        let (phase_one, phase_two) = phases(&app_data);
+        assert!((beat_offset(phase_one, phase_two) - 0.2).abs() < 0.01);
+        controller.handle_event(&mut app_data, BoothEvent::SyncOne);
+        assert_eq!(app_data.turntable_one.pitch(), 1.0);
+        let (phase_one, phase_two) = phases(&app_data);
+        assert!(beat_offset(phase_one, phase_two).abs() < 0.01);
+
+        let _ = std::fs::remove_file(path_one);
+        let _ = std::fs::remove_file(path_two);
+    }
+
+    #[test]
+    fn test_copy_tempo() {
+        let path_one = constant_wav("copy_tempo_one", 1.0, 0.5);
+        let path_two = constant_wav("copy_tempo_two", 1.0, 0.5);
+        let root_dir = std::env::temp_dir().to_string_lossy().to_string();
+        let mut app_data = AppData::new(Settings::default(), KeyMap::load(), &root_dir);
+        let controller = Controller::new();
+        controller.handle_event(&mut app_data, BoothEvent::TrackLoad(&path_one));
+        controller.handle_event(&mut app_data, BoothEvent::FocusChanged(TurntableFocus::Two));
+        controller.handle_event(&mut app_data, BoothEvent::TrackLoad(&path_two));
+        let set_bpm = |app_data: &mut AppData, path: &Path, bpm: f64| {
+            app_data.library.track_mut(path.to_str().unwrap()).bpm = Some(bpm);
+        };
+        set_bpm(&mut app_data, &path_one, 123.0);
+        set_bpm(&mut app_data, &path_two, 120.0);
+        app_data.turntable_one.set_pitch(1.02);
+
+        // the focused deck takes the tempo of the other one, pitch included
+        controller.handle_event(&mut app_data, BoothEvent::CopyTempo);
+        assert!((app_data.deck_bpm(TurntableFocus::Two).unwrap() - 125.46).abs() < 1e-9);
+        assert_eq!(app_data.turntable_one.pitch(), 1.02);
+
+        // not half or double the tempo, unlike sync, out of the pitch range
+        set_bpm(&mut app_data, &path_two, 64.0);
+        controller.handle_event(&mut app_data, BoothEvent::CopyTempo);
+        assert!((app_data.turntable_two.pitch() - 125.46 / 120.0).abs() < 1e-9);
+
+        let _ = std::fs::remove_file(path_one);
+        let _ = std::fs::remove_file(path_two);
+    }
+}
@@ -0,0 +1,83 @@
+use crate::{app::AppData, controller::TurntableFocus};
+
+/// Seconds a memory point is previewed for
+pub const PREVIEW_SECONDS: f64 = 4.0;
+
+/// A stopped deck played from one of its memory points into the cue only,
+/// to check the placement of the point without being heard on the master
+#[derive(Debug, Clone, Copy)]
+pub struct CuePreview {
+    pub deck: TurntableFocus,
+    /// memory point the deck returns to once the preview is over
+    pub point: f64,
+    remaining: f64,
+}
+
+/// Play `deck` from `point` into the cue only, ending any other preview
+pub fn start(app_data: &mut AppData, deck: TurntableFocus, point: f64) {
+    end(app_data, false);
+
+    let turntable = match deck {
+        TurntableFocus::One => &mut app_data.turntable_one,
+        TurntableFocus::Two => &mut app_data.turntable_two,
+    };
+    let Some(duration) = turntable.duration() else {
+        return;
+    };
+    if let Err(e) = turntable.seek(point / duration) {
+        log::error!("Cannot seek to memory point: {:?}", e);
+        return;
+    }
+    turntable.toggle_start_stop();
+
+    match deck {
+        TurntableFocus::One => app_data.mixer.set_preview_one(true),
+        TurntableFocus::Two => app_data.mixer.set_preview_two(true),
+    }
+    app_data.cue_preview = Some(CuePreview {
+        deck,
+        point,
+        remaining: PREVIEW_SECONDS,
+    });
+}
+
+/// End the preview, putting the deck back on the master. The deck keeps
+/// playing if `keep_playing`, e.g. when started during the preview, and is
+/// otherwise stopped back on its memory point
+pub fn end(app_data: &mut AppData, keep_playing: bool) {
+    let Some(preview) = app_data.cue_preview.take() else {
+        return;
+    };
+
+    let turntable = match preview.deck {
+        TurntableFocus::One => &mut app_data.turntable_one,
+        TurntableFocus::Two => &mut app_data.turntable_two,
+    };
+    if !keep_playing {
+        if turntable.is_playing() {
+            turntable.toggle_start_stop();
+        }
+        if let Some(duration) = turntable.duration() {
+            if let Err(e) = turntable.seek(preview.point / duration) {
+                log::error!("Cannot seek back to memory point: {:?}", e);
+            }
+        }
+    }
+
+    match preview.deck {
+        TurntableFocus::One => app_data.mixer.set_preview_one(false),
+        TurntableFocus::Two => app_data.mixer.set_preview_two(false),
+    }
+}
+
+/// Count down the running preview, ending it once over
+pub fn process(app_data: &mut AppData, delta: f64) {
+    let Some(preview) = app_data.cue_preview.as_mut() else {
+        return;
+    };
+
+    preview.remaining -= delta;
+    if preview.remaining <= 0.0 {
+        end(app_data, false);
+    }
+}
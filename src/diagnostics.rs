@@ -0,0 +1,142 @@
+use std::{
+    fs, io,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use cpal::traits::{DeviceTrait, HostTrait};
+use serde::Serialize;
+
+use crate::{app::AppData, config::data_dir, logger, mixer::Mixer, turntable::Turntable};
+
+/// Input events kept for diagnostic snapshots
+pub const MAX_RECENT_EVENTS: usize = 100;
+
+#[derive(Debug, Serialize)]
+struct DeckSnapshot {
+    path: Option<String>,
+    position: Option<f64>,
+    duration: Option<f64>,
+    playing: bool,
+    pitch: f64,
+    speed: f64,
+}
+
+impl DeckSnapshot {
+    fn capture(turntable: &Turntable) -> Self {
+        Self {
+            path: turntable.currently_loaded(),
+            position: turntable.position(),
+            duration: turntable.duration(),
+            playing: turntable.is_playing(),
+            pitch: turntable.pitch(),
+            speed: turntable.speed(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct MixerSnapshot {
+    cue_mix: f64,
+    volumes: [f64; 2],
+    cues: [bool; 2],
+    cue_modes: [String; 2],
+    eq_low: [f64; 2],
+    eq_high: [f64; 2],
+    inputs: [bool; 2],
+    mic: bool,
+    mic_record_level: f64,
+    recording: bool,
+}
+
+impl MixerSnapshot {
+    fn capture(mixer: &Mixer) -> Self {
+        Self {
+            cue_mix: mixer.get_cue_mix_value(),
+            volumes: [mixer.get_ch_one_volume(), mixer.get_ch_two_volume()],
+            cues: [mixer.is_cue_one_enabled(), mixer.is_cue_two_enabled()],
+            cue_modes: [
+                mixer.get_cue_one_mode().label().to_string(),
+                mixer.get_cue_two_mode().label().to_string(),
+            ],
+            eq_low: [mixer.get_eq_low_one_gain(), mixer.get_eq_low_two_gain()],
+            eq_high: [mixer.get_eq_high_one_gain(), mixer.get_eq_high_two_gain()],
+            inputs: [mixer.is_input_one_enabled(), mixer.is_input_two_enabled()],
+            mic: mixer.is_mic_enabled(),
+            mic_record_level: mixer.get_mic_record_level(),
+            recording: mixer.recorder().is_recording(),
+        }
+    }
+}
+
+/// Audio and input devices in use
+#[derive(Debug, Serialize)]
+struct DevicesSnapshot {
+    audio_output: Option<String>,
+    audio_input: Option<String>,
+    /// details reported by the input sources, e.g. the MIDI preset
+    inputs: Vec<String>,
+}
+
+/// State of the app attached to bug reports so that they can be reproduced
+#[derive(Debug, Serialize)]
+pub struct Snapshot {
+    version: &'static str,
+    os: &'static str,
+    arch: &'static str,
+    /// seconds since the Unix epoch
+    time: u64,
+    devices: DevicesSnapshot,
+    mixer: MixerSnapshot,
+    deck_one: DeckSnapshot,
+    deck_two: DeckSnapshot,
+    focus: String,
+    /// last input events, oldest first
+    events: Vec<String>,
+    /// last warnings and errors, oldest first
+    errors: Vec<String>,
+}
+
+impl Snapshot {
+    pub fn capture(app_data: &AppData) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            devices: DevicesSnapshot {
+                audio_output: cpal::default_host()
+                    .default_output_device()
+                    .and_then(|device| device.name().ok()),
+                audio_input: app_data.mixer.input_device_name().map(str::to_string),
+                inputs: app_data.input_debug_info.clone(),
+            },
+            mixer: MixerSnapshot::capture(&app_data.mixer),
+            deck_one: DeckSnapshot::capture(&app_data.turntable_one),
+            deck_two: DeckSnapshot::capture(&app_data.turntable_two),
+            focus: format!("{:?}", app_data.turntable_focus),
+            events: app_data.recent_events.iter().cloned().collect(),
+            errors: logger::recent_lines()
+                .into_iter()
+                .filter(|line| line.contains(" ERROR ") || line.contains(" WARN "))
+                .collect(),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    /// Write the snapshot to the `diagnostics` folder of the data directory
+    pub fn save(&self) -> io::Result<PathBuf> {
+        let dir = data_dir().join("diagnostics");
+        fs::create_dir_all(&dir)?;
+
+        let path = dir.join(format!("snapshot-{}.json", self.time));
+        fs::write(&path, self.to_json())?;
+        Ok(path)
+    }
+}
@@ -0,0 +1,125 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::Duration,
+};
+
+use crate::{controller::BoothEvent, input_source::InputSource, processable::Processable};
+
+/// Longest wait on a client before dropping it, so that a silent connection
+/// does not hold back the requests after it
+const CLIENT_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Listens on a local port for paging or announcement systems ducking the
+/// master, with HTTP requests on `/duck` and `/release`
+pub struct DuckServer {
+    receiver: Receiver<bool>,
+}
+
+impl DuckServer {
+    /// Listen on `port` of the loopback interface. `wake` is called from the
+    /// server thread so that the owner polls the requests without waiting
+    /// for its next update
+    pub fn new<F>(port: u16, wake: F) -> Option<Self>
+    where
+        F: Fn() + Send + 'static,
+    {
+        let listener = match TcpListener::bind((Ipv4Addr::LOCALHOST, port)) {
+            Ok(listener) => listener,
+            Err(e) => {
+                log::error!("Cannot listen for ducking on port {}: {}", port, e);
+                return None;
+            }
+        };
+        log::info!("Listening for ducking on port {}", port);
+
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming().filter_map(|stream| stream.ok()) {
+                if handle(stream, &sender) {
+                    wake();
+                }
+            }
+        });
+
+        Some(Self { receiver })
+    }
+}
+
+/// Answer a request, returning whether it ducked or released the master
+fn handle(mut stream: TcpStream, sender: &Sender<bool>) -> bool {
+    let _ = stream.set_read_timeout(Some(CLIENT_TIMEOUT));
+    let _ = stream.set_write_timeout(Some(CLIENT_TIMEOUT));
+
+    let mut request_line = String::new();
+    {
+        let mut reader = BufReader::new(&stream);
+        if reader.read_line(&mut request_line).is_err() {
+            return false;
+        }
+        let mut header = String::new();
+        while reader.read_line(&mut header).is_ok_and(|read| read > 2) {
+            header.clear();
+        }
+    }
+
+    let ducked = match parse_request(&request_line) {
+        Some(ducked) => ducked,
+        None => {
+            let _ = stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n");
+            return false;
+        }
+    };
+    let _ = stream.write_all(b"HTTP/1.1 204 No Content\r\n\r\n");
+    sender.send(ducked).is_ok()
+}
+
+/// Whether an HTTP request line asks to duck, or to release, the master
+fn parse_request(request_line: &str) -> Option<bool> {
+    let path = request_line.split_whitespace().nth(1)?;
+    match path.split('?').next()? {
+        "/duck" => Some(true),
+        "/release" => Some(false),
+        _ => None,
+    }
+}
+
+impl Processable for DuckServer {}
+
+impl InputSource for DuckServer {
+    fn poll(&mut self, events: &mut Vec<BoothEvent<'static>>) {
+        events.extend(self.receiver.try_iter().map(BoothEvent::DuckChanged));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_request() {
+        assert_eq!(parse_request("POST /duck HTTP/1.1\r\n"), Some(true));
+        assert_eq!(
+            parse_request("GET /release?from=paging HTTP/1.1\r\n"),
+            Some(false)
+        );
+        assert_eq!(parse_request("GET / HTTP/1.1\r\n"), None);
+        assert_eq!(parse_request(""), None);
+    }
+
+    #[test]
+    fn test_silent_client() {
+        let server = DuckServer::new(47913, || {}).unwrap();
+        let _silent = TcpStream::connect((Ipv4Addr::LOCALHOST, 47913)).unwrap();
+        let mut client = TcpStream::connect((Ipv4Addr::LOCALHOST, 47913)).unwrap();
+        client
+            .write_all(b"POST /duck HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        assert_eq!(
+            server.receiver.recv_timeout(Duration::from_secs(2)),
+            Ok(true)
+        );
+    }
+}
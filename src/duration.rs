@@ -0,0 +1,85 @@
+use std::{fs::File, io, path::Path};
+
+use symphonia::core::{
+    codecs::CODEC_TYPE_NULL,
+    errors::Error as SymphoniaError,
+    formats::{FormatOptions, FormatReader, Track},
+    io::MediaSourceStream,
+    meta::MetadataOptions,
+    probe::Hint,
+};
+
+/// Duration in seconds of the track at `path`, summed from the lengths of
+/// all its packets. Unlike the duration in the header, which is estimated
+/// from the bitrate of the first frame for variable bitrate MP3s without a
+/// Xing or VBRI header, it is exact. The packets are only read, not decoded
+pub fn measure(path: &Path) -> Result<f64, SymphoniaError> {
+    let (mut format, track) = open(path)?;
+    let (track_id, time_base, sample_rate) = (
+        track.id,
+        track.codec_params.time_base,
+        track.codec_params.sample_rate,
+    );
+
+    let mut length = 0;
+    loop {
+        match format.next_packet() {
+            Ok(packet) if packet.track_id() == track_id => length += packet.dur,
+            Ok(_) => (),
+            Err(SymphoniaError::IoError(e)) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e),
+        }
+    }
+
+    match (time_base, sample_rate) {
+        (Some(time_base), _) => {
+            let time = time_base.calc_time(length);
+            Ok(time.seconds as f64 + time.frac)
+        }
+        (None, Some(sample_rate)) => Ok(length as f64 / sample_rate as f64),
+        (None, None) => Err(SymphoniaError::Unsupported("unknown time base")),
+    }
+}
+
+/// Reader of the packets of the track at `path` and its audio track
+pub fn open(path: &Path) -> Result<(Box<dyn FormatReader>, Track), SymphoniaError> {
+    let file = File::open(path)?;
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|extension| extension.to_str()) {
+        hint.with_extension(extension);
+    }
+
+    let probed = symphonia::default::get_probe().format(
+        &hint,
+        MediaSourceStream::new(Box::new(file), Default::default()),
+        &FormatOptions::default(),
+        &MetadataOptions::default(),
+    )?;
+    let track = probed
+        .format
+        .default_track()
+        .filter(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or(SymphoniaError::Unsupported("no audio track"))?
+        .clone();
+
+    Ok((probed.format, track))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::audio_backend::testing::constant_wav;
+
+    #[test]
+    fn test_measure() {
+        let path = constant_wav("duration", 1.5, 0.5);
+        assert!((measure(&path).unwrap() - 1.5).abs() < 1e-3);
+
+        fs::write(&path, b"not a track").unwrap();
+        assert!(measure(&path).is_err());
+
+        let _ = fs::remove_file(path);
+    }
+}
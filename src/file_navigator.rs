@@ -1,9 +1,15 @@
 use core::fmt;
-use std::{ffi::OsStr, fs, path::Path};
+use std::{cmp::Ordering, ffi::OsStr, fs, path::Path};
+
+use crate::sampler::BANK_EXTENSION;
+
+/// Entries skipped by a page up or down
+const PAGE_ROWS: usize = 10;
 
 #[derive(Debug)]
 pub enum FileNavigatorSelection {
     File(String),      // selected audio file with path
+    Bank(String),      // selected sampler bank with path
     Directory(String), // selected directory with path
     None,              // selected nothing
 }
@@ -27,6 +33,12 @@ pub struct FileNavigator {
     cwd_stack: Vec<String>,
     entries: Vec<String>,
     cursor_stack: Vec<usize>,
+    /// set when the cursor moved, until the list is scrolled to it
+    scroll_request: Option<usize>,
+    /// whether the entries were sorted by a key since they were listed
+    sorted_by_key: bool,
+    /// whether files were filtered out since the entries were listed
+    filtered: bool,
 }
 
 impl FileNavigator {
@@ -35,6 +47,9 @@ impl FileNavigator {
             cwd_stack: vec![starting_folder.clone()],
             entries: Vec::new(),
             cursor_stack: Vec::new(),
+            scroll_request: None,
+            sorted_by_key: false,
+            filtered: false,
         };
 
         file_navigator.update_entries();
@@ -64,7 +79,79 @@ impl FileNavigator {
         }
     }
 
-    fn is_supported_audio_filename(filename: &String) -> bool {
+    /// Move the cursor a page up, stopping at the first entry
+    pub fn page_up(&mut self) {
+        if let Some(cursor) = self.cursor() {
+            self.set_cursor(cursor.saturating_sub(PAGE_ROWS));
+        }
+    }
+
+    /// Move the cursor a page down, stopping at the last entry
+    pub fn page_down(&mut self) {
+        if let Some(cursor) = self.cursor() {
+            let last = self.entries.len().saturating_sub(1);
+            self.set_cursor((cursor + PAGE_ROWS).min(last));
+        }
+    }
+
+    /// Select the first entry starting with `prefix`, ignoring case.
+    /// Returns whether one was found
+    pub fn jump_to_prefix(&mut self, prefix: &str) -> bool {
+        let prefix = prefix.to_lowercase();
+        match self
+            .entries
+            .iter()
+            .position(|entry| entry.to_lowercase().starts_with(&prefix))
+        {
+            Some(index) => {
+                self.move_cursor(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Select the first entry starting with the letter following the one of
+    /// the selected entry
+    pub fn next_letter(&mut self) {
+        let cursor = self.cursor().copied().unwrap_or(0);
+        let Some(letter) = self.entries.get(cursor).map(|entry| initial(entry)) else {
+            return;
+        };
+
+        if let Some(offset) = self.entries[cursor..]
+            .iter()
+            .position(|entry| initial(entry) != letter)
+        {
+            self.move_cursor(cursor + offset);
+        }
+    }
+
+    /// Select the first entry starting with the letter preceding the one of
+    /// the selected entry
+    pub fn previous_letter(&mut self) {
+        let cursor = self.cursor().copied().unwrap_or(0);
+        let Some(letter) = self.entries.get(cursor).map(|entry| initial(entry)) else {
+            return;
+        };
+
+        let group_start = self.entries[..cursor]
+            .iter()
+            .rposition(|entry| initial(entry) != letter)
+            .map_or(0, |index| index + 1);
+        if group_start == 0 {
+            return;
+        }
+
+        let previous = initial(&self.entries[group_start - 1]);
+        let previous_start = self.entries[..group_start]
+            .iter()
+            .rposition(|entry| initial(entry) != previous)
+            .map_or(0, |index| index + 1);
+        self.move_cursor(previous_start);
+    }
+
+    pub fn is_supported_audio_filename(filename: &String) -> bool {
         match Path::new(filename)
             .extension()
             .and_then(OsStr::to_str)
@@ -80,6 +167,12 @@ impl FileNavigator {
         }
     }
 
+    fn is_bank_filename(filename: &String) -> bool {
+        Path::new(filename)
+            .extension()
+            .is_some_and(|ext| ext == BANK_EXTENSION)
+    }
+
     fn update_entries(&mut self) {
         self.entries.clear();
         if let Ok(paths) = fs::read_dir(self.cwd()) {
@@ -93,7 +186,8 @@ impl FileNavigator {
                                 if metadata.is_dir() {
                                     self.entries.push(name);
                                 } else if metadata.is_file()
-                                    && FileNavigator::is_supported_audio_filename(&name)
+                                    && (FileNavigator::is_supported_audio_filename(&name)
+                                        || FileNavigator::is_bank_filename(&name))
                                 {
                                     self.entries.push(name);
                                 }
@@ -105,7 +199,77 @@ impl FileNavigator {
             }
         }
 
-        self.entries.sort()
+        self.entries.sort();
+        self.sorted_by_key = false;
+        self.filtered = false;
+    }
+
+    /// List the folders, the banks and the audio files for which `keep` is
+    /// true by name, keeping the same entry selected if it is kept
+    pub fn filter(&mut self, keep: impl Fn(&str) -> bool) {
+        let selected = self.selected().cloned();
+        self.update_entries();
+        self.entries
+            .retain(|entry| !FileNavigator::is_supported_audio_filename(entry) || keep(entry));
+        self.filtered = true;
+        self.reselect(selected);
+    }
+
+    /// List every entry again, keeping the same entry selected
+    pub fn clear_filter(&mut self) {
+        let selected = self.selected().cloned();
+        self.update_entries();
+        self.reselect(selected);
+    }
+
+    pub fn is_filtered(&self) -> bool {
+        self.filtered
+    }
+
+    /// Sort the entries by decreasing `key`, the entries without a key
+    /// coming last by name, keeping the same entry selected
+    pub fn sort_by_key(&mut self, key: impl Fn(&str) -> Option<f64>) {
+        let selected = self.selected().cloned();
+        let mut keyed: Vec<_> = self
+            .entries
+            .drain(..)
+            .map(|entry| (key(&entry), entry))
+            .collect();
+        keyed.sort_by(|(key_a, a), (key_b, b)| match (key_a, key_b) {
+            (Some(key_a), Some(key_b)) => key_b.total_cmp(key_a).then_with(|| a.cmp(b)),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => a.cmp(b),
+        });
+
+        self.entries = keyed.into_iter().map(|(_, entry)| entry).collect();
+        self.sorted_by_key = true;
+        self.reselect(selected);
+    }
+
+    /// Sort the entries by name again, keeping the same entry selected
+    pub fn sort_by_name(&mut self) {
+        let selected = self.selected().cloned();
+        self.entries.sort();
+        self.sorted_by_key = false;
+        self.reselect(selected);
+    }
+
+    pub fn is_sorted_by_key(&self) -> bool {
+        self.sorted_by_key
+    }
+
+    /// Select `entry` again after the entries changed, or the closest entry
+    /// if it is gone
+    fn reselect(&mut self, entry: Option<String>) {
+        match entry.and_then(|entry| self.entries.iter().position(|e| *e == entry)) {
+            Some(index) => self.set_cursor(index),
+            None => {
+                if let Some(cursor) = self.cursor().copied() {
+                    self.set_cursor(cursor.min(self.entries.len().saturating_sub(1)));
+                }
+            }
+        }
     }
 
     pub fn select(&mut self) -> FileNavigatorSelection {
@@ -113,6 +277,7 @@ impl FileNavigator {
             None => {
                 if let Some(_) = self.entries().first() {
                     self.cursor_stack.push(0);
+                    self.scroll_request = Some(0);
                 }
             }
             Some(cursor) => {
@@ -123,10 +288,15 @@ impl FileNavigator {
                         return FileNavigatorSelection::File(file_path);
                     }
 
+                    if FileNavigator::is_bank_filename(entry) {
+                        return FileNavigatorSelection::Bank(file_path);
+                    }
+
                     let out = FileNavigatorSelection::Directory(file_path.clone());
 
                     self.cwd_stack.push(entry.clone());
                     self.cursor_stack.push(0);
+                    self.scroll_request = Some(0);
                     self.update_entries();
 
                     return out;
@@ -145,6 +315,7 @@ impl FileNavigator {
             _ => {
                 self.cwd_stack.pop();
                 self.cursor_stack.pop();
+                self.scroll_request = self.cursor().copied();
             }
         }
 
@@ -159,9 +330,27 @@ impl FileNavigator {
     fn set_cursor(&mut self, new_cursor: usize) {
         if let Some(cursor) = self.cursor_stack.last_mut() {
             *cursor = new_cursor;
+            self.scroll_request = Some(new_cursor);
+        }
+    }
+
+    /// Set the cursor, starting the navigation if nothing was selected yet
+    fn move_cursor(&mut self, index: usize) {
+        match self.cursor_stack.is_empty() {
+            true => {
+                self.cursor_stack.push(index);
+                self.scroll_request = Some(index);
+            }
+            false => self.set_cursor(index),
         }
     }
 
+    /// Index of the entry to scroll to, once after each cursor move so that
+    /// the list can be scrolled freely in between
+    pub fn take_scroll_request(&mut self) -> Option<usize> {
+        self.scroll_request.take()
+    }
+
     pub fn cwd(&self) -> String {
         self.cwd_stack.join("/")
     }
@@ -170,10 +359,107 @@ impl FileNavigator {
         &self.entries
     }
 
+    /// Path of the selected entry if it is an audio file
+    pub fn selected_file(&self) -> Option<String> {
+        self.selected()
+            .filter(|entry| FileNavigator::is_supported_audio_filename(entry))
+            .map(|entry| format!("{}/{}", self.cwd(), entry))
+    }
+
     pub fn selected(&self) -> Option<&String> {
-        match self.cursor() {
-            Some(cursor) => Some(&self.entries[*cursor]),
-            None => None,
+        self.cursor().and_then(|cursor| self.entries.get(*cursor))
+    }
+}
+
+fn initial(entry: &str) -> Option<char> {
+    entry.chars().next().map(|c| c.to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jumps() {
+        let dir = std::env::temp_dir().join("bousse-file-navigator-test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let names = [
+            "alpha.wav",
+            "aphex.mp3",
+            "beta.flac",
+            "bravo.wav",
+            "charlie.mp3",
+        ];
+        for name in names {
+            fs::write(dir.join(name), b"").unwrap();
+        }
+        for index in 0..20 {
+            fs::write(dir.join(format!("zulu{:02}.wav", index)), b"").unwrap();
+        }
+
+        let mut navigator = FileNavigator::new(&dir.to_string_lossy().to_string());
+        assert!(navigator.jump_to_prefix("APH"));
+        assert_eq!(navigator.selected().unwrap(), "aphex.mp3");
+        assert!(!navigator.jump_to_prefix("delta"));
+        assert_eq!(navigator.selected().unwrap(), "aphex.mp3");
+
+        navigator.next_letter();
+        assert_eq!(navigator.selected().unwrap(), "beta.flac");
+        navigator.next_letter();
+        assert_eq!(navigator.selected().unwrap(), "charlie.mp3");
+        navigator.go_down();
+        navigator.previous_letter();
+        assert_eq!(navigator.selected().unwrap(), "charlie.mp3");
+        navigator.previous_letter();
+        assert_eq!(navigator.selected().unwrap(), "beta.flac");
+
+        navigator.page_down();
+        assert_eq!(navigator.selected().unwrap(), "zulu07.wav");
+        navigator.page_down();
+        navigator.page_down();
+        assert_eq!(navigator.selected().unwrap(), "zulu19.wav");
+        navigator.page_up();
+        assert_eq!(navigator.selected().unwrap(), "zulu09.wav");
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_sort_by_key() {
+        let dir = std::env::temp_dir().join("bousse-file-navigator-sort-test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("folder")).unwrap();
+        for name in ["calm.wav", "loud.wav", "new.wav"] {
+            fs::write(dir.join(name), b"").unwrap();
         }
+
+        let mut navigator = FileNavigator::new(&dir.to_string_lossy().to_string());
+        navigator.jump_to_prefix("new");
+        navigator.sort_by_key(|entry| match entry {
+            "calm.wav" => Some(2.0),
+            "loud.wav" => Some(8.0),
+            _ => None,
+        });
+        assert!(navigator.is_sorted_by_key());
+        assert_eq!(
+            navigator.entries(),
+            &["loud.wav", "calm.wav", "folder", "new.wav"]
+        );
+        assert_eq!(navigator.selected().unwrap(), "new.wav");
+
+        navigator.sort_by_name();
+        assert_eq!(navigator.entries()[0], "calm.wav");
+        assert_eq!(navigator.selected().unwrap(), "new.wav");
+
+        // folders are kept, the selection moves if filtered out
+        navigator.filter(|entry| entry != "new.wav");
+        assert!(navigator.is_filtered());
+        assert_eq!(navigator.entries(), &["calm.wav", "folder", "loud.wav"]);
+        assert_eq!(navigator.selected().unwrap(), "loud.wav");
+        navigator.clear_filter();
+        assert_eq!(navigator.entries().len(), 4);
+
+        let _ = fs::remove_dir_all(dir);
     }
 }
@@ -0,0 +1,100 @@
+use gilrs::{Axis, EventType, Gilrs};
+
+use crate::{
+    controller::BoothEvent, input_source::InputSource, mapping::GamepadMapping,
+    processable::Processable, settings::Settings,
+};
+
+/// Game controllers, e.g. for travel setups
+pub struct GamepadController {
+    gilrs: Gilrs,
+    mapping: GamepadMapping,
+    events: Vec<BoothEvent<'static>>,
+}
+
+impl GamepadController {
+    /// Returns `None` if gamepads are not supported on this platform
+    pub fn new(mapping: GamepadMapping) -> Option<Self> {
+        let gilrs = match Gilrs::new() {
+            Ok(gilrs) => gilrs,
+            Err(e) => {
+                log::warn!("Gamepads not supported: {}", e);
+                return None;
+            }
+        };
+
+        for (_, gamepad) in gilrs.gamepads() {
+            log::info!("Gamepad found: {}", gamepad.name());
+        }
+
+        Some(Self {
+            gilrs,
+            mapping,
+            events: Vec::new(),
+        })
+    }
+
+    fn read_gilrs_events(&mut self) {
+        while let Some(event) = self.gilrs.next_event() {
+            let events = match event.event {
+                EventType::ButtonPressed(button, _) => self.mapping.map_button(button, true),
+                EventType::ButtonReleased(button, _) => self.mapping.map_button(button, false),
+                EventType::ButtonChanged(button, value, _) => {
+                    self.mapping.map_trigger(button, value)
+                }
+                EventType::AxisChanged(axis, value, _) => self.mapping.map_stick(axis, value),
+                EventType::Connected => {
+                    log::info!("Gamepad connected: {}", self.gilrs.gamepad(event.id).name());
+                    Vec::new()
+                }
+                EventType::Disconnected => {
+                    log::info!(
+                        "Gamepad disconnected: {}",
+                        self.gilrs.gamepad(event.id).name()
+                    );
+                    Vec::new()
+                }
+                _ => Vec::new(),
+            };
+
+            self.events.extend(events);
+        }
+    }
+}
+
+impl Processable for GamepadController {
+    fn process(&mut self, _delta: f64) {
+        self.read_gilrs_events();
+
+        let gamepads: Vec<_> = self.gilrs.gamepads().map(|(_, gamepad)| gamepad).collect();
+        let position = |axis: Axis| {
+            gamepads
+                .iter()
+                .map(|gamepad| gamepad.value(axis))
+                .fold(0.0, |a: f32, b| if b.abs() > a.abs() { b } else { a })
+        };
+
+        let events = self.mapping.map_continuous(position);
+        self.events.extend(events);
+    }
+}
+
+impl InputSource for GamepadController {
+    fn poll(&mut self, events: &mut Vec<BoothEvent<'static>>) {
+        self.read_gilrs_events();
+        events.append(&mut self.events);
+    }
+
+    fn reload_mapping(&mut self, _settings: &Settings) {
+        self.mapping = GamepadMapping::load();
+    }
+
+    /// Gamepad events do not wake the event loop up
+    fn needs_polling(&self) -> bool {
+        self.gilrs.gamepads().next().is_some()
+    }
+
+    fn debug_info(&self) -> Option<String> {
+        Some(format!("gamepads: {}", self.gilrs.gamepads().count()))
+    }
+}
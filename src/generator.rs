@@ -0,0 +1,322 @@
+use std::{
+    f32::consts::TAU,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicU8, Ordering},
+        Arc,
+    },
+};
+
+use kira::{
+    clock::clock_info::ClockInfoProvider,
+    effect::{Effect, EffectBuilder},
+    modulator::value_provider::ModulatorValueProvider,
+    track::{TrackBuilder, TrackHandle, TrackId, TrackRoutes},
+    tween::Tween,
+    Frame,
+};
+
+use crate::audio_backend::Manager;
+
+pub const MIN_FREQUENCY: f32 = 20.0;
+pub const MAX_FREQUENCY: f32 = 20_000.0;
+/// Time taken by a sweep to go through the whole frequency range
+const SWEEP_SECONDS: f32 = 10.0;
+
+/// Sound of the generator
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Waveform {
+    /// equal energy at every frequency
+    #[default]
+    WhiteNoise,
+    /// equal energy in every octave, as music
+    PinkNoise,
+    Sine,
+    /// sine rising from the lowest to the highest frequency, over and over
+    Sweep,
+}
+
+impl Waveform {
+    pub const ALL: [Waveform; 4] = [
+        Waveform::WhiteNoise,
+        Waveform::PinkNoise,
+        Waveform::Sine,
+        Waveform::Sweep,
+    ];
+}
+
+/// Bus the generator is heard on
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum GeneratorBus {
+    /// the headphones only
+    #[default]
+    Cue,
+    /// the master, and the recording
+    Master,
+    /// the input of channel one, through its EQ, effects and fader
+    ChannelOne,
+    ChannelTwo,
+}
+
+impl GeneratorBus {
+    pub const ALL: [GeneratorBus; 4] = [
+        GeneratorBus::Cue,
+        GeneratorBus::Master,
+        GeneratorBus::ChannelOne,
+        GeneratorBus::ChannelTwo,
+    ];
+}
+
+/// Noise and tone source, one sample at a time
+#[derive(Default)]
+struct Oscillator {
+    /// state of the xorshift noise
+    seed: u32,
+    /// filters of the pink noise
+    pink: [f32; 7],
+    /// from 0 to 1 along the current cycle of the sine
+    phase: f32,
+    /// seconds into the current sweep
+    sweep: f32,
+}
+
+impl Oscillator {
+    fn white(&mut self) -> f32 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        self.seed as f32 / u32::MAX as f32 * 2.0 - 1.0
+    }
+
+    fn next(&mut self, waveform: Waveform, frequency: f32, dt: f32) -> f32 {
+        match waveform {
+            Waveform::WhiteNoise => self.white(),
+            Waveform::PinkNoise => {
+                // Paul Kellet's filters of white noise, within 0.05 dB of
+                // -3 dB per octave
+                let white = self.white();
+                let b = &mut self.pink;
+                b[0] = 0.99886 * b[0] + white * 0.0555179;
+                b[1] = 0.99332 * b[1] + white * 0.0750759;
+                b[2] = 0.96900 * b[2] + white * 0.153852;
+                b[3] = 0.86650 * b[3] + white * 0.3104856;
+                b[4] = 0.55000 * b[4] + white * 0.5329522;
+                b[5] = -0.7616 * b[5] - white * 0.0168980;
+                let pink = b[..6].iter().sum::<f32>() + b[6] + white * 0.5362;
+                b[6] = white * 0.115926;
+                pink * 0.11
+            }
+            Waveform::Sine => self.sine(frequency, dt),
+            Waveform::Sweep => {
+                self.sweep = (self.sweep + dt) % SWEEP_SECONDS;
+                let frequency = MIN_FREQUENCY
+                    * (MAX_FREQUENCY / MIN_FREQUENCY).powf(self.sweep / SWEEP_SECONDS);
+                self.sine(frequency, dt)
+            }
+        }
+    }
+
+    fn sine(&mut self, frequency: f32, dt: f32) -> f32 {
+        self.phase = (self.phase + frequency * dt).fract();
+        (TAU * self.phase).sin()
+    }
+}
+
+/// Settings of the generator read by the audio thread
+struct Shared {
+    enabled: AtomicBool,
+    waveform: AtomicU8,
+    /// bits of the frequency of the sine in Hz
+    frequency: AtomicU32,
+}
+
+/// Effect replacing the silence of the generator track by its sound
+pub struct GeneratorEffect {
+    oscillator: Oscillator,
+    shared: Arc<Shared>,
+}
+
+impl EffectBuilder for GeneratorEffect {
+    type Handle = ();
+
+    fn build(self) -> (Box<dyn Effect>, Self::Handle) {
+        (Box::new(self), ())
+    }
+}
+
+impl Effect for GeneratorEffect {
+    fn process(
+        &mut self,
+        input: Frame,
+        dt: f64,
+        _clock_info_provider: &ClockInfoProvider,
+        _modulator_value_provider: &ModulatorValueProvider,
+    ) -> Frame {
+        if !self.shared.enabled.load(Ordering::Relaxed) {
+            return input;
+        }
+
+        let waveform = Waveform::ALL[self.shared.waveform.load(Ordering::Relaxed) as usize];
+        let frequency = f32::from_bits(self.shared.frequency.load(Ordering::Relaxed));
+        Frame::from_mono(self.oscillator.next(waveform, frequency, dt as f32))
+    }
+}
+
+/// Utility channel playing noise or tones for sound checks, testing the EQ
+/// of the booth or building noise risers through a channel
+pub struct Generator {
+    track: TrackHandle,
+    shared: Arc<Shared>,
+    waveform: Waveform,
+    frequency: f32,
+    level: f64,
+    bus: GeneratorBus,
+    /// master, record, cue and channel inputs, in that order
+    buses: [TrackId; 5],
+}
+
+impl Generator {
+    /// Create a stopped generator, routed to the cue
+    pub fn new(
+        manager: &mut Manager,
+        master: &TrackHandle,
+        record: &TrackHandle,
+        cue: &TrackHandle,
+        input_one: &TrackHandle,
+        input_two: &TrackHandle,
+    ) -> Self {
+        let frequency = 1_000.0;
+        let level = 0.1;
+        let shared = Arc::new(Shared {
+            enabled: AtomicBool::new(false),
+            waveform: AtomicU8::new(0),
+            frequency: AtomicU32::new(f32::to_bits(frequency)),
+        });
+
+        let buses = [master, record, cue, input_one, input_two].map(TrackHandle::id);
+        let routes = buses.iter().fold(TrackRoutes::empty(), |routes, bus| {
+            routes.with_route(*bus, 0.0)
+        });
+        let mut builder = TrackBuilder::new().volume(level).routes(routes);
+        builder.add_effect(GeneratorEffect {
+            oscillator: Oscillator {
+                seed: 0x9e37_79b9,
+                ..Default::default()
+            },
+            shared: Arc::clone(&shared),
+        });
+        let track = manager.add_sub_track(builder).unwrap();
+
+        let mut generator = Self {
+            track,
+            shared,
+            waveform: Waveform::default(),
+            frequency,
+            level,
+            bus: GeneratorBus::default(),
+            buses,
+        };
+        generator.set_bus(GeneratorBus::default());
+        generator
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.shared.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.shared.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn waveform(&self) -> Waveform {
+        self.waveform
+    }
+
+    pub fn set_waveform(&mut self, waveform: Waveform) {
+        self.waveform = waveform;
+        let index = Waveform::ALL
+            .iter()
+            .position(|w| *w == waveform)
+            .unwrap_or_default();
+        self.shared.waveform.store(index as u8, Ordering::Relaxed);
+    }
+
+    /// Frequency of the sine in Hz
+    pub fn frequency(&self) -> f32 {
+        self.frequency
+    }
+
+    pub fn set_frequency(&mut self, frequency: f32) {
+        self.frequency = frequency.clamp(MIN_FREQUENCY, MAX_FREQUENCY);
+        self.shared
+            .frequency
+            .store(self.frequency.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn level(&self) -> f64 {
+        self.level
+    }
+
+    pub fn set_level(&mut self, level: f64) {
+        self.level = level;
+        self.track.set_volume(level, Tween::default());
+    }
+
+    pub fn bus(&self) -> GeneratorBus {
+        self.bus
+    }
+
+    pub fn set_bus(&mut self, bus: GeneratorBus) {
+        self.bus = bus;
+        let [master, record, cue, input_one, input_two] = self.buses;
+        let volumes = match bus {
+            GeneratorBus::Master => [1.0, 1.0, 0.0, 0.0, 0.0],
+            GeneratorBus::Cue => [0.0, 0.0, 1.0, 0.0, 0.0],
+            GeneratorBus::ChannelOne => [0.0, 0.0, 0.0, 1.0, 0.0],
+            GeneratorBus::ChannelTwo => [0.0, 0.0, 0.0, 0.0, 1.0],
+        };
+
+        for (bus, volume) in [master, record, cue, input_one, input_two]
+            .into_iter()
+            .zip(volumes)
+        {
+            if let Err(e) = self.track.set_route(bus, volume, Tween::default()) {
+                log::error!("Cannot route the generator: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_oscillator() {
+        let mut oscillator = Oscillator {
+            seed: 1,
+            ..Default::default()
+        };
+        let dt = 1.0 / 48_000.0;
+
+        for waveform in [Waveform::WhiteNoise, Waveform::PinkNoise] {
+            let samples: Vec<f32> = (0..48_000)
+                .map(|_| oscillator.next(waveform, 0.0, dt))
+                .collect();
+            assert!(samples.iter().all(|sample| sample.abs() <= 1.0));
+            let mean = samples.iter().sum::<f32>() / samples.len() as f32;
+            assert!(mean.abs() < 0.05);
+        }
+
+        // a second of a 1 kHz sine crosses zero upwards a thousand times
+        let mut previous = 0.0;
+        let mut crossings = 0;
+        for _ in 0..48_000 {
+            let sample = oscillator.next(Waveform::Sine, 1_000.0, dt);
+            if previous < 0.0 && sample >= 0.0 {
+                crossings += 1;
+            }
+            previous = sample;
+        }
+        assert!((999..=1_001).contains(&crossings));
+    }
+}
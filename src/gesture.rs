@@ -0,0 +1,193 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// How a button binding is triggered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum Gesture {
+    /// when the button is pressed, or released if it may still be held or
+    /// pressed twice
+    #[default]
+    Press,
+    /// once the button has been held down for the hold time
+    Hold,
+    /// when the button is pressed again within the double press time
+    DoublePress,
+}
+
+/// Timings of the gestures, set per mapping
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct GestureTimings {
+    /// time a button must be held down to trigger a hold
+    #[serde(default = "default_hold_ms")]
+    pub hold_ms: u64,
+    /// maximum time between the two presses of a double press
+    #[serde(default = "default_double_press_ms")]
+    pub double_press_ms: u64,
+}
+
+fn default_hold_ms() -> u64 {
+    500
+}
+
+fn default_double_press_ms() -> u64 {
+    300
+}
+
+impl Default for GestureTimings {
+    fn default() -> Self {
+        Self {
+            hold_ms: default_hold_ms(),
+            double_press_ms: default_double_press_ms(),
+        }
+    }
+}
+
+/// Gestures bound to a button besides the simple press. A press is only
+/// delayed when it may still turn into one of them
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BoundGestures {
+    pub hold: bool,
+    pub double_press: bool,
+}
+
+impl BoundGestures {
+    pub fn from_gestures(gestures: impl IntoIterator<Item = Gesture>) -> Self {
+        gestures
+            .into_iter()
+            .fold(Self::default(), |bound, gesture| match gesture {
+                Gesture::Press => bound,
+                Gesture::Hold => Self {
+                    hold: true,
+                    ..bound
+                },
+                Gesture::DoublePress => Self {
+                    double_press: true,
+                    ..bound
+                },
+            })
+    }
+
+    pub fn any(&self) -> bool {
+        self.hold || self.double_press
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct ButtonState {
+    bound: BoundGestures,
+    /// when the button went down, while it is held
+    pressed_at: Option<Instant>,
+    /// whether the current press already triggered a hold or a double press
+    consumed: bool,
+    /// first press of a possible double press, triggered as a simple press
+    /// if the second one does not come in time
+    pending: Option<Instant>,
+}
+
+/// Turns the presses and releases of buttons identified by `K` into
+/// gestures. Holds and delayed presses depend on time only, so
+/// [`GestureRecognizer::poll`] must be called regularly
+#[derive(Debug, Clone)]
+pub struct GestureRecognizer<K> {
+    buttons: HashMap<K, ButtonState>,
+}
+
+impl<K> Default for GestureRecognizer<K> {
+    fn default() -> Self {
+        Self {
+            buttons: HashMap::new(),
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone> GestureRecognizer<K> {
+    /// Gestures triggered by pressing `button` at `time`
+    pub fn press(
+        &mut self,
+        button: K,
+        bound: BoundGestures,
+        timings: &GestureTimings,
+        time: Instant,
+    ) -> Vec<Gesture> {
+        let state = self.buttons.entry(button).or_default();
+        let mut gestures = Vec::new();
+
+        state.bound = bound;
+        state.pressed_at = Some(time);
+        state.consumed = false;
+
+        if let Some(first) = state.pending.take() {
+            if time.duration_since(first) <= Duration::from_millis(timings.double_press_ms) {
+                state.consumed = true;
+                return vec![Gesture::DoublePress];
+            }
+            // the first press expired before being polled
+            gestures.push(Gesture::Press);
+        }
+
+        if !bound.any() {
+            gestures.push(Gesture::Press);
+        }
+        gestures
+    }
+
+    /// Gestures triggered by releasing `button`
+    pub fn release(&mut self, button: &K) -> Vec<Gesture> {
+        let Some(state) = self.buttons.get_mut(button) else {
+            return Vec::new();
+        };
+        let Some(pressed_at) = state.pressed_at.take() else {
+            return Vec::new();
+        };
+
+        match (state.consumed, state.bound.double_press, state.bound.hold) {
+            (true, _, _) => Vec::new(),
+            (false, true, _) => {
+                state.pending = Some(pressed_at);
+                Vec::new()
+            }
+            (false, false, true) => vec![Gesture::Press],
+            (false, false, false) => Vec::new(),
+        }
+    }
+
+    /// Whether a hold or a delayed press may still be triggered by
+    /// [`GestureRecognizer::poll`]
+    pub fn is_pending(&self) -> bool {
+        self.buttons.values().any(|state| {
+            state.pending.is_some()
+                || (state.pressed_at.is_some() && state.bound.hold && !state.consumed)
+        })
+    }
+
+    /// Holds and delayed presses due at `time`
+    pub fn poll(&mut self, timings: &GestureTimings, time: Instant) -> Vec<(K, Gesture)> {
+        let hold = Duration::from_millis(timings.hold_ms);
+        let double_press = Duration::from_millis(timings.double_press_ms);
+        let mut gestures = Vec::new();
+
+        for (button, state) in self.buttons.iter_mut() {
+            if let Some(pressed_at) = state.pressed_at {
+                if state.bound.hold && !state.consumed && time.duration_since(pressed_at) >= hold {
+                    state.consumed = true;
+                    gestures.push((button.clone(), Gesture::Hold));
+                }
+            }
+
+            if state
+                .pending
+                .is_some_and(|first| time.duration_since(first) > double_press)
+            {
+                state.pending = None;
+                gestures.push((button.clone(), Gesture::Press));
+            }
+        }
+
+        gestures
+    }
+}
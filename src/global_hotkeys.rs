@@ -0,0 +1,124 @@
+use winit::{
+    event::{DeviceEvent, ElementState, RawKeyEvent, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
+};
+
+use crate::{
+    controller::BoothEvent,
+    input_source::InputSource,
+    mapping::{GlobalBinding, KeyMap},
+    processable::Processable,
+    settings::Settings,
+};
+
+/// Hotkeys working while another program is focused, e.g. to use the app as
+/// background music playout. Keys are read from the raw device events, only
+/// delivered outside the window on X11 and Windows
+pub struct GlobalHotkeys {
+    bindings: Vec<GlobalBinding>,
+    pressed: Vec<KeyCode>,
+    /// the keyboard controller handles the keys while the window is focused
+    focused: bool,
+    events: Vec<BoothEvent<'static>>,
+}
+
+impl GlobalHotkeys {
+    pub fn new(bindings: Vec<GlobalBinding>, focused: bool) -> Self {
+        Self {
+            bindings,
+            pressed: Vec::new(),
+            focused,
+            events: Vec::new(),
+        }
+    }
+}
+
+impl Processable for GlobalHotkeys {}
+
+impl InputSource for GlobalHotkeys {
+    fn on_window_event(&mut self, event: &WindowEvent) {
+        if let WindowEvent::Focused(focused) = event {
+            self.focused = *focused;
+            self.pressed.clear();
+        }
+    }
+
+    fn on_device_event(&mut self, event: &DeviceEvent) {
+        let DeviceEvent::Key(RawKeyEvent {
+            physical_key: PhysicalKey::Code(key),
+            state,
+        }) = event
+        else {
+            return;
+        };
+
+        match state {
+            ElementState::Pressed => {
+                // raw key repeats
+                if self.pressed.contains(key) {
+                    return;
+                }
+                self.pressed.push(*key);
+            }
+            ElementState::Released => {
+                self.pressed.retain(|pressed| pressed != key);
+                return;
+            }
+        }
+
+        if self.focused {
+            return;
+        }
+        let events = self
+            .bindings
+            .iter()
+            .filter(|binding| binding.matches(&self.pressed))
+            .map(|binding| binding.target.to_event(1.0));
+        self.events.extend(events);
+    }
+
+    fn poll(&mut self, events: &mut Vec<BoothEvent<'static>>) {
+        events.append(&mut self.events);
+    }
+
+    fn reload_mapping(&mut self, _settings: &Settings) {
+        self.bindings = KeyMap::load().global;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mapping::Target;
+
+    #[test]
+    fn test_global_hotkeys() {
+        let key = |key: KeyCode, state: ElementState| {
+            DeviceEvent::Key(RawKeyEvent {
+                physical_key: PhysicalKey::Code(key),
+                state,
+            })
+        };
+        let binding = GlobalBinding {
+            keys: vec![KeyCode::ControlLeft, KeyCode::F9],
+            target: Target::ToggleStartStopOne,
+        };
+        let mut hotkeys = GlobalHotkeys::new(vec![binding], true);
+        let mut events = Vec::new();
+
+        // handled by the keyboard controller while focused
+        hotkeys.on_device_event(&key(KeyCode::ControlLeft, ElementState::Pressed));
+        hotkeys.on_device_event(&key(KeyCode::F9, ElementState::Pressed));
+        hotkeys.poll(&mut events);
+        assert!(events.is_empty());
+
+        hotkeys.on_window_event(&WindowEvent::Focused(false));
+        hotkeys.on_device_event(&key(KeyCode::ControlLeft, ElementState::Pressed));
+        hotkeys.on_device_event(&key(KeyCode::F9, ElementState::Pressed));
+        hotkeys.on_device_event(&key(KeyCode::F9, ElementState::Pressed));
+        hotkeys.on_device_event(&key(KeyCode::F9, ElementState::Released));
+        hotkeys.on_device_event(&key(KeyCode::KeyA, ElementState::Pressed));
+        hotkeys.poll(&mut events);
+        assert!(matches!(events[..], [BoothEvent::ToggleStartStopOne]));
+    }
+}
@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use log::info;
+use log::{error, info, warn};
 use winit::{dpi::PhysicalSize, window::Window};
 
 pub struct Gpu {
@@ -65,6 +65,24 @@ impl Gpu {
         return gpu;
     }
 
+    /// Texture of the next frame, or None to skip the frame. An outdated or
+    /// lost surface, e.g. after a GPU reset or a resolution change, is
+    /// configured again for the next one
+    pub fn surface_texture(&mut self) -> Option<wgpu::SurfaceTexture> {
+        match self.surface.get_current_texture() {
+            Ok(surface_texture) => Some(surface_texture),
+            Err(e @ (wgpu::SurfaceError::Outdated | wgpu::SurfaceError::Lost)) => {
+                warn!("Surface {}, configuring it again", e);
+                self.surface.configure(&self.device, &self.config);
+                None
+            }
+            Err(e) => {
+                error!("Cannot acquire the next surface texture: {}", e);
+                None
+            }
+        }
+    }
+
     pub fn resize(&mut self, physical_size: PhysicalSize<u32>) {
         info!("Surface resize {:?}", physical_size);
         self.size = physical_size;
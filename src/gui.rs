@@ -1,40 +1,51 @@
-use egui::Context;
+use std::time::Duration;
+
+use egui::{Context, ViewportId};
 use egui_wgpu::{Renderer, ScreenDescriptor};
 use egui_winit::State;
 use wgpu::{CommandEncoder, Device, Queue, TextureView};
 use winit::{event::WindowEvent, window::Window};
 
-use crate::gpu::Gpu;
+use crate::{gpu::Gpu, overview_gpu::OverviewResources};
 
 pub struct Gui {
     state: State,
     renderer: Renderer,
-    pixels_per_point: f32,
 }
 
 impl Gui {
     pub fn new(window: &Window, gpu_state: &Gpu) -> Self {
         let egui_context = Context::default();
         let id = egui_context.viewport_id();
+        // the zoom follows the UI scale of the settings
+        egui_context.options_mut(|options| options.zoom_with_keyboard = false);
 
         let egui_state = State::new(egui_context.clone(), id, &window, None, None);
 
-        let egui_renderer = Renderer::new(
+        let mut egui_renderer = Renderer::new(
             &gpu_state.device,
             gpu_state.config.format,
             None,
             gpu_state.samples,
         );
+        egui_renderer
+            .callback_resources
+            .insert(OverviewResources::new(
+                &gpu_state.device,
+                gpu_state.config.format,
+                gpu_state.samples,
+            ));
 
         Self {
             state: egui_state,
             renderer: egui_renderer,
-            pixels_per_point: window.scale_factor() as f32,
         }
     }
 
-    pub fn handle_event(&mut self, window: &Window, event: &WindowEvent) {
-        let _ = self.state.on_window_event(window, event);
+    /// Pass a window event to egui, returning whether it was consumed, e.g.
+    /// a key typed in a text field
+    pub fn handle_event(&mut self, window: &Window, event: &WindowEvent) -> bool {
+        self.state.on_window_event(window, event).consumed
     }
 
     pub fn draw(
@@ -44,9 +55,9 @@ impl Gui {
         encoder: &mut CommandEncoder,
         window: &Window,
         window_surface_view: &TextureView,
-        screen_descriptor: ScreenDescriptor,
+        mut screen_descriptor: ScreenDescriptor,
         run_ui: impl FnOnce(&Context),
-    ) {
+    ) -> Duration {
         let raw_input = self.state.take_egui_input(&window);
         let full_output = self.state.egui_ctx().run(raw_input, |_ui| {
             run_ui(&self.state.egui_ctx());
@@ -55,10 +66,13 @@ impl Gui {
         self.state
             .handle_platform_output(&window, full_output.platform_output);
 
+        // follows the scale factor of the monitor and the UI scale, both of
+        // which can change between frames
+        screen_descriptor.pixels_per_point = full_output.pixels_per_point;
         let tris = self
             .state
             .egui_ctx()
-            .tessellate(full_output.shapes, self.pixels_per_point);
+            .tessellate(full_output.shapes, full_output.pixels_per_point);
 
         for (id, image_delta) in &full_output.textures_delta.set {
             self.renderer
@@ -90,5 +104,11 @@ impl Gui {
         for x in &full_output.textures_delta.free {
             self.renderer.free_texture(x)
         }
+
+        // delay after which egui wants to be run again, e.g. for animations
+        full_output
+            .viewport_output
+            .get(&ViewportId::ROOT)
+            .map_or(Duration::MAX, |output| output.repaint_delay)
     }
 }
@@ -0,0 +1,129 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+        Arc,
+    },
+    thread,
+};
+
+use hidapi::HidApi;
+
+use crate::{
+    controller::BoothEvent, input_source::InputSource, mapping::HidMapping,
+    processable::Processable, settings::Settings,
+};
+
+/// Controllers without MIDI support, read through raw HID reports
+pub struct HidController {
+    receiver: Receiver<Vec<u8>>,
+    mapping: HidMapping,
+    previous_report: Vec<u8>,
+    device_name: String,
+    running: Arc<AtomicBool>,
+}
+
+impl HidController {
+    /// Open the device described by the mapping and read its reports on a
+    /// dedicated thread. `wake` is called from this thread on every report.
+    ///
+    /// Returns `None` if the device cannot be opened
+    pub fn new<F>(mapping: HidMapping, wake: F) -> Option<Self>
+    where
+        F: Fn() + Send + 'static,
+    {
+        let api = match HidApi::new() {
+            Ok(api) => api,
+            Err(e) => {
+                log::error!("Cannot initialize HID: {}", e);
+                return None;
+            }
+        };
+
+        let device = match api.open(mapping.vendor_id, mapping.product_id) {
+            Ok(device) => device,
+            Err(e) => {
+                log::warn!(
+                    "Cannot open HID device {:04x}:{:04x}: {}",
+                    mapping.vendor_id,
+                    mapping.product_id,
+                    e
+                );
+                return None;
+            }
+        };
+
+        let device_name = device
+            .get_product_string()
+            .ok()
+            .flatten()
+            .unwrap_or_else(|| "Unknown HID device".to_string());
+        log::info!("Reading HID input from '{}'", device_name);
+
+        let (sender, receiver) = mpsc::channel();
+        let running = Arc::new(AtomicBool::new(true));
+        let thread_running = Arc::clone(&running);
+
+        thread::spawn(move || {
+            let mut buffer = [0u8; 64];
+
+            while thread_running.load(Ordering::Relaxed) {
+                match device.read_timeout(&mut buffer, 100) {
+                    Ok(0) => (),
+                    Ok(len) => {
+                        if sender.send(buffer[..len].to_vec()).is_err() {
+                            break;
+                        }
+                        wake();
+                    }
+                    Err(e) => {
+                        log::error!("HID read error, closing device: {}", e);
+                        break;
+                    }
+                }
+            }
+        });
+
+        Some(Self {
+            receiver,
+            mapping,
+            previous_report: Vec::new(),
+            device_name,
+            running,
+        })
+    }
+}
+
+impl Processable for HidController {}
+
+impl InputSource for HidController {
+    fn poll(&mut self, events: &mut Vec<BoothEvent<'static>>) {
+        for report in self.receiver.try_iter() {
+            events.extend(self.mapping.map(&report, &self.previous_report));
+            self.previous_report = report;
+        }
+    }
+
+    fn reload_mapping(&mut self, _settings: &Settings) {
+        match HidMapping::load() {
+            Some(mapping)
+                if mapping.vendor_id == self.mapping.vendor_id
+                    && mapping.product_id == self.mapping.product_id =>
+            {
+                self.mapping = mapping
+            }
+            Some(_) => log::warn!("Restart to use another HID device"),
+            None => (),
+        }
+    }
+
+    fn debug_info(&self) -> Option<String> {
+        Some(format!("hid_device: {}", self.device_name))
+    }
+}
+
+impl Drop for HidController {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,47 @@
+use winit::event::{DeviceEvent, WindowEvent};
+
+use crate::{app::AppData, controller::BoothEvent, processable::Processable, settings::Settings};
+
+/// A trait for devices producing booth events, e.g. keyboard, trackpad, MIDI
+/// controllers. Window and device events are forwarded to every source.
+/// Sources running on their own thread wake the event loop up when they have
+/// pending events so that they get polled, the others are polled on every
+/// frame. Sources are also processed with the app, e.g. to produce events
+/// while an analog stick is held
+pub trait InputSource: Processable {
+    fn on_window_event(&mut self, _event: &WindowEvent) {}
+
+    fn on_device_event(&mut self, _event: &DeviceEvent) {}
+
+    /// Move the booth events produced since the last call into `events`
+    fn poll(&mut self, events: &mut Vec<BoothEvent<'static>>);
+
+    /// Whether the source must be polled at the full frame rate even when the
+    /// app is idle, e.g. because it cannot wake the event loop up or waits
+    /// for a gesture to time out
+    fn needs_polling(&self) -> bool {
+        false
+    }
+
+    /// Whether the pointer is moving a vinyl, during which the cursor is
+    /// grabbed so that it cannot leave the window
+    fn grabs_pointer(&self) -> bool {
+        false
+    }
+
+    /// Whether the cursor could be locked for the source grabbing the
+    /// pointer. Relative motion then comes from device events, otherwise
+    /// from the moves of the cursor
+    fn set_pointer_locked(&mut self, _locked: bool) {}
+
+    /// Reload the mapping from the config directory
+    fn reload_mapping(&mut self, _settings: &Settings) {}
+
+    /// Reflect the booth state on the device, e.g. to light its LEDs
+    fn update_feedback(&mut self, _app_data: &AppData) {}
+
+    /// Status line displayed in the debug panel
+    fn debug_info(&self) -> Option<String> {
+        None
+    }
+}
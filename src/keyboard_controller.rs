@@ -0,0 +1,62 @@
+use winit::{
+    event::{KeyEvent, WindowEvent},
+    keyboard::{ModifiersState, PhysicalKey},
+};
+
+use crate::{
+    controller::BoothEvent, input_source::InputSource, mapping::KeyMap, processable::Processable,
+    settings::Settings,
+};
+
+pub struct KeyboardController {
+    keymap: KeyMap,
+    modifiers: ModifiersState,
+    events: Vec<BoothEvent<'static>>,
+}
+
+impl KeyboardController {
+    pub fn new(keymap: KeyMap) -> Self {
+        Self {
+            keymap,
+            modifiers: ModifiersState::empty(),
+            events: Vec::new(),
+        }
+    }
+}
+
+impl Processable for KeyboardController {}
+
+impl InputSource for KeyboardController {
+    fn on_window_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::ModifiersChanged(modifiers) => self.modifiers = modifiers.state(),
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(key),
+                        state,
+                        repeat,
+                        ..
+                    },
+                ..
+            } => {
+                let events = self.keymap.map(*key, *state, *repeat, self.modifiers);
+                self.events.extend(events);
+            }
+            _ => (),
+        }
+    }
+
+    fn poll(&mut self, events: &mut Vec<BoothEvent<'static>>) {
+        events.append(&mut self.events);
+        events.extend(self.keymap.poll_gestures(self.modifiers));
+    }
+
+    fn needs_polling(&self) -> bool {
+        self.keymap.has_pending_gestures()
+    }
+
+    fn reload_mapping(&mut self, _settings: &Settings) {
+        self.keymap = KeyMap::load();
+    }
+}
@@ -0,0 +1,158 @@
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use kira::{
+    manager::error::PlaySoundError,
+    sound::{
+        static_sound::{StaticSoundData, StaticSoundHandle, StaticSoundSettings},
+        FromFileError, PlaybackState,
+    },
+    track::TrackHandle,
+    tween::Tween,
+};
+
+use crate::audio_backend::Manager;
+
+/// Acapella or loop played on top of the track of a deck, through the same
+/// channel and at the tempo of the deck
+pub struct Layer {
+    sound_data: Option<StaticSoundData>,
+    sound: Option<StaticSoundHandle>,
+    audio_manager: Arc<Mutex<Manager>>,
+    output_destination: Arc<Mutex<TrackHandle>>,
+    currently_loaded: Option<String>,
+    looping: bool,
+    level: f64,
+    /// playback rate taking the layer to the tempo of the deck
+    rate: f64,
+}
+
+impl Layer {
+    pub fn new(
+        audio_manager: Arc<Mutex<Manager>>,
+        output_destination: Arc<Mutex<TrackHandle>>,
+    ) -> Self {
+        Self {
+            sound_data: None,
+            sound: None,
+            audio_manager,
+            output_destination,
+            currently_loaded: None,
+            looping: false,
+            level: 1.0,
+            rate: 1.0,
+        }
+    }
+
+    /// Load an audio file, stopping the layer playing
+    pub fn load(&mut self, path: &Path) -> Result<(), FromFileError> {
+        let sound_data = StaticSoundData::from_file(path)?;
+        self.stop();
+        self.sound_data = Some(sound_data);
+        self.currently_loaded = Some(path.to_string_lossy().to_string());
+
+        Ok(())
+    }
+
+    pub fn currently_loaded(&self) -> Option<String> {
+        self.currently_loaded.clone()
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.sound
+            .as_ref()
+            .is_some_and(|sound| sound.state() == PlaybackState::Playing)
+    }
+
+    /// Play the layer from `start` in seconds
+    pub fn play(&mut self, start: f64) -> Result<(), PlaySoundError<()>> {
+        self.stop();
+        let Some(sound_data) = &self.sound_data else {
+            return Ok(());
+        };
+
+        let mut settings = StaticSoundSettings::new()
+            .output_destination(&*self.output_destination.lock().unwrap())
+            .start_position(start)
+            .playback_rate(self.rate)
+            .volume(self.level);
+        if self.looping {
+            settings = settings.loop_region(..);
+        }
+        self.sound = Some(
+            self.audio_manager
+                .lock()
+                .unwrap()
+                .play(sound_data.with_settings(settings))?,
+        );
+
+        Ok(())
+    }
+
+    pub fn stop(&mut self) {
+        if let Some(mut sound) = self.sound.take() {
+            sound.stop(Tween::default());
+        }
+    }
+
+    pub fn is_looping(&self) -> bool {
+        self.looping
+    }
+
+    /// Play the whole layer over and over, e.g. for a drum loop, or once,
+    /// e.g. for an acapella
+    pub fn set_looping(&mut self, looping: bool) {
+        self.looping = looping;
+        if let Some(sound) = &mut self.sound {
+            match looping {
+                true => sound.set_loop_region(..),
+                false => sound.set_loop_region(None),
+            }
+        }
+    }
+
+    pub fn level(&self) -> f64 {
+        self.level
+    }
+
+    pub fn set_level(&mut self, level: f64) {
+        self.level = level.clamp(0.0, 1.0);
+        if let Some(sound) = &mut self.sound {
+            sound.set_volume(self.level, Tween::default());
+        }
+    }
+
+    /// Follow the tempo of the deck, playing `rate` times faster than the
+    /// layer. The pitch follows as on a turntable
+    pub fn set_rate(&mut self, rate: f64) {
+        self.rate = rate;
+        if let Some(sound) = &mut self.sound {
+            sound.set_playback_rate(rate, Tween::default());
+        }
+    }
+}
+
+/// Position to play a layer of `bpm` from so that its beats, starting at
+/// `first_beat`, fall on those of a deck `phase` into its current beat
+pub fn aligned_start(first_beat: f64, bpm: f64, phase: f64) -> f64 {
+    let beat = 60.0 / bpm;
+    (first_beat + phase * beat).rem_euclid(beat)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aligned_start() {
+        // on the beat of the deck, the layer starts on one of its beats
+        assert!((aligned_start(0.25, 120.0, 0.0) - 0.25).abs() < 1e-9);
+        assert!((aligned_start(1.3, 120.0, 0.0) - 0.3).abs() < 1e-9);
+
+        // halfway through the beat of the deck, halfway through its own
+        assert!((aligned_start(0.0, 120.0, 0.5) - 0.25).abs() < 1e-9);
+        assert!((aligned_start(0.4, 120.0, 0.5) - 0.15).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,528 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    analysis::TrackAnalysis,
+    beatgrid,
+    config::{data_dir, root_dir},
+    sampler::BEATS_PER_BAR,
+};
+
+const LIBRARY_FILE: &str = "library.toml";
+/// Memory points closer than this in seconds are considered the same
+const MEMORY_POINT_TOLERANCE: f64 = 0.05;
+/// Saved loops of a track, one per pad
+pub const SAVED_LOOP_PADS: usize = 8;
+/// Quantized loops are no shorter than 1/2^5, a 32nd, of a beat
+const MAX_LOOP_DIVISION: f64 = 5.0;
+/// Bars of a phrase, after which dance tracks usually change
+const PHRASE_BARS: f64 = 8.0;
+/// Bars of the loop set on the next phrase
+const PHRASE_LOOP_BARS: f64 = 4.0;
+/// Taps from which the tempo of the beat grid is corrected, fewer only
+/// moving the grid
+const MIN_TEMPO_TAPS: usize = 4;
+
+/// Loop kept with a track and activated from its pad
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedLoop {
+    pub name: String,
+    /// in and out positions in seconds
+    pub start: f64,
+    pub end: f64,
+}
+
+impl SavedLoop {
+    pub fn length(&self) -> f64 {
+        self.end - self.start
+    }
+}
+
+/// What is remembered about a track between sessions
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrackInfo {
+    /// positions in seconds marked while preparing the track, in order
+    pub memory_points: Vec<f64>,
+    /// image shown on the platter instead of the `cover.jpg` of the folder
+    pub cover: Option<String>,
+    /// accent color tinting the deck header and progress bar
+    pub color: Option<[u8; 3]>,
+    /// tempo of the track at its original speed
+    pub bpm: Option<f64>,
+    /// position in seconds of a downbeat, the first beat of a bar, anchoring
+    /// the beat grid
+    pub first_beat: f64,
+    /// silences, intro and outro found when the track was first loaded
+    pub analysis: Option<TrackAnalysis>,
+    /// musical key in Camelot notation, e.g. `8A`
+    pub key: Option<String>,
+    pub genre: Option<String>,
+    /// free text shown on the deck, e.g. `long intro, drop at 1:32`
+    pub notes: String,
+    /// from 0, unrated, to 5 stars
+    pub rating: u8,
+    /// sessions in which the track was heard on the master
+    pub play_count: u32,
+    /// when the track was last heard on the master, in seconds since the
+    /// Unix epoch
+    pub last_played: Option<u64>,
+    pub saved_loops: Vec<SavedLoop>,
+}
+
+impl TrackInfo {
+    /// Mark `position`, unless a memory point is already there
+    pub fn add_memory_point(&mut self, position: f64) {
+        if self
+            .memory_points
+            .iter()
+            .any(|point| (point - position).abs() < MEMORY_POINT_TOLERANCE)
+        {
+            return;
+        }
+
+        let index = self
+            .memory_points
+            .partition_point(|point| *point < position);
+        self.memory_points.insert(index, position);
+    }
+
+    /// Keep the loop from `start` to `end`, returning its pad, unless every
+    /// pad is taken. A loop already saved keeps its pad and name
+    pub fn save_loop(&mut self, start: f64, end: f64) -> Option<usize> {
+        let saved = self.saved_loops.iter().position(|saved| {
+            (saved.start - start).abs() < MEMORY_POINT_TOLERANCE
+                && (saved.end - end).abs() < MEMORY_POINT_TOLERANCE
+        });
+        if saved.is_some() {
+            return saved;
+        }
+        if self.saved_loops.len() >= SAVED_LOOP_PADS {
+            return None;
+        }
+
+        self.saved_loops.push(SavedLoop {
+            name: format!("Loop {}", self.saved_loops.len() + 1),
+            start,
+            end,
+        });
+        Some(self.saved_loops.len() - 1)
+    }
+
+    /// Snap a loop to the beat grid, its start on the nearest beat and its
+    /// length to a whole number of beats, or to a power of two fraction of
+    /// a beat when shorter. Unchanged without a tempo
+    pub fn quantize_loop(&self, start: f64, end: f64) -> (f64, f64) {
+        let Some(beat) = self.bpm.filter(|bpm| *bpm > 0.0).map(|bpm| 60.0 / bpm) else {
+            return (start, end);
+        };
+
+        let beats = (end - start) / beat;
+        let beats = match beats >= 1.0 {
+            true => beats.round(),
+            false => 2f64.powf(beats.log2().round().max(-MAX_LOOP_DIVISION)),
+        };
+        let start = self.first_beat + ((start - self.first_beat) / beat).round() * beat;
+        (start, start + beats * beat)
+    }
+
+    /// Length in seconds of a beat at the original speed
+    pub fn beat_length(&self) -> Option<f64> {
+        self.bpm.filter(|bpm| *bpm > 0.0).map(|bpm| 60.0 / bpm)
+    }
+
+    /// Beats of the grid from `start` to `end`, with whether they start a
+    /// bar
+    pub fn beats(&self, start: f64, end: f64) -> Vec<(f64, bool)> {
+        let Some(beat) = self.beat_length() else {
+            return Vec::new();
+        };
+
+        let first = ((start - self.first_beat) / beat).ceil() as i64;
+        let last = ((end - self.first_beat) / beat).floor() as i64;
+        (first..=last)
+            .map(|index| {
+                (
+                    self.first_beat + index as f64 * beat,
+                    index.rem_euclid(BEATS_PER_BAR as i64) == 0,
+                )
+            })
+            .collect()
+    }
+
+    pub fn nearest_beat(&self, position: f64) -> Option<f64> {
+        let beat = self.beat_length()?;
+        Some(self.first_beat + ((position - self.first_beat) / beat).round() * beat)
+    }
+
+    /// First downbeat of the grid once the track sounds, past its leading
+    /// silence when analyzed
+    pub fn first_downbeat(&self) -> Option<f64> {
+        let bar = self.beat_length()? * BEATS_PER_BAR as f64;
+        let start = self.analysis.map_or(0.0, |analysis| {
+            analysis.sound_start - MEMORY_POINT_TOLERANCE
+        });
+        Some(self.first_beat + ((start - self.first_beat) / bar).ceil() * bar)
+    }
+
+    /// Move the beat grid by `seconds`, later when positive
+    pub fn nudge_grid(&mut self, seconds: f64) {
+        self.first_beat += seconds;
+    }
+
+    /// Move the downbeat by `beats`, when the first beat of the bar was
+    /// taken for another one
+    pub fn shift_downbeat(&mut self, beats: i64) {
+        if let Some(beat) = self.beat_length() {
+            self.first_beat += beats as f64 * beat;
+        }
+    }
+
+    /// Correct the beat grid from taps on consecutive beats, positions in
+    /// seconds. Enough taps also correct the tempo, otherwise the nearest
+    /// beat moves onto the last tap. The beats keep their place in the bar
+    pub fn tap_grid(&mut self, taps: &[f64]) {
+        let Some(&last) = taps.last() else {
+            return;
+        };
+
+        let fit =
+            beatgrid::fit_taps(taps).filter(|_| taps.len() >= MIN_TEMPO_TAPS || self.bpm.is_none());
+        match (fit, self.nearest_beat(last)) {
+            (Some((bpm, first_tap)), _) => {
+                let beat_of_bar = match self.beat_length() {
+                    Some(beat) => ((first_tap - self.first_beat) / beat).round() as i64,
+                    None => 0,
+                }
+                .rem_euclid(BEATS_PER_BAR as i64);
+                let beat = 60.0 / bpm;
+                let bar = beat * BEATS_PER_BAR as f64;
+                self.bpm = Some(bpm);
+                self.first_beat = (first_tap - beat_of_bar as f64 * beat).rem_euclid(bar);
+            }
+            (None, Some(nearest)) => self.first_beat += last - nearest,
+            (None, None) => (),
+        }
+    }
+
+    /// First phrase boundary from `position`, the phrases being counted on
+    /// the beat grid from the start of the body of the track when analyzed,
+    /// since it usually starts a phrase, or else from its first beat
+    pub fn next_phrase(&self, position: f64) -> Option<f64> {
+        let beat = 60.0 / self.bpm.filter(|bpm| *bpm > 0.0)?;
+        let bar = beat * BEATS_PER_BAR as f64;
+        let phrase = bar * PHRASE_BARS;
+
+        let anchor = match &self.analysis {
+            Some(analysis) => {
+                self.first_beat + ((analysis.intro_end - self.first_beat) / bar).round() * bar
+            }
+            None => self.first_beat,
+        };
+        Some(anchor + ((position - anchor) / phrase).ceil() * phrase)
+    }
+
+    /// Loop of 4 bars starting on the next phrase boundary from `position`
+    pub fn phrase_loop(&self, position: f64) -> Option<(f64, f64)> {
+        let start = self.next_phrase(position)?;
+        let beat = 60.0 / self.bpm?;
+        Some((
+            start,
+            start + beat * BEATS_PER_BAR as f64 * PHRASE_LOOP_BARS,
+        ))
+    }
+
+    /// First memory point after `position`
+    pub fn next_memory_point(&self, position: f64) -> Option<f64> {
+        self.memory_points
+            .iter()
+            .copied()
+            .find(|point| *point > position + MEMORY_POINT_TOLERANCE)
+    }
+
+    /// Last memory point before `position`
+    pub fn previous_memory_point(&self, position: f64) -> Option<f64> {
+        self.memory_points
+            .iter()
+            .copied()
+            .rev()
+            .find(|point| *point < position - MEMORY_POINT_TOLERANCE)
+    }
+}
+
+/// Track information keyed by path, saved in the data directory
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Library {
+    tracks: BTreeMap<String, TrackInfo>,
+    /// root folder of the file explorer. Tracks under it are saved relative
+    /// to it, so that a music drive mounted elsewhere still resolves
+    #[serde(skip)]
+    root: Option<String>,
+}
+
+/// The library as saved, the paths under the root being relative
+#[derive(Serialize)]
+struct SavedLibrary<'a> {
+    tracks: BTreeMap<&'a str, &'a TrackInfo>,
+}
+
+impl Library {
+    /// Load the saved library, or an empty one
+    pub fn load() -> Self {
+        let path = data_dir().join(LIBRARY_FILE);
+        let Ok(content) = fs::read_to_string(&path) else {
+            return Self::default();
+        };
+
+        let mut library: Library = match toml::from_str(&content) {
+            Ok(library) => library,
+            Err(e) => {
+                log::error!("Cannot read '{}': {}", path.display(), e);
+                return Self::default();
+            }
+        };
+
+        let root = root_dir().map(|dir| dir.to_string_lossy().to_string());
+        let rebound = library.bind(root, |path| Path::new(path).exists());
+        if rebound > 0 {
+            log::info!("Found {} missing tracks under the root folder", rebound);
+            library.save();
+        }
+        library
+    }
+
+    /// Resolve the relative paths against `root`, and rebind the tracks
+    /// missing at their absolute path to the same file under `root`, e.g.
+    /// after moving the music drive to another machine. Returns the number
+    /// of rebound tracks
+    fn bind(&mut self, root: Option<String>, exists: impl Fn(&str) -> bool) -> usize {
+        let mut rebound = 0;
+        for (path, track) in std::mem::take(&mut self.tracks) {
+            let path = match &root {
+                Some(root) if Path::new(&path).is_relative() => format!("{}/{}", root, path),
+                Some(root) if !exists(&path) => match rebind(root, &path, &exists) {
+                    Some(path) => {
+                        rebound += 1;
+                        path
+                    }
+                    None => path,
+                },
+                _ => path,
+            };
+            // a track saved at both places keeps its first information
+            self.tracks.entry(path).or_insert(track);
+        }
+
+        self.root = root;
+        rebound
+    }
+
+    fn saved(&self) -> SavedLibrary<'_> {
+        let root = self.root.as_deref();
+        SavedLibrary {
+            tracks: self
+                .tracks
+                .iter()
+                .map(|(path, track)| (relative_path(root, path).unwrap_or(path), track))
+                .collect(),
+        }
+    }
+
+    pub fn save(&self) {
+        let dir = data_dir();
+        let result = toml::to_string(&self.saved())
+            .map_err(|e| e.to_string())
+            .and_then(|content| {
+                fs::create_dir_all(&dir)
+                    .and_then(|_| fs::write(dir.join(LIBRARY_FILE), content))
+                    .map_err(|e| e.to_string())
+            });
+
+        match result {
+            Ok(()) => (),
+            Err(e) => log::error!("Cannot save library: {}", e),
+        }
+    }
+
+    pub fn tracks(&self) -> impl Iterator<Item = (&String, &TrackInfo)> {
+        self.tracks.iter()
+    }
+
+    pub fn track(&self, path: &str) -> Option<&TrackInfo> {
+        self.tracks.get(path)
+    }
+
+    /// Information of the track at `path`, added if unknown
+    pub fn track_mut(&mut self, path: &str) -> &mut TrackInfo {
+        self.tracks.entry(path.to_string()).or_default()
+    }
+}
+
+/// `path` relative to `root`, if under it
+fn relative_path<'a>(root: Option<&str>, path: &'a str) -> Option<&'a str> {
+    path.strip_prefix(root?)?
+        .strip_prefix(['/', '\\'])
+        .filter(|relative| !relative.is_empty())
+}
+
+/// The file under `root` ending with the longest part of `path`, if any
+fn rebind(root: &str, path: &str, exists: impl Fn(&str) -> bool) -> Option<String> {
+    let components: Vec<&str> = path
+        .split(['/', '\\'])
+        .filter(|component| !component.is_empty())
+        .collect();
+
+    (1..components.len())
+        .map(|start| format!("{}/{}", root, components[start..].join("/")))
+        .find(|candidate| exists(candidate))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relative_paths() {
+        let mut library = Library::default();
+        library
+            .tracks
+            .insert("house/a.mp3".to_string(), TrackInfo::default());
+        library.tracks.insert(
+            "/old/usb/music/techno/b.mp3".to_string(),
+            TrackInfo::default(),
+        );
+        library
+            .tracks
+            .insert("/elsewhere/c.mp3".to_string(), TrackInfo::default());
+
+        let exists = |path: &str| path == "/media/usb/music/techno/b.mp3";
+        let rebound = library.bind(Some("/media/usb/music".to_string()), exists);
+        assert_eq!(rebound, 1);
+        let paths: Vec<_> = library.tracks().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "/elsewhere/c.mp3",
+                "/media/usb/music/house/a.mp3",
+                "/media/usb/music/techno/b.mp3"
+            ]
+        );
+
+        let saved: Vec<_> = library.saved().tracks.into_keys().collect();
+        assert_eq!(saved, ["/elsewhere/c.mp3", "house/a.mp3", "techno/b.mp3"]);
+    }
+
+    #[test]
+    fn test_memory_points() {
+        let mut track = TrackInfo::default();
+        track.add_memory_point(30.0);
+        track.add_memory_point(10.0);
+        track.add_memory_point(20.0);
+        track.add_memory_point(20.01);
+        assert_eq!(track.memory_points, vec![10.0, 20.0, 30.0]);
+
+        assert_eq!(track.next_memory_point(0.0), Some(10.0));
+        // standing on a point goes to the following one
+        assert_eq!(track.next_memory_point(20.0), Some(30.0));
+        assert_eq!(track.next_memory_point(30.0), None);
+        assert_eq!(track.previous_memory_point(20.0), Some(10.0));
+        assert_eq!(track.previous_memory_point(5.0), None);
+    }
+
+    #[test]
+    fn test_quantize_loop() {
+        let mut track = TrackInfo::default();
+        assert_eq!(track.quantize_loop(1.1, 2.3), (1.1, 2.3));
+
+        // a beat every half second
+        track.bpm = Some(120.0);
+        track.first_beat = 0.2;
+        let (start, end) = track.quantize_loop(1.1, 2.3);
+        assert!((start - 1.2).abs() < 1e-9);
+        assert!((end - 2.2).abs() < 1e-9);
+        let (start, end) = track.quantize_loop(1.2, 1.36);
+        assert!((start - 1.2).abs() < 1e-9);
+        assert!((end - 1.325).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_beat_grid() {
+        let mut track = TrackInfo::default();
+        assert!(track.beats(0.0, 10.0).is_empty());
+
+        track.bpm = Some(120.0);
+        track.first_beat = 0.2;
+        assert_eq!(
+            track.beats(1.0, 2.5),
+            vec![(1.2, false), (1.7, false), (2.2, true)]
+        );
+        assert_eq!(track.nearest_beat(1.4), Some(1.2));
+
+        // the bars before the track sounds are skipped
+        assert_eq!(track.first_downbeat(), Some(0.2));
+        track.analysis = Some(TrackAnalysis {
+            sound_start: 2.21,
+            ..Default::default()
+        });
+        assert_eq!(track.first_downbeat(), Some(2.2));
+        track.analysis = None;
+
+        track.nudge_grid(0.05);
+        assert_eq!(track.nearest_beat(1.4), Some(1.25));
+        track.shift_downbeat(1);
+        assert_eq!(track.beats(2.5, 2.8), vec![(2.75, true)]);
+
+        // a single tap moves the grid, the beats keeping their place
+        track.tap_grid(&[2.3]);
+        assert!((track.first_beat - 0.8).abs() < 1e-9);
+
+        // enough taps correct the tempo, the first tap staying on the last
+        // beat of a bar
+        track.tap_grid(&[10.3, 10.8, 11.3, 11.8]);
+        assert!((track.bpm.unwrap() - 120.0).abs() < 1e-6);
+        track.tap_grid(&[10.3, 10.78, 11.26, 11.74]);
+        assert!((track.bpm.unwrap() - 125.0).abs() < 1e-6);
+        let beats = track.beats(10.0, 10.5);
+        assert!((beats[0].0 - 10.3).abs() < 1e-6 && !beats[0].1);
+    }
+
+    #[test]
+    fn test_phrase_loop() {
+        let mut track = TrackInfo::default();
+        assert_eq!(track.phrase_loop(10.0), None);
+
+        // 2 second bars, 16 second phrases
+        track.bpm = Some(120.0);
+        track.first_beat = 0.5;
+        assert_eq!(track.next_phrase(10.0), Some(16.5));
+        assert_eq!(track.next_phrase(16.5), Some(16.5));
+        assert_eq!(track.phrase_loop(20.0), Some((32.5, 40.5)));
+
+        track.analysis = Some(TrackAnalysis {
+            intro_end: 24.4,
+            ..Default::default()
+        });
+        assert_eq!(track.next_phrase(10.0), Some(24.5));
+        assert_eq!(track.next_phrase(0.0), Some(8.5));
+    }
+
+    #[test]
+    fn test_save_loop() {
+        let mut track = TrackInfo::default();
+        assert_eq!(track.save_loop(10.0, 12.0), Some(0));
+        assert_eq!(track.save_loop(30.0, 38.0), Some(1));
+        assert_eq!(track.save_loop(10.01, 12.0), Some(0));
+        assert_eq!(track.saved_loops[1].name, "Loop 2");
+        assert_eq!(track.saved_loops[1].length(), 8.0);
+
+        for pad in 2..SAVED_LOOP_PADS {
+            assert_eq!(
+                track.save_loop(pad as f64 * 20.0, pad as f64 * 20.0 + 4.0),
+                Some(pad)
+            );
+        }
+        assert_eq!(track.save_loop(500.0, 504.0), None);
+    }
+}
@@ -0,0 +1,174 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use kira::{
+    clock::clock_info::ClockInfoProvider,
+    effect::{Effect, EffectBuilder},
+    modulator::value_provider::ModulatorValueProvider,
+    Frame,
+};
+
+use crate::processable::Processable;
+
+/// Level a limited channel never exceeds, just under full scale
+const CEILING: f32 = 0.95;
+/// Time for the gain to recover most of the way after a peak
+const RELEASE_SECONDS: f32 = 0.1;
+/// Time the clip indicator stays lit after a peak
+const CLIP_HOLD_SECONDS: f64 = 1.0;
+
+/// Peak limiter with an instant attack, so that nothing goes past the
+/// ceiling, and a smooth release
+struct Limiter {
+    gain: f32,
+    /// part of the way back to unity gain recovered on each frame
+    release: f32,
+}
+
+impl Limiter {
+    fn new(sample_rate: u32) -> Self {
+        Self {
+            gain: 1.0,
+            release: 1.0 - (-1.0 / (RELEASE_SECONDS * sample_rate as f32)).exp(),
+        }
+    }
+
+    fn process(&mut self, input: Frame) -> Frame {
+        let peak = input.left.abs().max(input.right.abs());
+        let target = match peak > CEILING {
+            true => CEILING / peak,
+            false => 1.0,
+        };
+
+        self.gain = match target < self.gain {
+            true => target,
+            false => self.gain + (target - self.gain) * self.release,
+        };
+        input * self.gain
+    }
+}
+
+/// Effect limiting a channel after its EQ, so that boosted bands cannot clip
+/// the master. Peaks over full scale are reported whether limiting or not
+pub struct LimiterEffect {
+    limiter: Limiter,
+    enabled: Arc<AtomicBool>,
+    clipped: Arc<AtomicBool>,
+}
+
+impl EffectBuilder for LimiterEffect {
+    type Handle = ();
+
+    fn build(self) -> (Box<dyn Effect>, Self::Handle) {
+        (Box::new(self), ())
+    }
+}
+
+impl Effect for LimiterEffect {
+    fn init(&mut self, sample_rate: u32) {
+        self.limiter = Limiter::new(sample_rate);
+    }
+
+    fn on_change_sample_rate(&mut self, sample_rate: u32) {
+        self.limiter = Limiter::new(sample_rate);
+    }
+
+    fn process(
+        &mut self,
+        input: Frame,
+        _dt: f64,
+        _clock_info_provider: &ClockInfoProvider,
+        _modulator_value_provider: &ModulatorValueProvider,
+    ) -> Frame {
+        if input.left.abs() > 1.0 || input.right.abs() > 1.0 {
+            self.clipped.store(true, Ordering::Relaxed);
+        }
+
+        // the gain keeps following the signal so that enabling the limiter
+        // does not jump
+        let limited = self.limiter.process(input);
+        match self.enabled.load(Ordering::Relaxed) {
+            true => limited,
+            false => input,
+        }
+    }
+}
+
+/// Clip protection of a channel, and its clip indicator
+pub struct ChannelLimiter {
+    enabled: Arc<AtomicBool>,
+    clipped: Arc<AtomicBool>,
+    /// time left lighting the clip indicator
+    clip_hold: f64,
+}
+
+impl ChannelLimiter {
+    /// Create a limiter along with the effect to add to the channel
+    pub fn new() -> (Self, LimiterEffect) {
+        let enabled = Arc::new(AtomicBool::new(true));
+        let clipped = Arc::new(AtomicBool::new(false));
+
+        (
+            Self {
+                enabled: Arc::clone(&enabled),
+                clipped: Arc::clone(&clipped),
+                clip_hold: 0.0,
+            },
+            LimiterEffect {
+                limiter: Limiter::new(48_000),
+                enabled,
+                clipped,
+            },
+        )
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Whether the channel went over full scale in the last second, limited
+    /// or not
+    pub fn is_clipping(&self) -> bool {
+        self.clip_hold > 0.0
+    }
+}
+
+impl Processable for ChannelLimiter {
+    fn process(&mut self, delta: f64) {
+        self.clip_hold = match self.clipped.swap(false, Ordering::Relaxed) {
+            true => CLIP_HOLD_SECONDS,
+            false => (self.clip_hold - delta).max(0.0),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limiter() {
+        let sample_rate = 48_000;
+        let mut limiter = Limiter::new(sample_rate);
+
+        let quiet = limiter.process(Frame::from_mono(0.5));
+        assert_eq!(quiet.left, 0.5);
+
+        // a boosted peak is held under the ceiling at once
+        let loud = limiter.process(Frame::new(1.8, -0.4));
+        assert!(loud.left <= CEILING);
+        assert!((loud.right + 0.4 * CEILING / 1.8).abs() < 1e-6);
+
+        // then the gain recovers
+        for _ in 0..sample_rate {
+            limiter.process(Frame::from_mono(0.5));
+        }
+        assert!((limiter.process(Frame::from_mono(0.5)).left - 0.5).abs() < 1e-3);
+    }
+}
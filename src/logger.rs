@@ -0,0 +1,202 @@
+use std::{
+    collections::VecDeque,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    panic,
+    path::PathBuf,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use env_logger::{Env, Target};
+use log::{Log, Metadata, Record};
+
+use crate::config::data_dir;
+
+/// Size from which the current log file is rotated
+const MAX_FILE_SIZE: u64 = 5 * 1024 * 1024;
+/// Number of rotated files kept along with the current one
+const MAX_ROTATED_FILES: usize = 4;
+/// Number of log lines kept in memory for crash reports
+const MAX_RECENT_LINES: usize = 200;
+
+static RECENT_LINES: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+/// Directory holding the log files, in the platform data directory
+pub fn log_dir() -> PathBuf {
+    data_dir().join("logs")
+}
+
+/// Last log lines, oldest first, whatever the configured output
+pub fn recent_lines() -> Vec<String> {
+    match RECENT_LINES.try_lock() {
+        Ok(lines) => lines.iter().cloned().collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Initialize the logger.
+///
+/// Logs go to stderr by default. When the `LOG_TO_FILE` environment variable
+/// is set, they are also written to rotating files in [`log_dir`] and the
+/// default level is raised to `info` so that device events are recorded
+pub fn init() {
+    let to_file = dotenv::var("LOG_TO_FILE").is_ok_and(|value| value != "0" && value != "false");
+
+    let mut builder = env_logger::Builder::from_env(Env::default().default_filter_or(if to_file {
+        "info"
+    } else {
+        "error"
+    }));
+
+    let file = if to_file {
+        match RotatingFile::open(log_dir(), "bousse.log") {
+            Ok(file) => Some(file),
+            Err(e) => {
+                eprintln!("Cannot open log file in '{}': {}", log_dir().display(), e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    if let Some(file) = file {
+        builder.target(Target::Pipe(Box::new(Tee(io::stderr(), file))));
+    }
+
+    let logger = Logger {
+        inner: builder.build(),
+    };
+    log::set_max_level(logger.inner.filter());
+    log::set_boxed_logger(Box::new(logger)).expect("Logger already initialized");
+
+    // panics would otherwise only reach stderr and be missing from the logs
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        log::error!("{}", info);
+        default_hook(info);
+    }));
+}
+
+/// Forwards records to env_logger and keeps the last lines in memory
+struct Logger {
+    inner: env_logger::Logger,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.inner.matches(record) {
+            return;
+        }
+
+        self.inner.log(record);
+
+        // never block, a panic may occur while the lock is held
+        if let Ok(mut lines) = RECENT_LINES.try_lock() {
+            if lines.len() == MAX_RECENT_LINES {
+                lines.pop_front();
+            }
+            lines.push_back(format!(
+                "[{:.3} {} {}] {}",
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs_f64(),
+                record.level(),
+                record.target(),
+                record.args()
+            ));
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Log file that is renamed to `<name>.1` once it grows past
+/// [`MAX_FILE_SIZE`], older files being shifted up to `<name>.MAX_ROTATED_FILES`
+struct RotatingFile {
+    dir: PathBuf,
+    name: String,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn open(dir: PathBuf, name: &str) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(name))?;
+        let size = file.metadata()?.len();
+
+        Ok(Self {
+            dir,
+            name: name.to_string(),
+            file,
+            size,
+        })
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        self.dir.join(format!("{}.{}", self.name, index))
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+
+        for index in (1..MAX_ROTATED_FILES).rev() {
+            let path = self.rotated_path(index);
+            if path.exists() {
+                fs::rename(path, self.rotated_path(index + 1))?;
+            }
+        }
+        fs::rename(self.dir.join(&self.name), self.rotated_path(1))?;
+
+        self.file = File::create(self.dir.join(&self.name))?;
+        self.size = 0;
+
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > MAX_FILE_SIZE {
+            self.rotate()?;
+        }
+
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Writes to both outputs, the first one being kept for the terminal
+struct Tee<A: Write, B: Write>(A, B);
+
+impl<A: Write, B: Write> Write for Tee<A, B> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write_all(buf)?;
+        self.1.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()?;
+        self.1.flush()
+    }
+}
@@ -0,0 +1,308 @@
+use std::{collections::VecDeque, f64::consts::PI};
+
+use kira::{
+    clock::clock_info::ClockInfoProvider,
+    effect::{Effect, EffectBuilder},
+    modulator::value_provider::ModulatorValueProvider,
+    Frame,
+};
+use ringbuf::{HeapConsumer, HeapProducer, HeapRb};
+
+use crate::processable::Processable;
+
+/// Loudness is measured on blocks of 100 ms
+const BLOCKS_PER_SECOND: u32 = 10;
+const MOMENTARY_BLOCKS: usize = 4;
+const SHORT_TERM_BLOCKS: usize = 30;
+const ABSOLUTE_GATE: f64 = -70.0;
+const RELATIVE_GATE: f64 = -10.0;
+
+/// Loudness in LUFS of a mean square power, as defined by ITU-R BS.1770
+fn to_lufs(power: f64) -> f64 {
+    -0.691 + 10.0 * power.log10()
+}
+
+/// Second order IIR filter
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    /// Band pass around `center` Hz with a peak gain of 0 dB, wider as `q`
+    /// gets smaller
+    pub fn band_pass(sample_rate: f64, center: f64, q: f64) -> Self {
+        let w0 = 2.0 * PI * center / sample_rate;
+        let alpha = w0.sin() / (2.0 * q);
+        let a0 = 1.0 + alpha;
+
+        Self {
+            b: [alpha / a0, 0.0, -alpha / a0],
+            a: [-2.0 * w0.cos() / a0, (1.0 - alpha) / a0],
+            ..Default::default()
+        }
+    }
+
+    pub fn process(&mut self, input: f64) -> f64 {
+        let output = self.b[0] * input + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[0] * self.y[0]
+            - self.a[1] * self.y[1];
+
+        self.x = [input, self.x[0]];
+        self.y = [output, self.y[0]];
+
+        output
+    }
+}
+
+/// K-weighting filter of BS.1770: a high shelf modelling the head followed
+/// by a high pass
+#[derive(Debug, Clone, Copy)]
+struct KWeighting {
+    shelf: Biquad,
+    high_pass: Biquad,
+}
+
+impl KWeighting {
+    fn new(sample_rate: f64) -> Self {
+        // coefficients derived for any sample rate, as in libebur128
+        let (f0, gain, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+        let k = (PI * f0 / sample_rate).tan();
+        let vh = 10f64.powf(gain / 20.0);
+        let vb = vh.powf(0.4996667741545416);
+        let a0 = 1.0 + k / q + k * k;
+        let shelf = Biquad {
+            b: [
+                (vh + vb * k / q + k * k) / a0,
+                2.0 * (k * k - vh) / a0,
+                (vh - vb * k / q + k * k) / a0,
+            ],
+            a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+            ..Default::default()
+        };
+
+        let (f0, q) = (38.13547087602444, 0.5003270373238773);
+        let k = (PI * f0 / sample_rate).tan();
+        let a0 = 1.0 + k / q + k * k;
+        let high_pass = Biquad {
+            b: [1.0, -2.0, 1.0],
+            a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+            ..Default::default()
+        };
+
+        Self { shelf, high_pass }
+    }
+
+    fn process(&mut self, input: f64) -> f64 {
+        self.high_pass.process(self.shelf.process(input))
+    }
+}
+
+/// Computes the K-weighted mean square power of consecutive 100 ms blocks
+struct BlockAnalyzer {
+    left: KWeighting,
+    right: KWeighting,
+    block_size: u32,
+    count: u32,
+    sum: f64,
+}
+
+impl BlockAnalyzer {
+    fn new(sample_rate: u32) -> Self {
+        Self {
+            left: KWeighting::new(sample_rate as f64),
+            right: KWeighting::new(sample_rate as f64),
+            block_size: sample_rate / BLOCKS_PER_SECOND,
+            count: 0,
+            sum: 0.0,
+        }
+    }
+
+    /// Returns the power of the block completed by this frame, if any
+    fn push(&mut self, frame: Frame) -> Option<f64> {
+        let left = self.left.process(frame.left as f64);
+        let right = self.right.process(frame.right as f64);
+        self.sum += left * left + right * right;
+        self.count += 1;
+
+        if self.count < self.block_size {
+            return None;
+        }
+
+        let power = self.sum / self.count as f64;
+        self.count = 0;
+        self.sum = 0.0;
+        Some(power)
+    }
+}
+
+/// Effect passing its input through while measuring its loudness
+pub struct LoudnessTap {
+    analyzer: BlockAnalyzer,
+    producer: HeapProducer<f64>,
+}
+
+impl EffectBuilder for LoudnessTap {
+    type Handle = ();
+
+    fn build(self) -> (Box<dyn Effect>, Self::Handle) {
+        (Box::new(self), ())
+    }
+}
+
+impl Effect for LoudnessTap {
+    fn init(&mut self, sample_rate: u32) {
+        self.analyzer = BlockAnalyzer::new(sample_rate);
+    }
+
+    fn on_change_sample_rate(&mut self, sample_rate: u32) {
+        self.analyzer = BlockAnalyzer::new(sample_rate);
+    }
+
+    fn process(
+        &mut self,
+        input: Frame,
+        _dt: f64,
+        _clock_info_provider: &ClockInfoProvider,
+        _modulator_value_provider: &ModulatorValueProvider,
+    ) -> Frame {
+        if let Some(power) = self.analyzer.push(input) {
+            let _ = self.producer.push(power);
+        }
+
+        input
+    }
+}
+
+/// Momentary, short-term and integrated loudness of a track, in LUFS
+pub struct LoudnessMeter {
+    consumer: HeapConsumer<f64>,
+    /// last blocks, enough for the short-term loudness
+    blocks: VecDeque<f64>,
+    /// power of every 400 ms block since the last reset, for the integrated
+    /// loudness
+    gating_blocks: Vec<f64>,
+    integrated: Option<f64>,
+}
+
+impl LoudnessMeter {
+    /// Create a meter along with the effect feeding it
+    pub fn new() -> (Self, LoudnessTap) {
+        let (producer, consumer) = HeapRb::new(BLOCKS_PER_SECOND as usize * 10).split();
+
+        (
+            Self {
+                consumer,
+                blocks: VecDeque::new(),
+                gating_blocks: Vec::new(),
+                integrated: None,
+            },
+            LoudnessTap {
+                analyzer: BlockAnalyzer::new(48_000),
+                producer,
+            },
+        )
+    }
+
+    fn average(&self, count: usize) -> Option<f64> {
+        if self.blocks.len() < count {
+            return None;
+        }
+
+        let sum: f64 = self.blocks.iter().rev().take(count).sum();
+        Some(to_lufs(sum / count as f64))
+    }
+
+    /// Loudness over the last 400 ms
+    pub fn momentary(&self) -> Option<f64> {
+        self.average(MOMENTARY_BLOCKS)
+    }
+
+    /// Loudness over the last 3 s
+    pub fn short_term(&self) -> Option<f64> {
+        self.average(SHORT_TERM_BLOCKS)
+    }
+
+    /// Gated loudness since the last reset
+    pub fn integrated(&self) -> Option<f64> {
+        self.integrated
+    }
+
+    pub fn reset(&mut self) {
+        self.blocks.clear();
+        self.gating_blocks.clear();
+        self.integrated = None;
+    }
+
+    fn add_block(&mut self, power: f64) {
+        self.blocks.push_back(power);
+        if self.blocks.len() > SHORT_TERM_BLOCKS {
+            self.blocks.pop_front();
+        }
+
+        // 400 ms gating blocks overlapping by 75%
+        if self.blocks.len() >= MOMENTARY_BLOCKS {
+            let sum: f64 = self.blocks.iter().rev().take(MOMENTARY_BLOCKS).sum();
+            self.gating_blocks.push(sum / MOMENTARY_BLOCKS as f64);
+        }
+    }
+
+    fn update_integrated(&mut self) {
+        let gated_mean = |threshold: f64| {
+            let (sum, count) = self
+                .gating_blocks
+                .iter()
+                .filter(|power| to_lufs(**power) > threshold)
+                .fold((0.0, 0), |(sum, count), power| (sum + power, count + 1));
+            (count > 0).then(|| sum / count as f64)
+        };
+
+        self.integrated = gated_mean(ABSOLUTE_GATE)
+            .and_then(|power| gated_mean(to_lufs(power) + RELATIVE_GATE))
+            .map(to_lufs);
+    }
+}
+
+impl Processable for LoudnessMeter {
+    fn process(&mut self, _delta: f64) {
+        let mut updated = false;
+        while let Some(power) = self.consumer.pop() {
+            self.add_block(power);
+            updated = true;
+        }
+
+        if updated {
+            self.update_integrated();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sine_loudness() {
+        // EBU Tech 3341: a 1 kHz sine at -23 dBFS on both channels is -23 LUFS
+        let sample_rate = 48_000;
+        let amplitude = 10f32.powf(-23.0 / 20.0);
+        let (mut meter, mut tap) = LoudnessMeter::new();
+        tap.analyzer = BlockAnalyzer::new(sample_rate);
+
+        for i in 0..sample_rate * 5 {
+            let t = i as f32 / sample_rate as f32;
+            let value = amplitude * (2.0 * std::f32::consts::PI * 1000.0 * t).sin();
+            if let Some(power) = tap.analyzer.push(Frame::from_mono(value)) {
+                meter.add_block(power);
+            }
+        }
+        meter.update_integrated();
+
+        assert!((meter.momentary().unwrap() + 23.0).abs() < 0.1);
+        assert!((meter.short_term().unwrap() + 23.0).abs() < 0.1);
+        assert!((meter.integrated().unwrap() + 23.0).abs() < 0.1);
+    }
+}
@@ -1,54 +1,105 @@
-use std::{error::Error, sync::Arc, time::Duration};
+use std::{error::Error, time::Duration};
 
-use egui::mutex::Mutex;
-use midi_controller::MidiController;
 use winit::{
     event::{Event, StartCause},
     event_loop::{ControlFlow, EventLoop},
 };
 
+mod analysis;
 mod app;
+mod audio_backend;
+mod audio_input;
+mod automation;
+mod backup;
+mod beatgrid;
+mod browser_filter;
+mod clap_host;
+mod config;
 mod controller;
 mod cover_img;
+mod cue_preview;
+mod diagnostics;
+mod duck_server;
+mod duration;
 mod file_navigator;
+mod gamepad_controller;
+mod generator;
+mod gesture;
+mod global_hotkeys;
 mod gpu;
 mod gui;
+mod hid_controller;
+mod input_source;
+mod keyboard_controller;
+mod layer;
+mod library;
+mod limiter;
+mod logger;
+mod loudness;
+mod mapping;
+mod mapping_script;
+mod master_tempo;
+mod metronome;
 mod midi_controller;
 mod mixer;
+mod morph;
+mod musical_key;
+mod overlay_server;
+mod overview;
+mod overview_gpu;
+mod plugin_slot;
+mod preflight;
 mod processable;
+mod recorder;
+mod routing;
+mod sampler;
+mod scheduler;
+mod session;
+mod settings;
+mod spectrogram;
+mod stats;
+mod stems;
+mod suggestions;
+mod theme;
+mod thumbnails;
+mod timecode;
+mod toast;
+mod track_loader;
+mod trackpad_controller;
+mod trainer;
+mod transform;
 mod turntable;
+#[cfg(test)]
+mod turntable_simulation;
+mod tutorial;
 mod utils;
+mod watchdog;
+mod window_geometry;
 
 use app::App;
 use dotenv::dotenv;
 
 fn main() -> Result<(), Box<dyn Error>> {
-    env_logger::init();
     dotenv().ok();
+    // the settings of a portable install travel with it
+    if let Some(dir) = config::portable_dir() {
+        dotenv::from_path(dir.join(".env")).ok();
+    }
+    logger::init();
+    session::install_crash_handler();
 
-    println!("{} v{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+    log::info!("{} v{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
 
     let event_loop = EventLoop::new().unwrap();
     event_loop.set_control_flow(ControlFlow::wait_duration(Duration::default()));
 
-    let app = Arc::new(Mutex::new(App::new(&event_loop)));
-    let app_clone = Arc::clone(&app);
-
-    // the midi controller has to be kept alive during the whole execution of
-    // the application, hence the named variable
-    let _midi_controller = MidiController::new(
-        move |message, app_clone| {
-            app_clone.lock().on_midi_event(message);
-        },
-        app_clone,
-    );
+    let mut app = App::new(&event_loop);
 
     event_loop.run(move |event, elwt| match event {
-        Event::DeviceEvent { event, .. } => app.lock().on_device_event(event),
-        Event::WindowEvent { event, .. } => app.lock().on_window_event(event, elwt),
-        Event::NewEvents(StartCause::ResumeTimeReached { .. }) => {
-            app.lock().on_resume_time_reached(elwt)
-        }
+        Event::DeviceEvent { event, .. } => app.on_device_event(event),
+        Event::WindowEvent { event, .. } => app.on_window_event(event, elwt),
+        Event::UserEvent(()) => app.on_input_ready(),
+        Event::NewEvents(StartCause::ResumeTimeReached { .. }) => app.on_resume_time_reached(elwt),
         _ => (),
     })?;
 
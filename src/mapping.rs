@@ -0,0 +1,1646 @@
+use std::{
+    fs,
+    time::{Duration, Instant},
+};
+
+use gilrs::{Axis, Button};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use winit::{
+    event::ElementState,
+    keyboard::{KeyCode, ModifiersState},
+};
+
+use crate::{
+    config::{config_dir, read_config_file},
+    controller::{BoothEvent, TurntableFocus},
+    gesture::{BoundGestures, Gesture, GestureRecognizer, GestureTimings},
+    stems::Stem,
+    utils::remap,
+};
+
+const DEFAULT_KEYMAP: &str = include_str!("../config/keymap.toml");
+const DEFAULT_MIDI_MAPPING: &str = include_str!("../config/midi_mapping.toml");
+/// MIDI presets shipped with the app, the default mapping included
+const BUILTIN_MIDI_PRESETS: [&str; 2] = [
+    DEFAULT_MIDI_MAPPING,
+    include_str!("../config/presets/pioneer_ddj_400.toml"),
+];
+const DEFAULT_GAMEPAD_MAPPING: &str = include_str!("../config/gamepad_mapping.toml");
+/// Encoder messages closer than this to the previous one are accelerated
+const ACCELERATION_WINDOW: Duration = Duration::from_millis(40);
+
+/// The booth events an input can be mapped to
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Target {
+    FocusOne,
+    FocusTwo,
+    ToggleDebug,
+    ToggleHelp,
+    ToggleRecording,
+    ToggleAutomationRecording,
+    ToggleAutomationReplay,
+    ReplayLastTransition,
+    ToggleTrainer,
+    ScratchBegin,
+    ScratchEnd,
+    ToggleStartStopOne,
+    ToggleStartStopTwo,
+    BrakeOne,
+    BrakeTwo,
+    SyncOne,
+    SyncTwo,
+    CopyTempo,
+    ReturnToStartOne,
+    ReturnToStartTwo,
+    MemoryPointAddOne,
+    MemoryPointAddTwo,
+    MemoryPointNextOne,
+    MemoryPointNextTwo,
+    MemoryPointPreviousOne,
+    MemoryPointPreviousTwo,
+    LoopInOne,
+    LoopInTwo,
+    LoopOutOne,
+    LoopOutTwo,
+    LoopExitOne,
+    LoopExitTwo,
+    LoopSaveOne,
+    LoopSaveTwo,
+    SavedLoopOne(usize),
+    SavedLoopTwo(usize),
+    PhraseLoopOne,
+    PhraseLoopTwo,
+    LoopHalveOne,
+    LoopHalveTwo,
+    LoopDoubleOne,
+    LoopDoubleTwo,
+    LoopMoveForwardOne,
+    LoopMoveForwardTwo,
+    LoopMoveBackwardOne,
+    LoopMoveBackwardTwo,
+    ToggleCueOne,
+    ToggleCueTwo,
+    ToggleInputOne,
+    ToggleInputTwo,
+    ToggleMic,
+    ToggleMetronome,
+    TapTempo,
+    ToggleGenerator,
+    ToggleMuteMaster,
+    Duck,
+    DuckRelease,
+    MicRecordLevel,
+    ResetChannelOne,
+    ResetChannelTwo,
+    ResetMixer,
+    MorphStoreA,
+    MorphStoreB,
+    MorphToA,
+    MorphToB,
+    Morph,
+    FileNavigatorDown,
+    FileNavigatorUp,
+    FileNavigatorSelect,
+    FileNavigatorBack,
+    FileNavigatorPageUp,
+    FileNavigatorPageDown,
+    FileNavigatorNextLetter,
+    FileNavigatorPreviousLetter,
+    LoadSelectedOne,
+    LoadSelectedTwo,
+    PrepareAdd,
+    PrepareLoadOne,
+    PrepareLoadTwo,
+    CueMix,
+    Crossfader,
+    /// pressed while the value is not zero, e.g. from a MIDI note on and off
+    TransformOne,
+    TransformTwo,
+    /// released whatever the value, e.g. on a key release
+    TransformOneRelease,
+    TransformTwoRelease,
+    Force,
+    VolumeOne,
+    VolumeTwo,
+    PitchOne,
+    PitchTwo,
+    PitchResetOne,
+    PitchResetTwo,
+    PitchUpOne,
+    PitchUpTwo,
+    PitchDownOne,
+    PitchDownTwo,
+    StemMuteOne(Stem),
+    StemMuteTwo(Stem),
+    StemLevelOne(Stem),
+    StemLevelTwo(Stem),
+    LayerLoadOne,
+    LayerLoadTwo,
+    LayerToggleOne,
+    LayerToggleTwo,
+    LayerLevelOne,
+    LayerLevelTwo,
+    EqLowOne,
+    EqHighOne,
+    EqLowTwo,
+    EqHighTwo,
+    SeekOne,
+    SeekTwo,
+    SamplerPad(usize),
+}
+
+impl Target {
+    /// Build the booth event for this target. `value` is only used by
+    /// continuous targets such as volumes or EQ gains
+    pub fn to_event(self, value: f64) -> BoothEvent<'static> {
+        match self {
+            Target::FocusOne => BoothEvent::FocusChanged(TurntableFocus::One),
+            Target::FocusTwo => BoothEvent::FocusChanged(TurntableFocus::Two),
+            Target::ToggleDebug => BoothEvent::ToggleDebug,
+            Target::ToggleHelp => BoothEvent::ToggleHelp,
+            Target::ToggleRecording => BoothEvent::ToggleRecording,
+            Target::ToggleAutomationRecording => BoothEvent::ToggleAutomationRecording,
+            Target::ToggleAutomationReplay => BoothEvent::ToggleAutomationReplay,
+            Target::ReplayLastTransition => BoothEvent::ReplayLastTransition,
+            Target::ToggleTrainer => BoothEvent::ToggleTrainer,
+            Target::ScratchBegin => BoothEvent::ScratchBegin,
+            Target::ScratchEnd => BoothEvent::ScratchEnd,
+            Target::ToggleStartStopOne => BoothEvent::ToggleStartStopOne,
+            Target::ToggleStartStopTwo => BoothEvent::ToggleStartStopTwo,
+            Target::BrakeOne => BoothEvent::BrakeOne,
+            Target::BrakeTwo => BoothEvent::BrakeTwo,
+            Target::SyncOne => BoothEvent::SyncOne,
+            Target::SyncTwo => BoothEvent::SyncTwo,
+            Target::CopyTempo => BoothEvent::CopyTempo,
+            Target::ReturnToStartOne => BoothEvent::ReturnToStartOne,
+            Target::ReturnToStartTwo => BoothEvent::ReturnToStartTwo,
+            Target::MemoryPointAddOne => BoothEvent::MemoryPointAdd(TurntableFocus::One),
+            Target::MemoryPointAddTwo => BoothEvent::MemoryPointAdd(TurntableFocus::Two),
+            Target::MemoryPointNextOne => BoothEvent::MemoryPointNext(TurntableFocus::One),
+            Target::MemoryPointNextTwo => BoothEvent::MemoryPointNext(TurntableFocus::Two),
+            Target::MemoryPointPreviousOne => BoothEvent::MemoryPointPrevious(TurntableFocus::One),
+            Target::MemoryPointPreviousTwo => BoothEvent::MemoryPointPrevious(TurntableFocus::Two),
+            Target::LoopInOne => BoothEvent::LoopIn(TurntableFocus::One),
+            Target::LoopInTwo => BoothEvent::LoopIn(TurntableFocus::Two),
+            Target::LoopOutOne => BoothEvent::LoopOut(TurntableFocus::One),
+            Target::LoopOutTwo => BoothEvent::LoopOut(TurntableFocus::Two),
+            Target::LoopExitOne => BoothEvent::LoopExit(TurntableFocus::One),
+            Target::LoopExitTwo => BoothEvent::LoopExit(TurntableFocus::Two),
+            Target::LoopSaveOne => BoothEvent::LoopSave(TurntableFocus::One),
+            Target::LoopSaveTwo => BoothEvent::LoopSave(TurntableFocus::Two),
+            Target::SavedLoopOne(pad) => BoothEvent::SavedLoop(TurntableFocus::One, pad),
+            Target::SavedLoopTwo(pad) => BoothEvent::SavedLoop(TurntableFocus::Two, pad),
+            Target::PhraseLoopOne => BoothEvent::PhraseLoop(TurntableFocus::One),
+            Target::PhraseLoopTwo => BoothEvent::PhraseLoop(TurntableFocus::Two),
+            Target::LoopHalveOne => BoothEvent::LoopHalve(TurntableFocus::One),
+            Target::LoopHalveTwo => BoothEvent::LoopHalve(TurntableFocus::Two),
+            Target::LoopDoubleOne => BoothEvent::LoopDouble(TurntableFocus::One),
+            Target::LoopDoubleTwo => BoothEvent::LoopDouble(TurntableFocus::Two),
+            Target::LoopMoveForwardOne => BoothEvent::LoopMoveForward(TurntableFocus::One),
+            Target::LoopMoveForwardTwo => BoothEvent::LoopMoveForward(TurntableFocus::Two),
+            Target::LoopMoveBackwardOne => BoothEvent::LoopMoveBackward(TurntableFocus::One),
+            Target::LoopMoveBackwardTwo => BoothEvent::LoopMoveBackward(TurntableFocus::Two),
+            Target::ToggleCueOne => BoothEvent::ToggleCueOne,
+            Target::ToggleCueTwo => BoothEvent::ToggleCueTwo,
+            Target::ToggleInputOne => BoothEvent::ToggleInputOne,
+            Target::ToggleInputTwo => BoothEvent::ToggleInputTwo,
+            Target::ToggleMic => BoothEvent::ToggleMic,
+            Target::ToggleMetronome => BoothEvent::ToggleMetronome,
+            Target::TapTempo => BoothEvent::TapTempo,
+            Target::ToggleGenerator => BoothEvent::ToggleGenerator,
+            Target::ToggleMuteMaster => BoothEvent::ToggleMuteMaster,
+            Target::Duck => BoothEvent::DuckChanged(true),
+            Target::DuckRelease => BoothEvent::DuckChanged(false),
+            Target::MicRecordLevel => BoothEvent::MicRecordLevelChanged(value),
+            Target::ResetChannelOne => BoothEvent::ResetChannel(TurntableFocus::One),
+            Target::ResetChannelTwo => BoothEvent::ResetChannel(TurntableFocus::Two),
+            Target::ResetMixer => BoothEvent::ResetMixer,
+            Target::MorphStoreA => BoothEvent::MorphStoreA,
+            Target::MorphStoreB => BoothEvent::MorphStoreB,
+            Target::MorphToA => BoothEvent::MorphToA,
+            Target::MorphToB => BoothEvent::MorphToB,
+            Target::Morph => BoothEvent::MorphChanged(value),
+            Target::FileNavigatorDown => BoothEvent::FileNavigatorDown,
+            Target::FileNavigatorUp => BoothEvent::FileNavigatorUp,
+            Target::FileNavigatorSelect => BoothEvent::FileNavigatorSelect,
+            Target::FileNavigatorBack => BoothEvent::FileNavigatorBack,
+            Target::FileNavigatorPageUp => BoothEvent::FileNavigatorPageUp,
+            Target::FileNavigatorPageDown => BoothEvent::FileNavigatorPageDown,
+            Target::FileNavigatorNextLetter => BoothEvent::FileNavigatorNextLetter,
+            Target::FileNavigatorPreviousLetter => BoothEvent::FileNavigatorPreviousLetter,
+            Target::LoadSelectedOne => BoothEvent::LoadSelected(TurntableFocus::One),
+            Target::LoadSelectedTwo => BoothEvent::LoadSelected(TurntableFocus::Two),
+            Target::PrepareAdd => BoothEvent::PrepareAdd,
+            Target::PrepareLoadOne => BoothEvent::PrepareLoad(TurntableFocus::One),
+            Target::PrepareLoadTwo => BoothEvent::PrepareLoad(TurntableFocus::Two),
+            Target::CueMix => BoothEvent::CueMixChanged(value),
+            Target::Crossfader => BoothEvent::CrossfaderChanged(value),
+            Target::TransformOne => BoothEvent::Transform(TurntableFocus::One, value > 0.0),
+            Target::TransformTwo => BoothEvent::Transform(TurntableFocus::Two, value > 0.0),
+            Target::TransformOneRelease => BoothEvent::Transform(TurntableFocus::One, false),
+            Target::TransformTwoRelease => BoothEvent::Transform(TurntableFocus::Two, false),
+            Target::Force => BoothEvent::ForceApplied(value),
+            Target::VolumeOne => BoothEvent::VolumeOneChanged(value),
+            Target::VolumeTwo => BoothEvent::VolumeTwoChanged(value),
+            Target::PitchOne => BoothEvent::PitchOneChanged(value),
+            Target::PitchTwo => BoothEvent::PitchTwoChanged(value),
+            Target::PitchResetOne => BoothEvent::PitchReset(TurntableFocus::One),
+            Target::PitchResetTwo => BoothEvent::PitchReset(TurntableFocus::Two),
+            Target::PitchUpOne => BoothEvent::PitchUp(TurntableFocus::One),
+            Target::PitchUpTwo => BoothEvent::PitchUp(TurntableFocus::Two),
+            Target::PitchDownOne => BoothEvent::PitchDown(TurntableFocus::One),
+            Target::PitchDownTwo => BoothEvent::PitchDown(TurntableFocus::Two),
+            Target::StemMuteOne(stem) => BoothEvent::ToggleStem(TurntableFocus::One, stem),
+            Target::StemMuteTwo(stem) => BoothEvent::ToggleStem(TurntableFocus::Two, stem),
+            Target::LayerLoadOne => BoothEvent::LayerLoad(TurntableFocus::One),
+            Target::LayerLoadTwo => BoothEvent::LayerLoad(TurntableFocus::Two),
+            Target::LayerToggleOne => BoothEvent::ToggleLayer(TurntableFocus::One),
+            Target::LayerToggleTwo => BoothEvent::ToggleLayer(TurntableFocus::Two),
+            Target::LayerLevelOne => BoothEvent::LayerLevelChanged(TurntableFocus::One, value),
+            Target::LayerLevelTwo => BoothEvent::LayerLevelChanged(TurntableFocus::Two, value),
+            Target::StemLevelOne(stem) => {
+                BoothEvent::StemLevelChanged(TurntableFocus::One, stem, value)
+            }
+            Target::StemLevelTwo(stem) => {
+                BoothEvent::StemLevelChanged(TurntableFocus::Two, stem, value)
+            }
+            Target::EqLowOne => BoothEvent::EqLowOneChanged(value),
+            Target::EqHighOne => BoothEvent::EqHighOneChanged(value),
+            Target::EqLowTwo => BoothEvent::EqLowTwoChanged(value),
+            Target::EqHighTwo => BoothEvent::EqHighTwoChanged(value),
+            Target::SeekOne => BoothEvent::SeekOne(value),
+            Target::SeekTwo => BoothEvent::SeekTwo(value),
+            Target::SamplerPad(pad) => BoothEvent::SamplerPad(pad),
+        }
+    }
+
+    pub fn group(self) -> TargetGroup {
+        match self {
+            Target::FocusOne
+            | Target::ToggleStartStopOne
+            | Target::BrakeOne
+            | Target::SyncOne
+            | Target::ReturnToStartOne
+            | Target::MemoryPointAddOne
+            | Target::MemoryPointNextOne
+            | Target::MemoryPointPreviousOne
+            | Target::LoopInOne
+            | Target::LoopOutOne
+            | Target::LoopExitOne
+            | Target::LoopSaveOne
+            | Target::SavedLoopOne(_)
+            | Target::PhraseLoopOne
+            | Target::LoopHalveOne
+            | Target::LoopDoubleOne
+            | Target::LoopMoveForwardOne
+            | Target::LoopMoveBackwardOne
+            | Target::PitchOne
+            | Target::PitchResetOne
+            | Target::PitchUpOne
+            | Target::PitchDownOne
+            | Target::StemMuteOne(_)
+            | Target::StemLevelOne(_)
+            | Target::LayerLoadOne
+            | Target::LayerToggleOne
+            | Target::LayerLevelOne
+            | Target::TransformOne
+            | Target::TransformOneRelease
+            | Target::SeekOne => TargetGroup::DeckOne,
+            Target::FocusTwo
+            | Target::ToggleStartStopTwo
+            | Target::BrakeTwo
+            | Target::SyncTwo
+            | Target::ReturnToStartTwo
+            | Target::MemoryPointAddTwo
+            | Target::MemoryPointNextTwo
+            | Target::MemoryPointPreviousTwo
+            | Target::LoopInTwo
+            | Target::LoopOutTwo
+            | Target::LoopExitTwo
+            | Target::LoopSaveTwo
+            | Target::SavedLoopTwo(_)
+            | Target::PhraseLoopTwo
+            | Target::LoopHalveTwo
+            | Target::LoopDoubleTwo
+            | Target::LoopMoveForwardTwo
+            | Target::LoopMoveBackwardTwo
+            | Target::PitchTwo
+            | Target::PitchResetTwo
+            | Target::PitchUpTwo
+            | Target::PitchDownTwo
+            | Target::StemMuteTwo(_)
+            | Target::StemLevelTwo(_)
+            | Target::LayerLoadTwo
+            | Target::LayerToggleTwo
+            | Target::LayerLevelTwo
+            | Target::TransformTwo
+            | Target::TransformTwoRelease
+            | Target::SeekTwo => TargetGroup::DeckTwo,
+            Target::ScratchBegin | Target::ScratchEnd | Target::CopyTempo | Target::Force => {
+                TargetGroup::FocusedDeck
+            }
+            Target::ToggleCueOne
+            | Target::ToggleCueTwo
+            | Target::ToggleInputOne
+            | Target::ToggleInputTwo
+            | Target::ToggleMic
+            | Target::ToggleMetronome
+            | Target::TapTempo
+            | Target::ToggleGenerator
+            | Target::ToggleMuteMaster
+            | Target::Duck
+            | Target::DuckRelease
+            | Target::MicRecordLevel
+            | Target::ResetChannelOne
+            | Target::ResetChannelTwo
+            | Target::ResetMixer
+            | Target::MorphStoreA
+            | Target::MorphStoreB
+            | Target::MorphToA
+            | Target::MorphToB
+            | Target::Morph
+            | Target::CueMix
+            | Target::Crossfader
+            | Target::VolumeOne
+            | Target::VolumeTwo
+            | Target::EqLowOne
+            | Target::EqHighOne
+            | Target::EqLowTwo
+            | Target::EqHighTwo => TargetGroup::Mixer,
+            Target::FileNavigatorDown
+            | Target::FileNavigatorUp
+            | Target::FileNavigatorSelect
+            | Target::FileNavigatorBack
+            | Target::FileNavigatorPageUp
+            | Target::FileNavigatorPageDown
+            | Target::FileNavigatorNextLetter
+            | Target::FileNavigatorPreviousLetter
+            | Target::LoadSelectedOne
+            | Target::LoadSelectedTwo
+            | Target::PrepareAdd
+            | Target::PrepareLoadOne
+            | Target::PrepareLoadTwo => TargetGroup::Browser,
+            Target::SamplerPad(_) => TargetGroup::Sampler,
+            Target::ToggleDebug
+            | Target::ToggleHelp
+            | Target::ToggleRecording
+            | Target::ToggleAutomationRecording
+            | Target::ToggleAutomationReplay
+            | Target::ReplayLastTransition
+            | Target::ToggleTrainer => TargetGroup::General,
+        }
+    }
+
+    /// Short description shown in the help
+    pub fn label(self) -> &'static str {
+        match self {
+            Target::FocusOne => "Focus deck one",
+            Target::FocusTwo => "Focus deck two",
+            Target::ToggleDebug => "Show/hide debug panel",
+            Target::ToggleHelp => "Show/hide this help",
+            Target::ToggleRecording => "Start/stop recording",
+            Target::ToggleAutomationRecording => "Start/stop recording moves",
+            Target::ToggleAutomationReplay => "Start/stop replaying moves",
+            Target::ReplayLastTransition => "Replay last transition",
+            Target::ToggleTrainer => "Start/give up beatmatch training",
+            Target::ScratchBegin => "Start scratching",
+            Target::ScratchEnd => "Stop scratching",
+            Target::ToggleStartStopOne | Target::ToggleStartStopTwo => "Start/stop",
+            Target::BrakeOne | Target::BrakeTwo => "Brake",
+            Target::SyncOne | Target::SyncTwo => "Sync to the other deck",
+            Target::CopyTempo => "Copy the tempo of the other deck",
+            Target::ReturnToStartOne | Target::ReturnToStartTwo => "Return to start",
+            Target::MemoryPointAddOne | Target::MemoryPointAddTwo => "Add memory point",
+            Target::MemoryPointNextOne | Target::MemoryPointNextTwo => "Next memory point",
+            Target::MemoryPointPreviousOne | Target::MemoryPointPreviousTwo => {
+                "Previous memory point"
+            }
+            Target::LoopInOne | Target::LoopInTwo => "Loop in",
+            Target::LoopOutOne | Target::LoopOutTwo => "Loop out",
+            Target::LoopExitOne | Target::LoopExitTwo => "Exit loop",
+            Target::LoopSaveOne | Target::LoopSaveTwo => "Save loop",
+            Target::SavedLoopOne(_) | Target::SavedLoopTwo(_) => "Play/exit saved loop",
+            Target::PhraseLoopOne | Target::PhraseLoopTwo => "4-bar loop at next phrase",
+            Target::LoopHalveOne | Target::LoopHalveTwo => "Halve loop",
+            Target::LoopDoubleOne | Target::LoopDoubleTwo => "Double loop",
+            Target::LoopMoveForwardOne | Target::LoopMoveForwardTwo => "Move loop forward",
+            Target::LoopMoveBackwardOne | Target::LoopMoveBackwardTwo => "Move loop backward",
+            Target::ToggleCueOne => "Cue channel one",
+            Target::ToggleCueTwo => "Cue channel two",
+            Target::ToggleInputOne => "Line in on channel one",
+            Target::ToggleInputTwo => "Line in on channel two",
+            Target::ToggleMic => "Talkover mic",
+            Target::ToggleMetronome => "Metronome on the cue",
+            Target::TapTempo => "Tap master tempo",
+            Target::ToggleGenerator => "Noise and tone generator",
+            Target::ToggleMuteMaster => "Mute master",
+            Target::Duck => "Duck master",
+            Target::DuckRelease => "Release master duck",
+            Target::MicRecordLevel => "Mic level in recording",
+            Target::ResetChannelOne => "Flatten EQ channel one",
+            Target::ResetChannelTwo => "Flatten EQ channel two",
+            Target::ResetMixer => "Flatten EQs and center cue mix",
+            Target::MorphStoreA => "Store mixer snapshot A",
+            Target::MorphStoreB => "Store mixer snapshot B",
+            Target::MorphToA => "Glide to mixer snapshot A",
+            Target::MorphToB => "Glide to mixer snapshot B",
+            Target::Morph => "Morph between mixer snapshots",
+            Target::FileNavigatorDown => "Next entry",
+            Target::FileNavigatorUp => "Previous entry",
+            Target::FileNavigatorSelect => "Open folder / load track",
+            Target::FileNavigatorBack => "Parent folder",
+            Target::FileNavigatorPageUp => "Page up",
+            Target::FileNavigatorPageDown => "Page down",
+            Target::FileNavigatorNextLetter => "Next letter",
+            Target::FileNavigatorPreviousLetter => "Previous letter",
+            Target::LoadSelectedOne => "Load selected track on deck one",
+            Target::LoadSelectedTwo => "Load selected track on deck two",
+            Target::PrepareAdd => "Add selected track to the prepare list",
+            Target::PrepareLoadOne => "Load next prepared track on deck one",
+            Target::PrepareLoadTwo => "Load next prepared track on deck two",
+            Target::CueMix => "Cue mix",
+            Target::Crossfader => "Crossfader",
+            Target::TransformOne | Target::TransformTwo => "Transform (cut while held)",
+            Target::TransformOneRelease | Target::TransformTwoRelease => "Transform release",
+            Target::Force => "Nudge",
+            Target::VolumeOne => "Volume channel one",
+            Target::VolumeTwo => "Volume channel two",
+            Target::PitchOne | Target::PitchTwo => "Pitch",
+            Target::PitchResetOne | Target::PitchResetTwo => "Pitch reset to 0%",
+            Target::PitchUpOne | Target::PitchUpTwo => "Pitch +0.1%",
+            Target::PitchDownOne | Target::PitchDownTwo => "Pitch -0.1%",
+            Target::StemMuteOne(_) | Target::StemMuteTwo(_) => "Mute/unmute stem",
+            Target::StemLevelOne(_) | Target::StemLevelTwo(_) => "Stem level",
+            Target::LayerLoadOne | Target::LayerLoadTwo => "Load selected file as layer",
+            Target::LayerToggleOne | Target::LayerToggleTwo => "Play/stop layer",
+            Target::LayerLevelOne | Target::LayerLevelTwo => "Layer level",
+            Target::EqLowOne => "Low EQ channel one",
+            Target::EqHighOne => "High EQ channel one",
+            Target::EqLowTwo => "Low EQ channel two",
+            Target::EqHighTwo => "High EQ channel two",
+            Target::SeekOne | Target::SeekTwo => "Seek",
+            Target::SamplerPad(_) => "Sampler pad",
+        }
+    }
+}
+
+/// Part of the booth a target acts on, used to group bindings in the help
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TargetGroup {
+    DeckOne,
+    DeckTwo,
+    FocusedDeck,
+    Mixer,
+    Browser,
+    Sampler,
+    General,
+}
+
+impl TargetGroup {
+    pub const ALL: [TargetGroup; 7] = [
+        TargetGroup::DeckOne,
+        TargetGroup::DeckTwo,
+        TargetGroup::FocusedDeck,
+        TargetGroup::Mixer,
+        TargetGroup::Browser,
+        TargetGroup::Sampler,
+        TargetGroup::General,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TargetGroup::DeckOne => "Deck one",
+            TargetGroup::DeckTwo => "Deck two",
+            TargetGroup::FocusedDeck => "Focused deck",
+            TargetGroup::Mixer => "Mixer",
+            TargetGroup::Browser => "Browser",
+            TargetGroup::Sampler => "Sampler",
+            TargetGroup::General => "General",
+        }
+    }
+}
+
+/// How a 7 bit MIDI value is spread over the output range of a binding
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum Curve {
+    #[default]
+    Linear,
+    /// more resolution on the low values, e.g. for EQ gains in dB
+    Log,
+}
+
+impl Curve {
+    pub fn apply(&self, value: u8, range: (f64, f64)) -> f64 {
+        let (start, end) = range;
+
+        match self {
+            Curve::Linear => remap(value as f64, 0.0, 127.0, start, end),
+            Curve::Log => remap(
+                (value as f64 + 1.0).log10(),
+                0.0,
+                127.0_f64.log10(),
+                start,
+                end,
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Modifier {
+    Shift,
+    Control,
+    Alt,
+    Super,
+}
+
+impl Modifier {
+    pub fn label(self) -> &'static str {
+        match self {
+            Modifier::Shift => "Shift",
+            Modifier::Control => "Ctrl",
+            Modifier::Alt => "Alt",
+            Modifier::Super => "Super",
+        }
+    }
+
+    pub fn to_state(modifiers: &[Modifier]) -> ModifiersState {
+        modifiers
+            .iter()
+            .fold(ModifiersState::empty(), |state, modifier| {
+                state
+                    | match modifier {
+                        Modifier::Shift => ModifiersState::SHIFT,
+                        Modifier::Control => ModifiersState::CONTROL,
+                        Modifier::Alt => ModifiersState::ALT,
+                        Modifier::Super => ModifiersState::SUPER,
+                    }
+            })
+    }
+}
+
+/// Keys held to move the focused vinyl with the pointer, either exact
+/// modifiers, e.g. `["Super"]`, or a single key, e.g. `"Space"`, which
+/// avoids the shortcuts the system binds to modifiers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum HeldKeys {
+    Modifiers(Vec<Modifier>),
+    Key(KeyCode),
+}
+
+impl HeldKeys {
+    pub fn is_held(&self, modifiers: ModifiersState, pressed: &[KeyCode]) -> bool {
+        match self {
+            HeldKeys::Modifiers(held) => !held.is_empty() && Modifier::to_state(held) == modifiers,
+            HeldKeys::Key(key) => pressed.contains(key),
+        }
+    }
+
+    /// Human readable keys, e.g. `Super`
+    pub fn describe(&self) -> String {
+        match self {
+            HeldKeys::Modifiers(modifiers) => modifiers
+                .iter()
+                .map(|modifier| modifier.label())
+                .collect::<Vec<_>>()
+                .join("+"),
+            HeldKeys::Key(key) => describe_key(*key),
+        }
+    }
+}
+
+/// What the pointer does while keys are held
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PointerBindings {
+    /// hold the vinyl and move it with the pointer
+    pub scratch: HeldKeys,
+    /// push the vinyl with the pointer while it keeps turning, to seek or
+    /// nudge it
+    pub seek: HeldKeys,
+}
+
+impl Default for PointerBindings {
+    fn default() -> Self {
+        Self {
+            scratch: HeldKeys::Modifiers(vec![Modifier::Super]),
+            seek: HeldKeys::Modifiers(vec![Modifier::Alt]),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyBinding {
+    pub key: KeyCode,
+    #[serde(default = "default_key_state")]
+    pub state: ElementState,
+    /// exact modifiers required to trigger the binding, any if omitted
+    #[serde(default)]
+    pub modifiers: Option<Vec<Modifier>>,
+    /// whether key repeats trigger the binding too
+    #[serde(default)]
+    pub repeat: bool,
+    /// only for bindings triggered on press
+    #[serde(default)]
+    pub gesture: Gesture,
+    pub target: Target,
+}
+
+/// Keys pressed together anywhere on the system, e.g. to control the decks
+/// while another program is focused
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GlobalBinding {
+    pub keys: Vec<KeyCode>,
+    pub target: Target,
+}
+
+impl GlobalBinding {
+    /// Whether exactly the `pressed` keys are held, in any order
+    pub fn matches(&self, pressed: &[KeyCode]) -> bool {
+        pressed.len() == self.keys.len() && self.keys.iter().all(|key| pressed.contains(key))
+    }
+
+    pub fn describe(&self) -> String {
+        self.keys
+            .iter()
+            .map(|key| describe_key(*key))
+            .collect::<Vec<_>>()
+            .join("+")
+    }
+}
+
+/// Key code without its kind, e.g. `D` for `KeyD`
+fn describe_key(key: KeyCode) -> String {
+    let key = format!("{:?}", key);
+    key.strip_prefix("Key")
+        .or_else(|| key.strip_prefix("Digit"))
+        .or_else(|| key.strip_prefix("Arrow"))
+        .unwrap_or(&key)
+        .to_string()
+}
+
+fn default_key_state() -> ElementState {
+    ElementState::Pressed
+}
+
+impl KeyBinding {
+    /// Human readable key combination, e.g. `Ctrl+D`
+    pub fn describe(&self) -> String {
+        let mut parts: Vec<String> = self
+            .modifiers
+            .iter()
+            .flatten()
+            .map(|modifier| modifier.label().to_string())
+            .collect();
+        parts.push(describe_key(self.key));
+
+        let mut description = parts.join("+");
+        match (self.state, self.gesture) {
+            (ElementState::Released, _) => description.push_str(" (release)"),
+            (_, Gesture::Hold) => description.push_str(" (hold)"),
+            (_, Gesture::DoublePress) => description.push_str(" (double)"),
+            (_, Gesture::Press) => (),
+        }
+        description
+    }
+
+    pub fn matches(
+        &self,
+        key: KeyCode,
+        state: ElementState,
+        repeat: bool,
+        modifiers: ModifiersState,
+    ) -> bool {
+        self.key == key
+            && self.state == state
+            && (self.repeat || !repeat)
+            && self
+                .modifiers
+                .as_ref()
+                .is_none_or(|m| Modifier::to_state(m) == modifiers)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyMap {
+    #[serde(default)]
+    pub gestures: GestureTimings,
+    /// keys held to scratch or seek with the pointer
+    #[serde(default)]
+    pub pointer: PointerBindings,
+    pub keys: Vec<KeyBinding>,
+    /// keys working system-wide while the window is not focused
+    #[serde(default)]
+    pub global: Vec<GlobalBinding>,
+    #[serde(skip)]
+    recognizer: GestureRecognizer<KeyCode>,
+}
+
+impl KeyMap {
+    /// Load the user keymap from the config directory, or the default one
+    pub fn load() -> Self {
+        load_mapping("keymap.toml", DEFAULT_KEYMAP)
+    }
+
+    /// Booth events triggered by a key event. Every matching binding fires.
+    ///
+    /// Keys with hold or double press bindings go through the gesture
+    /// recognizer instead, their repeats are ignored
+    pub fn map(
+        &mut self,
+        key: KeyCode,
+        state: ElementState,
+        repeat: bool,
+        modifiers: ModifiersState,
+    ) -> Vec<BoothEvent<'static>> {
+        let bound = BoundGestures::from_gestures(
+            self.keys
+                .iter()
+                .filter(|binding| binding.key == key && binding.state == ElementState::Pressed)
+                .map(|binding| binding.gesture),
+        );
+
+        let mut events: Vec<_> = self
+            .keys
+            .iter()
+            .filter(|binding| !bound.any() || binding.state == ElementState::Released)
+            .filter(|binding| binding.matches(key, state, repeat, modifiers))
+            .map(|binding| binding.target.to_event(1.0))
+            .collect();
+
+        if bound.any() && !repeat {
+            let gestures = match state {
+                ElementState::Pressed => {
+                    self.recognizer
+                        .press(key, bound, &self.gestures, Instant::now())
+                }
+                ElementState::Released => self.recognizer.release(&key),
+            };
+            for gesture in gestures {
+                events.extend(self.gesture_events(key, gesture, modifiers));
+            }
+        }
+
+        events
+    }
+
+    pub fn has_pending_gestures(&self) -> bool {
+        self.recognizer.is_pending()
+    }
+
+    /// Booth events of the holds and delayed presses due now
+    pub fn poll_gestures(&mut self, modifiers: ModifiersState) -> Vec<BoothEvent<'static>> {
+        self.recognizer
+            .poll(&self.gestures, Instant::now())
+            .into_iter()
+            .flat_map(|(key, gesture)| self.gesture_events(key, gesture, modifiers))
+            .collect()
+    }
+
+    fn gesture_events(
+        &self,
+        key: KeyCode,
+        gesture: Gesture,
+        modifiers: ModifiersState,
+    ) -> Vec<BoothEvent<'static>> {
+        self.keys
+            .iter()
+            .filter(|binding| binding.gesture == gesture)
+            .filter(|binding| binding.matches(key, ElementState::Pressed, false, modifiers))
+            .map(|binding| binding.target.to_event(1.0))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MidiBinding {
+    /// status byte to match, any if omitted
+    #[serde(default)]
+    pub status: Option<u8>,
+    /// first data byte, i.e. the note or the control change number
+    pub data1: u8,
+    pub target: Target,
+    /// output range the second data byte is remapped to
+    #[serde(default = "default_midi_range")]
+    pub range: (f64, f64),
+    #[serde(default)]
+    pub curve: Curve,
+    /// set for endless encoders, whose messages are relative movements
+    #[serde(default)]
+    pub encoder: Option<MidiEncoder>,
+    /// for buttons, the gesture triggering the binding
+    #[serde(default)]
+    pub gesture: Gesture,
+    #[serde(skip)]
+    state: EncoderState,
+}
+
+impl MidiBinding {
+    /// Whether the binding is on the button sending `status` and `data1`,
+    /// with the status of a note off turned into the one of a note on
+    fn is_on_button(&self, (status, data1): (u8, u8)) -> bool {
+        self.data1 == data1 && self.status.is_none_or(|s| button_status(s) == status)
+    }
+}
+
+/// Status shared by the press and release messages of a button
+fn button_status(status: u8) -> u8 {
+    match status & 0xF0 {
+        0x80 => status & 0x0F | 0x90,
+        _ => status,
+    }
+}
+
+fn default_midi_range() -> (f64, f64) {
+    (0.0, 1.0)
+}
+
+/// Options of an endless encoder sending 1 to 63 ticks clockwise and 127
+/// down to 65 for 1 to 63 ticks counterclockwise
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MidiEncoder {
+    /// ticks needed to trigger one event
+    #[serde(default = "default_encoder_step")]
+    pub step: u32,
+    /// factor applied to the ticks of a message following the previous one
+    /// within [`ACCELERATION_WINDOW`]
+    #[serde(default = "default_encoder_acceleration")]
+    pub acceleration: f64,
+    /// minimum delay between two events in milliseconds. Ticks received in
+    /// between are accumulated until the next message
+    #[serde(default)]
+    pub debounce_ms: u64,
+    /// value sent with each clockwise event, negated counterclockwise
+    #[serde(default = "default_encoder_scale")]
+    pub scale: f64,
+    /// event triggered counterclockwise instead of the binding target, for
+    /// targets without a value such as `FileNavigatorDown`
+    #[serde(default)]
+    pub decrement_target: Option<Target>,
+}
+
+fn default_encoder_step() -> u32 {
+    1
+}
+
+fn default_encoder_acceleration() -> f64 {
+    1.0
+}
+
+fn default_encoder_scale() -> f64 {
+    1.0
+}
+
+#[derive(Debug, Clone, Default)]
+struct EncoderState {
+    /// ticks not turned into events yet, signed by direction
+    ticks: f64,
+    last_message: Option<Instant>,
+    last_event: Option<Instant>,
+}
+
+impl MidiEncoder {
+    fn map(
+        &self,
+        target: Target,
+        state: &mut EncoderState,
+        value: u8,
+        time: Instant,
+    ) -> Vec<BoothEvent<'static>> {
+        let mut ticks = match value {
+            0..=63 => value as f64,
+            _ => value as f64 - 128.0,
+        };
+
+        if state
+            .last_message
+            .is_some_and(|last| time.duration_since(last) < ACCELERATION_WINDOW)
+        {
+            ticks *= self.acceleration;
+        }
+        state.last_message = Some(time);
+
+        // turning back drops what was accumulated in the other direction
+        if state.ticks * ticks < 0.0 {
+            state.ticks = 0.0;
+        }
+        state.ticks += ticks;
+
+        let debounce = Duration::from_millis(self.debounce_ms);
+        if state
+            .last_event
+            .is_some_and(|last| time.duration_since(last) < debounce)
+        {
+            return Vec::new();
+        }
+
+        let step = self.step.max(1) as f64;
+        let count = (state.ticks.abs() / step).floor();
+        if count == 0.0 {
+            return Vec::new();
+        }
+
+        let clockwise = state.ticks > 0.0;
+        state.ticks -= count * step * state.ticks.signum();
+        state.last_event = Some(time);
+
+        let (target, value) = match (clockwise, self.decrement_target) {
+            (true, _) => (target, self.scale),
+            (false, Some(decrement_target)) => (decrement_target, self.scale),
+            (false, None) => (target, -self.scale),
+        };
+        (0..count as usize)
+            .map(|_| target.to_event(value))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MidiMapping {
+    /// preset name, displayed in the settings
+    #[serde(default)]
+    pub name: Option<String>,
+    /// port names the preset is selected for, matched on a part of the name
+    #[serde(default)]
+    pub ports: Vec<String>,
+    /// Rhai script of the config directory handling messages before the
+    /// bindings, see [`crate::mapping_script::MappingScript`]
+    #[serde(default)]
+    pub script: Option<String>,
+    #[serde(default)]
+    pub gestures: GestureTimings,
+    pub midi: Vec<MidiBinding>,
+    /// buttons identified by their press status and first data byte
+    #[serde(skip)]
+    recognizer: GestureRecognizer<(u8, u8)>,
+}
+
+impl MidiMapping {
+    /// Load the mapping for the controller connected to `port_name`.
+    ///
+    /// The `preset` chosen in the settings comes first, then the user
+    /// `midi_mapping.toml`, then the preset matching the port name and
+    /// finally the default mapping
+    pub fn load(port_name: Option<&str>, preset: Option<&str>) -> Self {
+        let presets = Self::presets();
+
+        if let Some(preset) = preset {
+            match presets.iter().find(|p| p.name.as_deref() == Some(preset)) {
+                Some(mapping) => return mapping.clone(),
+                None => log::error!("Unknown MIDI preset '{}'", preset),
+            }
+        }
+
+        if let Some(mapping) = read_mapping("midi_mapping.toml") {
+            return mapping;
+        }
+
+        let detected = port_name.and_then(|port_name| {
+            presets
+                .into_iter()
+                .find(|mapping| mapping.matches_port(port_name))
+        });
+        match detected {
+            Some(mapping) => {
+                log::info!("Using MIDI preset {:?}", mapping.name);
+                mapping
+            }
+            None => toml::from_str(DEFAULT_MIDI_MAPPING).expect("default mapping is invalid"),
+        }
+    }
+
+    /// Presets of the `presets` folder of the config directory, followed by
+    /// the built-in ones
+    pub fn presets() -> Vec<Self> {
+        let dir = config_dir().join("presets");
+        let mut paths: Vec<_> = match fs::read_dir(&dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        paths.sort();
+
+        let user_presets = paths.into_iter().filter_map(|path| {
+            let content = fs::read_to_string(&path).ok()?;
+            match toml::from_str(&content) {
+                Ok(mapping) => Some(mapping),
+                Err(e) => {
+                    log::error!("Invalid MIDI preset '{}': {}", path.display(), e);
+                    None
+                }
+            }
+        });
+
+        user_presets.chain(Self::builtin_presets()).collect()
+    }
+
+    fn builtin_presets() -> Vec<Self> {
+        BUILTIN_MIDI_PRESETS
+            .iter()
+            .map(|content| toml::from_str(content).expect("built-in MIDI preset is invalid"))
+            .collect()
+    }
+
+    /// Whether the preset is meant for the controller behind `port_name`
+    pub fn matches_port(&self, port_name: &str) -> bool {
+        let port_name = port_name.to_lowercase();
+        self.ports
+            .iter()
+            .any(|pattern| port_name.contains(&pattern.to_lowercase()))
+    }
+
+    /// Booth events triggered by a MIDI message received at `time`, `None` if
+    /// no binding matches. The first matching binding wins. Encoders may
+    /// trigger several events or none at all.
+    ///
+    /// Buttons with hold or double press bindings go through the gesture
+    /// recognizer instead, the first binding of each gesture winning
+    pub fn map(&mut self, message: &[u8], time: Instant) -> Option<Vec<BoothEvent<'static>>> {
+        let [status, data1, data2] = message else {
+            return None;
+        };
+
+        // many controllers send a zero velocity note on instead of a note off
+        let status = match (*status & 0xF0, data2) {
+            (0x90, 0) => *status & 0x0F | 0x80,
+            _ => *status,
+        };
+
+        let button = (button_status(status), *data1);
+        let bound = BoundGestures::from_gestures(
+            self.midi
+                .iter()
+                .filter(|binding| binding.is_on_button(button))
+                .map(|binding| binding.gesture),
+        );
+        if bound.any() {
+            let gestures = match (status & 0xF0, data2) {
+                (0x80, _) | (_, 0) => self.recognizer.release(&button),
+                _ => self.recognizer.press(button, bound, &self.gestures, time),
+            };
+            return Some(
+                gestures
+                    .into_iter()
+                    .filter_map(|gesture| self.gesture_event(button, gesture))
+                    .collect(),
+            );
+        }
+
+        let binding = self.midi.iter_mut().find(|binding| {
+            binding.data1 == *data1 && binding.status.is_none_or(|s| s == status)
+        })?;
+
+        Some(match &binding.encoder {
+            Some(encoder) => encoder.map(binding.target, &mut binding.state, *data2, time),
+            None => vec![binding
+                .target
+                .to_event(binding.curve.apply(*data2, binding.range))],
+        })
+    }
+
+    pub fn has_pending_gestures(&self) -> bool {
+        self.recognizer.is_pending()
+    }
+
+    /// Booth events of the holds and delayed presses due at `time`
+    pub fn poll_gestures(&mut self, time: Instant) -> Vec<BoothEvent<'static>> {
+        self.recognizer
+            .poll(&self.gestures, time)
+            .into_iter()
+            .filter_map(|(button, gesture)| self.gesture_event(button, gesture))
+            .collect()
+    }
+
+    /// Gestures have no value of their own, they send the end of the range
+    fn gesture_event(&self, button: (u8, u8), gesture: Gesture) -> Option<BoothEvent<'static>> {
+        self.midi
+            .iter()
+            .find(|binding| binding.is_on_button(button) && binding.gesture == gesture)
+            .map(|binding| {
+                binding
+                    .target
+                    .to_event(binding.curve.apply(127, binding.range))
+            })
+    }
+}
+
+/// A button stored as a bit in a HID report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HidButton {
+    /// index of the byte in the report, including the report ID if any
+    pub byte: usize,
+    pub mask: u8,
+    pub target: Target,
+}
+
+/// An absolute control such as a fader or a knob
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HidAxis {
+    pub byte: usize,
+    /// 1 or 2 bytes, little endian
+    #[serde(default = "default_hid_axis_size")]
+    pub size: usize,
+    /// maximum raw value of the control
+    #[serde(default = "default_hid_axis_max")]
+    pub max: u16,
+    #[serde(default = "default_midi_range")]
+    pub range: (f64, f64),
+    pub target: Target,
+}
+
+fn default_hid_axis_size() -> usize {
+    1
+}
+
+fn default_hid_axis_max() -> u16 {
+    255
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum JogMode {
+    /// the byte holds a signed delta since the last report
+    Relative,
+    /// the byte holds a wrapping position counter
+    Absolute,
+}
+
+/// A jog wheel or an endless encoder
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HidJog {
+    pub byte: usize,
+    pub mode: JogMode,
+    /// factor applied to each tick
+    #[serde(default = "default_hid_jog_scale")]
+    pub scale: f64,
+    pub target: Target,
+}
+
+fn default_hid_jog_scale() -> f64 {
+    1.0
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HidMapping {
+    pub vendor_id: u16,
+    pub product_id: u16,
+    #[serde(default)]
+    pub buttons: Vec<HidButton>,
+    #[serde(default)]
+    pub axes: Vec<HidAxis>,
+    #[serde(default)]
+    pub jogs: Vec<HidJog>,
+}
+
+impl HidMapping {
+    /// Load the HID mapping from the config directory. There is no default
+    /// HID device so `None` is returned if the file is missing or invalid
+    pub fn load() -> Option<Self> {
+        let content = read_config_file("hid_mapping.toml")?;
+
+        match toml::from_str(&content) {
+            Ok(mapping) => Some(mapping),
+            Err(e) => {
+                log::error!("Invalid 'hid_mapping.toml': {}", e);
+                None
+            }
+        }
+    }
+
+    /// Booth events produced by a new report. Buttons fire when pressed and
+    /// axes when their value changes, which is why the previous report is
+    /// needed. Jogs fire on every movement
+    pub fn map(&self, report: &[u8], previous: &[u8]) -> Vec<BoothEvent<'static>> {
+        let mut events = Vec::new();
+        let byte = |report: &[u8], index: usize| report.get(index).copied().unwrap_or(0);
+
+        for button in self.buttons.iter() {
+            let pressed = byte(report, button.byte) & button.mask != 0;
+            let was_pressed = byte(previous, button.byte) & button.mask != 0;
+
+            if pressed && !was_pressed {
+                events.push(button.target.to_event(1.0));
+            }
+        }
+
+        for axis in self.axes.iter() {
+            let raw = |report: &[u8]| match axis.size {
+                2 => u16::from_le_bytes([byte(report, axis.byte), byte(report, axis.byte + 1)]),
+                _ => byte(report, axis.byte) as u16,
+            };
+
+            if raw(report) != raw(previous) {
+                let (start, end) = axis.range;
+                let value = remap(raw(report) as f64, 0.0, axis.max as f64, start, end);
+                events.push(axis.target.to_event(value));
+            }
+        }
+
+        for jog in self.jogs.iter() {
+            let delta = match jog.mode {
+                JogMode::Relative => byte(report, jog.byte) as i8,
+                // the first report gives the initial position, not a movement
+                JogMode::Absolute => match previous.get(jog.byte) {
+                    Some(position) => byte(report, jog.byte).wrapping_sub(*position) as i8,
+                    None => 0,
+                },
+            };
+
+            if delta != 0 {
+                events.push(jog.target.to_event(delta as f64 * jog.scale));
+            }
+        }
+
+        events
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GamepadButton {
+    pub button: Button,
+    /// booth event triggered when the button is pressed
+    pub target: Target,
+    /// booth event triggered when the button is released, if any
+    #[serde(default)]
+    pub release_target: Option<Target>,
+}
+
+/// An analog button, e.g. a trigger, used as a fader
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GamepadTrigger {
+    pub button: Button,
+    /// output range the 0.0 to 1.0 value is remapped to
+    #[serde(default = "default_midi_range")]
+    pub range: (f64, f64),
+    pub target: Target,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum StickMode {
+    /// the stick position is remapped to the range whenever it changes
+    Absolute,
+    /// the stick position times the scale is sent on every update while the
+    /// stick is out of its dead zone, e.g. to apply a force on the vinyl
+    Continuous,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GamepadStick {
+    pub axis: Axis,
+    pub mode: StickMode,
+    /// output range the -1.0 to 1.0 position is remapped to (absolute mode)
+    #[serde(default = "default_stick_range")]
+    pub range: (f64, f64),
+    /// factor applied to the position (continuous mode)
+    #[serde(default = "default_hid_jog_scale")]
+    pub scale: f64,
+    #[serde(default = "default_stick_dead_zone")]
+    pub dead_zone: f64,
+    pub target: Target,
+}
+
+fn default_stick_range() -> (f64, f64) {
+    (-1.0, 1.0)
+}
+
+fn default_stick_dead_zone() -> f64 {
+    0.1
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GamepadMapping {
+    #[serde(default)]
+    pub buttons: Vec<GamepadButton>,
+    #[serde(default)]
+    pub triggers: Vec<GamepadTrigger>,
+    #[serde(default)]
+    pub sticks: Vec<GamepadStick>,
+}
+
+impl GamepadMapping {
+    /// Load the user gamepad mapping from the config directory, or the default one
+    pub fn load() -> Self {
+        load_mapping("gamepad_mapping.toml", DEFAULT_GAMEPAD_MAPPING)
+    }
+
+    pub fn map_button(&self, button: Button, pressed: bool) -> Vec<BoothEvent<'static>> {
+        self.buttons
+            .iter()
+            .filter(|binding| binding.button == button)
+            .filter_map(|binding| {
+                if pressed {
+                    Some(binding.target)
+                } else {
+                    binding.release_target
+                }
+            })
+            .map(|target| target.to_event(1.0))
+            .collect()
+    }
+
+    pub fn map_trigger(&self, button: Button, value: f32) -> Vec<BoothEvent<'static>> {
+        self.triggers
+            .iter()
+            .filter(|binding| binding.button == button)
+            .map(|binding| {
+                let (start, end) = binding.range;
+                binding
+                    .target
+                    .to_event(remap(value as f64, 0.0, 1.0, start, end))
+            })
+            .collect()
+    }
+
+    pub fn map_stick(&self, axis: Axis, value: f32) -> Vec<BoothEvent<'static>> {
+        self.sticks
+            .iter()
+            .filter(|binding| binding.axis == axis && binding.mode == StickMode::Absolute)
+            .map(|binding| {
+                let (start, end) = binding.range;
+                binding
+                    .target
+                    .to_event(remap(value as f64, -1.0, 1.0, start, end))
+            })
+            .collect()
+    }
+
+    /// Events sent on every update for sticks in continuous mode.
+    /// `position` gives the current value of an axis
+    pub fn map_continuous(&self, position: impl Fn(Axis) -> f32) -> Vec<BoothEvent<'static>> {
+        self.sticks
+            .iter()
+            .filter(|binding| binding.mode == StickMode::Continuous)
+            .filter_map(|binding| {
+                let value = position(binding.axis) as f64;
+
+                if value.abs() > binding.dead_zone {
+                    Some(binding.target.to_event(value * binding.scale))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+fn load_mapping<T: DeserializeOwned>(file_name: &str, default: &str) -> T {
+    read_mapping(file_name)
+        .unwrap_or_else(|| toml::from_str(default).expect("default mapping is invalid"))
+}
+
+/// User mapping from the config directory, `None` if missing or invalid
+fn read_mapping<T: DeserializeOwned>(file_name: &str) -> Option<T> {
+    let content = read_config_file(file_name)?;
+
+    match toml::from_str(&content) {
+        Ok(mapping) => Some(mapping),
+        Err(e) => {
+            log::error!("Invalid '{}', using default mapping: {}", file_name, e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_mappings() {
+        let keymap: KeyMap = toml::from_str(DEFAULT_KEYMAP).unwrap();
+        let midi_mapping: MidiMapping = toml::from_str(DEFAULT_MIDI_MAPPING).unwrap();
+        let gamepad_mapping: GamepadMapping = toml::from_str(DEFAULT_GAMEPAD_MAPPING).unwrap();
+
+        assert!(!keymap.keys.is_empty());
+        assert!(!midi_mapping.midi.is_empty());
+        assert!(!gamepad_mapping.buttons.is_empty());
+    }
+
+    #[test]
+    fn test_key_modifiers() {
+        let mut keymap: KeyMap = toml::from_str(DEFAULT_KEYMAP).unwrap();
+
+        let events = keymap.map(
+            KeyCode::KeyD,
+            ElementState::Pressed,
+            false,
+            ModifiersState::CONTROL,
+        );
+        assert!(matches!(events[..], [BoothEvent::ToggleDebug]));
+
+        let events = keymap.map(
+            KeyCode::KeyD,
+            ElementState::Pressed,
+            false,
+            ModifiersState::empty(),
+        );
+        assert!(events.is_empty());
+
+        assert_eq!(keymap.keys[0].describe(), "Ctrl+D");
+    }
+
+    #[test]
+    fn test_pointer_bindings() {
+        assert_eq!(
+            KeyMap::load().pointer.scratch.describe(),
+            PointerBindings::default().scratch.describe()
+        );
+
+        let keymap: KeyMap = toml::from_str(
+            r#"
+            keys = []
+            [pointer]
+            scratch = "Space"
+            seek = ["Control", "Shift"]
+            "#,
+        )
+        .unwrap();
+
+        let pointer = keymap.pointer;
+        assert!(pointer
+            .scratch
+            .is_held(ModifiersState::SUPER, &[KeyCode::Space]));
+        assert!(!pointer.scratch.is_held(ModifiersState::SUPER, &[]));
+        assert!(pointer
+            .seek
+            .is_held(ModifiersState::CONTROL | ModifiersState::SHIFT, &[]));
+        assert!(!pointer.seek.is_held(ModifiersState::CONTROL, &[]));
+        assert_eq!(pointer.seek.describe(), "Ctrl+Shift");
+    }
+
+    #[test]
+    fn test_midi_curve() {
+        let mut midi_mapping: MidiMapping = toml::from_str(DEFAULT_MIDI_MAPPING).unwrap();
+        let now = Instant::now();
+
+        match midi_mapping.map(&[176, 18, 127], now).as_deref() {
+            Some([BoothEvent::VolumeOneChanged(volume)]) => assert_eq!(*volume, 1.0),
+            events => panic!("unexpected events {:?}", events),
+        }
+
+        match midi_mapping.map(&[176, 17, 0], now).as_deref() {
+            Some([BoothEvent::EqLowOneChanged(gain)]) => assert_eq!(*gain, -24.0),
+            events => panic!("unexpected events {:?}", events),
+        }
+
+        assert!(midi_mapping.map(&[176, 127, 0], now).is_none());
+    }
+
+    #[test]
+    fn test_midi_encoder() {
+        let mut midi_mapping: MidiMapping = toml::from_str(
+            "[[midi]]
+            data1 = 16
+            target = \"FileNavigatorDown\"
+            encoder = { step = 2, debounce_ms = 50, decrement_target = \"FileNavigatorUp\" }",
+        )
+        .unwrap();
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        // one tick is not enough for a step
+        assert!(midi_mapping.map(&[176, 16, 1], at(0)).unwrap().is_empty());
+        assert!(matches!(
+            midi_mapping.map(&[176, 16, 1], at(100)).unwrap()[..],
+            [BoothEvent::FileNavigatorDown]
+        ));
+
+        // debounced, the ticks are kept for later
+        assert!(midi_mapping.map(&[176, 16, 4], at(120)).unwrap().is_empty());
+        assert!(matches!(
+            midi_mapping.map(&[176, 16, 2], at(200)).unwrap()[..],
+            [
+                BoothEvent::FileNavigatorDown,
+                BoothEvent::FileNavigatorDown,
+                BoothEvent::FileNavigatorDown
+            ]
+        ));
+
+        assert!(matches!(
+            midi_mapping.map(&[176, 16, 126], at(300)).unwrap()[..],
+            [BoothEvent::FileNavigatorUp]
+        ));
+    }
+
+    #[test]
+    fn test_midi_gestures() {
+        let mut midi_mapping: MidiMapping = toml::from_str(
+            "[[midi]]
+            status = 144
+            data1 = 1
+            target = \"ToggleStartStopOne\"
+
+            [[midi]]
+            status = 144
+            data1 = 1
+            target = \"BrakeOne\"
+            gesture = \"Hold\"
+
+            [[midi]]
+            status = 144
+            data1 = 2
+            target = \"SeekOne\"
+            range = [0.0, 0.0]
+            gesture = \"DoublePress\"",
+        )
+        .unwrap();
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        // a tap waits for the release as it may still be a hold
+        assert!(midi_mapping.map(&[144, 1, 127], at(0)).unwrap().is_empty());
+        assert!(matches!(
+            midi_mapping.map(&[128, 1, 0], at(100)).unwrap()[..],
+            [BoothEvent::ToggleStartStopOne]
+        ));
+
+        assert!(midi_mapping
+            .map(&[144, 1, 127], at(1000))
+            .unwrap()
+            .is_empty());
+        assert!(midi_mapping.poll_gestures(at(1200)).is_empty());
+        assert!(matches!(
+            midi_mapping.poll_gestures(at(1500))[..],
+            [BoothEvent::BrakeOne]
+        ));
+        assert!(midi_mapping.map(&[144, 1, 0], at(2000)).unwrap().is_empty());
+
+        // a single press of a button without press binding does nothing
+        midi_mapping.map(&[144, 2, 127], at(3000));
+        midi_mapping.map(&[144, 2, 0], at(3050));
+        assert!(midi_mapping.poll_gestures(at(3500)).is_empty());
+
+        midi_mapping.map(&[144, 2, 127], at(4000));
+        midi_mapping.map(&[144, 2, 0], at(4050));
+        match midi_mapping.map(&[144, 2, 127], at(4200)).as_deref() {
+            Some([BoothEvent::SeekOne(percent)]) => assert_eq!(*percent, 0.0),
+            events => panic!("unexpected events {:?}", events),
+        }
+    }
+
+    #[test]
+    fn test_midi_presets() {
+        let presets = MidiMapping::builtin_presets();
+        let detect = |port_name| {
+            presets
+                .iter()
+                .find(|preset| preset.matches_port(port_name))
+                .and_then(|preset| preset.name.as_deref())
+        };
+
+        assert_eq!(
+            detect("MIDI Mix:MIDI Mix MIDI 1 20:0"),
+            Some("Akai MIDImix")
+        );
+        assert_eq!(detect("PIONEER DDJ-400"), Some("Pioneer DDJ-400"));
+        assert_eq!(detect("Unknown controller"), None);
+
+        // releasing a note may be a zero velocity note on
+        let mut ddj_400 = presets[1].clone();
+        let now = Instant::now();
+        assert!(matches!(
+            ddj_400.map(&[144, 11, 127], now).as_deref(),
+            Some([BoothEvent::ToggleStartStopOne])
+        ));
+        assert!(ddj_400.map(&[144, 11, 0], now).is_none());
+    }
+
+    #[test]
+    fn test_hid_report() {
+        let hid_mapping: HidMapping = toml::from_str(
+            "vendor_id = 0x1234
+            product_id = 0x5678
+
+            [[buttons]]
+            byte = 1
+            mask = 0x02
+            target = \"ToggleStartStopOne\"
+
+            [[axes]]
+            byte = 2
+            target = \"VolumeOne\"
+
+            [[jogs]]
+            byte = 3
+            mode = \"Absolute\"
+            target = \"Force\"",
+        )
+        .unwrap();
+
+        let events = hid_mapping.map(&[0, 0b10, 255, 1], &[0, 0, 0, 255]);
+        assert!(matches!(
+            events[..],
+            [
+                BoothEvent::ToggleStartStopOne,
+                BoothEvent::VolumeOneChanged(1.0),
+                BoothEvent::ForceApplied(2.0)
+            ]
+        ));
+
+        let events = hid_mapping.map(&[0, 0b10, 255, 1], &[0, 0b10, 255, 1]);
+        assert!(events.is_empty());
+    }
+}
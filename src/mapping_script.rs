@@ -0,0 +1,244 @@
+use std::fs;
+
+use rhai::{
+    module_resolvers::DummyModuleResolver, Array, CallFnOptions, Dynamic, Engine, FuncArgs, Map,
+    ParseError, Scope, AST,
+};
+use serde::{
+    de::{value::Error as ValueError, IntoDeserializer},
+    Deserialize,
+};
+
+use crate::{
+    app::AppData,
+    config::config_dir,
+    controller::{BoothEvent, TurntableFocus},
+    mapping::Target,
+};
+
+/// Operations a script call may run before being aborted, so that a faulty
+/// script cannot freeze the app
+const MAX_OPERATIONS: u64 = 100_000;
+
+/// Mapping logic written in Rhai for controllers that need more than
+/// declarative bindings, e.g. shift layers, jog modes or LEDs.
+///
+/// The script may define:
+/// - `on_midi(status, data1, data2)`, returning an array of targets to
+///   trigger, as names or `#{ target: "...", value: ... }` maps, or `()` to
+///   fall back to the bindings of the mapping
+/// - `feedback(booth)`, returning the `[status, data1, data2]` messages to
+///   send back to the controller for the current booth state
+///
+/// Both are called with `this` bound to a map kept between calls. Scripts
+/// cannot import modules nor access files
+pub struct MappingScript {
+    engine: Engine,
+    ast: AST,
+    state: Dynamic,
+}
+
+impl MappingScript {
+    /// Compile a script from the config directory
+    pub fn load(file_name: &str) -> Option<Self> {
+        let path = config_dir().join(file_name);
+
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) => {
+                log::error!("Cannot read mapping script '{}': {}", path.display(), e);
+                return None;
+            }
+        };
+
+        match Self::compile(&content) {
+            Ok(script) => {
+                log::info!("Using mapping script '{}'", path.display());
+                Some(script)
+            }
+            Err(e) => {
+                log::error!("Invalid mapping script '{}': {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    fn compile(source: &str) -> Result<Self, ParseError> {
+        let mut engine = Engine::new();
+        engine
+            .set_module_resolver(DummyModuleResolver::new())
+            .set_max_operations(MAX_OPERATIONS)
+            .set_max_call_levels(32)
+            .set_max_string_size(1024)
+            .set_max_array_size(1024)
+            .set_max_map_size(1024)
+            .on_print(|text| log::info!("Mapping script: {}", text));
+
+        let ast = engine.compile(source)?;
+
+        Ok(Self {
+            engine,
+            ast,
+            state: Dynamic::from_map(Map::new()),
+        })
+    }
+
+    /// Call a function of the script, `None` if it is not defined or fails
+    fn call(&mut self, name: &str, args: impl FuncArgs) -> Option<Dynamic> {
+        if !self
+            .ast
+            .iter_functions()
+            .any(|function| function.name == name)
+        {
+            return None;
+        }
+
+        let options = CallFnOptions::new()
+            .eval_ast(false)
+            .bind_this_ptr(&mut self.state);
+
+        match self
+            .engine
+            .call_fn_with_options(options, &mut Scope::new(), &self.ast, name, args)
+        {
+            Ok(result) => Some(result),
+            Err(e) => {
+                log::error!("Mapping script error in '{}': {}", name, e);
+                None
+            }
+        }
+    }
+
+    /// Booth events triggered by a MIDI message, `None` to use the bindings
+    pub fn on_midi(&mut self, message: &[u8]) -> Option<Vec<BoothEvent<'static>>> {
+        let [status, data1, data2] = message else {
+            return None;
+        };
+
+        let result = self.call("on_midi", (*status as i64, *data1 as i64, *data2 as i64))?;
+        if result.is_unit() {
+            return None;
+        }
+
+        match result.try_cast::<Array>() {
+            Some(targets) => Some(targets.into_iter().filter_map(to_event).collect()),
+            None => {
+                log::error!("Mapping script 'on_midi' must return an array or ()");
+                Some(Vec::new())
+            }
+        }
+    }
+
+    /// Messages the script sends back to the controller for this state
+    pub fn feedback(&mut self, app_data: &AppData) -> Vec<[u8; 3]> {
+        let mut booth = Map::new();
+        booth.insert(
+            "playing_one".into(),
+            app_data.turntable_one.is_playing().into(),
+        );
+        booth.insert(
+            "playing_two".into(),
+            app_data.turntable_two.is_playing().into(),
+        );
+        booth.insert("cue_one".into(), app_data.mixer.is_cue_one_enabled().into());
+        booth.insert("cue_two".into(), app_data.mixer.is_cue_two_enabled().into());
+        booth.insert(
+            "focus_one".into(),
+            (app_data.turntable_focus == TurntableFocus::One).into(),
+        );
+        booth.insert(
+            "recording".into(),
+            app_data.mixer.recorder().is_recording().into(),
+        );
+
+        let Some(result) = self.call("feedback", (booth,)) else {
+            return Vec::new();
+        };
+
+        result
+            .try_cast::<Array>()
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|message| {
+                let bytes: Vec<u8> = message
+                    .try_cast::<Array>()?
+                    .into_iter()
+                    .map(|byte| byte.as_int().ok().map(|byte| byte as u8))
+                    .collect::<Option<_>>()?;
+                bytes.try_into().ok()
+            })
+            .collect()
+    }
+}
+
+/// Parse a target returned by a script
+fn to_event(item: Dynamic) -> Option<BoothEvent<'static>> {
+    let (name, value) = if item.is_string() {
+        (item.into_string().ok()?, 1.0)
+    } else {
+        let map = item.try_cast::<Map>()?;
+        let value = map.get("value").and_then(|value| {
+            value
+                .as_float()
+                .ok()
+                .or_else(|| value.as_int().ok().map(|value| value as f64))
+        });
+        (
+            map.get("target")?.clone().into_string().ok()?,
+            value.unwrap_or(1.0),
+        )
+    };
+
+    let target: Result<Target, ValueError> = Target::deserialize(name.as_str().into_deserializer());
+    match target {
+        Ok(target) => Some(target.to_event(value)),
+        Err(_) => {
+            log::error!("Unknown target '{}' in mapping script", name);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_on_midi_state() {
+        let mut script = MappingScript::compile(
+            "fn on_midi(status, data1, data2) {
+                if data1 == 1 {
+                    this.shift = data2 > 0;
+                    return [];
+                }
+                if data1 == 2 && data2 > 0 {
+                    if this.shift == true {
+                        return [#{ target: \"PitchOne\", value: 1.0 }];
+                    }
+                    return [\"ToggleStartStopOne\", \"Unknown\"];
+                }
+            }",
+        )
+        .unwrap();
+
+        assert!(matches!(
+            script.on_midi(&[144, 2, 127]).as_deref(),
+            Some([BoothEvent::ToggleStartStopOne])
+        ));
+        assert!(script.on_midi(&[144, 1, 127]).unwrap().is_empty());
+        assert!(matches!(
+            script.on_midi(&[144, 2, 127]).as_deref(),
+            Some([BoothEvent::PitchOneChanged(1.0)])
+        ));
+
+        // the bindings are used when the script returns nothing
+        assert!(script.on_midi(&[144, 3, 127]).is_none());
+
+        let mut example =
+            MappingScript::compile(include_str!("../config/mapping_script.example.rhai")).unwrap();
+        assert!(matches!(
+            example.on_midi(&[176, 0x21, 66]).as_deref(),
+            Some([BoothEvent::ForceApplied(1.0)])
+        ));
+    }
+}
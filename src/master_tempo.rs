@@ -0,0 +1,135 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    controller::TurntableFocus,
+    sampler::{MAX_BPM, MIN_BPM},
+};
+
+/// Taps further apart than this start a new tempo
+const TAP_TIMEOUT: Duration = Duration::from_secs(2);
+/// Last taps averaged into the tapped tempo
+const MAX_TAPS: usize = 8;
+
+/// Where the master tempo is taken from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TempoSource {
+    /// the playing deck heard the most on the master
+    Auto,
+    Deck(TurntableFocus),
+    /// the BPM typed or tapped
+    Manual,
+}
+
+impl TempoSource {
+    pub const ALL: [TempoSource; 4] = [
+        TempoSource::Auto,
+        TempoSource::Deck(TurntableFocus::One),
+        TempoSource::Deck(TurntableFocus::Two),
+        TempoSource::Manual,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TempoSource::Auto => "Master deck",
+            TempoSource::Deck(TurntableFocus::One) => "Deck one",
+            TempoSource::Deck(TurntableFocus::Two) => "Deck two",
+            TempoSource::Manual => "Manual",
+        }
+    }
+}
+
+/// Tempo of the booth followed by the metronome and the sampler clock,
+/// taken from the tempo master deck or set by hand and by tapping
+pub struct MasterTempo {
+    source: TempoSource,
+    bpm: f64,
+    taps: Vec<Instant>,
+}
+
+impl Default for MasterTempo {
+    fn default() -> Self {
+        Self {
+            source: TempoSource::Auto,
+            bpm: 120.0,
+            taps: Vec::new(),
+        }
+    }
+}
+
+impl MasterTempo {
+    pub fn source(&self) -> TempoSource {
+        self.source
+    }
+
+    pub fn set_source(&mut self, source: TempoSource) {
+        self.source = source;
+    }
+
+    pub fn bpm(&self) -> f64 {
+        self.bpm
+    }
+
+    /// Set the tempo by hand, the decks no longer changing it
+    pub fn set_bpm(&mut self, bpm: f64) {
+        self.source = TempoSource::Manual;
+        self.bpm = bpm.clamp(MIN_BPM, MAX_BPM);
+    }
+
+    /// Take the tempo of the tempo master deck, unless set by hand. The last
+    /// tempo is kept while the deck has none, e.g. once stopped
+    pub fn follow(&mut self, deck_bpm: Option<f64>) {
+        if let (false, Some(bpm)) = (self.source == TempoSource::Manual, deck_bpm) {
+            self.bpm = bpm.clamp(MIN_BPM, MAX_BPM);
+        }
+    }
+
+    /// Count a tap on the beat, setting the tempo by hand from the second tap
+    pub fn tap(&mut self, now: Instant) {
+        if self
+            .taps
+            .last()
+            .is_some_and(|last| now.duration_since(*last) > TAP_TIMEOUT)
+        {
+            self.taps.clear();
+        }
+        self.taps.push(now);
+        if self.taps.len() > MAX_TAPS {
+            self.taps.remove(0);
+        }
+
+        if let [first, .., last] = self.taps[..] {
+            let beat = last.duration_since(first).as_secs_f64() / (self.taps.len() - 1) as f64;
+            self.set_bpm(60.0 / beat);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tap() {
+        let mut tempo = MasterTempo::default();
+        tempo.follow(Some(126.0));
+        assert_eq!(tempo.bpm(), 126.0);
+
+        let start = Instant::now();
+        tempo.tap(start);
+        assert_eq!(tempo.source(), TempoSource::Auto);
+        for beat in 1..4 {
+            tempo.tap(start + Duration::from_millis(500 * beat));
+        }
+        assert_eq!(tempo.source(), TempoSource::Manual);
+        assert!((tempo.bpm() - 120.0).abs() < 1e-9);
+
+        // set by hand, the decks no longer change it
+        tempo.follow(Some(126.0));
+        assert!((tempo.bpm() - 120.0).abs() < 1e-9);
+
+        // a late tap starts over
+        tempo.tap(start + Duration::from_secs(10));
+        tempo.tap(start + Duration::from_millis(10_400));
+        assert!((tempo.bpm() - 150.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,156 @@
+use std::{f32::consts::TAU, sync::Arc};
+
+use kira::{
+    clock::{ClockHandle, ClockSpeed, ClockTime},
+    sound::static_sound::{StaticSoundData, StaticSoundSettings},
+    track::{TrackBuilder, TrackHandle, TrackRoutes},
+    tween::Tween,
+    Frame, StartTime,
+};
+
+use crate::{
+    audio_backend::Manager,
+    sampler::{BEATS_PER_BAR, MAX_BPM, MIN_BPM},
+};
+
+const CLICK_SAMPLE_RATE: u32 = 48_000;
+const CLICK_FREQUENCY: f32 = 1_000.0;
+const CLICK_SECONDS: f32 = 0.03;
+/// Playback rate of the click on the first beat of a bar
+const ACCENT_RATE: f64 = 1.5;
+
+/// A click played on every beat into the cue only, to practice
+/// beatmatching against it in the headphones
+pub struct Metronome {
+    clock: ClockHandle,
+    track: TrackHandle,
+    click: StaticSoundData,
+    enabled: bool,
+    bpm: f64,
+    volume: f64,
+    /// take the master tempo instead of the BPM set by hand
+    follow_tempo: bool,
+    /// tick of the last click sent to the audio thread
+    scheduled: Option<u64>,
+}
+
+impl Metronome {
+    pub fn new(manager: &mut Manager, cue: &TrackHandle) -> Self {
+        let bpm = 120.0;
+        let volume = 0.5;
+        let clock = manager.add_clock(ClockSpeed::TicksPerMinute(bpm)).unwrap();
+        let track = manager
+            .add_sub_track(
+                TrackBuilder::new()
+                    .volume(volume)
+                    .routes(TrackRoutes::empty().with_route(cue, 1.0)),
+            )
+            .unwrap();
+
+        Self {
+            clock,
+            track,
+            click: click(),
+            enabled: false,
+            bpm,
+            volume,
+            follow_tempo: false,
+            scheduled: None,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.scheduled = None;
+        match enabled {
+            true => self.clock.start(),
+            false => self.clock.stop(),
+        }
+    }
+
+    pub fn bpm(&self) -> f64 {
+        self.bpm
+    }
+
+    pub fn set_bpm(&mut self, bpm: f64) {
+        let bpm = bpm.clamp(MIN_BPM, MAX_BPM);
+        if bpm == self.bpm {
+            return;
+        }
+
+        self.bpm = bpm;
+        self.clock
+            .set_speed(ClockSpeed::TicksPerMinute(self.bpm), Tween::default());
+    }
+
+    pub fn volume(&self) -> f64 {
+        self.volume
+    }
+
+    pub fn set_volume(&mut self, volume: f64) {
+        self.volume = volume;
+        self.track.set_volume(volume, Tween::default());
+    }
+
+    pub fn follows_tempo(&self) -> bool {
+        self.follow_tempo
+    }
+
+    pub fn set_follow_tempo(&mut self, follow_tempo: bool) {
+        self.follow_tempo = follow_tempo;
+    }
+
+    /// Send the click of the next beat to the audio thread, if not sent yet
+    pub fn schedule(&mut self, manager: &mut Manager) {
+        if !self.enabled {
+            return;
+        }
+
+        let next = self.clock.time().ticks + 1;
+        if self.scheduled == Some(next) {
+            return;
+        }
+
+        let rate = match next % BEATS_PER_BAR {
+            0 => ACCENT_RATE,
+            _ => 1.0,
+        };
+        let sound = self
+            .click
+            .output_destination(&self.track)
+            .playback_rate(rate)
+            .start_time(StartTime::ClockTime(ClockTime {
+                clock: self.clock.id(),
+                ticks: next,
+                fraction: 0.0,
+            }));
+
+        match manager.play(sound) {
+            Ok(_) => self.scheduled = Some(next),
+            Err(e) => log::error!("Cannot play metronome click: {}", e),
+        }
+    }
+}
+
+/// Short sine burst fading out
+fn click() -> StaticSoundData {
+    let length = (CLICK_SAMPLE_RATE as f32 * CLICK_SECONDS) as usize;
+    let frames: Vec<Frame> = (0..length)
+        .map(|i| {
+            let t = i as f32 / CLICK_SAMPLE_RATE as f32;
+            let envelope = 1.0 - i as f32 / length as f32;
+            Frame::from_mono((TAU * CLICK_FREQUENCY * t).sin() * envelope * envelope)
+        })
+        .collect();
+
+    StaticSoundData {
+        sample_rate: CLICK_SAMPLE_RATE,
+        frames: Arc::from(frames),
+        settings: StaticSoundSettings::new(),
+        slice: None,
+    }
+}
@@ -1,19 +1,52 @@
-use std::{io::stdin, sync::Arc};
+use std::{
+    io::stdin,
+    sync::mpsc::{self, Receiver},
+    time::{Duration, Instant},
+};
 
-use egui::mutex::Mutex;
-use midir::{Ignore, MidiInput, MidiInputConnection};
+use midir::{Ignore, MidiInput, MidiInputConnection, MidiOutput, MidiOutputConnection};
 
-use crate::app::App;
+use crate::{
+    app::AppData, controller::BoothEvent, input_source::InputSource, mapping::MidiMapping,
+    mapping_script::MappingScript, processable::Processable, settings::Settings,
+};
+
+/// A MIDI message along with the time at which the MIDI backend received it
+#[derive(Debug, Clone)]
+pub struct MidiMessage {
+    /// Time of reception, derived from the backend timestamp rather than from
+    /// the time the message is eventually handled
+    pub time: Instant,
+    /// Raw backend timestamp in microseconds, used to order messages
+    pub stamp: u64,
+    pub bytes: Vec<u8>,
+}
 
 pub struct MidiController {
-    _conn_in: Option<MidiInputConnection<Arc<Mutex<App>>>>,
+    _conn_in: Option<MidiInputConnection<Option<(u64, Instant)>>>,
+    receiver: Receiver<MidiMessage>,
+    port_name: Option<String>,
+    mapping: MidiMapping,
+    script: Option<MappingScript>,
+    /// output port of the controller, for the feedback of the script
+    conn_out: Option<MidiOutputConnection>,
+    /// feedback messages sent last, so that only changes are sent
+    feedback: Vec<[u8; 3]>,
+    /// delay between the reception of the last MIDI message and its handling
+    latency: Duration,
 }
 
 impl MidiController {
-    pub fn new<F>(f: F, app_clone: Arc<Mutex<App>>) -> Self
+    /// Connect to a MIDI input port and load the mapping matching it, unless
+    /// `preset` is set. Messages are queued and `wake` is called from the
+    /// MIDI thread so that the owner polls them without waiting for its next
+    /// update
+    pub fn new<F>(preset: Option<&str>, wake: F) -> Self
     where
-        F: Fn(&[u8], &Arc<Mutex<App>>) + Send + 'static,
+        F: Fn() + Send + 'static,
     {
+        let (sender, receiver) = mpsc::channel();
+
         let mut midi_in = MidiInput::new("midir reading input").unwrap();
         midi_in.ignore(Ignore::None);
 
@@ -21,7 +54,17 @@ impl MidiController {
         let in_port = match in_ports.len() {
             0 => {
                 log::warn!("No MIDI Input port found");
-                return Self { _conn_in: None };
+                let mapping = MidiMapping::load(None, preset);
+                return Self {
+                    _conn_in: None,
+                    receiver,
+                    port_name: None,
+                    script: load_script(&mapping),
+                    mapping,
+                    conn_out: None,
+                    feedback: Vec::new(),
+                    latency: Duration::ZERO,
+                };
             }
             1 => {
                 log::info!(
@@ -52,10 +95,24 @@ impl MidiController {
             .connect(
                 in_port,
                 "midir-read-input",
-                move |_, message, app| {
-                    f(message, app);
+                move |stamp, message, anchor| {
+                    // backend timestamps start at an unspecified point in the
+                    // past, so the first message anchors them to the app clock
+                    let (anchor_stamp, anchor_time) =
+                        *anchor.get_or_insert((stamp, Instant::now()));
+
+                    let message = MidiMessage {
+                        time: anchor_time
+                            + Duration::from_micros(stamp.saturating_sub(anchor_stamp)),
+                        stamp,
+                        bytes: message.to_vec(),
+                    };
+
+                    if sender.send(message).is_ok() {
+                        wake();
+                    }
                 },
-                app_clone,
+                None,
             )
             .unwrap();
 
@@ -64,8 +121,101 @@ impl MidiController {
             in_port_name
         );
 
+        let mapping = MidiMapping::load(Some(&in_port_name), preset);
         Self {
             _conn_in: Some(_conn_in),
+            receiver,
+            script: load_script(&mapping),
+            mapping,
+            conn_out: connect_output(&in_port_name),
+            feedback: Vec::new(),
+            port_name: Some(in_port_name),
+            latency: Duration::ZERO,
+        }
+    }
+}
+
+fn load_script(mapping: &MidiMapping) -> Option<MappingScript> {
+    mapping.script.as_deref().and_then(MappingScript::load)
+}
+
+/// Open the output port named like the input port, if the controller has one
+fn connect_output(port_name: &str) -> Option<MidiOutputConnection> {
+    let midi_out = MidiOutput::new("midir feedback output").ok()?;
+    let port = midi_out
+        .ports()
+        .into_iter()
+        .find(|port| midi_out.port_name(port).is_ok_and(|name| name == port_name))?;
+
+    match midi_out.connect(&port, "midir-write-output") {
+        Ok(conn_out) => Some(conn_out),
+        Err(e) => {
+            log::warn!("Cannot open MIDI output '{}': {}", port_name, e);
+            None
         }
     }
 }
+
+impl Processable for MidiController {}
+
+impl InputSource for MidiController {
+    /// Map pending MIDI messages in the order they were received by the
+    /// backend, regardless of when the MIDI thread managed to queue them
+    fn poll(&mut self, events: &mut Vec<BoothEvent<'static>>) {
+        let mut messages: Vec<MidiMessage> = self.receiver.try_iter().collect();
+        messages.sort_by_key(|message| message.stamp);
+
+        for message in messages {
+            self.latency = message.time.elapsed();
+
+            let scripted = match &mut self.script {
+                Some(script) => script.on_midi(&message.bytes),
+                None => None,
+            };
+
+            match scripted.or_else(|| self.mapping.map(&message.bytes, message.time)) {
+                Some(mapped) => events.extend(mapped),
+                None => log::info!("Unmatched midi message: {:?}", message.bytes),
+            }
+        }
+
+        events.extend(self.mapping.poll_gestures(Instant::now()));
+    }
+
+    fn needs_polling(&self) -> bool {
+        self.mapping.has_pending_gestures()
+    }
+
+    fn reload_mapping(&mut self, settings: &Settings) {
+        self.mapping =
+            MidiMapping::load(self.port_name.as_deref(), settings.midi_preset.as_deref());
+        self.script = load_script(&self.mapping);
+        self.feedback.clear();
+    }
+
+    fn update_feedback(&mut self, app_data: &AppData) {
+        let (Some(script), Some(conn_out)) = (&mut self.script, &mut self.conn_out) else {
+            return;
+        };
+
+        let feedback = script.feedback(app_data);
+        for message in feedback.iter() {
+            if self.feedback.contains(message) {
+                continue;
+            }
+            match conn_out.send(message) {
+                Ok(()) => (),
+                Err(e) => log::error!("Cannot send MIDI feedback: {}", e),
+            }
+        }
+        self.feedback = feedback;
+    }
+
+    fn debug_info(&self) -> Option<String> {
+        Some(format!(
+            "midi_preset: {}, midi_latency: {:?}",
+            self.mapping.name.as_deref().unwrap_or("custom"),
+            self.latency
+        ))
+    }
+}
@@ -1,40 +1,152 @@
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::{
+    audio_backend::{self, Manager},
+    audio_input::{AudioInput, AudioInputError},
+    generator::Generator,
+    limiter::ChannelLimiter,
+    loudness::LoudnessMeter,
+    metronome::Metronome,
+    plugin_slot::PluginSlot,
+    processable::Processable,
+    recorder::{RecordSource, Recorder, RecorderError, SplitOptions},
+    routing::{self, Channel, ChannelRoutes},
+    sampler::Sampler,
+};
 
 use kira::{
     effect::eq_filter::{EqFilterBuilder, EqFilterHandle, EqFilterKind},
-    manager::{AudioManager, AudioManagerSettings, DefaultBackend},
     track::{TrackBuilder, TrackHandle, TrackRoutes},
     tween::Tween,
 };
 
+/// Gain of an EQ band leaving the sound untouched, in dB
+pub const EQ_FLAT_GAIN: f64 = 0.0;
+/// Cue mix hearing the cue and the master equally
+pub const CENTER_CUE_MIX: f64 = 0.5;
+pub const CENTER_CROSSFADER: f64 = 0.5;
+/// Insert effects of each channel, processed in order
+pub const INSERT_SLOTS: usize = 3;
+
+/// Where the cue of a channel is taken from
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum CueMode {
+    /// pre-fader listen, before the EQ, the insert effects and the fader
+    #[default]
+    PreFader,
+    /// after-fader listen, as the channel is heard on the master
+    AfterFader,
+}
+
+impl CueMode {
+    pub fn label(self) -> &'static str {
+        match self {
+            CueMode::PreFader => "PFL",
+            CueMode::AfterFader => "AFL",
+        }
+    }
+
+    pub fn toggled(self) -> Self {
+        match self {
+            CueMode::PreFader => CueMode::AfterFader,
+            CueMode::AfterFader => CueMode::PreFader,
+        }
+    }
+}
+
 pub struct Mixer {
-    audio_manager: Arc<Mutex<AudioManager>>,
+    audio_manager: Arc<Mutex<Manager>>,
     master_track: TrackHandle,
+    /// what is recorded, i.e. the master without the cue mix, where the mic
+    /// has its own level
+    record_track: TrackHandle,
+    mic_track: TrackHandle,
+    mic: Option<AudioInput>,
+    mic_record_level: f64,
     cue_track: TrackHandle,
     cue_mix_value: f64,
+    /// from 0.0, channel one only, to 1.0, channel two only
+    crossfader: f64,
+    /// silences the master whatever the cue mix
+    master_muted: bool,
+    /// gain in dB of the master while ducked, e.g. under an announcement
+    duck_gain: Option<f64>,
+    /// volume last given to the master track, which is only set on changes
+    /// so that the ducking ramps are not cut by the cue mix set every frame
+    master_volume: Option<f64>,
+    /// track the deck and the line in of channel one play into
+    ch_one_input: Arc<Mutex<TrackHandle>>,
     ch_one_track: Arc<Mutex<TrackHandle>>,
     cue_one_enabled: bool,
+    cue_one_mode: CueMode,
+    /// channel heard on the cue only, whatever its fader and cue
+    preview_one: bool,
+    /// channel taken off the master by its transform button
+    cut_one: bool,
     ch_one_volume: f64,
     eq_low_one: EqFilterHandle,
     eq_low_one_gain: f64,
     eq_high_one: EqFilterHandle,
     eq_high_one_gain: f64,
+    fx_one: Vec<PluginSlot>,
+    limiter_one: ChannelLimiter,
+    ch_two_input: Arc<Mutex<TrackHandle>>,
     ch_two_track: Arc<Mutex<TrackHandle>>,
     cue_two_enabled: bool,
+    cue_two_mode: CueMode,
+    preview_two: bool,
+    cut_two: bool,
     ch_two_volume: f64,
     eq_low_two: EqFilterHandle,
     eq_low_two_gain: f64,
     eq_high_two: EqFilterHandle,
     eq_high_two_gain: f64,
+    fx_two: Vec<PluginSlot>,
+    limiter_two: ChannelLimiter,
+    input_one: Option<AudioInput>,
+    input_two: Option<AudioInput>,
+    recorder: Recorder,
+    loudness: LoudnessMeter,
+    sampler: Sampler,
+    sampler_track: TrackHandle,
+    /// practice click, heard on the cue only
+    metronome: Metronome,
+    generator: Generator,
 }
 
 impl Mixer {
     pub fn new() -> Self {
-        let mut manager =
-            AudioManager::<DefaultBackend>::new(AudioManagerSettings::default()).unwrap();
+        let mut manager = audio_backend::new_manager().unwrap();
+
+        let mut recorder = Recorder::new();
+        let (loudness, loudness_tap) = LoudnessMeter::new();
+        let (fx_one, fx_one_effects): (Vec<_>, Vec<_>) =
+            (0..INSERT_SLOTS).map(|_| PluginSlot::new()).unzip();
+        let (fx_two, fx_two_effects): (Vec<_>, Vec<_>) =
+            (0..INSERT_SLOTS).map(|_| PluginSlot::new()).unzip();
+        let (limiter_one, limiter_one_effect) = ChannelLimiter::new();
+        let (limiter_two, limiter_two_effect) = ChannelLimiter::new();
+        let mut sampler = Sampler::new(&mut manager);
 
         let master = manager.add_sub_track(TrackBuilder::new()).unwrap();
         let cue = manager.add_sub_track(TrackBuilder::new()).unwrap();
+        let metronome = Metronome::new(&mut manager, &cue);
+
+        // the record bus is only heard through the recorder
+        let record = manager
+            .add_sub_track({
+                let mut builder = TrackBuilder::new().routes(TrackRoutes::empty());
+                builder.add_effect(recorder.tap(RecordSource::Master));
+                builder.add_effect(sampler.tap(RecordSource::Master));
+                builder.add_effect(loudness_tap);
+                builder
+            })
+            .unwrap();
+
+        let mic = manager
+            .add_sub_track(TrackBuilder::new().routes(TrackRoutes::new().with_route(&record, 1.0)))
+            .unwrap();
 
         let eq_low_one;
         let eq_high_one;
@@ -43,7 +155,8 @@ impl Mixer {
                 let mut builder = TrackBuilder::new().volume(1.).routes(
                     TrackRoutes::empty()
                         .with_route(&master, 0.0)
-                        .with_route(&cue, 0.0),
+                        .with_route(&cue, 0.0)
+                        .with_route(&record, 0.0),
                 );
 
                 eq_low_one = builder.add_effect(EqFilterBuilder::new(
@@ -60,10 +173,28 @@ impl Mixer {
                     0.2,
                 ));
 
+                for effect in fx_one_effects {
+                    builder.add_effect(effect);
+                }
+                builder.add_effect(limiter_one_effect);
+                builder.add_effect(recorder.tap(RecordSource::DeckOne));
+                builder.add_effect(sampler.tap(RecordSource::DeckOne));
+
                 builder
             })
             .unwrap();
 
+        // the cue is taken from the input track before the EQ when pre-fader
+        let input_one = manager
+            .add_sub_track(
+                TrackBuilder::new().routes(
+                    TrackRoutes::empty()
+                        .with_route(&track_one, 1.0)
+                        .with_route(&cue, 0.0),
+                ),
+            )
+            .unwrap();
+
         let eq_low_two;
         let eq_high_two;
         let track_two = manager
@@ -71,7 +202,8 @@ impl Mixer {
                 let mut builder = TrackBuilder::new().volume(1.).routes(
                     TrackRoutes::empty()
                         .with_route(&master, 0.0)
-                        .with_route(&cue, 0.0),
+                        .with_route(&cue, 0.0)
+                        .with_route(&record, 0.0),
                 );
 
                 eq_low_two = builder.add_effect(EqFilterBuilder::new(
@@ -88,42 +220,193 @@ impl Mixer {
                     0.2,
                 ));
 
+                for effect in fx_two_effects {
+                    builder.add_effect(effect);
+                }
+                builder.add_effect(limiter_two_effect);
+                builder.add_effect(recorder.tap(RecordSource::DeckTwo));
+                builder.add_effect(sampler.tap(RecordSource::DeckTwo));
+
                 builder
             })
             .unwrap();
 
+        let input_two = manager
+            .add_sub_track(
+                TrackBuilder::new().routes(
+                    TrackRoutes::empty()
+                        .with_route(&track_two, 1.0)
+                        .with_route(&cue, 0.0),
+                ),
+            )
+            .unwrap();
+
+        let generator =
+            Generator::new(&mut manager, &master, &record, &cue, &input_one, &input_two);
+
+        // pads are played to the master and recorded, but never sampled
+        // again as they do not go through the channels
+        let sampler_track = manager
+            .add_sub_track(
+                TrackBuilder::new().routes(
+                    TrackRoutes::empty()
+                        .with_route(&master, 1.0)
+                        .with_route(&record, 1.0),
+                ),
+            )
+            .unwrap();
+
         Self {
             audio_manager: Arc::new(Mutex::new(manager)),
             master_track: master,
+            record_track: record,
+            mic_track: mic,
+            mic: None,
+            mic_record_level: 1.0,
             cue_track: cue,
-            cue_mix_value: 0.5,
+            cue_mix_value: CENTER_CUE_MIX,
+            crossfader: CENTER_CROSSFADER,
+            master_muted: false,
+            duck_gain: None,
+            master_volume: None,
+            ch_one_input: Arc::new(Mutex::new(input_one)),
             ch_one_track: Arc::new(Mutex::new(track_one)),
             cue_one_enabled: false,
+            cue_one_mode: CueMode::default(),
+            preview_one: false,
+            cut_one: false,
             ch_one_volume: 0.0,
             eq_low_one: eq_low_one,
-            eq_low_one_gain: 0.0,
+            eq_low_one_gain: EQ_FLAT_GAIN,
             eq_high_one: eq_high_one,
-            eq_high_one_gain: 0.0,
+            eq_high_one_gain: EQ_FLAT_GAIN,
+            fx_one,
+            limiter_one,
+            ch_two_input: Arc::new(Mutex::new(input_two)),
             ch_two_track: Arc::new(Mutex::new(track_two)),
             cue_two_enabled: false,
+            cue_two_mode: CueMode::default(),
+            preview_two: false,
+            cut_two: false,
             ch_two_volume: 0.0,
             eq_low_two: eq_low_two,
-            eq_low_two_gain: 0.0,
+            eq_low_two_gain: EQ_FLAT_GAIN,
             eq_high_two: eq_high_two,
-            eq_high_two_gain: 0.0,
+            eq_high_two_gain: EQ_FLAT_GAIN,
+            fx_two,
+            limiter_two,
+            input_one: None,
+            input_two: None,
+            recorder,
+            loudness,
+            sampler,
+            sampler_track,
+            metronome,
+            generator,
         }
     }
 
-    pub fn get_audio_manager(&self) -> Arc<Mutex<AudioManager>> {
+    /// Insert effect slots of channel one in order, after the EQ
+    pub fn fx_one(&mut self) -> &mut [PluginSlot] {
+        &mut self.fx_one
+    }
+
+    /// Insert effect slots of channel two in order, after the EQ
+    pub fn fx_two(&mut self) -> &mut [PluginSlot] {
+        &mut self.fx_two
+    }
+
+    /// Clip protection of channel one, after the EQ and the insert effects
+    pub fn limiter_one(&self) -> &ChannelLimiter {
+        &self.limiter_one
+    }
+
+    pub fn limiter_two(&self) -> &ChannelLimiter {
+        &self.limiter_two
+    }
+
+    pub fn set_limiters_enabled(&mut self, enabled: bool) {
+        self.limiter_one.set_enabled(enabled);
+        self.limiter_two.set_enabled(enabled);
+    }
+
+    pub fn sampler(&mut self) -> &mut Sampler {
+        &mut self.sampler
+    }
+
+    pub fn metronome(&mut self) -> &mut Metronome {
+        &mut self.metronome
+    }
+
+    pub fn generator(&mut self) -> &mut Generator {
+        &mut self.generator
+    }
+
+    pub fn is_metronome_enabled(&self) -> bool {
+        self.metronome.is_enabled()
+    }
+
+    pub fn is_sampler_playing(&self) -> bool {
+        self.sampler.slots().iter().any(|slot| slot.is_playing())
+    }
+
+    /// Capture into an empty sampler pad, otherwise start or stop its loop
+    pub fn trigger_sampler_pad(&mut self, pad: usize) {
+        self.sampler.trigger(
+            &mut self.audio_manager.lock().unwrap(),
+            &self.sampler_track,
+            pad,
+        );
+    }
+
+    pub fn recorder(&self) -> &Recorder {
+        &self.recorder
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.recorder.sample_rate()
+    }
+
+    /// Start recording the master output, along with each deck post-EQ
+    /// signal to separate files when `stems` is set
+    pub fn start_recording(
+        &mut self,
+        stems: bool,
+        split: &SplitOptions,
+    ) -> Result<(), RecorderError> {
+        self.loudness.reset();
+        self.recorder.start(stems, split)
+    }
+
+    /// Loudness of the record bus
+    pub fn loudness(&self) -> &LoudnessMeter {
+        &self.loudness
+    }
+
+    pub fn reset_loudness(&mut self) {
+        self.loudness.reset();
+    }
+
+    pub fn track_loaded(&mut self) {
+        self.recorder.track_loaded();
+    }
+
+    pub fn stop_recording(&mut self) -> Result<(), RecorderError> {
+        self.recorder.stop()
+    }
+
+    pub fn get_audio_manager(&self) -> Arc<Mutex<Manager>> {
         self.audio_manager.clone()
     }
 
+    /// Track to play into channel one, before the EQ
     pub fn get_ch_one_track(&self) -> Arc<Mutex<TrackHandle>> {
-        self.ch_one_track.clone()
+        self.ch_one_input.clone()
     }
 
+    /// Track to play into channel two, before the EQ
     pub fn get_ch_two_track(&self) -> Arc<Mutex<TrackHandle>> {
-        self.ch_two_track.clone()
+        self.ch_two_input.clone()
     }
 
     pub fn get_cue_mix_value(&self) -> f64 {
@@ -133,11 +416,52 @@ impl Mixer {
     pub fn set_cue_mix_value(&mut self, value: f64) {
         self.cue_mix_value = value;
 
-        let (cue_volume, master_volume) = Mixer::cue_crossfade(self.cue_mix_value);
+        let (cue_volume, _) = routing::cue_mix(self.cue_mix_value);
 
         self.cue_track.set_volume(cue_volume, Tween::default());
-        self.master_track
-            .set_volume(master_volume, Tween::default());
+        self.update_master_volume(Tween::default());
+    }
+
+    pub fn get_crossfader(&self) -> f64 {
+        self.crossfader
+    }
+
+    pub fn set_crossfader(&mut self, value: f64) {
+        self.crossfader = value.clamp(0.0, 1.0);
+        self.route_one();
+        self.route_two();
+    }
+
+    fn update_master_volume(&mut self, tween: Tween) {
+        let volume = routing::master_volume(self.cue_mix_value, self.master_muted, self.duck_gain);
+
+        if self.master_volume != Some(volume) {
+            self.master_volume = Some(volume);
+            self.master_track.set_volume(volume, tween);
+        }
+    }
+
+    pub fn is_ducked(&self) -> bool {
+        self.duck_gain.is_some()
+    }
+
+    /// Lower the master by `gain` dB, or bring it back with None, ramping
+    /// over `ramp`
+    pub fn set_duck(&mut self, gain: Option<f64>, ramp: Duration) {
+        self.duck_gain = gain;
+        self.update_master_volume(Tween {
+            duration: ramp,
+            ..Default::default()
+        });
+    }
+
+    pub fn is_master_muted(&self) -> bool {
+        self.master_muted
+    }
+
+    pub fn set_master_muted(&mut self, muted: bool) {
+        self.master_muted = muted;
+        self.update_master_volume(Tween::default());
     }
 
     pub fn is_cue_one_enabled(&self) -> bool {
@@ -146,16 +470,48 @@ impl Mixer {
 
     pub fn set_cue_one(&mut self, enabled: bool) {
         self.cue_one_enabled = enabled;
+        self.route_one();
+    }
 
-        self.ch_one_track
-            .lock()
-            .unwrap()
-            .set_route(
-                &self.cue_track,
-                if self.cue_one_enabled { 1.0 } else { 0.0 },
-                Tween::default(),
-            )
-            .unwrap();
+    pub fn get_cue_one_mode(&self) -> CueMode {
+        self.cue_one_mode
+    }
+
+    pub fn set_cue_one_mode(&mut self, mode: CueMode) {
+        self.cue_one_mode = mode;
+        self.route_one();
+    }
+
+    /// Take channel one off the master and record buses and hear it on the
+    /// cue before its fader, or put it back
+    pub fn set_preview_one(&mut self, enabled: bool) {
+        self.preview_one = enabled;
+        self.route_one();
+    }
+
+    /// Take channel one off the master and record buses, whatever its fader,
+    /// or put it back
+    pub fn set_cut_one(&mut self, cut: bool) {
+        if cut != self.cut_one {
+            self.cut_one = cut;
+            self.route_one();
+        }
+    }
+
+    fn route_one(&self) {
+        let channel = Channel {
+            volume: self.ch_one_volume,
+            cue: self.cue_one_enabled,
+            cue_mode: self.cue_one_mode,
+            preview: self.preview_one,
+            cut: self.cut_one,
+        };
+        let (crossfader_gain, _) = routing::crossfader_gains(self.crossfader);
+        self.route(
+            &self.ch_one_input,
+            &self.ch_one_track,
+            routing::channel_routes(channel, crossfader_gain),
+        );
     }
 
     pub fn is_cue_two_enabled(&self) -> bool {
@@ -164,15 +520,65 @@ impl Mixer {
 
     pub fn set_cue_two(&mut self, enabled: bool) {
         self.cue_two_enabled = enabled;
+        self.route_two();
+    }
 
-        self.ch_two_track
+    pub fn get_cue_two_mode(&self) -> CueMode {
+        self.cue_two_mode
+    }
+
+    pub fn set_cue_two_mode(&mut self, mode: CueMode) {
+        self.cue_two_mode = mode;
+        self.route_two();
+    }
+
+    /// Take channel two off the master and record buses and hear it on the
+    /// cue before its fader, or put it back
+    pub fn set_preview_two(&mut self, enabled: bool) {
+        self.preview_two = enabled;
+        self.route_two();
+    }
+
+    pub fn set_cut_two(&mut self, cut: bool) {
+        if cut != self.cut_two {
+            self.cut_two = cut;
+            self.route_two();
+        }
+    }
+
+    fn route_two(&self) {
+        let channel = Channel {
+            volume: self.ch_two_volume,
+            cue: self.cue_two_enabled,
+            cue_mode: self.cue_two_mode,
+            preview: self.preview_two,
+            cut: self.cut_two,
+        };
+        let (_, crossfader_gain) = routing::crossfader_gains(self.crossfader);
+        self.route(
+            &self.ch_two_input,
+            &self.ch_two_track,
+            routing::channel_routes(channel, crossfader_gain),
+        );
+    }
+
+    /// Apply the routes of a channel, from its input track to the cue bus
+    /// and from its channel track to the master, record and cue buses
+    fn route(&self, input: &Mutex<TrackHandle>, track: &Mutex<TrackHandle>, routes: ChannelRoutes) {
+        input
             .lock()
             .unwrap()
-            .set_route(
-                &self.cue_track,
-                if self.cue_two_enabled { 1.0 } else { 0.0 },
-                Tween::default(),
-            )
+            .set_route(&self.cue_track, routes.cue_pre_fader, Tween::default())
+            .unwrap();
+        let mut track = track.lock().unwrap();
+        track
+            .set_route(&self.cue_track, routes.cue_after_fader, Tween::default())
+            .unwrap();
+        track
+            .set_route(&self.master_track, routes.master, Tween::default())
+            .unwrap();
+        track
+            .set_route(&self.record_track, routes.master, Tween::default())
             .unwrap();
     }
 
@@ -182,12 +588,7 @@ impl Mixer {
 
     pub fn set_ch_one_volume(&mut self, volume: f64) {
         self.ch_one_volume = volume;
-
-        self.ch_one_track
-            .lock()
-            .unwrap()
-            .set_route(&self.master_track, self.ch_one_volume, Tween::default())
-            .unwrap();
+        self.route_one();
     }
 
     pub fn get_ch_two_volume(&self) -> f64 {
@@ -196,12 +597,7 @@ impl Mixer {
 
     pub fn set_ch_two_volume(&mut self, volume: f64) {
         self.ch_two_volume = volume;
-
-        self.ch_two_track
-            .lock()
-            .unwrap()
-            .set_route(&self.master_track, self.ch_two_volume, Tween::default())
-            .unwrap();
+        self.route_two();
     }
 
     pub fn get_eq_low_one_gain(&self) -> f64 {
@@ -244,10 +640,323 @@ impl Mixer {
             .set_gain(self.eq_high_two_gain, Tween::default());
     }
 
-    /// Explode a given value between 0.0 and 1.0 into respective mixed values.
-    /// The sum of the two output values is 1.0
-    fn cue_crossfade(norm_value: f64) -> (f64, f64) {
-        let norm_value = norm_value.clamp(0.0, 1.0);
-        (1. - norm_value, norm_value)
+    /// Flatten the EQ of channel one
+    pub fn reset_channel_one(&mut self) {
+        self.set_eq_low_one_gain(EQ_FLAT_GAIN);
+        self.set_eq_high_one_gain(EQ_FLAT_GAIN);
+    }
+
+    /// Flatten the EQ of channel two
+    pub fn reset_channel_two(&mut self) {
+        self.set_eq_low_two_gain(EQ_FLAT_GAIN);
+        self.set_eq_high_two_gain(EQ_FLAT_GAIN);
+    }
+
+    /// Flatten both channels and center the cue mix. Faders are left alone
+    /// so that a reset never cuts the music
+    pub fn reset(&mut self) {
+        self.reset_channel_one();
+        self.reset_channel_two();
+        self.set_cue_mix_value(CENTER_CUE_MIX);
+    }
+
+    pub fn is_input_one_enabled(&self) -> bool {
+        self.input_one.is_some()
+    }
+
+    /// Play the hardware audio input into channel one, bypassing the deck
+    pub fn set_input_one(&mut self, enabled: bool) -> Result<(), AudioInputError> {
+        self.input_one = None;
+
+        if enabled {
+            self.input_one = Some(AudioInput::new(
+                &mut self.audio_manager.lock().unwrap(),
+                &self.ch_one_input.lock().unwrap(),
+                dotenv::var("AUDIO_INPUT_DEVICE").ok().as_deref(),
+            )?);
+        }
+
+        Ok(())
+    }
+
+    pub fn is_input_two_enabled(&self) -> bool {
+        self.input_two.is_some()
+    }
+
+    /// Play the hardware audio input into channel two, bypassing the deck
+    pub fn set_input_two(&mut self, enabled: bool) -> Result<(), AudioInputError> {
+        self.input_two = None;
+
+        if enabled {
+            self.input_two = Some(AudioInput::new(
+                &mut self.audio_manager.lock().unwrap(),
+                &self.ch_two_input.lock().unwrap(),
+                dotenv::var("AUDIO_INPUT_DEVICE").ok().as_deref(),
+            )?);
+        }
+
+        Ok(())
+    }
+
+    pub fn is_mic_enabled(&self) -> bool {
+        self.mic.is_some()
+    }
+
+    /// Talk over the music with the input device named by the
+    /// `MIC_INPUT_DEVICE` environment variable, or the default input device
+    pub fn set_mic(&mut self, enabled: bool) -> Result<(), AudioInputError> {
+        self.mic = None;
+
+        if enabled {
+            self.mic = Some(AudioInput::new(
+                &mut self.audio_manager.lock().unwrap(),
+                &self.mic_track,
+                dotenv::var("MIC_INPUT_DEVICE").ok().as_deref(),
+            )?);
+        }
+
+        Ok(())
+    }
+
+    pub fn get_mic_record_level(&self) -> f64 {
+        self.mic_record_level
+    }
+
+    /// Level of the mic in the recording, independently of its level in the
+    /// room. 0.0 keeps the mic out of the recording
+    pub fn set_mic_record_level(&mut self, level: f64) {
+        self.mic_record_level = level;
+
+        self.mic_track
+            .set_route(&self.record_track, self.mic_record_level, Tween::default())
+            .unwrap();
+    }
+
+    pub fn input_device_name(&self) -> Option<&str> {
+        self.input_one
+            .as_ref()
+            .or(self.input_two.as_ref())
+            .map(|input| input.device_name())
+    }
+}
+
+impl Processable for Mixer {
+    fn process(&mut self, delta: f64) {
+        self.loudness.process(delta);
+        for slot in self.fx_one.iter_mut().chain(&mut self.fx_two) {
+            slot.process(delta);
+        }
+        self.limiter_one.process(delta);
+        self.limiter_two.process(delta);
+        self.sampler.process(delta);
+        self.metronome
+            .schedule(&mut self.audio_manager.lock().unwrap());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use kira::{
+        sound::static_sound::{StaticSoundData, StaticSoundSettings},
+        Frame,
+    };
+
+    use super::*;
+    use crate::audio_backend::testing::{render, SAMPLE_RATE};
+
+    /// Play a constant level into channel one
+    fn play_constant(mixer: &Mixer, level: f32) {
+        let sound = StaticSoundData {
+            sample_rate: SAMPLE_RATE,
+            frames: Arc::from(vec![Frame::from_mono(level); SAMPLE_RATE as usize]),
+            settings: StaticSoundSettings::new(),
+            slice: None,
+        }
+        .loop_region(..)
+        .output_destination(&*mixer.get_ch_one_track().lock().unwrap());
+
+        mixer
+            .get_audio_manager()
+            .lock()
+            .unwrap()
+            .play(sound)
+            .unwrap();
+    }
+
+    /// Output level once the parameter tweens are over
+    fn output(mixer: &Mixer) -> f32 {
+        render(&mut mixer.get_audio_manager().lock().unwrap(), 4800).left
+    }
+
+    #[test]
+    fn test_channel_routing() {
+        let mut mixer = Mixer::new();
+        play_constant(&mixer, 0.5);
+
+        // faders start closed
+        assert!(output(&mixer).abs() < 1e-4);
+
+        mixer.set_cue_mix_value(1.0);
+        mixer.set_ch_one_volume(1.0);
+        assert!((output(&mixer) - 0.5).abs() < 1e-3);
+
+        mixer.set_ch_one_volume(0.5);
+        assert!((output(&mixer) - 0.25).abs() < 1e-3);
+
+        // the cue is only heard through the cue mix
+        mixer.set_cue_one(true);
+        assert!((output(&mixer) - 0.25).abs() < 1e-3);
+        mixer.set_cue_mix_value(0.0);
+        assert!((output(&mixer) - 0.5).abs() < 1e-3);
+        mixer.set_cue_one(false);
+        assert!(output(&mixer).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_crossfader() {
+        assert_eq!(routing::crossfader_gains(CENTER_CROSSFADER), (1.0, 1.0));
+
+        let mut mixer = Mixer::new();
+        play_constant(&mixer, 0.5);
+        mixer.set_cue_mix_value(1.0);
+        mixer.set_ch_one_volume(1.0);
+        mixer.set_crossfader(0.75);
+        assert!((output(&mixer) - 0.25).abs() < 1e-3);
+        mixer.set_crossfader(1.0);
+        assert!(output(&mixer).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_cut() {
+        let mut mixer = Mixer::new();
+        play_constant(&mixer, 0.5);
+        mixer.set_cue_mix_value(1.0);
+        mixer.set_ch_one_volume(1.0);
+        mixer.set_cut_one(true);
+        assert!(output(&mixer).abs() < 1e-4);
+        mixer.set_cut_one(false);
+        assert!((output(&mixer) - 0.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_cue_modes() {
+        let mut mixer = Mixer::new();
+        play_constant(&mixer, 0.5);
+
+        // only the cue is heard
+        mixer.set_cue_mix_value(0.0);
+        mixer.set_ch_one_volume(0.5);
+        mixer.set_eq_low_one_gain(-24.0);
+        mixer.set_cue_one(true);
+        assert!((output(&mixer) - 0.5).abs() < 1e-3);
+
+        // the constant level is mostly cut by the low EQ
+        mixer.set_cue_one_mode(CueMode::AfterFader);
+        assert!(output(&mixer) < 0.1);
+
+        mixer.set_eq_low_one_gain(0.0);
+        assert!((output(&mixer) - 0.25).abs() < 1e-3);
+        mixer.set_ch_one_volume(1.0);
+        assert!((output(&mixer) - 0.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_duck() {
+        let mut mixer = Mixer::new();
+        play_constant(&mixer, 0.5);
+        mixer.set_cue_mix_value(1.0);
+        mixer.set_ch_one_volume(1.0);
+
+        mixer.set_duck(Some(-12.0), Duration::from_millis(10));
+        assert!((output(&mixer) - 0.5 * 10f32.powf(-12.0 / 20.0)).abs() < 1e-3);
+        // the cue mix set every frame keeps the master ducked
+        mixer.set_cue_mix_value(1.0);
+        assert!((output(&mixer) - 0.5 * 10f32.powf(-12.0 / 20.0)).abs() < 1e-3);
+
+        mixer.set_duck(None, Duration::from_millis(10));
+        assert!((output(&mixer) - 0.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_limiter() {
+        let mut mixer = Mixer::new();
+        play_constant(&mixer, 0.8);
+        mixer.set_cue_mix_value(1.0);
+        mixer.set_ch_one_volume(1.0);
+
+        // the boosted low band would go over full scale
+        mixer.set_eq_low_one_gain(6.0);
+        assert!(output(&mixer) < 1.0);
+        mixer.process(0.1);
+        assert!(mixer.limiter_one().is_clipping());
+        assert!(!mixer.limiter_two().is_clipping());
+
+        mixer.set_limiters_enabled(false);
+        assert!(output(&mixer) > 1.5);
+    }
+
+    #[test]
+    fn test_preview() {
+        let mut mixer = Mixer::new();
+        play_constant(&mixer, 0.5);
+        mixer.set_ch_one_volume(0.5);
+
+        // the channel leaves the master for the cue, before its fader
+        mixer.set_preview_one(true);
+        mixer.set_cue_mix_value(1.0);
+        assert!(output(&mixer).abs() < 1e-4);
+        mixer.set_cue_mix_value(0.0);
+        assert!((output(&mixer) - 0.5).abs() < 1e-3);
+
+        mixer.set_preview_one(false);
+        assert!(output(&mixer).abs() < 1e-4);
+        mixer.set_cue_mix_value(1.0);
+        assert!((output(&mixer) - 0.25).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_metronome() {
+        let mut mixer = Mixer::new();
+        let peak = |mixer: &Mixer, frames: usize| {
+            let manager = mixer.get_audio_manager();
+            let mut manager = manager.lock().unwrap();
+            (0..frames)
+                .map(|_| render(&mut manager, 1).left.abs())
+                .fold(0.0, f32::max)
+        };
+
+        mixer.metronome().set_bpm(120.0);
+        mixer.metronome().set_enabled(true);
+
+        // the click is heard through the cue
+        mixer.set_cue_mix_value(0.0);
+        mixer.process(0.0);
+        assert!(peak(&mixer, SAMPLE_RATE as usize * 6 / 10) > 0.1);
+
+        // but never on the master
+        mixer.set_cue_mix_value(1.0);
+        mixer.process(0.0);
+        assert!(peak(&mixer, SAMPLE_RATE as usize * 6 / 10) < 1e-4);
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut mixer = Mixer::new();
+        mixer.set_eq_low_one_gain(-24.0);
+        mixer.set_eq_high_two_gain(3.0);
+        mixer.set_ch_one_volume(0.7);
+        mixer.set_cue_mix_value(1.0);
+
+        mixer.reset_channel_two();
+        assert_eq!(mixer.get_eq_high_two_gain(), EQ_FLAT_GAIN);
+        assert_eq!(mixer.get_eq_low_one_gain(), -24.0);
+
+        mixer.reset();
+        assert_eq!(mixer.get_eq_low_one_gain(), EQ_FLAT_GAIN);
+        assert_eq!(mixer.get_cue_mix_value(), CENTER_CUE_MIX);
+        // the faders are part of the mix
+        assert_eq!(mixer.get_ch_one_volume(), 0.7);
     }
 }
@@ -0,0 +1,168 @@
+use crate::{mixer::Mixer, utils::lerp};
+
+/// Mixer controls taken into a morph snapshot
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MixerState {
+    volumes: [f64; 2],
+    eq_low: [f64; 2],
+    eq_high: [f64; 2],
+}
+
+impl MixerState {
+    pub fn capture(mixer: &Mixer) -> Self {
+        Self {
+            volumes: [mixer.get_ch_one_volume(), mixer.get_ch_two_volume()],
+            eq_low: [mixer.get_eq_low_one_gain(), mixer.get_eq_low_two_gain()],
+            eq_high: [mixer.get_eq_high_one_gain(), mixer.get_eq_high_two_gain()],
+        }
+    }
+
+    pub fn apply(&self, mixer: &mut Mixer) {
+        mixer.set_ch_one_volume(self.volumes[0]);
+        mixer.set_ch_two_volume(self.volumes[1]);
+        mixer.set_eq_low_one_gain(self.eq_low[0]);
+        mixer.set_eq_low_two_gain(self.eq_low[1]);
+        mixer.set_eq_high_one_gain(self.eq_high[0]);
+        mixer.set_eq_high_two_gain(self.eq_high[1]);
+    }
+
+    /// State `weight` of the way from `self` to `other`
+    fn blend(&self, other: &Self, weight: f64) -> Self {
+        let blend = |from: [f64; 2], to: [f64; 2]| {
+            [lerp(from[0], to[0], weight), lerp(from[1], to[1], weight)]
+        };
+
+        Self {
+            volumes: blend(self.volumes, other.volumes),
+            eq_low: blend(self.eq_low, other.eq_low),
+            eq_high: blend(self.eq_high, other.eq_high),
+        }
+    }
+}
+
+/// Automatic move of the morph position
+#[derive(Debug, Clone, Copy)]
+struct Glide {
+    from: f64,
+    to: f64,
+    elapsed: f64,
+    duration: f64,
+}
+
+/// Two mixer snapshots, A and B, and a position blending the mixer between
+/// them, either set directly or glided over time
+#[derive(Debug, Default)]
+pub struct Morph {
+    a: Option<MixerState>,
+    b: Option<MixerState>,
+    /// 0.0 is snapshot A and 1.0 snapshot B
+    position: f64,
+    glide: Option<Glide>,
+}
+
+impl Morph {
+    /// Store the current mixer as snapshot A, which the position now stands on
+    pub fn store_a(&mut self, mixer: &Mixer) {
+        self.a = Some(MixerState::capture(mixer));
+        self.position = 0.0;
+        self.glide = None;
+    }
+
+    /// Store the current mixer as snapshot B, which the position now stands on
+    pub fn store_b(&mut self, mixer: &Mixer) {
+        self.b = Some(MixerState::capture(mixer));
+        self.position = 1.0;
+        self.glide = None;
+    }
+
+    pub fn has_a(&self) -> bool {
+        self.a.is_some()
+    }
+
+    pub fn has_b(&self) -> bool {
+        self.b.is_some()
+    }
+
+    pub fn position(&self) -> f64 {
+        self.position
+    }
+
+    pub fn is_gliding(&self) -> bool {
+        self.glide.is_some()
+    }
+
+    /// Blend the mixer at `position` between the snapshots, stopping any
+    /// glide. Does nothing until both snapshots are stored
+    pub fn set_position(&mut self, position: f64, mixer: &mut Mixer) {
+        self.glide = None;
+        self.move_to(position.clamp(0.0, 1.0), mixer);
+    }
+
+    /// Move the position to `to` over `duration` seconds
+    pub fn glide_to(&mut self, to: f64, duration: f64, mixer: &mut Mixer) {
+        match duration > 0.0 {
+            true => {
+                self.glide = Some(Glide {
+                    from: self.position,
+                    to,
+                    elapsed: 0.0,
+                    duration,
+                })
+            }
+            false => self.set_position(to, mixer),
+        }
+    }
+
+    pub fn process(&mut self, delta: f64, mixer: &mut Mixer) {
+        let Some(mut glide) = self.glide else {
+            return;
+        };
+
+        glide.elapsed += delta;
+        let progress = (glide.elapsed / glide.duration).min(1.0);
+        self.glide = match progress < 1.0 {
+            true => Some(glide),
+            false => None,
+        };
+        self.move_to(lerp(glide.from, glide.to, progress), mixer);
+    }
+
+    fn move_to(&mut self, position: f64, mixer: &mut Mixer) {
+        let (Some(a), Some(b)) = (self.a, self.b) else {
+            return;
+        };
+
+        self.position = position;
+        a.blend(&b, position).apply(mixer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_morph() {
+        let mut mixer = Mixer::new();
+        let mut morph = Morph::default();
+
+        mixer.set_ch_one_volume(1.0);
+        mixer.set_eq_low_two_gain(-24.0);
+        morph.store_a(&mixer);
+        mixer.set_ch_one_volume(0.0);
+        mixer.set_eq_low_two_gain(0.0);
+        morph.store_b(&mixer);
+
+        morph.set_position(0.25, &mut mixer);
+        assert_eq!(mixer.get_ch_one_volume(), 0.75);
+        assert_eq!(mixer.get_eq_low_two_gain(), -18.0);
+
+        morph.glide_to(0.0, 1.0, &mut mixer);
+        morph.process(0.5, &mut mixer);
+        assert!(morph.is_gliding());
+        assert_eq!(morph.position(), 0.125);
+        morph.process(0.6, &mut mixer);
+        assert!(!morph.is_gliding());
+        assert_eq!(mixer.get_ch_one_volume(), 1.0);
+    }
+}
@@ -0,0 +1,173 @@
+use std::f32::consts::TAU;
+
+use kira::Frame;
+use serde::{Deserialize, Serialize};
+
+use crate::spectrogram::fft;
+
+/// Samples of each spectrum, a power of two, fine enough to tell the
+/// semitones of the lowest analyzed octave apart
+const FFT_SIZE: usize = 8192;
+/// Most spectra taken over a track, spread evenly
+const MAX_SPECTRA: usize = 256;
+/// Range of the frequencies counted into the pitch classes, from the bass
+/// to the top of the leads, above which harmonics blur the chroma
+const MIN_FREQUENCY: f32 = 80.0;
+const MAX_FREQUENCY: f32 = 2_000.0;
+/// Weights of the pitch classes of major and minor keys from their tonic,
+/// after Krumhansl and Kessler
+const MAJOR_PROFILE: [f64; 12] = [
+    6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88,
+];
+const MINOR_PROFILE: [f64; 12] = [
+    6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17,
+];
+
+/// Musical key of a track
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Key {
+    /// pitch class of the tonic, from 0 for C to 11 for B
+    pub tonic: u8,
+    pub minor: bool,
+}
+
+impl Key {
+    /// Key in Camelot notation, e.g. `8A` for A minor and `8B` for C major,
+    /// neighbouring numbers being a fifth apart
+    pub fn camelot(&self) -> String {
+        // minor keys share the number of their relative major
+        let major_tonic = match self.minor {
+            true => (self.tonic + 3) % 12,
+            false => self.tonic,
+        };
+        let number = (7 + 7 * major_tonic as usize) % 12 + 1;
+        format!("{}{}", number, if self.minor { 'A' } else { 'B' })
+    }
+}
+
+/// Find the key of a track from its chroma, the energy of each pitch class
+/// summed over the track, matched against the profiles of the 24 keys
+pub fn detect(frames: &[Frame], sample_rate: u32) -> Option<Key> {
+    if frames.len() < FFT_SIZE {
+        return None;
+    }
+
+    let window: Vec<f32> = (0..FFT_SIZE)
+        .map(|i| 0.5 - 0.5 * (TAU * i as f32 / FFT_SIZE as f32).cos())
+        .collect();
+    let twiddles: Vec<(f32, f32)> = (0..FFT_SIZE / 2)
+        .map(|k| {
+            let (sin, cos) = (-TAU * k as f32 / FFT_SIZE as f32).sin_cos();
+            (cos, sin)
+        })
+        .collect();
+    let bin_frequency = sample_rate as f32 / FFT_SIZE as f32;
+    let pitch_classes: Vec<(usize, usize)> = (1..FFT_SIZE / 2)
+        .filter(|bin| (MIN_FREQUENCY..=MAX_FREQUENCY).contains(&(*bin as f32 * bin_frequency)))
+        .map(|bin| {
+            // semitones from A4, A being pitch class 9
+            let semitones = (12.0 * (bin as f32 * bin_frequency / 440.0).log2()).round();
+            (bin, (semitones as i32 + 9).rem_euclid(12) as usize)
+        })
+        .collect();
+
+    let spectra = ((frames.len() - FFT_SIZE) / FFT_SIZE + 1).min(MAX_SPECTRA);
+    let mut chroma = [0.0f64; 12];
+    let (mut re, mut im) = (vec![0.0; FFT_SIZE], vec![0.0; FFT_SIZE]);
+    for spectrum in 0..spectra {
+        let start = spectrum * (frames.len() - FFT_SIZE) / (spectra - 1).max(1);
+        for (i, value) in re.iter_mut().enumerate() {
+            let frame = frames[start + i];
+            *value = (frame.left + frame.right) / 2.0 * window[i];
+        }
+        im.fill(0.0);
+        fft(&mut re, &mut im, &twiddles);
+
+        for (bin, pitch_class) in pitch_classes.iter() {
+            chroma[*pitch_class] += (re[*bin] * re[*bin] + im[*bin] * im[*bin]).sqrt() as f64;
+        }
+    }
+    if chroma.iter().all(|energy| *energy == 0.0) {
+        return None;
+    }
+
+    (0..12u8)
+        .flat_map(|tonic| [false, true].map(|minor| Key { tonic, minor }))
+        .map(|key| {
+            let profile = match key.minor {
+                true => &MINOR_PROFILE,
+                false => &MAJOR_PROFILE,
+            };
+            let rotated: Vec<f64> = (0..12)
+                .map(|pitch_class| profile[(pitch_class + 12 - key.tonic as usize) % 12])
+                .collect();
+            (key, correlation(&chroma, &rotated))
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(key, _)| key)
+}
+
+/// Pearson correlation of two sequences of the same length
+fn correlation(a: &[f64], b: &[f64]) -> f64 {
+    let mean = |values: &[f64]| values.iter().sum::<f64>() / values.len() as f64;
+    let (mean_a, mean_b) = (mean(a), mean(b));
+    let (covariance, variance_a, variance_b) = a.iter().zip(b).fold(
+        (0.0, 0.0, 0.0),
+        |(covariance, variance_a, variance_b), (a, b)| {
+            let (a, b) = (a - mean_a, b - mean_b);
+            (covariance + a * b, variance_a + a * a, variance_b + b * b)
+        },
+    );
+
+    covariance / (variance_a * variance_b).sqrt().max(f64::EPSILON)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_camelot() {
+        let camelot = |tonic, minor| Key { tonic, minor }.camelot();
+        assert_eq!(camelot(9, true), "8A");
+        assert_eq!(camelot(0, false), "8B");
+        assert_eq!(camelot(7, false), "9B");
+        assert_eq!(camelot(5, false), "7B");
+        assert_eq!(camelot(8, true), "1A");
+        assert_eq!(camelot(11, false), "1B");
+    }
+
+    #[test]
+    fn test_detect() {
+        let sample_rate = 8_000;
+        // a chord of the tonic, third and fifth over the bass on the tonic
+        let chord = |frequencies: &[f32]| -> Vec<Frame> {
+            (0..10 * sample_rate)
+                .map(|i| {
+                    let time = i as f32 / sample_rate as f32;
+                    let value: f32 = frequencies
+                        .iter()
+                        .map(|frequency| (TAU * frequency * time).sin())
+                        .sum();
+                    Frame::from_mono(0.2 * value)
+                })
+                .collect()
+        };
+
+        let a_minor = chord(&[110.0, 220.0, 261.63, 329.63]);
+        assert_eq!(
+            detect(&a_minor, sample_rate),
+            Some(Key {
+                tonic: 9,
+                minor: true
+            })
+        );
+        let g_major = chord(&[98.0, 196.0, 246.94, 293.66]);
+        assert_eq!(detect(&g_major, sample_rate).unwrap().camelot(), "9B");
+
+        assert_eq!(
+            detect(&vec![Frame::ZERO; 10 * sample_rate as usize], sample_rate),
+            None
+        );
+    }
+}
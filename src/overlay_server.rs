@@ -0,0 +1,431 @@
+use std::{
+    fs,
+    io::{BufRead, BufReader, Write},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+    path::Path,
+    sync::{
+        mpsc::{self, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use serde::Serialize;
+
+use crate::{
+    app::AppData,
+    controller::{cover_path, TurntableFocus},
+    turntable::Turntable,
+    utils::to_base64,
+};
+
+/// Longest wait on a browser source before dropping it
+const CLIENT_TIMEOUT: Duration = Duration::from_millis(200);
+/// Appended to the key of a WebSocket handshake before hashing it
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Page shown by the browser source, filled from the states pushed on `/ws`
+const OVERLAY_PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<style>
+body { margin: 0; font-family: sans-serif; color: white; background: transparent; }
+.deck { display: flex; align-items: center; gap: 12px; padding: 8px; opacity: 0.5; }
+.deck.on-air { opacity: 1; }
+.deck img { width: 64px; height: 64px; object-fit: cover; border-radius: 50%; }
+.title { font-size: 20px; font-weight: bold; text-shadow: 0 0 4px black; }
+.info { font-size: 14px; text-shadow: 0 0 4px black; }
+</style>
+</head>
+<body>
+<div id="decks"></div>
+<script>
+const time = (s) => s == null ? "" : Math.floor(s / 60) + ":" + String(s % 60).padStart(2, "0");
+const escape = (text) => text.replace(/[&<>"]/g, (c) => ({ "&": "&amp;", "<": "&lt;", ">": "&gt;", '"': "&quot;" })[c]);
+function render(state) {
+  document.getElementById("decks").innerHTML = state.decks.map((deck, i) => deck.title == null ? "" :
+    `<div class="deck${deck.on_air ? " on-air" : ""}">
+      <img src="/cover/${i + 1}?${encodeURIComponent(deck.title)}" onerror="this.style.visibility='hidden'">
+      <div><div class="title">${escape(deck.title)}</div>
+      <div class="info">${time(deck.elapsed)} / -${time(deck.remaining)}${deck.bpm ? " · " + deck.bpm.toFixed(1) + " BPM" : ""}</div></div>
+    </div>`).join("");
+}
+function connect() {
+  const socket = new WebSocket(`ws://${location.host}/ws`);
+  socket.onmessage = (message) => render(JSON.parse(message.data));
+  socket.onclose = () => setTimeout(connect, 1000);
+}
+connect();
+</script>
+</body>
+</html>
+"#;
+
+/// What the overlay shows, changing about once a second while playing
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct OverlayState {
+    pub decks: [OverlayDeck; 2],
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct OverlayDeck {
+    /// name of the loaded track
+    pub title: Option<String>,
+    pub playing: bool,
+    /// the playing deck heard the most on the master
+    pub on_air: bool,
+    /// whole seconds played
+    pub elapsed: Option<u64>,
+    /// whole seconds left
+    pub remaining: Option<u64>,
+    /// tempo at the current pitch, to a tenth of a BPM
+    pub bpm: Option<f64>,
+    /// image shown on the platter, served on `/cover/1` or `/cover/2` and
+    /// kept out of the page
+    #[serde(skip)]
+    pub cover: Option<String>,
+}
+
+impl OverlayState {
+    pub fn capture(app_data: &AppData) -> Self {
+        let on_air = app_data.master_deck();
+        let deck = |deck: TurntableFocus, turntable: &Turntable| {
+            let path = turntable.currently_loaded();
+            let position = turntable.position();
+            OverlayDeck {
+                title: path.as_ref().map(|path| {
+                    Path::new(path)
+                        .file_stem()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_default()
+                }),
+                playing: turntable.is_playing(),
+                on_air: on_air == Some(deck),
+                elapsed: position.map(|position| position as u64),
+                remaining: position
+                    .zip(turntable.duration())
+                    .map(|(position, duration)| (duration - position).max(0.0) as u64),
+                bpm: app_data
+                    .deck_bpm(deck)
+                    .map(|bpm| (bpm * 10.0).round() / 10.0),
+                cover: path.map(|path| cover_path(app_data, Path::new(&path))),
+            }
+        };
+        Self {
+            decks: [
+                deck(TurntableFocus::One, &app_data.turntable_one),
+                deck(TurntableFocus::Two, &app_data.turntable_two),
+            ],
+        }
+    }
+}
+
+#[derive(Default)]
+struct Shared {
+    /// last state published, as sent to the browser sources
+    json: String,
+    covers: [Option<String>; 2],
+    /// browser sources connected on `/ws`
+    clients: Vec<TcpStream>,
+}
+
+/// Serves on a local port a page for the browser source of streaming
+/// software, showing the tracks of the decks with their covers, timers and
+/// tempo, updated over a WebSocket
+pub struct OverlayServer {
+    shared: Arc<Mutex<Shared>>,
+    /// WebSocket frames written to the browser sources on their own thread,
+    /// so that a stalled one never holds up the UI
+    frames: Sender<Vec<u8>>,
+    state: OverlayState,
+}
+
+impl OverlayServer {
+    /// Listen on `port` of the loopback interface
+    pub fn new(port: u16) -> Option<Self> {
+        let listener = match TcpListener::bind((Ipv4Addr::LOCALHOST, port)) {
+            Ok(listener) => listener,
+            Err(e) => {
+                log::error!("Cannot serve the overlay on port {}: {}", port, e);
+                return None;
+            }
+        };
+        log::info!("Serving the overlay on http://localhost:{}", port);
+
+        let shared = Arc::new(Mutex::new(Shared::default()));
+        let server_shared = shared.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().filter_map(|stream| stream.ok()) {
+                handle(stream, &server_shared);
+            }
+        });
+
+        let (frames, pending) = mpsc::channel::<Vec<u8>>();
+        let broadcast_shared = shared.clone();
+        thread::spawn(move || {
+            while let Ok(frame) = pending.recv() {
+                // a state queued behind a stalled browser source is outdated
+                let frame = pending.try_iter().last().unwrap_or(frame);
+                broadcast(&frame, &broadcast_shared);
+            }
+        });
+
+        Some(Self {
+            shared,
+            frames,
+            state: OverlayState::default(),
+        })
+    }
+
+    /// Push `state` to the browser sources when it changed
+    pub fn publish(&mut self, state: OverlayState) {
+        if state == self.state {
+            return;
+        }
+        let json = match serde_json::to_string(&state) {
+            Ok(json) => json,
+            Err(e) => {
+                log::error!("Cannot serialize the overlay state: {}", e);
+                return;
+            }
+        };
+
+        let frame = websocket_frame(&json);
+        {
+            let mut shared = self.shared.lock().unwrap();
+            shared.json = json;
+            shared.covers = state.decks.clone().map(|deck| deck.cover);
+        }
+        let _ = self.frames.send(frame);
+        self.state = state;
+    }
+}
+
+/// Write `frame` to the browser sources, dropping those that fail or time
+/// out. The lock is not held while writing, so that pages and covers are
+/// still served meanwhile
+fn broadcast(frame: &[u8], shared: &Mutex<Shared>) {
+    let mut clients = std::mem::take(&mut shared.lock().unwrap().clients);
+    clients.retain_mut(|client| client.write_all(frame).is_ok());
+    shared.lock().unwrap().clients.extend(clients);
+}
+
+/// Answer a request for the page, a cover or the WebSocket
+fn handle(mut stream: TcpStream, shared: &Mutex<Shared>) {
+    let _ = stream.set_read_timeout(Some(CLIENT_TIMEOUT));
+    let _ = stream.set_write_timeout(Some(CLIENT_TIMEOUT));
+
+    let mut request_line = String::new();
+    let mut websocket_key = None;
+    {
+        let mut reader = BufReader::new(&stream);
+        if reader.read_line(&mut request_line).is_err() {
+            return;
+        }
+        let mut header = String::new();
+        while reader.read_line(&mut header).is_ok_and(|read| read > 2) {
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("sec-websocket-key") {
+                    websocket_key = Some(value.trim().to_string());
+                }
+            }
+            header.clear();
+        }
+    }
+
+    let path = request_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|path| path.split('?').next())
+        .unwrap_or_default();
+    let response = match (path, websocket_key) {
+        ("/", _) => respond(
+            &mut stream,
+            "text/html; charset=utf-8",
+            OVERLAY_PAGE.as_bytes(),
+        ),
+        ("/state", _) => {
+            let json = shared.lock().unwrap().json.clone();
+            respond(&mut stream, "application/json", json.as_bytes())
+        }
+        ("/cover/1" | "/cover/2", _) => {
+            let index = if path == "/cover/1" { 0 } else { 1 };
+            let cover = shared.lock().unwrap().covers[index].clone();
+            match cover.and_then(|cover| Some((fs::read(&cover).ok()?, cover))) {
+                Some((image, cover)) => respond(&mut stream, image_type(&cover), &image),
+                None => not_found(&mut stream),
+            }
+        }
+        ("/ws", Some(key)) => {
+            let accept = to_base64(&sha1(format!("{}{}", key, WEBSOCKET_GUID).as_bytes()));
+            let mut shared = shared.lock().unwrap();
+            let result = stream
+                .write_all(
+                    format!(
+                        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\
+                         Connection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+                        accept
+                    )
+                    .as_bytes(),
+                )
+                .and_then(|_| stream.write_all(&websocket_frame(&shared.json)));
+            if result.is_ok() {
+                shared.clients.push(stream);
+            }
+            result
+        }
+        _ => not_found(&mut stream),
+    };
+    if let Err(e) = response {
+        log::warn!("Cannot answer an overlay request: {}", e);
+    }
+}
+
+fn respond(stream: &mut TcpStream, content_type: &str, body: &[u8]) -> std::io::Result<()> {
+    stream.write_all(
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\n\
+             Cache-Control: no-store\r\n\r\n",
+            content_type,
+            body.len()
+        )
+        .as_bytes(),
+    )?;
+    stream.write_all(body)
+}
+
+fn not_found(stream: &mut TcpStream) -> std::io::Result<()> {
+    stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n")
+}
+
+fn image_type(path: &str) -> &'static str {
+    match Path::new(path)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .as_deref()
+    {
+        Some("png") => "image/png",
+        Some("webp") => "image/webp",
+        Some("gif") => "image/gif",
+        _ => "image/jpeg",
+    }
+}
+
+/// Unmasked WebSocket frame carrying `text` whole
+fn websocket_frame(text: &str) -> Vec<u8> {
+    let mut frame = vec![0x81];
+    let length = text.len();
+    if length < 126 {
+        frame.push(length as u8);
+    } else if length <= u16::MAX as usize {
+        frame.push(126);
+        frame.extend((length as u16).to_be_bytes());
+    } else {
+        frame.push(127);
+        frame.extend((length as u64).to_be_bytes());
+    }
+    frame.extend(text.as_bytes());
+    frame
+}
+
+/// SHA-1 digest, only needed by the WebSocket handshake
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend((data.len() as u64 * 8).to_be_bytes());
+
+    for chunk in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in chunk.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (h, value) in h.iter_mut().zip([a, b, c, d, e]) {
+            *h = h.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0; 20];
+    for (bytes, value) in digest.chunks_mut(4).zip(h) {
+        bytes.copy_from_slice(&value.to_be_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_websocket_accept() {
+        // example handshake of RFC 6455
+        let key = format!("{}{}", "dGhlIHNhbXBsZSBub25jZQ==", WEBSOCKET_GUID);
+
+        assert_eq!(
+            to_base64(&sha1(key.as_bytes())),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    #[test]
+    fn test_sha1() {
+        let hex = |data: &[u8]| {
+            sha1(data)
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<String>()
+        };
+
+        // vectors of FIPS 180, the second spanning two blocks
+        assert_eq!(hex(b""), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+        assert_eq!(hex(b"abc"), "a9993e364706816aba3e25717850c26c9cd0d89d");
+        assert_eq!(
+            hex(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+            "84983e441c3bd26ebaae4aa1f95129e5e54670f1"
+        );
+        assert_eq!(
+            hex(&[b'a'; 1_000_000]),
+            "34aa973cd4c4daa4f61eeb2bdbad27316534016f"
+        );
+    }
+
+    #[test]
+    fn test_websocket_frame() {
+        assert_eq!(websocket_frame("hi"), vec![0x81, 2, b'h', b'i']);
+        assert_eq!(&websocket_frame(&"a".repeat(300))[..4], &[0x81, 126, 1, 44]);
+        assert_eq!(
+            &websocket_frame(&"a".repeat(70_000))[..10],
+            &[0x81, 127, 0, 0, 0, 0, 0, 1, 17, 112]
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use std::{io, path::Path, sync::Arc};
+
+use kira::Frame;
+use symphonia::core::{
+    audio::SampleBuffer, codecs::DecoderOptions, errors::Error as SymphoniaError,
+};
+
+use crate::duration;
+
+/// Columns over the whole track, enough for a wide window
+const COLUMNS: usize = 1024;
+/// Frames summed together while reading a track packet by packet, before its
+/// length and so its columns are known
+const BLOCK_FRAMES: usize = 4096;
+
+/// Levels of a whole track drawn under its deck, to see its breakdowns and
+/// drops ahead
+pub struct Overview {
+    /// from 0 to 1, the peak and the RMS level of each column
+    levels: Arc<[(f32, f32)]>,
+    pub duration: f64,
+}
+
+impl Overview {
+    pub fn compute(frames: &[Frame], sample_rate: u32) -> Self {
+        let levels = (0..COLUMNS)
+            .map(|column| {
+                let start = column * frames.len() / COLUMNS;
+                let end = ((column + 1) * frames.len() / COLUMNS).max(start + 1);
+                let frames = &frames[start.min(frames.len())..end.min(frames.len())];
+                if frames.is_empty() {
+                    return (0.0, 0.0);
+                }
+
+                let (peak, sum) = frames.iter().fold((0.0f32, 0.0f32), |(peak, sum), frame| {
+                    let value = (frame.left + frame.right) / 2.0;
+                    (peak.max(value.abs()), sum + value * value)
+                });
+                let rms = (sum / frames.len() as f32).sqrt();
+                (peak.min(1.0), rms.min(1.0))
+            })
+            .collect();
+
+        Self {
+            levels,
+            duration: frames.len() as f64 / sample_rate as f64,
+        }
+    }
+
+    /// Overview of the track at `path`, decoded packet by packet so that a
+    /// long track, streamed rather than decoded into memory by the decks, is
+    /// never whole in memory either
+    pub fn read(path: &Path) -> Result<Self, SymphoniaError> {
+        let (mut format, track) = duration::open(path)?;
+        let sample_rate = track
+            .codec_params
+            .sample_rate
+            .ok_or(SymphoniaError::Unsupported("unknown sample rate"))?;
+        let mut decoder = symphonia::default::get_codecs()
+            .make(&track.codec_params, &DecoderOptions::default())?;
+
+        // peak, sum of the squares and frames of each block
+        let mut blocks: Vec<(f32, f64, usize)> = Vec::new();
+        let mut samples: Option<SampleBuffer<f32>> = None;
+        loop {
+            let packet = match format.next_packet() {
+                Ok(packet) if packet.track_id() == track.id => packet,
+                Ok(_) => continue,
+                Err(SymphoniaError::IoError(e)) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                    break
+                }
+                Err(e) => return Err(e),
+            };
+            let decoded = match decoder.decode(&packet) {
+                Ok(decoded) => decoded,
+                // a corrupt packet is skipped, as when playing the track
+                Err(SymphoniaError::DecodeError(e)) => {
+                    log::warn!("Cannot decode a packet of {}: {}", path.display(), e);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+
+            let spec = *decoded.spec();
+            let channels = spec.channels.count();
+            let samples = match &mut samples {
+                Some(samples) if samples.capacity() >= decoded.capacity() * channels => samples,
+                samples => samples.insert(SampleBuffer::new(decoded.capacity() as u64, spec)),
+            };
+            samples.copy_interleaved_ref(decoded);
+            for frame in samples.samples().chunks(channels) {
+                // the left and right channels, or twice the only one
+                let value = (frame[0] + frame[frame.len().min(2) - 1]) / 2.0;
+                match blocks.last_mut() {
+                    Some((peak, sum, frames)) if *frames < BLOCK_FRAMES => {
+                        *peak = peak.max(value.abs());
+                        *sum += (value * value) as f64;
+                        *frames += 1;
+                    }
+                    _ => blocks.push((value.abs(), (value * value) as f64, 1)),
+                }
+            }
+        }
+
+        let levels = (0..COLUMNS)
+            .map(|column| {
+                let start = column * blocks.len() / COLUMNS;
+                let end = ((column + 1) * blocks.len() / COLUMNS).max(start + 1);
+                let blocks = &blocks[start.min(blocks.len())..end.min(blocks.len())];
+                let (peak, sum, frames) =
+                    blocks
+                        .iter()
+                        .fold((0.0f32, 0.0, 0), |(peak, sum, frames), block| {
+                            (peak.max(block.0), sum + block.1, frames + block.2)
+                        });
+                if frames == 0 {
+                    return (0.0, 0.0);
+                }
+
+                let rms = (sum / frames as f64).sqrt() as f32;
+                (peak.min(1.0), rms.min(1.0))
+            })
+            .collect();
+        let frames: usize = blocks.iter().map(|block| block.2).sum();
+
+        Ok(Self {
+            levels,
+            duration: frames as f64 / sample_rate as f64,
+        })
+    }
+
+    /// Peak and RMS levels of the columns, from the start of the track
+    pub fn levels(&self) -> &Arc<[(f32, f32)]> {
+        &self.levels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio_backend::testing::constant_wav;
+
+    #[test]
+    fn test_compute() {
+        let sample_rate = 8_000;
+        // silent first half, then a square wave at 0.5
+        let frames: Vec<Frame> = (0..4 * sample_rate)
+            .map(|i| match (i < 2 * sample_rate, i % 2 == 0) {
+                (true, _) => Frame::ZERO,
+                (false, true) => Frame::from_mono(0.5),
+                (false, false) => Frame::from_mono(-0.5),
+            })
+            .collect();
+
+        let overview = Overview::compute(&frames, sample_rate);
+        assert_eq!(overview.duration, 4.0);
+        assert_eq!(overview.levels().len(), COLUMNS);
+        assert_eq!(overview.levels()[COLUMNS / 4], (0.0, 0.0));
+        assert_eq!(overview.levels()[COLUMNS * 3 / 4], (0.5, 0.5));
+    }
+
+    #[test]
+    fn test_read() {
+        let path = constant_wav("overview", 3.0, 0.5);
+        let overview = Overview::read(&path).unwrap();
+        assert!((overview.duration - 3.0).abs() < 1e-3);
+        assert_eq!(overview.levels().len(), COLUMNS);
+        assert_eq!(overview.levels()[COLUMNS / 2], (0.5, 0.5));
+
+        let _ = std::fs::remove_file(path);
+    }
+}
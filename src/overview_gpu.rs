@@ -0,0 +1,301 @@
+use std::sync::Arc;
+
+use egui::Color32;
+use egui_wgpu::{CallbackResources, CallbackTrait, ScreenDescriptor};
+use wgpu::util::DeviceExt;
+
+use crate::controller::TurntableFocus;
+
+/// Position in normalized device coordinates and premultiplied sRGBA color
+const VERTEX_SIZE: usize = 2 * 4 + 4;
+
+/// Draws the levels of an overview straight from the vertices, the whole
+/// rect being the -1 to 1 range of both axes
+const SHADER: &str = r#"
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+};
+
+@vertex
+fn vs_main(@location(0) position: vec2<f32>, @location(1) color: vec4<f32>) -> VertexOutput {
+    var out: VertexOutput;
+    out.position = vec4<f32>(position, 0.0, 1.0);
+    out.color = color;
+    return out;
+}
+
+fn linear_from_gamma_rgb(srgb: vec3<f32>) -> vec3<f32> {
+    let cutoff = srgb < vec3<f32>(0.04045);
+    let lower = srgb / vec3<f32>(12.92);
+    let higher = pow((srgb + vec3<f32>(0.055)) / vec3<f32>(1.055), vec3<f32>(2.4));
+    return select(higher, lower, cutoff);
+}
+
+@fragment
+fn fs_linear_framebuffer(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(linear_from_gamma_rgb(in.color.rgb), in.color.a);
+}
+
+@fragment
+fn fs_gamma_framebuffer(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color;
+}
+"#;
+
+/// What the geometry of an overview was built from, built again when any of
+/// it changes
+#[derive(Debug, Clone, PartialEq)]
+struct Key {
+    path: String,
+    /// width of the overview in physical pixels, changing with the window
+    /// and the zoom of the interface
+    width: u32,
+    colors: [Color32; 2],
+}
+
+/// Vertices of the overview of a deck, kept on the GPU between frames
+struct Geometry {
+    key: Key,
+    buffer: wgpu::Buffer,
+    vertices: u32,
+}
+
+/// Pipeline and cached geometry of the overviews, kept with the resources
+/// of the egui renderer
+pub struct OverviewResources {
+    pipeline: wgpu::RenderPipeline,
+    decks: [Option<Geometry>; 2],
+}
+
+impl OverviewResources {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, samples: u32) -> Self {
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Overview shader"),
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Overview pipeline layout"),
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+
+        // blended as egui blends its own shapes
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Overview pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &module,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: VERTEX_SIZE as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Unorm8x4],
+                }],
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: samples,
+                ..Default::default()
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &module,
+                entry_point: match format.is_srgb() {
+                    true => "fs_linear_framebuffer",
+                    false => "fs_gamma_framebuffer",
+                },
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState {
+                        color: wgpu::BlendComponent {
+                            src_factor: wgpu::BlendFactor::One,
+                            dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                            operation: wgpu::BlendOperation::Add,
+                        },
+                        alpha: wgpu::BlendComponent {
+                            src_factor: wgpu::BlendFactor::OneMinusDstAlpha,
+                            dst_factor: wgpu::BlendFactor::One,
+                            operation: wgpu::BlendOperation::Add,
+                        },
+                    }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        });
+
+        Self {
+            pipeline,
+            decks: [None, None],
+        }
+    }
+}
+
+/// Paints the levels of the overview of a deck from its cached geometry,
+/// only tessellated again when the track, its color or the width of the
+/// overview changes. The bars and the playhead are painted over it by egui
+pub struct OverviewCallback {
+    deck: TurntableFocus,
+    key: Key,
+    levels: Arc<[(f32, f32)]>,
+}
+
+impl OverviewCallback {
+    /// `colors` of the peak and of the RMS level
+    pub fn new(
+        deck: TurntableFocus,
+        path: String,
+        width: u32,
+        colors: [Color32; 2],
+        levels: Arc<[(f32, f32)]>,
+    ) -> Self {
+        Self {
+            deck,
+            key: Key {
+                path,
+                width,
+                colors,
+            },
+            levels,
+        }
+    }
+}
+
+impl CallbackTrait for OverviewCallback {
+    fn prepare(
+        &self,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+        _screen_descriptor: &ScreenDescriptor,
+        _egui_encoder: &mut wgpu::CommandEncoder,
+        callback_resources: &mut CallbackResources,
+    ) -> Vec<wgpu::CommandBuffer> {
+        let Some(resources) = callback_resources.get_mut::<OverviewResources>() else {
+            return Vec::new();
+        };
+        let geometry = &mut resources.decks[index(self.deck)];
+        if geometry
+            .as_ref()
+            .is_some_and(|geometry| geometry.key == self.key)
+        {
+            return Vec::new();
+        }
+
+        let vertices = vertices(&self.levels, self.key.width, self.key.colors);
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Overview vertices"),
+            contents: &vertices,
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        *geometry = Some(Geometry {
+            key: self.key.clone(),
+            buffer,
+            vertices: (vertices.len() / VERTEX_SIZE) as u32,
+        });
+        Vec::new()
+    }
+
+    fn paint<'a>(
+        &'a self,
+        _info: egui::PaintCallbackInfo,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        callback_resources: &'a CallbackResources,
+    ) {
+        let Some(resources) = callback_resources.get::<OverviewResources>() else {
+            return;
+        };
+        let Some(geometry) = resources.decks[index(self.deck)]
+            .as_ref()
+            .filter(|geometry| geometry.vertices > 0)
+        else {
+            return;
+        };
+
+        render_pass.set_pipeline(&resources.pipeline);
+        render_pass.set_vertex_buffer(0, geometry.buffer.slice(..));
+        render_pass.draw(0..geometry.vertices, 0..1);
+    }
+}
+
+/// Two triangles per level of each column, the peak under the RMS level. The
+/// levels are merged down to one column per pixel on narrow overviews
+fn vertices(levels: &[(f32, f32)], width: u32, colors: [Color32; 2]) -> Vec<u8> {
+    let columns = levels.len().min(width.max(1) as usize);
+    let mut vertices = Vec::with_capacity(columns * 2 * 6 * VERTEX_SIZE);
+
+    for column in 0..columns {
+        let start = column * levels.len() / columns;
+        let end = ((column + 1) * levels.len() / columns).max(start + 1);
+        let merged = &levels[start..end.min(levels.len())];
+        let peak = merged.iter().fold(0.0f32, |peak, level| peak.max(level.0));
+        let rms = (merged.iter().map(|level| level.1 * level.1).sum::<f32>() / merged.len() as f32)
+            .sqrt();
+
+        let left = column as f32 / columns as f32 * 2.0 - 1.0;
+        let right = (column + 1) as f32 / columns as f32 * 2.0 - 1.0;
+        for (level, color) in [(peak, colors[0]), (rms, colors[1])] {
+            for (x, y) in [
+                (left, -level),
+                (right, -level),
+                (left, level),
+                (left, level),
+                (right, -level),
+                (right, level),
+            ] {
+                vertices.extend(x.to_ne_bytes());
+                vertices.extend(y.to_ne_bytes());
+                vertices.extend(color.to_array());
+            }
+        }
+    }
+    vertices
+}
+
+fn index(deck: TurntableFocus) -> usize {
+    match deck {
+        TurntableFocus::One => 0,
+        TurntableFocus::Two => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shader() {
+        use wgpu::naga::{
+            front::wgsl,
+            valid::{Capabilities, ValidationFlags, Validator},
+        };
+
+        let module = wgsl::parse_str(SHADER).unwrap();
+        Validator::new(ValidationFlags::all(), Capabilities::empty())
+            .validate(&module)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_vertices() {
+        let levels = [(1.0, 0.5), (0.5, 0.5), (0.0, 0.0), (0.0, 0.0)];
+        let colors = [Color32::GRAY, Color32::WHITE];
+        let vertex = |vertices: &[u8], index: usize| {
+            let bytes = &vertices[index * VERTEX_SIZE..(index + 1) * VERTEX_SIZE];
+            let value = |at: usize| f32::from_ne_bytes(bytes[at..at + 4].try_into().unwrap());
+            (value(0), value(4))
+        };
+
+        let wide = vertices(&levels, 800, colors);
+        assert_eq!(wide.len(), 4 * 2 * 6 * VERTEX_SIZE);
+        assert_eq!(vertex(&wide, 0), (-1.0, -1.0));
+        assert_eq!(vertex(&wide, 11), (-0.5, 0.5));
+
+        // two pixels wide, the columns are merged by two
+        let narrow = vertices(&levels, 2, colors);
+        assert_eq!(narrow.len(), 2 * 2 * 6 * VERTEX_SIZE);
+        assert_eq!(vertex(&narrow, 5), (0.0, 1.0));
+        assert_eq!(vertex(&narrow, 11), (0.0, 0.5));
+        assert_eq!(vertex(&narrow, 12 + 5), (1.0, 0.0));
+    }
+}
@@ -0,0 +1,401 @@
+use std::{
+    fs, io,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+};
+
+use kira::{
+    clock::clock_info::ClockInfoProvider,
+    effect::{Effect, EffectBuilder},
+    modulator::value_provider::ModulatorValueProvider,
+    Frame,
+};
+use ringbuf::{HeapConsumer, HeapProducer, HeapRb};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    clap_host::{Plugin, PluginError, PluginInfo, PluginProcessor},
+    config::data_dir,
+    processable::Processable,
+};
+
+/// Sample rate used until the audio thread reports the actual one
+const DEFAULT_SAMPLE_RATE: u32 = 48_000;
+const COMMAND_CAPACITY: usize = 256;
+const CHAINS_DIR: &str = "fx_chains";
+
+enum SlotCommand {
+    Load(Box<PluginProcessor>),
+    Unload,
+    SetParam(u32, f64),
+    SetBypass(bool),
+}
+
+/// Effect hosting the plugin of a [`PluginSlot`]
+pub struct PluginSlotEffect {
+    processor: Option<Box<PluginProcessor>>,
+    bypass: bool,
+    commands: HeapConsumer<SlotCommand>,
+    /// processors handed back to the main thread, where plugins are destroyed
+    released: HeapProducer<Box<PluginProcessor>>,
+    sample_rate: Arc<AtomicU32>,
+}
+
+impl PluginSlotEffect {
+    fn release(&mut self) {
+        if let Some(mut processor) = self.processor.take() {
+            processor.stop();
+            // the slot retires no more plugins than the queue holds, so there
+            // is always room. Were there not, the retired plugin would still
+            // be destroyed on the main thread once the processor is dropped
+            let _ = self.released.push(processor);
+        }
+    }
+}
+
+impl EffectBuilder for PluginSlotEffect {
+    type Handle = ();
+
+    fn build(self) -> (Box<dyn Effect>, Self::Handle) {
+        (Box::new(self), ())
+    }
+}
+
+impl Effect for PluginSlotEffect {
+    fn init(&mut self, sample_rate: u32) {
+        self.sample_rate.store(sample_rate, Ordering::Relaxed);
+    }
+
+    fn on_change_sample_rate(&mut self, sample_rate: u32) {
+        self.sample_rate.store(sample_rate, Ordering::Relaxed);
+    }
+
+    fn process(
+        &mut self,
+        input: Frame,
+        _dt: f64,
+        _clock_info_provider: &ClockInfoProvider,
+        _modulator_value_provider: &ModulatorValueProvider,
+    ) -> Frame {
+        while let Some(command) = self.commands.pop() {
+            match command {
+                SlotCommand::Load(processor) => {
+                    self.release();
+                    self.processor = Some(processor);
+                }
+                SlotCommand::Unload => self.release(),
+                SlotCommand::SetParam(id, value) => {
+                    if let Some(processor) = &mut self.processor {
+                        processor.set_param(id, value);
+                    }
+                }
+                SlotCommand::SetBypass(bypass) => self.bypass = bypass,
+            }
+        }
+
+        match &mut self.processor {
+            Some(processor) if !self.bypass => processor.process(input),
+            _ => input,
+        }
+    }
+}
+
+/// Plugin of a slot with its state, to load it again in another slot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SlotContent {
+    plugin: PluginInfo,
+    bypass: bool,
+    state: Option<Vec<u8>>,
+}
+
+/// Insert effects of a channel in order, saved under a name
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct ChainPreset {
+    slots: Vec<SlotContent>,
+}
+
+/// An insert effect slot of a mixer channel holding a CLAP plugin
+pub struct PluginSlot {
+    plugin: Option<Plugin>,
+    bypass: bool,
+    commands: HeapProducer<SlotCommand>,
+    released: HeapConsumer<Box<PluginProcessor>>,
+    /// plugins unloaded but still used by the audio thread
+    retired: Vec<Plugin>,
+    sample_rate: Arc<AtomicU32>,
+}
+
+impl PluginSlot {
+    /// Create an empty slot along with the effect to add to the channel
+    pub fn new() -> (Self, PluginSlotEffect) {
+        let (commands, commands_consumer) = HeapRb::new(COMMAND_CAPACITY).split();
+        let (released_producer, released) = HeapRb::new(COMMAND_CAPACITY).split();
+        let sample_rate = Arc::new(AtomicU32::new(DEFAULT_SAMPLE_RATE));
+
+        (
+            Self {
+                plugin: None,
+                bypass: false,
+                commands,
+                released,
+                retired: Vec::new(),
+                sample_rate: sample_rate.clone(),
+            },
+            PluginSlotEffect {
+                processor: None,
+                bypass: false,
+                commands: commands_consumer,
+                released: released_producer,
+                sample_rate,
+            },
+        )
+    }
+
+    pub fn plugin(&self) -> Option<&Plugin> {
+        self.plugin.as_ref()
+    }
+
+    fn content(&self) -> Option<SlotContent> {
+        let plugin = self.plugin.as_ref()?;
+        Some(SlotContent {
+            plugin: plugin.info().clone(),
+            bypass: self.bypass,
+            state: plugin.save_state(),
+        })
+    }
+
+    fn set_content(&mut self, content: Option<&SlotContent>) -> Result<(), PluginError> {
+        let Some(content) = content else {
+            self.unload();
+            self.set_bypass(false);
+            return Ok(());
+        };
+
+        self.load(&content.plugin)?;
+        if let (Some(plugin), Some(state)) = (&self.plugin, &content.state) {
+            if !plugin.load_state(state) {
+                log::warn!("Plugin '{}' rejected its state", content.plugin.name);
+            }
+        }
+        self.set_bypass(content.bypass);
+        Ok(())
+    }
+
+    pub fn load(&mut self, info: &PluginInfo) -> Result<(), PluginError> {
+        if self.is_retiring_full() {
+            log::error!("Plugin slot is still releasing its previous plugins");
+            return Ok(());
+        }
+        let sample_rate = self.sample_rate.load(Ordering::Relaxed) as f64;
+        let plugin = Plugin::new(info, sample_rate)?;
+
+        if self
+            .commands
+            .push(SlotCommand::Load(Box::new(plugin.processor())))
+            .is_err()
+        {
+            log::error!("Plugin slot command queue is full");
+            return Ok(());
+        }
+
+        log::info!("Loaded plugin '{}'", info.name);
+        if let Some(previous) = self.plugin.replace(plugin) {
+            self.retired.push(previous);
+        }
+
+        Ok(())
+    }
+
+    pub fn unload(&mut self) {
+        if self.is_retiring_full() {
+            log::error!("Plugin slot is still releasing its previous plugins");
+            return;
+        }
+        if let Some(plugin) = self.plugin.take() {
+            let _ = self.commands.push(SlotCommand::Unload);
+            self.retired.push(plugin);
+        }
+    }
+
+    /// Whether as many plugins are waiting for the audio thread to release
+    /// them as the queue handing them back holds
+    fn is_retiring_full(&self) -> bool {
+        self.retired.len() >= COMMAND_CAPACITY
+    }
+
+    pub fn set_param(&mut self, id: u32, value: f64) {
+        let _ = self.commands.push(SlotCommand::SetParam(id, value));
+    }
+
+    pub fn is_bypassed(&self) -> bool {
+        self.bypass
+    }
+
+    pub fn set_bypass(&mut self, bypass: bool) {
+        self.bypass = bypass;
+        let _ = self.commands.push(SlotCommand::SetBypass(bypass));
+    }
+
+    /// Save the state of the loaded plugin as a named preset
+    pub fn save_preset(&self, name: &str) -> io::Result<()> {
+        let plugin = self
+            .plugin
+            .as_ref()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no plugin loaded"))?;
+        let state = plugin.save_state().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::Unsupported, "plugin state cannot be saved")
+        })?;
+
+        let dir = presets_dir(plugin.info());
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join(format!("{}.preset", sanitize(name))), state)
+    }
+
+    pub fn load_preset(&self, name: &str) -> io::Result<()> {
+        let plugin = self
+            .plugin
+            .as_ref()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no plugin loaded"))?;
+        let state =
+            fs::read(presets_dir(plugin.info()).join(format!("{}.preset", sanitize(name))))?;
+
+        match plugin.load_state(&state) {
+            true => Ok(()),
+            false => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "plugin rejected the preset",
+            )),
+        }
+    }
+
+    /// Names of the presets saved for the loaded plugin
+    pub fn presets(&self) -> Vec<String> {
+        let Some(plugin) = &self.plugin else {
+            return Vec::new();
+        };
+        let Ok(entries) = fs::read_dir(presets_dir(plugin.info())) else {
+            return Vec::new();
+        };
+
+        let mut presets: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "preset"))
+            .filter_map(|path| Some(path.file_stem()?.to_string_lossy().to_string()))
+            .collect();
+        presets.sort();
+        presets
+    }
+}
+
+/// Move the plugin of slot `from` of a chain to slot `to`, shifting the
+/// ones in between. The moved plugins are loaded again with their state, as
+/// the effects of a channel cannot be reordered on the audio thread
+pub fn move_slot(slots: &mut [PluginSlot], from: usize, to: usize) -> Result<(), PluginError> {
+    if from == to || from >= slots.len() || to >= slots.len() {
+        return Ok(());
+    }
+
+    let mut contents: Vec<Option<SlotContent>> = slots.iter().map(PluginSlot::content).collect();
+    let content = contents.remove(from);
+    contents.insert(to, content);
+
+    let range = from.min(to)..=from.max(to);
+    for (slot, content) in slots[range.clone()].iter_mut().zip(&contents[range]) {
+        slot.set_content(content.as_ref())?;
+    }
+    Ok(())
+}
+
+/// Save the plugins of a chain in order, with their state, as a named
+/// preset
+pub fn save_chain(slots: &[PluginSlot], name: &str) -> io::Result<()> {
+    let preset = ChainPreset {
+        slots: slots.iter().filter_map(PluginSlot::content).collect(),
+    };
+    let content = toml::to_string(&preset).map_err(io::Error::other)?;
+
+    let dir = data_dir().join(CHAINS_DIR);
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join(format!("{}.toml", sanitize(name))), content)
+}
+
+/// Load a chain preset over the slots, emptying the ones left over
+pub fn load_chain(slots: &mut [PluginSlot], name: &str) -> io::Result<()> {
+    let path = data_dir()
+        .join(CHAINS_DIR)
+        .join(format!("{}.toml", sanitize(name)));
+    let content = fs::read_to_string(path)?;
+    let preset: ChainPreset = toml::from_str(&content).map_err(io::Error::other)?;
+
+    for (index, slot) in slots.iter_mut().enumerate() {
+        slot.set_content(preset.slots.get(index))
+            .map_err(|e| io::Error::other(e.to_string()))?;
+    }
+    Ok(())
+}
+
+/// Names of the saved chain presets
+pub fn chains() -> Vec<String> {
+    let Ok(entries) = fs::read_dir(data_dir().join(CHAINS_DIR)) else {
+        return Vec::new();
+    };
+
+    let mut chains: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .filter_map(|path| Some(path.file_stem()?.to_string_lossy().to_string()))
+        .collect();
+    chains.sort();
+    chains
+}
+
+fn presets_dir(info: &PluginInfo) -> PathBuf {
+    data_dir().join("plugin_presets").join(sanitize(&info.id))
+}
+
+/// File name made of `name`, its path separators, other special characters
+/// and a leading dot replaced so that it stays in its folder
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .enumerate()
+        .map(|(i, c)| {
+            if c.is_alphanumeric() || (c == '.' && i > 0) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+impl Processable for PluginSlot {
+    fn process(&mut self, _delta: f64) {
+        // dropping the audio thread side first lets the plugins be destroyed
+        while self.released.pop().is_some() {}
+        self.retired.retain(|plugin| !plugin.is_released());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    #[test]
+    fn test_sanitize() {
+        assert_eq!(sanitize("com.example.reverb"), "com.example.reverb");
+        assert_eq!(sanitize("../../escape"), "_._.._escape");
+        assert_eq!(sanitize(".."), "_.");
+        assert_eq!(sanitize("a/b\\c d"), "a_b_c_d");
+        for name in ["../x", "/etc/passwd", ".."] {
+            let file = format!("{}.preset", sanitize(name));
+            assert_eq!(Path::new(&file).components().count(), 1);
+        }
+    }
+}
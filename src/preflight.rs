@@ -0,0 +1,121 @@
+use std::{
+    fmt,
+    fs::{self, File},
+    io,
+    path::Path,
+};
+
+use symphonia::core::{
+    codecs::{CODEC_TYPE_MP3, CODEC_TYPE_NULL, CODEC_TYPE_VORBIS},
+    errors::Error as SymphoniaError,
+    formats::FormatOptions,
+    io::MediaSourceStream,
+    meta::MetadataOptions,
+    probe::Hint,
+};
+
+/// Lossy tracks below this bitrate in kbps are reported as low quality
+const LOW_BITRATE_KBPS: f64 = 128.0;
+
+/// Reasons a track cannot be loaded, found before decoding it
+#[derive(Debug)]
+pub enum PreflightError {
+    Unreadable(io::Error),
+    Empty,
+    Undecodable(SymphoniaError),
+    NoAudio,
+}
+
+impl fmt::Display for PreflightError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PreflightError::Unreadable(e) => write!(f, "cannot read the file: {}", e),
+            PreflightError::Empty => write!(f, "the file is empty"),
+            PreflightError::Undecodable(e) => write!(f, "not a supported audio file: {}", e),
+            PreflightError::NoAudio => write!(f, "the file has no audio track"),
+        }
+    }
+}
+
+/// Check from its header only that the track at `path` can be loaded,
+/// returning warnings about its quality when played at `sample_rate`
+pub fn check(path: &Path, sample_rate: u32) -> Result<Vec<String>, PreflightError> {
+    let size = fs::metadata(path)
+        .map_err(PreflightError::Unreadable)?
+        .len();
+    if size == 0 {
+        return Err(PreflightError::Empty);
+    }
+
+    let file = File::open(path).map_err(PreflightError::Unreadable)?;
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|extension| extension.to_str()) {
+        hint.with_extension(extension);
+    }
+
+    let probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            MediaSourceStream::new(Box::new(file), Default::default()),
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(PreflightError::Undecodable)?;
+    let params = &probed
+        .format
+        .default_track()
+        .filter(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or(PreflightError::NoAudio)?
+        .codec_params;
+
+    if params.n_frames == Some(0) {
+        return Err(PreflightError::Empty);
+    }
+
+    let mut warnings = Vec::new();
+    if let Some(track_rate) = params.sample_rate.filter(|rate| *rate != sample_rate) {
+        warnings.push(format!(
+            "{} Hz track resampled to {} Hz",
+            track_rate, sample_rate
+        ));
+    }
+
+    // the bitrate is estimated from the file size, which is enough to spot
+    // poor encodings
+    let lossy = [CODEC_TYPE_MP3, CODEC_TYPE_VORBIS].contains(&params.codec);
+    if let (true, Some(frames), Some(rate)) = (lossy, params.n_frames, params.sample_rate) {
+        let kbps = size as f64 * 8.0 / (frames as f64 / rate as f64) / 1000.0;
+        if kbps < LOW_BITRATE_KBPS {
+            warnings.push(format!("low bitrate of {:.0} kbps", kbps));
+        }
+    }
+
+    Ok(warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio_backend::testing::{constant_wav, SAMPLE_RATE};
+
+    #[test]
+    fn test_check() {
+        let path = constant_wav("preflight", 0.1, 0.5);
+        assert!(check(&path, SAMPLE_RATE).unwrap().is_empty());
+        assert_eq!(check(&path, 44_100).unwrap().len(), 1);
+
+        fs::write(&path, b"").unwrap();
+        assert!(matches!(
+            check(&path, SAMPLE_RATE),
+            Err(PreflightError::Empty)
+        ));
+
+        fs::write(&path, b"not a track").unwrap();
+        assert!(matches!(
+            check(&path, SAMPLE_RATE),
+            Err(PreflightError::Undecodable(_))
+        ));
+
+        let _ = fs::remove_file(path);
+    }
+}
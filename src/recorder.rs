@@ -0,0 +1,528 @@
+use core::fmt;
+use std::{
+    fs,
+    io::BufWriter,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use hound::{SampleFormat, WavSpec, WavWriter};
+use kira::{
+    clock::clock_info::ClockInfoProvider,
+    effect::{Effect, EffectBuilder},
+    modulator::value_provider::ModulatorValueProvider,
+    Frame,
+};
+use ringbuf::{HeapConsumer, HeapProducer, HeapRb};
+use serde::{Deserialize, Serialize};
+
+use crate::config::recordings_dir;
+
+/// Frames buffered between the audio thread and the writer thread
+const BUFFER_SECONDS: usize = 2;
+const WRITE_INTERVAL: Duration = Duration::from_millis(20);
+/// Level under which the master is considered silent, i.e. -60 dBFS
+const SILENCE_THRESHOLD: f32 = 0.001;
+
+#[derive(Debug)]
+pub enum RecorderError {
+    AlreadyRecording,
+    NotRecording,
+    Stopped,
+}
+
+impl fmt::Display for RecorderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self {
+            RecorderError::AlreadyRecording => write!(f, "Already recording"),
+            RecorderError::NotRecording => write!(f, "Not recording"),
+            RecorderError::Stopped => write!(f, "Recorder thread stopped"),
+        }
+    }
+}
+
+/// Mixer signals that can be recorded
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RecordSource {
+    /// record bus: both decks and the mic at its recording level
+    Master,
+    /// post-EQ, pre-fader signal of a deck
+    DeckOne,
+    DeckTwo,
+}
+
+impl RecordSource {
+    pub const ALL: [RecordSource; 3] = [
+        RecordSource::Master,
+        RecordSource::DeckOne,
+        RecordSource::DeckTwo,
+    ];
+
+    fn file_suffix(&self) -> &'static str {
+        match self {
+            RecordSource::Master => "",
+            RecordSource::DeckOne => "-deck-one",
+            RecordSource::DeckTwo => "-deck-two",
+        }
+    }
+}
+
+/// When a recording is split into numbered files. Zero disables an option
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SplitOptions {
+    pub every_minutes: u32,
+    /// split when the sound comes back after this much silence
+    pub after_silence_seconds: u32,
+    pub every_tracks: u32,
+}
+
+impl SplitOptions {
+    fn is_enabled(&self) -> bool {
+        self.every_minutes > 0 || self.after_silence_seconds > 0 || self.every_tracks > 0
+    }
+}
+
+enum Command {
+    Start {
+        base_name: String,
+        sources: Vec<RecordSource>,
+        split: SplitOptions,
+    },
+    Split,
+    Stop,
+}
+
+/// Records mixer tracks to WAV files.
+///
+/// Tracks are captured by [`RecorderTap`] effects feeding a writer thread.
+/// Every source starts and stops on the same frame so that the files stay
+/// in sync
+pub struct Recorder {
+    armed: Arc<AtomicBool>,
+    sample_rate: Arc<AtomicU32>,
+    producers: Vec<(RecordSource, HeapProducer<Frame>)>,
+    commands: Sender<Command>,
+    started: Option<Instant>,
+    files: Arc<Mutex<Vec<PathBuf>>>,
+    split_tracks: u32,
+    loaded_tracks: u32,
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        let armed = Arc::new(AtomicBool::new(false));
+        let sample_rate = Arc::new(AtomicU32::new(48_000));
+        let (commands, receiver) = mpsc::channel();
+
+        let mut producers = Vec::new();
+        let mut consumers = Vec::new();
+        for source in RecordSource::ALL {
+            let (producer, consumer) = HeapRb::<Frame>::new(192_000 * BUFFER_SECONDS).split();
+            producers.push((source, producer));
+            consumers.push((source, consumer));
+        }
+
+        let files = Arc::new(Mutex::new(Vec::new()));
+
+        let writer = Writer {
+            armed: Arc::clone(&armed),
+            sample_rate: Arc::clone(&sample_rate),
+            sources: consumers
+                .into_iter()
+                .map(|(source, consumer)| SourceWriter {
+                    source,
+                    consumer,
+                    frame: 0,
+                    file: None,
+                    next_file: None,
+                })
+                .collect(),
+            files: Arc::clone(&files),
+            base_name: String::new(),
+            split: SplitOptions::default(),
+            part: None,
+            split_at: None,
+            segment_start: 0,
+            silent_frames: 0,
+        };
+        thread::spawn(move || writer.run(receiver));
+
+        Self {
+            armed,
+            sample_rate,
+            producers,
+            commands,
+            started: None,
+            files,
+            split_tracks: 0,
+            loaded_tracks: 0,
+        }
+    }
+
+    /// Effect capturing a track for the given source. Each source can only
+    /// be tapped once
+    pub fn tap(&mut self, source: RecordSource) -> RecorderTap {
+        let index = self
+            .producers
+            .iter()
+            .position(|(s, _)| *s == source)
+            .expect("Recorder source already tapped");
+
+        RecorderTap {
+            producer: self.producers.remove(index).1,
+            armed: Arc::clone(&self.armed),
+            sample_rate: Arc::clone(&self.sample_rate),
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.started.is_some()
+    }
+
+    /// Sample rate of the audio output, known once the taps are initialized
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate.load(Ordering::Relaxed)
+    }
+
+    /// Time since the recording started
+    pub fn elapsed(&self) -> Option<Duration> {
+        self.started.map(|started| started.elapsed())
+    }
+
+    /// Files of the current or last recording
+    pub fn files(&self) -> Vec<PathBuf> {
+        match self.files.lock() {
+            Ok(files) => files.clone(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Start recording the master, along with each deck when `stems` is set.
+    /// The recording is split into numbered files according to `split`
+    pub fn start(&mut self, stems: bool, split: &SplitOptions) -> Result<(), RecorderError> {
+        if self.is_recording() {
+            return Err(RecorderError::AlreadyRecording);
+        }
+
+        let base_name = format!(
+            "{}-{}",
+            env!("CARGO_PKG_NAME").to_lowercase(),
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs()
+        );
+        let sources: Vec<RecordSource> = RecordSource::ALL
+            .into_iter()
+            .filter(|source| stems || *source == RecordSource::Master)
+            .collect();
+
+        self.commands
+            .send(Command::Start {
+                base_name,
+                sources,
+                split: split.clone(),
+            })
+            .map_err(|_| RecorderError::Stopped)?;
+        self.started = Some(Instant::now());
+        self.split_tracks = split.every_tracks;
+        self.loaded_tracks = 0;
+
+        Ok(())
+    }
+
+    pub fn stop(&mut self) -> Result<(), RecorderError> {
+        if !self.is_recording() {
+            return Err(RecorderError::NotRecording);
+        }
+
+        self.started = None;
+        self.commands
+            .send(Command::Stop)
+            .map_err(|_| RecorderError::Stopped)
+    }
+
+    /// Count a track loaded during the recording, for track based splits
+    pub fn track_loaded(&mut self) {
+        if !self.is_recording() || self.split_tracks == 0 {
+            return;
+        }
+
+        self.loaded_tracks += 1;
+        if self.loaded_tracks.is_multiple_of(self.split_tracks) {
+            let _ = self.commands.send(Command::Split);
+        }
+    }
+}
+
+fn recording_path(base_name: &str, part: Option<u32>, source: RecordSource) -> PathBuf {
+    let part = part.map(|part| format!("-{:03}", part)).unwrap_or_default();
+    recordings_dir().join(format!("{}{}{}.wav", base_name, part, source.file_suffix()))
+}
+
+/// Effect passing its input through while sending it to the recorder
+pub struct RecorderTap {
+    producer: HeapProducer<Frame>,
+    armed: Arc<AtomicBool>,
+    sample_rate: Arc<AtomicU32>,
+}
+
+impl EffectBuilder for RecorderTap {
+    type Handle = ();
+
+    fn build(self) -> (Box<dyn Effect>, Self::Handle) {
+        (Box::new(self), ())
+    }
+}
+
+impl Effect for RecorderTap {
+    fn init(&mut self, sample_rate: u32) {
+        self.sample_rate.store(sample_rate, Ordering::Relaxed);
+    }
+
+    fn on_change_sample_rate(&mut self, sample_rate: u32) {
+        self.sample_rate.store(sample_rate, Ordering::Relaxed);
+    }
+
+    fn process(
+        &mut self,
+        input: Frame,
+        _dt: f64,
+        _clock_info_provider: &ClockInfoProvider,
+        _modulator_value_provider: &ModulatorValueProvider,
+    ) -> Frame {
+        if self.armed.load(Ordering::Relaxed) {
+            // frames are dropped if the writer does not keep up
+            let _ = self.producer.push(input);
+        }
+
+        input
+    }
+}
+
+/// Writes the captured frames to disk, away from the audio thread
+struct Writer {
+    armed: Arc<AtomicBool>,
+    sample_rate: Arc<AtomicU32>,
+    sources: Vec<SourceWriter>,
+    files: Arc<Mutex<Vec<PathBuf>>>,
+    base_name: String,
+    split: SplitOptions,
+    /// number of the current file, if the recording is split
+    part: Option<u32>,
+    /// frame at which every source switches to the next file
+    split_at: Option<u64>,
+    segment_start: u64,
+    silent_frames: u64,
+}
+
+type WavFile = WavWriter<BufWriter<fs::File>>;
+
+/// Frames of one source and the files they are written to
+struct SourceWriter {
+    source: RecordSource,
+    consumer: HeapConsumer<Frame>,
+    /// index of the next frame since the start of the recording
+    frame: u64,
+    file: Option<WavFile>,
+    next_file: Option<WavFile>,
+}
+
+impl SourceWriter {
+    fn write(&mut self, frame: Frame, split_at: Option<u64>) {
+        if split_at.is_some_and(|at| self.frame >= at) && self.next_file.is_some() {
+            finalize(self.source, self.file.take());
+            self.file = self.next_file.take();
+        }
+
+        if let Some(file) = &mut self.file {
+            let result = file
+                .write_sample(frame.left)
+                .and_then(|_| file.write_sample(frame.right));
+            if let Err(e) = result {
+                log::error!("Cannot write recording of {:?}: {}", self.source, e);
+                self.file = None;
+            }
+        }
+
+        self.frame += 1;
+    }
+}
+
+fn finalize(source: RecordSource, file: Option<WavFile>) {
+    if let Some(file) = file {
+        match file.finalize() {
+            Ok(()) => log::info!("Recording of {:?} saved", source),
+            Err(e) => log::error!("Cannot finalize recording of {:?}: {}", source, e),
+        }
+    }
+}
+
+impl Writer {
+    fn run(mut self, commands: Receiver<Command>) {
+        loop {
+            match commands.recv_timeout(WRITE_INTERVAL) {
+                Ok(Command::Start {
+                    base_name,
+                    sources,
+                    split,
+                }) => self.start(base_name, &sources, split),
+                Ok(Command::Split) => {
+                    let at = self.sources.iter().map(|s| s.frame).max().unwrap_or(0);
+                    self.split(at);
+                }
+                Ok(Command::Stop) => self.stop(),
+                Err(RecvTimeoutError::Timeout) => (),
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.stop();
+                    return;
+                }
+            }
+
+            self.write();
+        }
+    }
+
+    /// Create the files of every recorded source for the current part
+    fn open_files(&mut self, sources: &[RecordSource]) -> Vec<(RecordSource, WavFile)> {
+        let spec = WavSpec {
+            channels: 2,
+            sample_rate: self.sample_rate.load(Ordering::Relaxed),
+            bits_per_sample: 32,
+            sample_format: SampleFormat::Float,
+        };
+
+        if let Err(e) = fs::create_dir_all(recordings_dir()) {
+            log::error!("Cannot create recordings dir: {}", e);
+            return Vec::new();
+        }
+
+        let mut files = Vec::new();
+        let mut paths = Vec::new();
+        for source in sources {
+            let path = recording_path(&self.base_name, self.part, *source);
+            match WavWriter::create(&path, spec) {
+                Ok(file) => {
+                    log::info!("Recording {:?} to '{}'", source, path.display());
+                    files.push((*source, file));
+                    paths.push(path);
+                }
+                Err(e) => log::error!("Cannot create '{}': {}", path.display(), e),
+            }
+        }
+
+        if let Ok(mut shared) = self.files.lock() {
+            *shared = paths;
+        }
+
+        files
+    }
+
+    fn start(&mut self, base_name: String, sources: &[RecordSource], split: SplitOptions) {
+        self.base_name = base_name;
+        self.part = split.is_enabled().then_some(1);
+        self.split = split;
+        self.split_at = None;
+        self.segment_start = 0;
+        self.silent_frames = 0;
+
+        for (source, file) in self.open_files(sources) {
+            if let Some(writer) = self.sources.iter_mut().find(|s| s.source == source) {
+                writer.file = Some(file);
+            }
+        }
+
+        // leftovers from a previous recording must not end up in this one
+        for writer in self.sources.iter_mut() {
+            writer.consumer.clear();
+            writer.frame = 0;
+        }
+        self.armed.store(true, Ordering::Relaxed);
+    }
+
+    /// Switch every source to the next numbered file once it reaches the
+    /// frame `at`, so that the parts of each source stay aligned
+    fn split(&mut self, at: u64) {
+        if self.split_at.is_some() || !self.armed.load(Ordering::Relaxed) {
+            return;
+        }
+
+        let sources: Vec<RecordSource> = self
+            .sources
+            .iter()
+            .filter(|s| s.file.is_some())
+            .map(|s| s.source)
+            .collect();
+        self.part = Some(self.part.unwrap_or(1) + 1);
+
+        for (source, file) in self.open_files(&sources) {
+            if let Some(writer) = self.sources.iter_mut().find(|s| s.source == source) {
+                writer.next_file = Some(file);
+            }
+        }
+
+        self.split_at = Some(at);
+        self.segment_start = at;
+    }
+
+    fn stop(&mut self) {
+        self.armed.store(false, Ordering::Relaxed);
+        self.write();
+
+        for writer in self.sources.iter_mut() {
+            finalize(writer.source, writer.file.take());
+            finalize(writer.source, writer.next_file.take());
+        }
+        self.split_at = None;
+    }
+
+    fn write(&mut self) {
+        let sample_rate = self.sample_rate.load(Ordering::Relaxed) as u64;
+
+        for i in 0..self.sources.len() {
+            while let Some(frame) = self.sources[i].consumer.pop() {
+                if self.sources[i].source == RecordSource::Master {
+                    let at = self.sources[i].frame;
+                    if self.should_split(frame, at, sample_rate) {
+                        let at = self.sources.iter().map(|s| s.frame).max().unwrap_or(at);
+                        self.split(at);
+                    }
+                }
+
+                self.sources[i].write(frame, self.split_at);
+            }
+        }
+
+        if self.sources.iter().all(|s| s.next_file.is_none()) {
+            self.split_at = None;
+        }
+    }
+
+    /// Whether the master frame at index `at` should start a new file
+    fn should_split(&mut self, frame: Frame, at: u64, sample_rate: u64) -> bool {
+        let every_frames = self.split.every_minutes as u64 * 60 * sample_rate;
+        if every_frames > 0 && at - self.segment_start >= every_frames {
+            return true;
+        }
+
+        if self.split.after_silence_seconds == 0 {
+            return false;
+        }
+
+        if frame.left.abs() < SILENCE_THRESHOLD && frame.right.abs() < SILENCE_THRESHOLD {
+            self.silent_frames += 1;
+            return false;
+        }
+
+        let silence = self.silent_frames;
+        self.silent_frames = 0;
+        silence >= self.split.after_silence_seconds as u64 * sample_rate
+    }
+}
@@ -0,0 +1,188 @@
+use crate::mixer::CueMode;
+
+/// Controls of a channel deciding where it is heard
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Channel {
+    /// fader, from 0.0 closed to 1.0 open
+    pub volume: f64,
+    pub cue: bool,
+    pub cue_mode: CueMode,
+    /// heard on the cue only, before its fader, whatever its fader and cue
+    pub preview: bool,
+    /// taken off the master by its transform button
+    pub cut: bool,
+}
+
+/// Gains of the routes out of a channel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChannelRoutes {
+    /// from the channel track, after the EQ, to the master and record buses
+    pub master: f64,
+    /// from the input track, before the EQ, to the cue bus
+    pub cue_pre_fader: f64,
+    /// from the channel track to the cue bus
+    pub cue_after_fader: f64,
+}
+
+/// Routes of `channel`, `crossfader_gain` being its gain from the
+/// crossfader
+pub fn channel_routes(channel: Channel, crossfader_gain: f64) -> ChannelRoutes {
+    let master = match channel.preview || channel.cut {
+        true => 0.0,
+        false => channel.volume * crossfader_gain,
+    };
+    let (cue, cue_mode) = match channel.preview {
+        true => (true, CueMode::PreFader),
+        false => (channel.cue, channel.cue_mode),
+    };
+    let (cue_pre_fader, cue_after_fader) = match (cue, cue_mode) {
+        (false, _) => (0.0, 0.0),
+        (true, CueMode::PreFader) => (1.0, 0.0),
+        (true, CueMode::AfterFader) => (0.0, channel.volume),
+    };
+
+    ChannelRoutes {
+        master,
+        cue_pre_fader,
+        cue_after_fader,
+    }
+}
+
+/// Gains of channels one and two on the master from the crossfader.
+/// Both are at full level in the middle, each fading out over its half
+/// of the way to the other side
+pub fn crossfader_gains(value: f64) -> (f64, f64) {
+    let value = value.clamp(0.0, 1.0);
+    ((2.0 * (1.0 - value)).min(1.0), (2.0 * value).min(1.0))
+}
+
+/// Volumes of the cue bus and of the master in the output from the cue
+/// mix, from 0.0, the cue only, to 1.0, the master only. They sum to 1.0
+pub fn cue_mix(value: f64) -> (f64, f64) {
+    let value = value.clamp(0.0, 1.0);
+    (1.0 - value, value)
+}
+
+/// Volume of the master bus from the cue mix, lowered by `duck_gain` dB
+/// while ducked and silent while muted
+pub fn master_volume(cue_mix_value: f64, muted: bool, duck_gain: Option<f64>) -> f64 {
+    let (_, volume) = cue_mix(cue_mix_value);
+    match (muted, duck_gain) {
+        (true, _) => 0.0,
+        (false, Some(gain)) => volume * 10f64.powf(gain / 20.0),
+        (false, None) => volume,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHANNEL: Channel = Channel {
+        volume: 0.5,
+        cue: false,
+        cue_mode: CueMode::PreFader,
+        preview: false,
+        cut: false,
+    };
+
+    #[test]
+    fn test_channel_routes() {
+        let routes = |channel: Channel| channel_routes(channel, 0.8);
+
+        assert_eq!(
+            routes(CHANNEL),
+            ChannelRoutes {
+                master: 0.4,
+                cue_pre_fader: 0.0,
+                cue_after_fader: 0.0,
+            }
+        );
+        assert_eq!(channel_routes(CHANNEL, 0.0).master, 0.0);
+
+        let cued = Channel {
+            cue: true,
+            ..CHANNEL
+        };
+        assert_eq!(
+            routes(cued),
+            ChannelRoutes {
+                master: 0.4,
+                cue_pre_fader: 1.0,
+                cue_after_fader: 0.0,
+            }
+        );
+        // after the fader the cue follows it, but not the crossfader
+        assert_eq!(
+            routes(Channel {
+                cue_mode: CueMode::AfterFader,
+                ..cued
+            }),
+            ChannelRoutes {
+                master: 0.4,
+                cue_pre_fader: 0.0,
+                cue_after_fader: 0.5,
+            }
+        );
+        assert_eq!(
+            routes(Channel {
+                cue_mode: CueMode::AfterFader,
+                ..CHANNEL
+            })
+            .cue_after_fader,
+            0.0
+        );
+
+        // a cut channel is still cued
+        assert_eq!(
+            routes(Channel { cut: true, ..cued }),
+            ChannelRoutes {
+                master: 0.0,
+                cue_pre_fader: 1.0,
+                cue_after_fader: 0.0,
+            }
+        );
+
+        // a preview is heard before the fader whatever the cue
+        for (cue, cue_mode) in [(false, CueMode::PreFader), (true, CueMode::AfterFader)] {
+            let preview = Channel {
+                cue,
+                cue_mode,
+                preview: true,
+                ..CHANNEL
+            };
+            assert_eq!(
+                routes(preview),
+                ChannelRoutes {
+                    master: 0.0,
+                    cue_pre_fader: 1.0,
+                    cue_after_fader: 0.0,
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn test_crossfader_gains() {
+        assert_eq!(crossfader_gains(0.0), (1.0, 0.0));
+        assert_eq!(crossfader_gains(0.25), (1.0, 0.5));
+        assert_eq!(crossfader_gains(0.5), (1.0, 1.0));
+        assert_eq!(crossfader_gains(0.75), (0.5, 1.0));
+        assert_eq!(crossfader_gains(1.0), (0.0, 1.0));
+        assert_eq!(crossfader_gains(-1.0), (1.0, 0.0));
+        assert_eq!(crossfader_gains(2.0), (0.0, 1.0));
+    }
+
+    #[test]
+    fn test_cue_mix() {
+        assert_eq!(cue_mix(0.0), (1.0, 0.0));
+        assert_eq!(cue_mix(0.25), (0.75, 0.25));
+        assert_eq!(cue_mix(1.0), (0.0, 1.0));
+        assert_eq!(cue_mix(1.5), (0.0, 1.0));
+
+        assert_eq!(master_volume(0.5, false, None), 0.5);
+        assert_eq!(master_volume(0.5, true, Some(-6.0)), 0.0);
+        assert!((master_volume(1.0, false, Some(-20.0)) - 0.1).abs() < 1e-12);
+        assert_eq!(master_volume(0.0, false, Some(-6.0)), 0.0);
+    }
+}
@@ -0,0 +1,695 @@
+use std::{
+    collections::VecDeque,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU32, AtomicU8, Ordering},
+        Arc,
+    },
+};
+
+use hound::{SampleFormat, WavSpec, WavWriter};
+use kira::{
+    clock::{clock_info::ClockInfoProvider, ClockHandle, ClockId, ClockSpeed, ClockTime},
+    effect::{Effect, EffectBuilder},
+    modulator::value_provider::ModulatorValueProvider,
+    sound::{
+        static_sound::{StaticSoundData, StaticSoundHandle, StaticSoundSettings},
+        FromFileError, PlaybackRate, PlaybackState,
+    },
+    track::TrackHandle,
+    tween::Tween,
+    Frame, StartTime, Volume,
+};
+use ringbuf::{HeapConsumer, HeapProducer, HeapRb};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    audio_backend::Manager, config::data_dir, processable::Processable, recorder::RecordSource,
+};
+
+pub const PAD_COUNT: usize = 8;
+pub const MAX_CAPTURE_BEATS: u32 = 16;
+pub const MIN_BPM: f64 = 60.0;
+pub const MAX_BPM: f64 = 200.0;
+pub const BEATS_PER_BAR: u64 = 4;
+/// Audio kept for captures, enough for the longest one at the lowest tempo
+const HISTORY_SECONDS: usize = 20;
+/// Frames buffered between the audio thread and the main thread
+const BUFFER_SECONDS: usize = 2;
+pub const BANK_EXTENSION: &str = "bank";
+
+/// A frame of the sampled track, flagged when a beat starts on it
+#[derive(Debug, Clone, Copy)]
+struct TappedFrame {
+    frame: Frame,
+    beat: bool,
+}
+
+/// Effect passing its input through while sending it to the sampler when
+/// its source is the selected one
+pub struct SamplerTap {
+    source: RecordSource,
+    selected: Arc<AtomicU8>,
+    clock: ClockId,
+    last_tick: Option<u64>,
+    producer: HeapProducer<TappedFrame>,
+    sample_rate: Arc<AtomicU32>,
+}
+
+impl EffectBuilder for SamplerTap {
+    type Handle = ();
+
+    fn build(self) -> (Box<dyn Effect>, Self::Handle) {
+        (Box::new(self), ())
+    }
+}
+
+impl Effect for SamplerTap {
+    fn init(&mut self, sample_rate: u32) {
+        self.sample_rate.store(sample_rate, Ordering::Relaxed);
+    }
+
+    fn on_change_sample_rate(&mut self, sample_rate: u32) {
+        self.sample_rate.store(sample_rate, Ordering::Relaxed);
+    }
+
+    fn process(
+        &mut self,
+        input: Frame,
+        _dt: f64,
+        clock_info_provider: &ClockInfoProvider,
+        _modulator_value_provider: &ModulatorValueProvider,
+    ) -> Frame {
+        let tick = clock_info_provider
+            .get(self.clock)
+            .map(|info| info.time.ticks);
+        let beat = tick.is_some() && tick != self.last_tick;
+        self.last_tick = tick;
+
+        if RecordSource::ALL[self.selected.load(Ordering::Relaxed) as usize] == self.source {
+            // frames are dropped if the main thread does not keep up
+            let _ = self.producer.push(TappedFrame { frame: input, beat });
+        }
+
+        input
+    }
+}
+
+/// Last seconds of the sampled source along with the beats they contain
+struct History {
+    frames: VecDeque<Frame>,
+    /// index of the first frame in `frames` since the history was cleared
+    start: u64,
+    /// beats, as indexes of the frame they start on
+    beats: VecDeque<u64>,
+}
+
+impl History {
+    fn new() -> Self {
+        Self {
+            frames: VecDeque::new(),
+            start: 0,
+            beats: VecDeque::new(),
+        }
+    }
+
+    fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    fn push(&mut self, tapped: TappedFrame, max_frames: usize) {
+        if tapped.beat {
+            self.beats.push_back(self.start + self.frames.len() as u64);
+        }
+        self.frames.push_back(tapped.frame);
+
+        while self.frames.len() > max_frames {
+            self.frames.pop_front();
+            self.start += 1;
+        }
+        while self.beats.front().is_some_and(|beat| *beat < self.start) {
+            self.beats.pop_front();
+        }
+    }
+
+    /// The `beats` last complete beats, `None` if the history is too short
+    fn last_beats(&self, beats: u32) -> Option<Vec<Frame>> {
+        let count = self.beats.len();
+        let end = *self.beats.back()?;
+        let begin = *self.beats.get(count.checked_sub(beats as usize + 1)?)?;
+
+        Some(
+            self.frames
+                .range((begin - self.start) as usize..(end - self.start) as usize)
+                .copied()
+                .collect(),
+        )
+    }
+}
+
+/// What pad triggers snap to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum Quantize {
+    /// play as soon as the pad is pressed
+    #[default]
+    Off,
+    /// play on the next beat of the sampler clock
+    Beat,
+    /// play on the next bar of 4 beats
+    Bar,
+}
+
+impl Quantize {
+    pub const ALL: [Quantize; 3] = [Quantize::Off, Quantize::Beat, Quantize::Bar];
+}
+
+/// How a pad plays its sample
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum PlayMode {
+    /// play once, restarting on every press
+    #[default]
+    OneShot,
+    /// loop until pressed again
+    Loop,
+}
+
+/// Playback settings of a sampler slot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SlotSettings {
+    /// start of the played region, in seconds
+    pub start: f64,
+    /// end of the played region in seconds, the end of the sample if unset
+    pub end: Option<f64>,
+    /// gain in dB
+    pub gain: f64,
+    pub mode: PlayMode,
+    /// pitch shift in semitones, changing the speed along with it
+    pub pitch: f64,
+    /// a pad stops the other pads of its group when triggered
+    pub choke_group: Option<u32>,
+}
+
+impl Default for SlotSettings {
+    fn default() -> Self {
+        Self {
+            start: 0.0,
+            end: None,
+            gain: 0.0,
+            mode: PlayMode::OneShot,
+            pitch: 0.0,
+            choke_group: None,
+        }
+    }
+}
+
+/// A pad of a saved bank
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct BankPad {
+    pad: usize,
+    file: PathBuf,
+    #[serde(default)]
+    settings: SlotSettings,
+}
+
+/// The sampler pads and settings, saved as a TOML file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Bank {
+    bpm: f64,
+    capture_beats: u32,
+    #[serde(default)]
+    quantize: Quantize,
+    #[serde(default)]
+    pads: Vec<BankPad>,
+}
+
+/// Directory of the banks saved from the app, along with their captures
+fn banks_dir() -> PathBuf {
+    data_dir().join("sampler_banks")
+}
+
+fn write_wav(path: &Path, data: &StaticSoundData) -> Result<(), hound::Error> {
+    let spec = WavSpec {
+        channels: 2,
+        sample_rate: data.sample_rate,
+        bits_per_sample: 32,
+        sample_format: SampleFormat::Float,
+    };
+
+    let mut writer = WavWriter::create(path, spec)?;
+    for frame in data.frames.iter() {
+        writer.write_sample(frame.left)?;
+        writer.write_sample(frame.right)?;
+    }
+    writer.finalize()
+}
+
+/// A sampler slot, triggered by its pad
+#[derive(Default)]
+pub struct SamplerSlot {
+    data: Option<StaticSoundData>,
+    /// file of the sample, unset for captures until their bank is saved
+    file: Option<PathBuf>,
+    settings: SlotSettings,
+    handle: Option<StaticSoundHandle>,
+}
+
+impl SamplerSlot {
+    pub fn settings(&self) -> &SlotSettings {
+        &self.settings
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_none()
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.handle.is_some()
+    }
+
+    pub fn file(&self) -> Option<&Path> {
+        self.file.as_deref()
+    }
+
+    /// Length of the sample in seconds
+    pub fn duration(&self) -> Option<f64> {
+        self.data.as_ref().map(|data| data.duration().as_secs_f64())
+    }
+
+    fn stop(&mut self) {
+        if let Some(mut handle) = self.handle.take() {
+            handle.stop(Tween::default());
+        }
+    }
+
+    /// Sound of the trimmed region with the settings applied
+    fn sound(&self, track: &TrackHandle) -> Option<StaticSoundData> {
+        let data = self.data.as_ref()?;
+        let duration = data.duration().as_secs_f64();
+        let end = self.settings.end.unwrap_or(duration).clamp(0.0, duration);
+        let start = self.settings.start.clamp(0.0, end);
+
+        let sound = data
+            .slice(start..end)
+            .volume(Volume::Decibels(self.settings.gain))
+            .playback_rate(PlaybackRate::Semitones(self.settings.pitch))
+            .output_destination(track);
+
+        Some(match self.settings.mode {
+            PlayMode::OneShot => sound,
+            PlayMode::Loop => sound.loop_region(..),
+        })
+    }
+}
+
+/// Captures the last beats of the master or of a channel into pads, to play
+/// them back as loops.
+///
+/// Beats are given by the tempo of the sampler clock, so that captures are
+/// quantized to whole beats
+pub struct Sampler {
+    clock: ClockHandle,
+    bpm: f64,
+    /// take the master tempo instead of the BPM set by hand
+    follow_tempo: bool,
+    capture_beats: u32,
+    quantize: Quantize,
+    selected: Arc<AtomicU8>,
+    sample_rate: Arc<AtomicU32>,
+    consumers: Vec<(RecordSource, HeapConsumer<TappedFrame>)>,
+    producers: Vec<(RecordSource, HeapProducer<TappedFrame>)>,
+    history: History,
+    slots: Vec<SamplerSlot>,
+}
+
+impl Sampler {
+    /// Create a sampler, to be tapped with [`Sampler::tap`]
+    pub fn new(manager: &mut Manager) -> Self {
+        let bpm = 120.0;
+        let mut clock = manager.add_clock(ClockSpeed::TicksPerMinute(bpm)).unwrap();
+        clock.start();
+
+        let mut producers = Vec::new();
+        let mut consumers = Vec::new();
+        for source in RecordSource::ALL {
+            let (producer, consumer) = HeapRb::<TappedFrame>::new(192_000 * BUFFER_SECONDS).split();
+            producers.push((source, producer));
+            consumers.push((source, consumer));
+        }
+
+        Self {
+            clock,
+            bpm,
+            follow_tempo: false,
+            capture_beats: 4,
+            quantize: Quantize::Off,
+            selected: Arc::new(AtomicU8::new(0)),
+            sample_rate: Arc::new(AtomicU32::new(48_000)),
+            consumers,
+            producers,
+            history: History::new(),
+            slots: (0..PAD_COUNT).map(|_| SamplerSlot::default()).collect(),
+        }
+    }
+
+    /// Effect capturing a track for the given source. Each source can only
+    /// be tapped once
+    pub fn tap(&mut self, source: RecordSource) -> SamplerTap {
+        let index = self
+            .producers
+            .iter()
+            .position(|(s, _)| *s == source)
+            .expect("Sampler source already tapped");
+
+        SamplerTap {
+            source,
+            selected: Arc::clone(&self.selected),
+            clock: self.clock.id(),
+            last_tick: None,
+            producer: self.producers.remove(index).1,
+            sample_rate: Arc::clone(&self.sample_rate),
+        }
+    }
+
+    pub fn source(&self) -> RecordSource {
+        RecordSource::ALL[self.selected.load(Ordering::Relaxed) as usize]
+    }
+
+    /// Sample another source, the audio of the previous one is discarded
+    pub fn set_source(&mut self, source: RecordSource) {
+        let index = RecordSource::ALL
+            .iter()
+            .position(|s| *s == source)
+            .unwrap_or_default();
+        self.selected.store(index as u8, Ordering::Relaxed);
+        self.history.clear();
+    }
+
+    pub fn bpm(&self) -> f64 {
+        self.bpm
+    }
+
+    pub fn set_bpm(&mut self, bpm: f64) {
+        self.bpm = bpm.clamp(MIN_BPM, MAX_BPM);
+        self.clock
+            .set_speed(ClockSpeed::TicksPerMinute(self.bpm), Tween::default());
+    }
+
+    pub fn follows_tempo(&self) -> bool {
+        self.follow_tempo
+    }
+
+    pub fn set_follow_tempo(&mut self, follow_tempo: bool) {
+        self.follow_tempo = follow_tempo;
+    }
+
+    /// Number of beats captured by a pad
+    pub fn capture_beats(&self) -> u32 {
+        self.capture_beats
+    }
+
+    pub fn set_capture_beats(&mut self, beats: u32) {
+        self.capture_beats = beats.clamp(1, MAX_CAPTURE_BEATS);
+    }
+
+    pub fn quantize(&self) -> Quantize {
+        self.quantize
+    }
+
+    pub fn set_quantize(&mut self, quantize: Quantize) {
+        self.quantize = quantize;
+    }
+
+    /// When a pad triggered now starts playing
+    fn start_time(&self) -> StartTime {
+        let ticks = self.clock.time().ticks;
+        let ticks = match self.quantize {
+            Quantize::Off => return StartTime::Immediate,
+            Quantize::Beat => ticks + 1,
+            Quantize::Bar => (ticks / BEATS_PER_BAR + 1) * BEATS_PER_BAR,
+        };
+
+        StartTime::ClockTime(ClockTime {
+            clock: self.clock.id(),
+            ticks,
+            fraction: 0.0,
+        })
+    }
+
+    pub fn slots(&self) -> &[SamplerSlot] {
+        &self.slots
+    }
+
+    /// Capture into an empty pad. Otherwise play it into `track`, or stop
+    /// it if it is a playing loop
+    pub fn trigger(&mut self, manager: &mut Manager, track: &TrackHandle, pad: usize) {
+        let Some(slot) = self.slots.get_mut(pad) else {
+            return;
+        };
+
+        if slot.is_empty() {
+            self.capture(pad);
+            return;
+        }
+
+        let playing_loop = slot.is_playing() && slot.settings.mode == PlayMode::Loop;
+        slot.stop();
+        if playing_loop {
+            return;
+        }
+
+        if let Some(group) = slot.settings.choke_group {
+            for other in self.slots.iter_mut() {
+                if other.settings.choke_group == Some(group) {
+                    other.stop();
+                }
+            }
+        }
+
+        let start_time = self.start_time();
+        let slot = &mut self.slots[pad];
+        let Some(sound) = slot.sound(track) else {
+            return;
+        };
+        let sound = sound.start_time(start_time);
+        match manager.play(sound) {
+            Ok(handle) => slot.handle = Some(handle),
+            Err(e) => log::error!("Cannot play sampler pad {}: {}", pad + 1, e),
+        }
+    }
+
+    /// Change the settings of a pad. Gain and pitch apply to the playing
+    /// sound, the other settings on the next trigger
+    pub fn set_slot_settings(&mut self, pad: usize, settings: SlotSettings) {
+        let Some(slot) = self.slots.get_mut(pad) else {
+            return;
+        };
+
+        if let Some(handle) = &mut slot.handle {
+            handle.set_volume(Volume::Decibels(settings.gain), Tween::default());
+            handle.set_playback_rate(PlaybackRate::Semitones(settings.pitch), Tween::default());
+        }
+        slot.settings = settings;
+    }
+
+    fn capture(&mut self, pad: usize) {
+        let Some(frames) = self.history.last_beats(self.capture_beats) else {
+            log::warn!(
+                "Not enough audio to capture {} beats yet",
+                self.capture_beats
+            );
+            return;
+        };
+
+        let slot = &mut self.slots[pad];
+        slot.stop();
+        slot.file = None;
+        slot.data = Some(StaticSoundData {
+            sample_rate: self.sample_rate.load(Ordering::Relaxed),
+            frames: frames.into(),
+            settings: StaticSoundSettings::new(),
+            slice: None,
+        });
+        slot.settings = SlotSettings {
+            mode: PlayMode::Loop,
+            ..Default::default()
+        };
+        log::info!(
+            "Captured {} beats into sampler pad {}",
+            self.capture_beats,
+            pad + 1
+        );
+    }
+
+    /// Stop and empty a pad
+    pub fn clear(&mut self, pad: usize) {
+        if let Some(slot) = self.slots.get_mut(pad) {
+            slot.stop();
+            slot.data = None;
+            slot.file = None;
+            slot.settings = SlotSettings::default();
+        }
+    }
+
+    /// Load an audio file into a pad, played as a one-shot
+    pub fn load_file(&mut self, pad: usize, path: &Path) -> Result<(), FromFileError> {
+        let data = StaticSoundData::from_file(path)?;
+
+        self.clear(pad);
+        if let Some(slot) = self.slots.get_mut(pad) {
+            slot.data = Some(data);
+            slot.file = Some(path.to_path_buf());
+        }
+
+        Ok(())
+    }
+
+    /// Save the pads and settings as a named bank. Captures are written to
+    /// WAV files next to the bank
+    pub fn save_bank(&mut self, name: &str) -> io::Result<()> {
+        let dir = banks_dir();
+        fs::create_dir_all(&dir)?;
+
+        let mut pads = Vec::new();
+        for (pad, slot) in self.slots.iter_mut().enumerate() {
+            let Some(data) = &slot.data else {
+                continue;
+            };
+
+            let file = match &slot.file {
+                Some(file) => file.clone(),
+                None => {
+                    let captures_dir = dir.join(name);
+                    fs::create_dir_all(&captures_dir)?;
+                    let file = captures_dir.join(format!("pad-{}.wav", pad + 1));
+                    write_wav(&file, data).map_err(io::Error::other)?;
+                    slot.file = Some(file.clone());
+                    file
+                }
+            };
+
+            pads.push(BankPad {
+                pad,
+                file,
+                settings: slot.settings.clone(),
+            });
+        }
+
+        let bank = Bank {
+            bpm: self.bpm,
+            capture_beats: self.capture_beats,
+            quantize: self.quantize,
+            pads,
+        };
+        let content = toml::to_string(&bank).map_err(io::Error::other)?;
+        fs::write(dir.join(format!("{}.{}", name, BANK_EXTENSION)), content)
+    }
+
+    /// Replace the pads and settings with those of a bank file
+    pub fn load_bank(&mut self, path: &Path) -> io::Result<()> {
+        let bank: Bank = toml::from_str(&fs::read_to_string(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        for pad in 0..PAD_COUNT {
+            self.clear(pad);
+        }
+        self.set_bpm(bank.bpm);
+        self.set_capture_beats(bank.capture_beats);
+        self.quantize = bank.quantize;
+
+        for BankPad {
+            pad,
+            file,
+            settings,
+        } in bank.pads
+        {
+            match self.load_file(pad, &file) {
+                Ok(()) => self.set_slot_settings(pad, settings),
+                Err(e) => log::error!(
+                    "Cannot load '{}' into sampler pad {}: {}",
+                    file.display(),
+                    pad + 1,
+                    e
+                ),
+            }
+        }
+
+        log::info!("Loaded sampler bank '{}'", path.display());
+        Ok(())
+    }
+
+    /// Load a bank saved from the app by name
+    pub fn load_saved_bank(&mut self, name: &str) -> io::Result<()> {
+        self.load_bank(&banks_dir().join(format!("{}.{}", name, BANK_EXTENSION)))
+    }
+
+    /// Names of the banks saved from the app
+    pub fn banks() -> Vec<String> {
+        let Ok(entries) = fs::read_dir(banks_dir()) else {
+            return Vec::new();
+        };
+
+        let mut banks: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == BANK_EXTENSION))
+            .filter_map(|path| Some(path.file_stem()?.to_string_lossy().to_string()))
+            .collect();
+        banks.sort();
+        banks
+    }
+}
+
+impl Processable for Sampler {
+    fn process(&mut self, _delta: f64) {
+        let selected = self.source();
+        let max_frames = self.sample_rate.load(Ordering::Relaxed) as usize * HISTORY_SECONDS;
+
+        for (source, consumer) in self.consumers.iter_mut() {
+            while let Some(tapped) = consumer.pop() {
+                // frames queued before a source change
+                if *source == selected {
+                    self.history.push(tapped, max_frames);
+                }
+            }
+        }
+
+        for slot in self.slots.iter_mut() {
+            if slot
+                .handle
+                .as_ref()
+                .is_some_and(|handle| handle.state() == PlaybackState::Stopped)
+            {
+                slot.handle = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_last_beats() {
+        let mut history = History::new();
+        for i in 0..100 {
+            let tapped = TappedFrame {
+                frame: Frame::from_mono(i as f32),
+                beat: i % 10 == 5,
+            };
+            history.push(tapped, 50);
+        }
+
+        // beats start on frames 55, 65, 75, 85 and 95, the last one is not
+        // complete
+        let frames = history.last_beats(2).unwrap();
+        assert_eq!(frames.len(), 20);
+        assert_eq!(frames[0].left, 75.0);
+        assert_eq!(frames[19].left, 94.0);
+
+        assert_eq!(history.last_beats(4).unwrap()[0].left, 55.0);
+        assert!(history.last_beats(5).is_none());
+    }
+}
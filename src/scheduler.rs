@@ -0,0 +1,499 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::Deserialize;
+
+use crate::{
+    app::AppData,
+    config::read_config_file,
+    controller::{BoothEvent, Controller, TurntableFocus},
+    file_navigator::FileNavigator,
+};
+
+const SCHEDULE_FILE: &str = "schedule.toml";
+/// Seconds of the crossfade from a track of a playlist to the next one
+const MIX_SECONDS: f64 = 8.0;
+/// Seconds of the fade out at the end of a slot
+const FADE_OUT_SECONDS: f64 = 10.0;
+
+/// Time span playing a playlist, e.g. the opening hours of a venue
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScheduleSlot {
+    /// local time as `HH:MM`, the slot spanning midnight if it ends earlier
+    pub start: String,
+    pub end: String,
+    /// `.m3u` file or folder of tracks
+    pub playlist: PathBuf,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Schedule {
+    /// offset of the local time from UTC, e.g. `120` for UTC+2
+    #[serde(default)]
+    pub utc_offset_minutes: i32,
+    #[serde(default)]
+    pub slots: Vec<ScheduleSlot>,
+}
+
+impl Schedule {
+    /// Load the schedule from the config directory, empty if there is none
+    pub fn load() -> Self {
+        let Some(content) = read_config_file(SCHEDULE_FILE) else {
+            return Self::default();
+        };
+
+        match toml::from_str(&content) {
+            Ok(schedule) => schedule,
+            Err(e) => {
+                log::error!("Cannot read {}: {}", SCHEDULE_FILE, e);
+                Self::default()
+            }
+        }
+    }
+
+    /// Index of the slot running at `minute` of the day
+    pub fn slot_at(&self, minute: u32) -> Option<usize> {
+        self.slots.iter().position(|slot| {
+            let (Some(start), Some(end)) = (parse_time(&slot.start), parse_time(&slot.end)) else {
+                return false;
+            };
+            match start <= end {
+                true => (start..end).contains(&minute),
+                false => minute >= start || minute < end,
+            }
+        })
+    }
+
+    /// Minute of the day in local time
+    fn minute_now(&self) -> u32 {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64;
+        (seconds / 60 + self.utc_offset_minutes as i64).rem_euclid(24 * 60) as u32
+    }
+}
+
+/// Minutes since midnight of a `HH:MM` time
+fn parse_time(time: &str) -> Option<u32> {
+    let (hours, minutes) = time.split_once(':')?;
+    let hours: u32 = hours.trim().parse().ok()?;
+    let minutes: u32 = minutes.trim().parse().ok()?;
+
+    (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+}
+
+/// Tracks of a playlist, either the lines of a `.m3u` file, relative to it,
+/// or the audio files of a folder in name order
+pub fn read_playlist(path: &Path) -> io::Result<Vec<String>> {
+    if path.is_dir() {
+        let mut tracks: Vec<String> = fs::read_dir(path)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path().to_string_lossy().to_string())
+            .filter(FileNavigator::is_supported_audio_filename)
+            .collect();
+        tracks.sort();
+        return Ok(tracks);
+    }
+
+    let dir = path.parent().unwrap_or(Path::new("."));
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| dir.join(line).to_string_lossy().to_string())
+        .collect())
+}
+
+#[derive(Debug)]
+struct Playout {
+    slot: usize,
+    tracks: Vec<String>,
+    /// index of the next track to load
+    next: usize,
+    /// deck on air, the other one getting the next track
+    deck: TurntableFocus,
+    /// next track being loaded on the other deck, played once it is loaded
+    loading: Option<String>,
+    /// seconds left of the crossfade to the other deck
+    mix: Option<f64>,
+    /// seconds left of the fade out and the channel volumes it started from
+    fade_out: Option<(f64, [f64; 2])>,
+}
+
+/// Plays playlists at set times, mixing their tracks from one deck to the
+/// other, as a lightweight radio automation
+#[derive(Debug, Default)]
+pub struct Scheduler {
+    pub schedule: Schedule,
+    playout: Option<Playout>,
+    /// slot last started, not started again while it runs
+    started: Option<usize>,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self {
+            schedule: Schedule::load(),
+            ..Default::default()
+        }
+    }
+
+    /// Track of the running playlist loaded after the one on air
+    pub fn next_track(&self) -> Option<&str> {
+        let playout = self.playout.as_ref()?;
+        playout.tracks.get(playout.next).map(String::as_str)
+    }
+
+    /// Running slot and the number of the track on air
+    pub fn status(&self) -> Option<(usize, usize, usize)> {
+        self.playout
+            .as_ref()
+            .map(|playout| (playout.slot, playout.next, playout.tracks.len()))
+    }
+}
+
+/// Fade the running slot out, it is not started again until it ends
+pub fn stop(app_data: &mut AppData) {
+    let volumes = [
+        app_data.mixer.get_ch_one_volume(),
+        app_data.mixer.get_ch_two_volume(),
+    ];
+    if let Some(playout) = app_data.scheduler.playout.as_mut() {
+        playout.mix = None;
+        playout.fade_out.get_or_insert((FADE_OUT_SECONDS, volumes));
+    }
+}
+
+/// Start the slot due now, and mix or fade out the running one
+pub fn process(app_data: &mut AppData, controller: &Controller, delta: f64) {
+    let scheduler = &mut app_data.scheduler;
+    let slot = scheduler.schedule.slot_at(scheduler.schedule.minute_now());
+    if slot != scheduler.started {
+        scheduler.started = None;
+    }
+
+    match (scheduler.playout.as_ref(), slot) {
+        (Some(playout), _) if slot != Some(playout.slot) => stop(app_data),
+        (None, Some(slot)) if scheduler.started.is_none() => {
+            scheduler.started = Some(slot);
+            start(app_data, controller, slot);
+            return;
+        }
+        _ => (),
+    }
+
+    let Some(mut playout) = app_data.scheduler.playout.take() else {
+        return;
+    };
+    let running = match (playout.fade_out, playout.loading.clone(), playout.mix) {
+        (Some(fade_out), _, _) => fade(app_data, controller, &mut playout, fade_out, delta),
+        (None, Some(path), _) => wait_loaded(app_data, controller, &mut playout, path),
+        (None, None, Some(mix)) => crossfade(app_data, controller, &mut playout, mix, delta),
+        (None, None, None) => follow(app_data, controller, &mut playout),
+    };
+    if running {
+        app_data.scheduler.playout = Some(playout);
+    }
+}
+
+fn start(app_data: &mut AppData, controller: &Controller, slot: usize) {
+    let path = app_data.scheduler.schedule.slots[slot].playlist.clone();
+    let tracks = match read_playlist(&path) {
+        Ok(tracks) => tracks,
+        Err(e) => {
+            log::error!("Cannot read playlist '{}': {}", path.display(), e);
+            app_data
+                .toasts
+                .error(format!("Cannot read playlist {}: {}", path.display(), e));
+            return;
+        }
+    };
+
+    // a deck left playing is mixed out to the playlist on the other one
+    let on_air = [TurntableFocus::One, TurntableFocus::Two]
+        .into_iter()
+        .find(|deck| is_playing(app_data, *deck));
+    let deck = on_air.map_or(TurntableFocus::One, other_deck);
+    if is_playing(app_data, deck) {
+        app_data.toasts.warn(format!(
+            "Cannot start playlist {}, both decks are playing",
+            path.display()
+        ));
+        return;
+    }
+
+    let mut playout = Playout {
+        slot,
+        tracks,
+        next: 0,
+        deck: other_deck(deck),
+        loading: None,
+        mix: None,
+        fade_out: None,
+    };
+    if !load_next(app_data, controller, &mut playout) {
+        app_data
+            .toasts
+            .warn(format!("Nothing to play in playlist {}", path.display()));
+        return;
+    }
+
+    log::info!("Starting scheduled playlist '{}'", path.display());
+    app_data.scheduler.playout = Some(playout);
+}
+
+/// Start mixing to the next track once the deck on air reaches its outro,
+/// returning whether the playout goes on
+fn follow(app_data: &mut AppData, controller: &Controller, playout: &mut Playout) -> bool {
+    let turntable = match playout.deck {
+        TurntableFocus::One => &app_data.turntable_one,
+        TurntableFocus::Two => &app_data.turntable_two,
+    };
+    let (Some(path), Some(position), Some(duration)) = (
+        turntable.currently_loaded(),
+        turntable.position(),
+        turntable.duration(),
+    ) else {
+        return false;
+    };
+    let playing = turntable.is_playing();
+    let mix_point = app_data
+        .library
+        .track(&path)
+        .and_then(|track| track.analysis)
+        .map_or(duration - MIX_SECONDS, |analysis| analysis.outro_start);
+
+    if position < mix_point {
+        return true;
+    }
+    let other = other_deck(playout.deck);
+    if is_playing(app_data, other) {
+        log::info!("Scheduled playlist taken over on the other deck");
+        return false;
+    }
+    if load_next(app_data, controller, playout) {
+        return true;
+    }
+    // the playlist is over once the last track ends
+    playing
+}
+
+/// Play the next track once it is loaded on the deck off air, mixed from the
+/// deck on air if it still plays, returning whether the playout goes on
+fn wait_loaded(
+    app_data: &mut AppData,
+    controller: &Controller,
+    playout: &mut Playout,
+    path: String,
+) -> bool {
+    let deck = other_deck(playout.deck);
+    if app_data.track_loader.loading(deck) == Some(Path::new(&path)) {
+        return true;
+    }
+    playout.loading = None;
+
+    let loaded = match deck {
+        TurntableFocus::One => app_data.turntable_one.currently_loaded(),
+        TurntableFocus::Two => app_data.turntable_two.currently_loaded(),
+    };
+    if loaded.as_ref() != Some(&path) {
+        // the track could not be loaded, its error already shown
+        return load_next(app_data, controller, playout) || is_playing(app_data, playout.deck);
+    }
+
+    if is_playing(app_data, playout.deck) {
+        playout.mix = Some(MIX_SECONDS);
+    } else {
+        set_volume(app_data, controller, deck, 1.0);
+        set_volume(app_data, controller, playout.deck, 0.0);
+        playout.deck = deck;
+    }
+    play_deck(app_data, controller, deck);
+    true
+}
+
+fn crossfade(
+    app_data: &mut AppData,
+    controller: &Controller,
+    playout: &mut Playout,
+    mix: f64,
+    delta: f64,
+) -> bool {
+    let remaining = mix - delta;
+    let on_air = (remaining / MIX_SECONDS).max(0.0);
+    let other = other_deck(playout.deck);
+    set_volume(app_data, controller, playout.deck, on_air);
+    set_volume(app_data, controller, other, 1.0 - on_air);
+
+    playout.mix = Some(remaining);
+    if remaining <= 0.0 {
+        stop_deck(app_data, controller, playout.deck);
+        playout.deck = other;
+        playout.mix = None;
+    }
+    true
+}
+
+fn fade(
+    app_data: &mut AppData,
+    controller: &Controller,
+    playout: &mut Playout,
+    (remaining, from): (f64, [f64; 2]),
+    delta: f64,
+) -> bool {
+    let remaining = remaining - delta;
+    let level = (remaining / FADE_OUT_SECONDS).max(0.0);
+    set_volume(app_data, controller, TurntableFocus::One, from[0] * level);
+    set_volume(app_data, controller, TurntableFocus::Two, from[1] * level);
+
+    playout.fade_out = Some((remaining, from));
+    if remaining > 0.0 {
+        return true;
+    }
+    log::info!("Scheduled playlist over");
+    stop_deck(app_data, controller, TurntableFocus::One);
+    stop_deck(app_data, controller, TurntableFocus::Two);
+    false
+}
+
+/// Start loading the next track of the playlist on the deck off air, which
+/// is not playing, returning whether there was one left
+fn load_next(app_data: &mut AppData, controller: &Controller, playout: &mut Playout) -> bool {
+    let Some(path) = playout.tracks.get(playout.next).cloned() else {
+        return false;
+    };
+    playout.next += 1;
+
+    let focus = app_data.turntable_focus;
+    app_data.turntable_focus = other_deck(playout.deck);
+    controller.handle_event(app_data, BoothEvent::TrackLoading(Path::new(&path)));
+    app_data.turntable_focus = focus;
+
+    playout.loading = Some(path);
+    true
+}
+
+fn is_playing(app_data: &AppData, deck: TurntableFocus) -> bool {
+    match deck {
+        TurntableFocus::One => app_data.turntable_one.is_playing(),
+        TurntableFocus::Two => app_data.turntable_two.is_playing(),
+    }
+}
+
+fn play_deck(app_data: &mut AppData, controller: &Controller, deck: TurntableFocus) {
+    let (turntable, event) = match deck {
+        TurntableFocus::One => (&app_data.turntable_one, BoothEvent::ToggleStartStopOne),
+        TurntableFocus::Two => (&app_data.turntable_two, BoothEvent::ToggleStartStopTwo),
+    };
+    if !turntable.is_playing() {
+        controller.handle_event(app_data, event);
+    }
+}
+
+fn stop_deck(app_data: &mut AppData, controller: &Controller, deck: TurntableFocus) {
+    let (turntable, event) = match deck {
+        TurntableFocus::One => (&app_data.turntable_one, BoothEvent::ToggleStartStopOne),
+        TurntableFocus::Two => (&app_data.turntable_two, BoothEvent::ToggleStartStopTwo),
+    };
+    if turntable.is_playing() {
+        controller.handle_event(app_data, event);
+    }
+}
+
+fn set_volume(app_data: &mut AppData, controller: &Controller, deck: TurntableFocus, volume: f64) {
+    let event = match deck {
+        TurntableFocus::One => BoothEvent::VolumeOneChanged(volume),
+        TurntableFocus::Two => BoothEvent::VolumeTwoChanged(volume),
+    };
+    controller.handle_event(app_data, event);
+}
+
+fn other_deck(deck: TurntableFocus) -> TurntableFocus {
+    match deck {
+        TurntableFocus::One => TurntableFocus::Two,
+        TurntableFocus::Two => TurntableFocus::One,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{audio_backend::testing::constant_wav, mapping::KeyMap, settings::Settings};
+
+    #[test]
+    fn test_slot_at() {
+        let slot = |start: &str, end: &str| ScheduleSlot {
+            start: start.to_string(),
+            end: end.to_string(),
+            playlist: PathBuf::new(),
+        };
+        let schedule = Schedule {
+            utc_offset_minutes: 0,
+            slots: vec![
+                slot("10:00", "12:30"),
+                slot("22:00", "02:00"),
+                slot("25:00", "26:00"),
+            ],
+        };
+
+        assert_eq!(parse_time("09:05"), Some(9 * 60 + 5));
+        assert_eq!(schedule.slot_at(9 * 60 + 59), None);
+        assert_eq!(schedule.slot_at(10 * 60), Some(0));
+        assert_eq!(schedule.slot_at(12 * 60 + 30), None);
+        // across midnight
+        assert_eq!(schedule.slot_at(23 * 60), Some(1));
+        assert_eq!(schedule.slot_at(60), Some(1));
+        assert_eq!(schedule.slot_at(3 * 60), None);
+    }
+
+    #[test]
+    fn test_start_on_idle_deck() {
+        let track = constant_wav("scheduled", 0.5, 0.5);
+        let playlist = std::env::temp_dir().join("scheduled.m3u");
+        fs::write(&playlist, format!("missing.wav\n{}\n", track.display())).unwrap();
+        let root_dir = std::env::temp_dir().to_string_lossy().to_string();
+        let mut app_data = AppData::new(Settings::default(), KeyMap::load(), &root_dir);
+        let controller = Controller::new();
+
+        controller.handle_event(&mut app_data, BoothEvent::TrackLoad(&track));
+        controller.handle_event(&mut app_data, BoothEvent::ToggleStartStopOne);
+        assert!(app_data.turntable_one.is_playing());
+
+        // the slot runs around noon, local time
+        let minute = Schedule::default().minute_now() as i32;
+        app_data.scheduler.schedule = Schedule {
+            utc_offset_minutes: 12 * 60 - minute,
+            slots: vec![ScheduleSlot {
+                start: "06:00".to_string(),
+                end: "18:00".to_string(),
+                playlist: playlist.clone(),
+            }],
+        };
+        for _ in 0..500 {
+            process(&mut app_data, &controller, 0.0);
+            for deck in app_data.track_loader.poll() {
+                controller.handle_event(&mut app_data, BoothEvent::TrackLoaded(deck));
+            }
+            if app_data.turntable_two.is_playing() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        // the missing track is skipped, the other one mixed in on deck two
+        assert!(app_data.turntable_two.is_playing());
+        assert_eq!(app_data.scheduler.status(), Some((0, 2, 2)));
+        let playout = app_data.scheduler.playout.as_ref().unwrap();
+        assert_eq!(playout.deck, TurntableFocus::One);
+        assert_eq!(playout.mix, Some(MIX_SECONDS));
+
+        let _ = fs::remove_file(track);
+        let _ = fs::remove_file(playlist);
+    }
+}
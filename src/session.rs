@@ -0,0 +1,167 @@
+use std::{
+    fs, panic,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::AppData,
+    config::data_dir,
+    controller::{BoothEvent, Controller, TurntableFocus},
+    logger,
+    turntable::Turntable,
+};
+
+/// Last captured session, written to disk if the app panics
+static SNAPSHOT: Mutex<Option<Session>> = Mutex::new(None);
+
+/// State of a turntable that can be restored after a crash
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DeckState {
+    pub path: Option<String>,
+    /// position in seconds
+    pub position: Option<f64>,
+    pub pitch: f64,
+}
+
+impl DeckState {
+    fn capture(turntable: &Turntable) -> Self {
+        Self {
+            path: turntable.currently_loaded(),
+            position: turntable.position(),
+            pitch: turntable.pitch(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    pub deck_one: DeckState,
+    pub deck_two: DeckState,
+}
+
+impl Session {
+    pub fn capture(app_data: &AppData) -> Self {
+        Self {
+            deck_one: DeckState::capture(&app_data.turntable_one),
+            deck_two: DeckState::capture(&app_data.turntable_two),
+        }
+    }
+
+    /// Whether the session has something worth restoring
+    pub fn is_empty(&self) -> bool {
+        self.deck_one.path.is_none() && self.deck_two.path.is_none()
+    }
+
+    /// Load the tracks back, paused at their previous position
+    pub fn restore(&self, app_data: &mut AppData, controller: &Controller) {
+        let focus = app_data.turntable_focus;
+
+        for (deck, deck_focus) in [
+            (&self.deck_one, TurntableFocus::One),
+            (&self.deck_two, TurntableFocus::Two),
+        ] {
+            let Some(path) = &deck.path else {
+                continue;
+            };
+
+            controller.handle_event(app_data, BoothEvent::FocusChanged(deck_focus));
+            controller.handle_event(app_data, BoothEvent::TrackLoad(Path::new(path)));
+
+            let turntable = match deck_focus {
+                TurntableFocus::One => &app_data.turntable_one,
+                TurntableFocus::Two => &app_data.turntable_two,
+            };
+            let percent = match (deck.position, turntable.duration()) {
+                (Some(position), Some(duration)) if duration > 0.0 => position / duration,
+                _ => 0.0,
+            };
+
+            let (seek, pitch) = match deck_focus {
+                TurntableFocus::One => (
+                    BoothEvent::SeekOne(percent),
+                    BoothEvent::PitchOneChanged(deck.pitch),
+                ),
+                TurntableFocus::Two => (
+                    BoothEvent::SeekTwo(percent),
+                    BoothEvent::PitchTwoChanged(deck.pitch),
+                ),
+            };
+            controller.handle_event(app_data, seek);
+            controller.handle_event(app_data, pitch);
+        }
+
+        controller.handle_event(app_data, BoothEvent::FocusChanged(focus));
+    }
+}
+
+fn recovery_dir() -> PathBuf {
+    data_dir().join("recovery")
+}
+
+/// Keep the session so that the crash handler can save it
+pub fn update_snapshot(session: Session) {
+    // never block, the crash handler may be holding the lock
+    if let Ok(mut snapshot) = SNAPSHOT.try_lock() {
+        *snapshot = Some(session);
+    }
+}
+
+/// Save the last session snapshot and the recent logs when the app panics
+pub fn install_crash_handler() {
+    let previous_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        previous_hook(info);
+
+        let session = match SNAPSHOT.try_lock() {
+            Ok(snapshot) => snapshot.clone(),
+            Err(_) => None,
+        };
+
+        match save_recovery(session.as_ref()) {
+            Ok(()) => eprintln!("Session saved to '{}'", recovery_dir().display()),
+            Err(e) => eprintln!("Cannot save session: {}", e),
+        }
+    }));
+}
+
+fn save_recovery(session: Option<&Session>) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = recovery_dir();
+    fs::create_dir_all(&dir)?;
+
+    fs::write(dir.join("crash.log"), logger::recent_lines().join("\n"))?;
+
+    if let Some(session) = session.filter(|session| !session.is_empty()) {
+        fs::write(dir.join("session.toml"), toml::to_string(session)?)?;
+    }
+
+    Ok(())
+}
+
+/// Session left by a previous crash, if any
+pub fn load_recovery() -> Option<Session> {
+    let path = recovery_dir().join("session.toml");
+    let content = fs::read_to_string(&path).ok()?;
+
+    match toml::from_str(&content) {
+        Ok(session) => {
+            log::warn!("Found a session to recover in '{}'", path.display());
+            Some(session)
+        }
+        Err(e) => {
+            log::error!("Cannot read '{}': {}", path.display(), e);
+            discard_recovery();
+            None
+        }
+    }
+}
+
+/// Remove the saved session once it has been restored or dismissed
+pub fn discard_recovery() {
+    match fs::remove_file(recovery_dir().join("session.toml")) {
+        Ok(()) => (),
+        Err(e) => log::error!("Cannot remove recovered session: {}", e),
+    }
+}
@@ -0,0 +1,151 @@
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{config::data_dir, recorder::SplitOptions, transform::TransformGate};
+
+const SETTINGS_FILE: &str = "settings.toml";
+
+/// User preferences changed from the settings window
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// larger text and controls with a high contrast theme
+    pub accessibility: bool,
+    /// larger hit targets and scratching by dragging the cover
+    pub touch_mode: bool,
+    /// scale of the whole interface on top of the scale factor of the
+    /// monitor
+    pub ui_scale: f32,
+    /// record each deck to its own file along with the master
+    pub record_stems: bool,
+    pub record_split: SplitOptions,
+    /// enabling the cue of a channel disables the cue of the other one
+    pub exclusive_cue: bool,
+    /// loading a track enables the cue of its channel
+    pub auto_cue: bool,
+    /// a track loaded without memory points gets one on its first downbeat
+    /// once analyzed, where its deck is cued if stopped
+    pub cue_first_beat: bool,
+    /// decks stop and return to the start at the end of their track
+    pub auto_pause: bool,
+    /// decode the next track of the prepare list or of the scheduled
+    /// playlist ahead, so that loading it is instant
+    pub preload_next: bool,
+    /// tracks longer than this many minutes are streamed from their file
+    /// instead of decoded into memory, 0 to always decode them
+    pub stream_over_minutes: u32,
+    /// minutes without any deck heard on the master before warning of dead
+    /// air and stopping the recording, 0 to disable
+    pub dead_air_minutes: u32,
+    /// channels are limited after their EQ so that they cannot clip the
+    /// master
+    pub channel_limiter: bool,
+    /// MIDI preset forced over the one detected from the port name
+    pub midi_preset: Option<String>,
+    /// seconds taken by the morph buttons to glide between snapshots
+    pub morph_time: f64,
+    /// moving to a memory point of a stopped deck previews it on the cue
+    pub cue_preview: bool,
+    /// closing the crossfader on deck one pauses it and opening it starts it
+    pub fader_start_one: bool,
+    pub fader_start_two: bool,
+    /// note value chopping a channel while its transform button is held
+    pub transform_gate: TransformGate,
+    /// loops changed from the loop controls snap to the beat grid of their
+    /// track
+    pub quantize_loops: bool,
+    /// lock the cursor while scratching or seeking with the pointer, off
+    /// where grabbing misbehaves
+    pub grab_cursor: bool,
+    /// keep the window above the other ones, e.g. next to streaming software
+    pub always_on_top: bool,
+    /// closing the window minimizes it and the audio keeps running, e.g. for
+    /// background music playout
+    pub background_mode: bool,
+    /// gain in dB of the master while ducked
+    pub duck_gain: f64,
+    /// milliseconds taken to duck and release the master
+    pub duck_ramp_ms: u64,
+    /// local port listening for ducking requests, read at startup
+    pub duck_port: Option<u16>,
+    /// address receiving the beat clock and the deck positions as OSC
+    /// messages, read at startup
+    pub timecode_target: Option<String>,
+    /// local port serving the now playing overlay, read at startup
+    pub overlay_port: Option<u16>,
+    /// program tagging tracks as vocal or instrumental instead of the
+    /// built-in heuristic, given the track path and printing `vocal` or
+    /// `instrumental`
+    pub vocal_detector: Option<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            accessibility: false,
+            touch_mode: false,
+            ui_scale: 1.0,
+            record_stems: false,
+            record_split: SplitOptions::default(),
+            exclusive_cue: false,
+            auto_cue: false,
+            cue_first_beat: false,
+            auto_pause: false,
+            preload_next: true,
+            stream_over_minutes: 20,
+            dead_air_minutes: 0,
+            channel_limiter: true,
+            midi_preset: None,
+            morph_time: 4.0,
+            cue_preview: false,
+            fader_start_one: false,
+            fader_start_two: false,
+            transform_gate: TransformGate::Off,
+            quantize_loops: false,
+            grab_cursor: true,
+            always_on_top: false,
+            background_mode: false,
+            duck_gain: -12.0,
+            duck_ramp_ms: 200,
+            duck_port: None,
+            timecode_target: None,
+            overlay_port: None,
+            vocal_detector: None,
+        }
+    }
+}
+
+impl Settings {
+    /// Load the saved settings, or the default ones
+    pub fn load() -> Self {
+        let path = data_dir().join(SETTINGS_FILE);
+        let Ok(content) = fs::read_to_string(&path) else {
+            return Self::default();
+        };
+
+        match toml::from_str(&content) {
+            Ok(settings) => settings,
+            Err(e) => {
+                log::error!("Cannot read '{}': {}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        let dir = data_dir();
+        let result = toml::to_string(self)
+            .map_err(|e| e.to_string())
+            .and_then(|content| {
+                fs::create_dir_all(&dir)
+                    .and_then(|_| fs::write(dir.join(SETTINGS_FILE), content))
+                    .map_err(|e| e.to_string())
+            });
+
+        match result {
+            Ok(()) => (),
+            Err(e) => log::error!("Cannot save settings: {}", e),
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use std::f32::consts::TAU;
+
+use egui::{Color32, ColorImage, TextureHandle};
+use kira::Frame;
+
+/// Columns over the whole track, enough for a wide window
+const COLUMNS: usize = 1024;
+/// Frequency bands from the lowest to the highest frequency, spaced
+/// logarithmically as heard
+const BANDS: usize = 160;
+const MIN_FREQUENCY: f32 = 30.0;
+const MAX_FREQUENCY: f32 = 16_000.0;
+/// Samples of each spectrum, a power of two
+const FFT_SIZE: usize = 4096;
+/// Level in dBFS shown black
+const FLOOR_DB: f32 = -90.0;
+
+/// Levels of the frequency bands over a whole track, to find its drops and
+/// breakdowns
+pub struct Spectrogram {
+    /// from 0 to 1, the bands of each column from the lowest one
+    levels: Vec<f32>,
+    pub duration: f64,
+}
+
+impl Spectrogram {
+    pub fn compute(frames: &[Frame], sample_rate: u32) -> Self {
+        let window: Vec<f32> = (0..FFT_SIZE)
+            .map(|i| 0.5 - 0.5 * (TAU * i as f32 / FFT_SIZE as f32).cos())
+            .collect();
+        let window_sum: f32 = window.iter().sum();
+        let twiddles: Vec<(f32, f32)> = (0..FFT_SIZE / 2)
+            .map(|k| {
+                let (sin, cos) = (-TAU * k as f32 / FFT_SIZE as f32).sin_cos();
+                (cos, sin)
+            })
+            .collect();
+        let bins: Vec<_> = (0..BANDS)
+            .map(|band| band_bins(band, sample_rate))
+            .collect();
+
+        let mut levels = Vec::with_capacity(COLUMNS * BANDS);
+        let (mut re, mut im) = (vec![0.0; FFT_SIZE], vec![0.0; FFT_SIZE]);
+        for column in 0..COLUMNS {
+            let start = column * frames.len() / COLUMNS;
+            for (i, value) in re.iter_mut().enumerate() {
+                let frame = frames.get(start + i).copied().unwrap_or(Frame::ZERO);
+                *value = (frame.left + frame.right) / 2.0 * window[i];
+            }
+            im.fill(0.0);
+            fft(&mut re, &mut im, &twiddles);
+
+            levels.extend(bins.iter().map(|(low, high)| {
+                let magnitude = (*low..*high)
+                    .map(|bin| (re[bin] * re[bin] + im[bin] * im[bin]).sqrt())
+                    .fold(0.0, f32::max);
+                let db = 20.0 * (2.0 * magnitude / window_sum).max(1e-9).log10();
+                ((db - FLOOR_DB) / -FLOOR_DB).clamp(0.0, 1.0)
+            }));
+        }
+
+        Self {
+            levels,
+            duration: frames.len() as f64 / sample_rate as f64,
+        }
+    }
+
+    fn level(&self, column: usize, band: usize) -> f32 {
+        self.levels[column * BANDS + band]
+    }
+
+    /// Image of the spectrogram, time going right and frequency up
+    pub fn image(&self) -> ColorImage {
+        let mut image = ColorImage::new([COLUMNS, BANDS], Color32::BLACK);
+        for band in 0..BANDS {
+            for column in 0..COLUMNS {
+                image[(column, BANDS - 1 - band)] = heat(self.level(column, band));
+            }
+        }
+        image
+    }
+}
+
+/// Range of the FFT bins of `band`, at least one bin wide
+fn band_bins(band: usize, sample_rate: u32) -> (usize, usize) {
+    let max_frequency = MAX_FREQUENCY.min(sample_rate as f32 / 2.0);
+    let frequency = |band: usize| {
+        MIN_FREQUENCY * (max_frequency / MIN_FREQUENCY).powf(band as f32 / BANDS as f32)
+    };
+    let bin = |frequency: f32| {
+        ((frequency * FFT_SIZE as f32 / sample_rate as f32).round() as usize).min(FFT_SIZE / 2 - 1)
+    };
+
+    let low = bin(frequency(band));
+    (low, bin(frequency(band + 1)).max(low + 1))
+}
+
+/// Black through blue, red and yellow to white as the level rises
+fn heat(level: f32) -> Color32 {
+    const STOPS: [[f32; 3]; 5] = [
+        [0.0, 0.0, 0.0],
+        [0.1, 0.1, 0.6],
+        [0.8, 0.1, 0.2],
+        [1.0, 0.8, 0.1],
+        [1.0, 1.0, 1.0],
+    ];
+
+    let position = level * (STOPS.len() - 1) as f32;
+    let index = (position as usize).min(STOPS.len() - 2);
+    let t = position - index as f32;
+    let [r, g, b] = [0, 1, 2].map(|channel| {
+        let value = STOPS[index][channel] * (1.0 - t) + STOPS[index + 1][channel] * t;
+        (value * 255.0) as u8
+    });
+    Color32::from_rgb(r, g, b)
+}
+
+/// In place radix-2 FFT, `twiddles` holding the roots of unity of the
+/// length of `re` and `im`
+pub fn fft(re: &mut [f32], im: &mut [f32], twiddles: &[(f32, f32)]) {
+    let n = re.len();
+
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut length = 2;
+    while length <= n {
+        let stride = n / length;
+        for start in (0..n).step_by(length) {
+            for k in 0..length / 2 {
+                let (cos, sin) = twiddles[k * stride];
+                let (a, b) = (start + k, start + k + length / 2);
+                let (re_b, im_b) = (re[b] * cos - im[b] * sin, re[b] * sin + im[b] * cos);
+                re[b] = re[a] - re_b;
+                im[b] = im[a] - im_b;
+                re[a] += re_b;
+                im[a] += im_b;
+            }
+        }
+        length <<= 1;
+    }
+}
+
+/// Spectrogram window of a track of the library
+pub struct SpectrogramView {
+    pub path: String,
+    /// texture and duration of the track, once computed
+    texture: Option<(TextureHandle, f64)>,
+}
+
+impl SpectrogramView {
+    pub fn new(path: String) -> Self {
+        Self {
+            path,
+            texture: None,
+        }
+    }
+
+    pub fn set(&mut self, ctx: &egui::Context, spectrogram: &Spectrogram) {
+        let texture = ctx.load_texture(
+            format!("spectrogram {}", self.path),
+            spectrogram.image(),
+            Default::default(),
+        );
+        self.texture = Some((texture, spectrogram.duration));
+    }
+
+    pub fn texture(&self) -> Option<&(TextureHandle, f64)> {
+        self.texture.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compute() {
+        let sample_rate = 8_000;
+        let frames: Vec<Frame> = (0..4 * sample_rate)
+            .map(|i| {
+                // a 1 kHz tone in the second half
+                let value = match i < 2 * sample_rate {
+                    true => 0.0,
+                    false => 0.5 * (TAU * 1_000.0 * i as f32 / sample_rate as f32).sin(),
+                };
+                Frame::from_mono(value)
+            })
+            .collect();
+
+        let spectrogram = Spectrogram::compute(&frames, sample_rate);
+        assert_eq!(spectrogram.duration, 4.0);
+        assert_eq!(spectrogram.level(COLUMNS / 4, BANDS / 2), 0.0);
+
+        let column = COLUMNS * 3 / 4;
+        let loudest = (0..BANDS)
+            .max_by(|a, b| {
+                spectrogram
+                    .level(column, *a)
+                    .total_cmp(&spectrogram.level(column, *b))
+            })
+            .unwrap();
+        let (low, high) = band_bins(loudest, sample_rate);
+        let bin = 1_000 * FFT_SIZE / sample_rate as usize;
+        assert!((low..high).contains(&bin));
+        // -6 dBFS
+        assert!((spectrogram.level(column, loudest) - 84.0 / 90.0).abs() < 0.02);
+    }
+}
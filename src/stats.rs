@@ -0,0 +1,233 @@
+use std::{
+    fs, io,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{config::data_dir, utils::to_hour_min_sec_str};
+
+/// Seconds between two tempo samples
+const BPM_SAMPLE_SECONDS: f64 = 10.0;
+/// Smallest tempo change sampled before the next sample is due, so that
+/// the timeline shows the jumps between tracks where they happen
+const BPM_CHANGE: f64 = 1.0;
+
+/// What the booth sounds like at a frame, for the statistics
+#[derive(Debug, Clone, Default)]
+pub struct StatsSample {
+    /// tracks of the decks heard on the master
+    pub audible: [Option<String>; 2],
+    pub volumes: [f64; 2],
+    /// tempo of the deck heard the most
+    pub master_bpm: Option<f64>,
+}
+
+/// Statistics of the running session, e.g. to review a practice session or
+/// to share a set summary
+#[derive(Debug)]
+pub struct SessionStats {
+    /// seconds since the start of the session
+    elapsed: f64,
+    started_at: SystemTime,
+    /// tracks in the order they were first heard
+    tracks: Vec<String>,
+    /// seconds since the start when each track was first heard
+    track_starts: Vec<f64>,
+    /// track last counted on each deck, not counted again while it plays
+    counted: [Option<String>; 2],
+    /// tempo samples, as seconds since the start and BPM
+    bpm: Vec<(f64, f64)>,
+    /// seconds both decks were heard together
+    mix_seconds: f64,
+    /// total travel of the channel faders, 1.0 being a full throw
+    fader_travel: f64,
+    last_volumes: Option<[f64; 2]>,
+}
+
+impl Default for SessionStats {
+    fn default() -> Self {
+        Self {
+            elapsed: 0.0,
+            started_at: SystemTime::now(),
+            tracks: Vec::new(),
+            track_starts: Vec::new(),
+            counted: [None, None],
+            bpm: Vec::new(),
+            mix_seconds: 0.0,
+            fader_travel: 0.0,
+            last_volumes: None,
+        }
+    }
+}
+
+impl SessionStats {
+    pub fn update(&mut self, delta: f64, sample: &StatsSample) {
+        self.elapsed += delta;
+
+        for (counted, audible) in self.counted.iter_mut().zip(sample.audible.iter()) {
+            if let Some(path) = audible {
+                if counted.as_ref() != Some(path) {
+                    self.tracks.push(path.clone());
+                    self.track_starts.push(self.elapsed);
+                    *counted = Some(path.clone());
+                }
+            }
+        }
+
+        if sample.audible.iter().all(Option::is_some) {
+            self.mix_seconds += delta;
+        }
+
+        if let Some(last) = self.last_volumes {
+            self.fader_travel += (sample.volumes[0] - last[0]).abs();
+            self.fader_travel += (sample.volumes[1] - last[1]).abs();
+        }
+        self.last_volumes = Some(sample.volumes);
+
+        let due = self.bpm.last().is_none_or(|(time, bpm)| {
+            self.elapsed - time >= BPM_SAMPLE_SECONDS
+                || sample
+                    .master_bpm
+                    .is_some_and(|master_bpm| (master_bpm - bpm).abs() >= BPM_CHANGE)
+        });
+        if let (true, Some(bpm)) = (due, sample.master_bpm) {
+            self.bpm.push((self.elapsed, bpm));
+        }
+    }
+
+    /// Seconds since the start of the session
+    pub fn set_length(&self) -> f64 {
+        self.elapsed
+    }
+
+    pub fn tracks(&self) -> &[String] {
+        &self.tracks
+    }
+
+    /// Whether the track at `path` was heard in this session, e.g. to avoid
+    /// playing it again
+    pub fn has_played(&self, path: &str) -> bool {
+        self.tracks.iter().any(|track| track == path)
+    }
+
+    /// Seconds since the start when each track of [`Self::tracks`] was first
+    /// heard
+    pub fn track_starts(&self) -> &[f64] {
+        &self.track_starts
+    }
+
+    /// Tempo samples, as seconds since the start and BPM
+    pub fn bpm_samples(&self) -> &[(f64, f64)] {
+        &self.bpm
+    }
+
+    pub fn average_bpm(&self) -> Option<f64> {
+        match self.bpm.len() {
+            0 => None,
+            count => Some(self.bpm.iter().map(|(_, bpm)| bpm).sum::<f64>() / count as f64),
+        }
+    }
+
+    pub fn mix_seconds(&self) -> f64 {
+        self.mix_seconds
+    }
+
+    pub fn fader_travel(&self) -> f64 {
+        self.fader_travel
+    }
+
+    /// Plain text summary of the session
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "Set length: {}\nTracks played: {}\n",
+            to_hour_min_sec_str(self.elapsed),
+            self.tracks.len()
+        );
+        if let Some(bpm) = self.average_bpm() {
+            summary += &format!("Average tempo: {:.1} BPM\n", bpm);
+        }
+        summary += &format!(
+            "Time mixing both decks: {}\nFader travel: {:.1} throws\n",
+            to_hour_min_sec_str(self.mix_seconds),
+            self.fader_travel
+        );
+
+        summary += "\nTracklist:\n";
+        for (index, (track, start)) in self.tracks.iter().zip(&self.track_starts).enumerate() {
+            let name = track.rsplit('/').next().unwrap_or(track);
+            summary += &format!("{}. {} {}\n", index + 1, to_hour_min_sec_str(*start), name);
+        }
+
+        summary += "\nTempo timeline:\n";
+        for (time, bpm) in self.bpm.iter() {
+            summary += &format!("{} {:.1}\n", to_hour_min_sec_str(*time), bpm);
+        }
+        summary
+    }
+
+    /// Write the summary to the `sessions` folder of the data directory
+    pub fn save(&self) -> io::Result<PathBuf> {
+        let dir = data_dir().join("sessions");
+        fs::create_dir_all(&dir)?;
+
+        let time = self
+            .started_at
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let path = dir.join(format!("session-{}.txt", time));
+        fs::write(&path, self.summary())?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats() {
+        let mut stats = SessionStats::default();
+        let mut sample = StatsSample {
+            audible: [Some("a.mp3".to_string()), None],
+            volumes: [1.0, 0.0],
+            master_bpm: Some(120.0),
+        };
+
+        stats.update(10.0, &sample);
+        stats.update(5.0, &sample);
+        stats.update(5.0, &sample);
+
+        sample.audible[1] = Some("b.mp3".to_string());
+        sample.volumes[1] = 0.5;
+        stats.update(10.0, &sample);
+        // a tempo change is sampled before the next sample is due
+        sample.master_bpm = Some(123.0);
+        stats.update(1.0, &sample);
+        stats.update(9.0, &sample);
+
+        sample.audible[0] = None;
+        sample.volumes = [0.0, 1.0];
+        stats.update(20.0, &sample);
+
+        assert_eq!(stats.tracks(), ["a.mp3", "b.mp3"]);
+        assert!(stats.has_played("b.mp3") && !stats.has_played("c.mp3"));
+        assert_eq!(stats.set_length(), 60.0);
+        assert_eq!(stats.mix_seconds(), 20.0);
+        assert_eq!(stats.fader_travel(), 2.0);
+        assert_eq!(stats.track_starts(), [10.0, 30.0]);
+        assert_eq!(
+            stats.bpm_samples(),
+            [
+                (10.0, 120.0),
+                (20.0, 120.0),
+                (30.0, 120.0),
+                (31.0, 123.0),
+                (60.0, 123.0)
+            ]
+        );
+        assert_eq!(stats.average_bpm(), Some(121.2));
+        assert!(stats.summary().contains("2. 0:00:30 b.mp3"));
+        assert!(stats.summary().contains("0:01:00 123.0"));
+    }
+}
@@ -0,0 +1,158 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use kira::{
+    sound::static_sound::StaticSoundData,
+    track::{TrackBuilder, TrackHandle, TrackRoutes},
+    tween::Tween,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{audio_backend::Manager, file_navigator::FileNavigator};
+
+/// Extension of the folder holding the stems of a track, next to the track
+const STEMS_EXTENSION: &str = "stems";
+
+/// Part of a track separated from the rest of the mix
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Stem {
+    Vocals,
+    Drums,
+    /// everything else, e.g. bass, melodies and pads
+    Music,
+}
+
+impl Stem {
+    pub const ALL: [Stem; 3] = [Stem::Vocals, Stem::Drums, Stem::Music];
+
+    fn index(&self) -> usize {
+        match self {
+            Stem::Vocals => 0,
+            Stem::Drums => 1,
+            Stem::Music => 2,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Stem::Vocals => "VOCALS",
+            Stem::Drums => "DRUMS",
+            Stem::Music => "MUSIC",
+        }
+    }
+
+    /// Stem of a separated file from its name, e.g. `vocals.wav`. Any other
+    /// file, such as the `bass.wav` and `other.wav` of a four stem
+    /// separation, is part of the music
+    fn of_file(path: &Path) -> Self {
+        match path
+            .file_stem()
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .as_deref()
+        {
+            Some("vocals") => Stem::Vocals,
+            Some("drums") => Stem::Drums,
+            _ => Stem::Music,
+        }
+    }
+}
+
+/// Separated files of a track, from the folder named after the track with a
+/// `.stems` extension, e.g. `Song.stems/vocals.wav` for `Song.mp3`
+pub fn find(track: &Path) -> Vec<(Stem, PathBuf)> {
+    let Ok(entries) = fs::read_dir(track.with_extension(STEMS_EXTENSION)) else {
+        return Vec::new();
+    };
+
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            FileNavigator::is_supported_audio_filename(&path.to_string_lossy().to_string())
+        })
+        .collect();
+    files.sort();
+    files
+        .into_iter()
+        .map(|path| (Stem::of_file(&path), path))
+        .collect()
+}
+
+/// Decode the separated files of a track, skipping the unreadable ones
+pub fn load(track: &Path) -> Vec<(Stem, StaticSoundData)> {
+    find(track)
+        .into_iter()
+        .filter_map(|(stem, path)| match StaticSoundData::from_file(&path) {
+            Ok(sound_data) => Some((stem, sound_data)),
+            Err(e) => {
+                log::warn!("Cannot load stem {}: {}", path.display(), e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Tracks of a deck playing each stem into its channel, with the level and
+/// mute of each stem
+pub struct StemTracks {
+    tracks: [TrackHandle; 3],
+    levels: [f64; 3],
+    muted: [bool; 3],
+}
+
+impl StemTracks {
+    pub fn new(manager: &mut Manager, channel: &TrackHandle) -> Self {
+        let tracks = Stem::ALL.map(|_| {
+            manager
+                .add_sub_track(TrackBuilder::new().routes(TrackRoutes::parent(channel)))
+                .unwrap()
+        });
+
+        Self {
+            tracks,
+            levels: [1.0; 3],
+            muted: [false; 3],
+        }
+    }
+
+    pub fn track(&self, stem: Stem) -> &TrackHandle {
+        &self.tracks[stem.index()]
+    }
+
+    pub fn level(&self, stem: Stem) -> f64 {
+        self.levels[stem.index()]
+    }
+
+    pub fn set_level(&mut self, stem: Stem, level: f64) {
+        self.levels[stem.index()] = level.clamp(0.0, 1.0);
+        self.update(stem);
+    }
+
+    pub fn is_muted(&self, stem: Stem) -> bool {
+        self.muted[stem.index()]
+    }
+
+    pub fn set_muted(&mut self, stem: Stem, muted: bool) {
+        self.muted[stem.index()] = muted;
+        self.update(stem);
+    }
+
+    /// Every stem back at full level, e.g. for a new track
+    pub fn reset(&mut self) {
+        for stem in Stem::ALL {
+            self.levels[stem.index()] = 1.0;
+            self.muted[stem.index()] = false;
+            self.update(stem);
+        }
+    }
+
+    fn update(&mut self, stem: Stem) {
+        let volume = match self.is_muted(stem) {
+            true => 0.0,
+            false => self.level(stem),
+        };
+        self.tracks[stem.index()].set_volume(volume, Tween::default());
+    }
+}
@@ -0,0 +1,103 @@
+use crate::library::{Library, TrackInfo};
+
+/// Largest tempo difference, as a part of the tempo of the master deck, of a
+/// suggested track, about what the pitch faders can make up
+const BPM_RANGE: f64 = 0.06;
+
+/// Number and mode of a key in Camelot notation, e.g. `8A` for A minor
+fn parse_camelot(key: &str) -> Option<(u8, bool)> {
+    let mut number = key.trim().to_uppercase();
+    let minor = match number.pop()? {
+        'A' => true,
+        'B' => false,
+        _ => return None,
+    };
+    let number: u8 = number.parse().ok()?;
+
+    (1..=12).contains(&number).then_some((number, minor))
+}
+
+/// Whether two keys in Camelot notation mix harmonically: the same key, a
+/// neighbour on the wheel or its relative major or minor
+pub fn is_harmonic(a: &str, b: &str) -> bool {
+    let (Some((number_a, minor_a)), Some((number_b, minor_b))) =
+        (parse_camelot(a), parse_camelot(b))
+    else {
+        return false;
+    };
+
+    let distance = (number_a as i32 - number_b as i32).rem_euclid(12);
+    match minor_a == minor_b {
+        true => [0, 1, 11].contains(&distance),
+        false => distance == 0,
+    }
+}
+
+/// Tracks of the library mixing well into the one playing at `bpm` in `key`,
+/// within the tempo range and harmonically compatible if its key is known,
+/// the best rated and then the most played first. `excluded` tracks, e.g. loaded on a deck, are left
+/// out
+pub fn suggest<'a>(
+    library: &'a Library,
+    bpm: f64,
+    key: Option<&str>,
+    excluded: &[String],
+) -> Vec<(&'a String, &'a TrackInfo)> {
+    let mut suggestions: Vec<_> = library
+        .tracks()
+        .filter(|(path, _)| !excluded.contains(path))
+        .filter(|(_, track)| {
+            track
+                .bpm
+                .is_some_and(|track_bpm| (track_bpm - bpm).abs() <= bpm * BPM_RANGE)
+        })
+        .filter(|(_, track)| match (key, &track.key) {
+            (Some(key), Some(track_key)) => is_harmonic(key, track_key),
+            (Some(_), None) => false,
+            (None, _) => true,
+        })
+        .collect();
+
+    suggestions.sort_by(|(path_a, a), (path_b, b)| {
+        (b.rating, b.play_count)
+            .cmp(&(a.rating, a.play_count))
+            .then(path_a.cmp(path_b))
+    });
+    suggestions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suggest() {
+        assert!(is_harmonic("8A", "8a"));
+        assert!(is_harmonic("12A", "1A"));
+        assert!(is_harmonic("8A", "8B"));
+        assert!(!is_harmonic("8A", "9B"));
+        assert!(!is_harmonic("8A", "10A"));
+        assert!(!is_harmonic("13A", "13A"));
+
+        let mut library = Library::default();
+        for (path, bpm, key, rating) in [
+            ("close.mp3", 124.0, "9A", 2),
+            ("rated.mp3", 118.0, "8B", 5),
+            ("clash.mp3", 120.0, "3A", 5),
+            ("fast.mp3", 140.0, "8A", 5),
+            ("playing.mp3", 120.0, "8A", 0),
+        ] {
+            let track = library.track_mut(path);
+            track.bpm = Some(bpm);
+            track.key = Some(key.to_string());
+            track.rating = rating;
+        }
+
+        let suggestions: Vec<_> =
+            suggest(&library, 120.0, Some("8A"), &["playing.mp3".to_string()])
+                .into_iter()
+                .map(|(path, _)| path.as_str())
+                .collect();
+        assert_eq!(suggestions, ["rated.mp3", "close.mp3"]);
+    }
+}
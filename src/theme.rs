@@ -0,0 +1,97 @@
+use egui::{Color32, Context, FontId, Stroke, Style, Visuals};
+
+use crate::settings::Settings;
+
+/// Scale applied to text and controls in accessibility mode
+const LARGE_SCALE: f32 = 1.5;
+/// Scale applied to hit targets in touch mode
+const TOUCH_SCALE: f32 = 2.0;
+
+pub fn visuals(settings: &Settings) -> Visuals {
+    if settings.accessibility {
+        return high_contrast_visuals();
+    }
+
+    let mut visuals = Visuals::light();
+    visuals.extreme_bg_color = visuals.widgets.inactive.weak_bg_fill;
+    visuals
+}
+
+fn high_contrast_visuals() -> Visuals {
+    let mut visuals = Visuals::dark();
+    visuals.override_text_color = Some(Color32::WHITE);
+    visuals.panel_fill = Color32::BLACK;
+    visuals.window_fill = Color32::BLACK;
+    visuals.extreme_bg_color = Color32::BLACK;
+    visuals.window_stroke = Stroke::new(2.0, Color32::WHITE);
+    visuals.selection.bg_fill = Color32::from_rgb(0, 90, 200);
+    visuals.selection.stroke = Stroke::new(2.0, Color32::YELLOW);
+
+    for widget in [
+        &mut visuals.widgets.noninteractive,
+        &mut visuals.widgets.inactive,
+        &mut visuals.widgets.hovered,
+        &mut visuals.widgets.active,
+        &mut visuals.widgets.open,
+    ] {
+        widget.bg_stroke = Stroke::new(2.0, Color32::WHITE);
+        widget.fg_stroke = Stroke::new(2.0, Color32::WHITE);
+    }
+    visuals.widgets.inactive.weak_bg_fill = Color32::from_gray(30);
+    visuals.widgets.hovered.bg_stroke = Stroke::new(3.0, Color32::YELLOW);
+
+    visuals
+}
+
+/// Fill of toggle buttons when enabled, e.g. Cue or LINE IN
+pub fn enabled_fill(settings: &Settings) -> Color32 {
+    match settings.accessibility {
+        true => Color32::from_rgb(0, 90, 200),
+        false => Color32::LIGHT_BLUE,
+    }
+}
+
+/// Fill of the focus button of the focused deck
+pub fn focus_fill(settings: &Settings) -> Color32 {
+    match settings.accessibility {
+        true => Color32::from_rgb(140, 0, 160),
+        false => Color32::from_rgb(170, 170, 255),
+    }
+}
+
+/// Apply the theme and sizes matching the settings
+pub fn apply(ctx: &Context, settings: &Settings) {
+    let mut style = Style {
+        visuals: visuals(settings),
+        ..Style::default()
+    };
+
+    if settings.accessibility {
+        for font_id in style.text_styles.values_mut() {
+            *font_id = FontId::new(font_id.size * LARGE_SCALE, font_id.family.clone());
+        }
+
+        let spacing = &mut style.spacing;
+        spacing.item_spacing *= LARGE_SCALE;
+        spacing.button_padding *= LARGE_SCALE;
+        spacing.interact_size *= LARGE_SCALE;
+        spacing.slider_width *= LARGE_SCALE;
+        spacing.icon_width *= LARGE_SCALE;
+        spacing.icon_width_inner *= LARGE_SCALE;
+    }
+
+    if settings.touch_mode {
+        let spacing = &mut style.spacing;
+        spacing.button_padding *= TOUCH_SCALE;
+        spacing.interact_size *= TOUCH_SCALE;
+        spacing.slider_width *= TOUCH_SCALE;
+        spacing.slider_rail_height *= TOUCH_SCALE;
+        spacing.icon_width *= TOUCH_SCALE;
+        spacing.icon_width_inner *= TOUCH_SCALE;
+        style.interaction.resize_grab_radius_side *= TOUCH_SCALE;
+        style.interaction.resize_grab_radius_corner *= TOUCH_SCALE;
+    }
+
+    ctx.set_style(style);
+    ctx.set_zoom_factor(settings.ui_scale);
+}
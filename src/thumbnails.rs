@@ -0,0 +1,139 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+
+use egui::{ColorImage, TextureHandle};
+use symphonia::core::{
+    formats::FormatOptions, io::MediaSourceStream, meta::MetadataOptions, probe::Hint,
+};
+
+use crate::file_navigator::FileNavigator;
+
+/// Size in pixels thumbnails are decoded to, large enough for high DPI
+/// screens
+const THUMBNAIL_SIZE: u32 = 32;
+
+/// Small covers of the browser entries, decoded on a background thread the
+/// first time they are asked for so that scrolling never waits on the disk
+pub struct Thumbnails {
+    /// `None` while loading or when the entry has no cover
+    textures: HashMap<String, Option<TextureHandle>>,
+    requests: Sender<String>,
+    loaded: Receiver<(String, Option<ColorImage>)>,
+}
+
+impl Thumbnails {
+    pub fn new() -> Self {
+        let (requests, pending) = mpsc::channel::<String>();
+        let (done, loaded) = mpsc::channel();
+
+        thread::spawn(move || {
+            for path in pending {
+                let image = load(Path::new(&path));
+                if done.send((path, image)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            textures: HashMap::new(),
+            requests,
+            loaded,
+        }
+    }
+
+    /// Thumbnail of the folder or track at `path`, requested in the
+    /// background if it was never asked for
+    pub fn get(&mut self, ctx: &egui::Context, path: &str) -> Option<&TextureHandle> {
+        for (loaded_path, image) in self.loaded.try_iter() {
+            let texture = image.map(|image| {
+                ctx.load_texture(
+                    format!("thumbnail {}", loaded_path),
+                    image,
+                    Default::default(),
+                )
+            });
+            self.textures.insert(loaded_path, texture);
+        }
+
+        if !self.textures.contains_key(path) {
+            self.textures.insert(path.to_string(), None);
+            let _ = self.requests.send(path.to_string());
+        }
+
+        self.textures.get(path)?.as_ref()
+    }
+}
+
+/// Cover of a folder, its `cover.jpg` or the art embedded in its first
+/// track, or of a track, its embedded art or the `cover.jpg` next to it
+fn load(path: &Path) -> Option<ColorImage> {
+    let image = match path.is_dir() {
+        true => image::open(path.join("cover.jpg"))
+            .ok()
+            .or_else(|| embedded_art(&first_track(path)?)),
+        false => embedded_art(path).or_else(|| image::open(path.with_file_name("cover.jpg")).ok()),
+    }?;
+
+    let thumbnail = image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE).to_rgba8();
+    Some(ColorImage::from_rgba_unmultiplied(
+        [thumbnail.width() as usize, thumbnail.height() as usize],
+        thumbnail.as_raw(),
+    ))
+}
+
+fn first_track(dir: &Path) -> Option<PathBuf> {
+    let mut tracks: Vec<_> = fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| FileNavigator::is_supported_audio_filename(&name.to_string()))
+        })
+        .collect();
+    tracks.sort();
+    tracks.into_iter().next()
+}
+
+/// First picture found in the tags of a track
+fn embedded_art(path: &Path) -> Option<image::DynamicImage> {
+    let file = File::open(path).ok()?;
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|extension| extension.to_str()) {
+        hint.with_extension(extension);
+    }
+
+    let mut probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            MediaSourceStream::new(Box::new(file), Default::default()),
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .ok()?;
+
+    // tags may come before the container, e.g. ID3 in front of MP3 frames
+    let data =
+        probed
+            .metadata
+            .get()
+            .and_then(|metadata| {
+                metadata.current().and_then(|revision| {
+                    revision.visuals().first().map(|visual| visual.data.clone())
+                })
+            })
+            .or_else(|| {
+                probed.format.metadata().current().and_then(|revision| {
+                    revision.visuals().first().map(|visual| visual.data.clone())
+                })
+            })?;
+
+    image::load_from_memory(&data).ok()
+}
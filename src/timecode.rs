@@ -0,0 +1,201 @@
+use std::net::{Ipv4Addr, UdpSocket};
+
+use crate::{
+    app::AppData, controller::TurntableFocus, sampler::BEATS_PER_BAR, turntable::Turntable,
+};
+
+/// Beat clock and deck positions sent to VJ software so that visuals follow
+/// the mix
+#[derive(Debug, Clone, PartialEq)]
+pub struct Timecode {
+    /// tempo of the booth
+    pub bpm: f64,
+    /// beats played since the first beat of the track of the master deck,
+    /// when it has a beat grid
+    pub beats: Option<f64>,
+    pub decks: [DeckTimecode; 2],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeckTimecode {
+    pub playing: bool,
+    /// seconds into the loaded track
+    pub position: Option<f64>,
+    pub duration: Option<f64>,
+}
+
+impl Timecode {
+    pub fn capture(app_data: &AppData) -> Self {
+        let deck = |turntable: &Turntable| DeckTimecode {
+            playing: turntable.is_playing(),
+            position: turntable.position(),
+            duration: turntable.duration(),
+        };
+
+        Self {
+            bpm: app_data.master_tempo.bpm(),
+            beats: app_data
+                .master_deck()
+                .and_then(|deck| app_data.deck_beats(deck)),
+            decks: [deck(&app_data.turntable_one), deck(&app_data.turntable_two)],
+        }
+    }
+
+    /// OSC messages of the timecode, the beat and bar counting from 1
+    fn messages(&self) -> Vec<Vec<u8>> {
+        let mut messages = vec![osc_message(
+            "/bousse/bpm",
+            &[OscArg::Float(self.bpm as f32)],
+        )];
+
+        if let Some(beats) = self.beats.filter(|beats| *beats >= 0.0) {
+            let beat = beats.floor() as u64;
+            messages.push(osc_message(
+                "/bousse/beat",
+                &[OscArg::Int((beat % BEATS_PER_BAR + 1) as i32)],
+            ));
+            messages.push(osc_message(
+                "/bousse/bar",
+                &[OscArg::Int((beat / BEATS_PER_BAR + 1) as i32)],
+            ));
+            messages.push(osc_message(
+                "/bousse/phase",
+                &[OscArg::Float(beats.fract() as f32)],
+            ));
+        }
+
+        for (deck, timecode) in [TurntableFocus::One, TurntableFocus::Two]
+            .into_iter()
+            .zip(self.decks.iter())
+        {
+            let number = match deck {
+                TurntableFocus::One => 1,
+                TurntableFocus::Two => 2,
+            };
+            messages.push(osc_message(
+                &format!("/bousse/deck/{}/playing", number),
+                &[OscArg::Int(timecode.playing as i32)],
+            ));
+            if let (Some(position), Some(duration)) = (timecode.position, timecode.duration) {
+                messages.push(osc_message(
+                    &format!("/bousse/deck/{}/position", number),
+                    &[
+                        OscArg::Float(position as f32),
+                        OscArg::Float((position / duration).clamp(0.0, 1.0) as f32),
+                    ],
+                ));
+            }
+        }
+
+        messages
+    }
+}
+
+/// Sends the timecode as OSC messages over UDP
+pub struct TimecodeSender {
+    socket: UdpSocket,
+}
+
+impl TimecodeSender {
+    /// Send to `target`, such as `127.0.0.1:7000`
+    pub fn new(target: &str) -> Option<Self> {
+        let socket = match UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+            .and_then(|socket| socket.connect(target).map(|_| socket))
+        {
+            Ok(socket) => socket,
+            Err(e) => {
+                log::error!("Cannot send the timecode to {}: {}", target, e);
+                return None;
+            }
+        };
+        log::info!("Sending the timecode to {}", target);
+
+        Some(Self { socket })
+    }
+
+    /// Tell that dead air started, or ended
+    pub fn send_dead_air(&self, dead_air: bool) {
+        let message = osc_message("/bousse/dead_air", &[OscArg::Int(dead_air as i32)]);
+        let _ = self.socket.send(&message);
+    }
+
+    pub fn send(&self, timecode: &Timecode) {
+        for message in timecode.messages() {
+            // nobody listening is not an error, the VJ software may start later
+            let _ = self.socket.send(&message);
+        }
+    }
+}
+
+enum OscArg {
+    Int(i32),
+    Float(f32),
+}
+
+/// Encode an OSC message, its strings padded with zeros to 4 bytes
+fn osc_message(address: &str, args: &[OscArg]) -> Vec<u8> {
+    let mut tags = String::from(",");
+    for arg in args {
+        tags.push(match arg {
+            OscArg::Int(_) => 'i',
+            OscArg::Float(_) => 'f',
+        });
+    }
+
+    let mut message = Vec::new();
+    push_osc_string(&mut message, address);
+    push_osc_string(&mut message, &tags);
+    for arg in args {
+        match arg {
+            OscArg::Int(value) => message.extend(value.to_be_bytes()),
+            OscArg::Float(value) => message.extend(value.to_be_bytes()),
+        }
+    }
+    message
+}
+
+fn push_osc_string(message: &mut Vec<u8>, string: &str) {
+    message.extend(string.as_bytes());
+    message.resize(message.len() + 4 - string.len() % 4, 0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_osc_message() {
+        let message = osc_message("/bpm", &[OscArg::Float(1.0), OscArg::Int(2)]);
+
+        assert_eq!(
+            message,
+            [
+                b"/bpm\0\0\0\0".as_slice(),
+                b",fi\0",
+                &[0x3f, 0x80, 0, 0],
+                &[0, 0, 0, 2],
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn test_messages_count_beats_from_one() {
+        let deck = DeckTimecode {
+            playing: false,
+            position: None,
+            duration: None,
+        };
+        let timecode = Timecode {
+            bpm: 120.0,
+            beats: Some(5.25),
+            decks: [deck, deck],
+        };
+
+        let messages = timecode.messages();
+
+        assert!(messages.contains(&osc_message("/bousse/beat", &[OscArg::Int(2)])));
+        assert!(messages.contains(&osc_message("/bousse/bar", &[OscArg::Int(2)])));
+        assert!(messages.contains(&osc_message("/bousse/phase", &[OscArg::Float(0.25)])));
+    }
+}
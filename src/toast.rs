@@ -0,0 +1,72 @@
+use std::time::{Duration, Instant};
+
+use egui::{Align2, Context, Id};
+
+/// Time a toast stays on screen
+const TOAST_DURATION: Duration = Duration::from_secs(6);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ToastLevel {
+    Info,
+    Warning,
+    Error,
+}
+
+struct Toast {
+    level: ToastLevel,
+    message: String,
+    shown_at: Instant,
+}
+
+/// Short messages stacked in a corner of the window, e.g. when a track
+/// cannot be loaded
+#[derive(Default)]
+pub struct Toasts {
+    toasts: Vec<Toast>,
+}
+
+impl Toasts {
+    pub fn info(&mut self, message: impl Into<String>) {
+        self.push(ToastLevel::Info, message.into());
+    }
+
+    pub fn warn(&mut self, message: impl Into<String>) {
+        self.push(ToastLevel::Warning, message.into());
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.push(ToastLevel::Error, message.into());
+    }
+
+    fn push(&mut self, level: ToastLevel, message: String) {
+        self.toasts.push(Toast {
+            level,
+            message,
+            shown_at: Instant::now(),
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.toasts.is_empty()
+    }
+
+    pub fn show(&mut self, ctx: &Context) {
+        self.toasts
+            .retain(|toast| toast.shown_at.elapsed() < TOAST_DURATION);
+
+        egui::Area::new(Id::new("toasts"))
+            .anchor(Align2::RIGHT_BOTTOM, [-10.0, -10.0])
+            .show(ctx, |ui| {
+                for toast in self.toasts.iter() {
+                    let color = match toast.level {
+                        ToastLevel::Info => ui.visuals().text_color(),
+                        ToastLevel::Warning => ui.visuals().warn_fg_color,
+                        ToastLevel::Error => ui.visuals().error_fg_color,
+                    };
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.colored_label(color, &toast.message);
+                    });
+                }
+            });
+    }
+}
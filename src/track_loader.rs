@@ -0,0 +1,237 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+
+use kira::sound::FromFileError;
+
+use crate::{
+    controller::TurntableFocus,
+    turntable::{self, DecodedTrack},
+};
+
+struct Request {
+    /// none when preloading
+    deck: Option<TurntableFocus>,
+    path: PathBuf,
+    stream_over: Option<f64>,
+}
+
+struct Done {
+    deck: Option<TurntableFocus>,
+    path: PathBuf,
+    stream_over: Option<f64>,
+    track: Result<DecodedTrack, FromFileError>,
+}
+
+/// Decodes the tracks loaded on the decks on a background thread, so that
+/// the window keeps running meanwhile
+pub struct TrackLoader {
+    requests: Sender<Request>,
+    done: Receiver<Done>,
+    /// track being decoded for each deck
+    loading: [Option<PathBuf>; 2],
+    /// decoded tracks waiting to be put on their deck
+    decoded: [Option<(PathBuf, Result<DecodedTrack, FromFileError>)>; 2],
+    /// preloads are decoded on their own thread, so that loading a deck
+    /// never waits for them
+    preloads: Sender<Request>,
+    /// next track to be loaded being decoded, with its streaming length
+    preloading: Option<(PathBuf, Option<f64>)>,
+    preloaded: Option<(PathBuf, Option<f64>, DecodedTrack)>,
+}
+
+impl TrackLoader {
+    pub fn new() -> Self {
+        let (sender, done) = mpsc::channel();
+        let requests = spawn_decoder(sender.clone());
+        let preloads = spawn_decoder(sender);
+
+        Self {
+            requests,
+            done,
+            loading: [None, None],
+            decoded: [None, None],
+            preloads,
+            preloading: None,
+            preloaded: None,
+        }
+    }
+
+    /// Decode `path` for `deck`, replacing the track being decoded for it.
+    /// Returns whether the track is ready at once, having been preloaded
+    pub fn request(&mut self, deck: TurntableFocus, path: &Path, stream_over: Option<f64>) -> bool {
+        if let Some(track) = self.take_preloaded(path, stream_over) {
+            self.loading[index(deck)] = None;
+            self.decoded[index(deck)] = Some((path.to_path_buf(), Ok(track)));
+            return true;
+        }
+
+        let request = Request {
+            deck: Some(deck),
+            path: path.to_path_buf(),
+            stream_over,
+        };
+        if self.requests.send(request).is_ok() {
+            self.loading[index(deck)] = Some(path.to_path_buf());
+        }
+        false
+    }
+
+    /// Decode `path` ahead of its loading, e.g. the next track of the
+    /// prepare list, replacing the track preloaded before
+    pub fn preload(&mut self, path: &Path, stream_over: Option<f64>) {
+        let preload = (path.to_path_buf(), stream_over);
+        let preloaded = self
+            .preloaded
+            .as_ref()
+            .map(|(path, stream_over, _)| (path.clone(), *stream_over));
+        if self.preloading.as_ref() == Some(&preload) || preloaded.as_ref() == Some(&preload) {
+            return;
+        }
+
+        let request = Request {
+            deck: None,
+            path: preload.0.clone(),
+            stream_over,
+        };
+        if self.preloads.send(request).is_ok() {
+            self.preloading = Some(preload);
+        }
+    }
+
+    /// Preloaded track of `path`, unless it was preloaded to be streamed
+    /// differently
+    pub fn take_preloaded(
+        &mut self,
+        path: &Path,
+        stream_over: Option<f64>,
+    ) -> Option<DecodedTrack> {
+        match self.preloaded.take() {
+            Some((preloaded, over, track)) if preloaded == path && over == stream_over => {
+                Some(track)
+            }
+            preloaded => {
+                self.preloaded = preloaded;
+                None
+            }
+        }
+    }
+
+    /// Track being decoded for `deck`
+    pub fn loading(&self, deck: TurntableFocus) -> Option<&Path> {
+        self.loading[index(deck)].as_deref()
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.loading.iter().any(Option::is_some)
+    }
+
+    /// Decks whose track finished decoding since the last poll, taken with
+    /// [`TrackLoader::take`]
+    pub fn poll(&mut self) -> Vec<TurntableFocus> {
+        let mut decks = Vec::new();
+        for done in self.done.try_iter() {
+            let Some(done_deck) = done.deck else {
+                if self.preloading.as_ref() != Some(&(done.path.clone(), done.stream_over)) {
+                    continue;
+                }
+                self.preloading = None;
+                // a deck asked for the track meanwhile
+                let waiting = [TurntableFocus::One, TurntableFocus::Two]
+                    .into_iter()
+                    .find(|deck| self.loading[index(*deck)].as_ref() == Some(&done.path));
+                if let Some(deck) = waiting {
+                    self.loading[index(deck)] = None;
+                    self.decoded[index(deck)] = Some((done.path, done.track));
+                    decks.push(deck);
+                    continue;
+                }
+                match done.track {
+                    Ok(track) => self.preloaded = Some((done.path, done.stream_over, track)),
+                    Err(e) => log::warn!("Cannot preload '{}': {}", done.path.display(), e),
+                }
+                continue;
+            };
+            let deck = index(done_deck);
+            // a track requested since for the same deck replaces this one
+            if self.loading[deck].as_ref() != Some(&done.path) {
+                continue;
+            }
+            self.loading[deck] = None;
+            self.decoded[deck] = Some((done.path, done.track));
+            decks.push(done_deck);
+        }
+        decks
+    }
+
+    /// Decoded track of `deck` with its path
+    pub fn take(
+        &mut self,
+        deck: TurntableFocus,
+    ) -> Option<(PathBuf, Result<DecodedTrack, FromFileError>)> {
+        self.decoded[index(deck)].take()
+    }
+}
+
+/// Decode the requests in order on a new thread
+fn spawn_decoder(done: Sender<Done>) -> Sender<Request> {
+    let (requests, pending) = mpsc::channel::<Request>();
+
+    thread::spawn(move || {
+        for request in pending {
+            let track = turntable::decode(&request.path, request.stream_over);
+            let decoded = Done {
+                deck: request.deck,
+                path: request.path,
+                stream_over: request.stream_over,
+                track,
+            };
+            if done.send(decoded).is_err() {
+                break;
+            }
+        }
+    });
+
+    requests
+}
+
+fn index(deck: TurntableFocus) -> usize {
+    match deck {
+        TurntableFocus::One => 0,
+        TurntableFocus::Two => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::audio_backend::testing::constant_wav;
+
+    #[test]
+    fn test_preload() {
+        let path = constant_wav("preload", 0.5, 0.5);
+        let mut loader = TrackLoader::new();
+
+        loader.preload(&path, None);
+        for _ in 0..500 {
+            assert!(loader.poll().is_empty());
+            if loader.preloading.is_none() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        // preloaded to be streamed differently, it is decoded again
+        assert!(loader.take_preloaded(&path, Some(0.1)).is_none());
+        assert!(loader.request(TurntableFocus::Two, &path, None));
+        assert!(!loader.is_loading());
+        assert!(matches!(loader.take(TurntableFocus::Two), Some((_, Ok(_)))));
+        assert!(loader.take_preloaded(&path, None).is_none());
+
+        let _ = std::fs::remove_file(path);
+    }
+}
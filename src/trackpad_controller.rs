@@ -0,0 +1,130 @@
+use winit::{
+    dpi::PhysicalPosition,
+    event::{DeviceEvent, ElementState, KeyEvent, WindowEvent},
+    keyboard::{KeyCode, ModifiersState, PhysicalKey},
+};
+
+use crate::{
+    controller::BoothEvent,
+    input_source::InputSource,
+    mapping::{KeyMap, PointerBindings},
+    processable::Processable,
+    settings::Settings,
+};
+
+/// Scratching and vinyl speed control with the trackpad while holding the
+/// keys of the pointer bindings of the keymap
+pub struct TrackpadController {
+    bindings: PointerBindings,
+    modifiers: ModifiersState,
+    /// keys held down, for bindings held with a key rather than modifiers
+    pressed: Vec<KeyCode>,
+    scratching: bool,
+    /// whether the cursor is locked, motion then coming from the device
+    /// rather than from the cursor position
+    locked: bool,
+    cursor: Option<PhysicalPosition<f64>>,
+    events: Vec<BoothEvent<'static>>,
+}
+
+impl TrackpadController {
+    pub fn new(bindings: PointerBindings) -> Self {
+        Self {
+            bindings,
+            modifiers: ModifiersState::empty(),
+            pressed: Vec::new(),
+            scratching: false,
+            locked: false,
+            cursor: None,
+            events: Vec::new(),
+        }
+    }
+
+    fn is_seeking(&self) -> bool {
+        self.bindings.seek.is_held(self.modifiers, &self.pressed)
+    }
+
+    fn push_force(&mut self, delta: f64) {
+        let dir = delta.signum();
+        let mag = delta.abs().powf(0.65); // apply pow to compensate for mouse acceleration / non linearity
+
+        self.events.push(BoothEvent::ForceApplied(-dir * mag));
+    }
+
+    fn update_scratching(&mut self) {
+        let scratching = self.bindings.scratch.is_held(self.modifiers, &self.pressed);
+        if scratching == self.scratching {
+            return;
+        }
+
+        self.scratching = scratching;
+        self.events.push(match scratching {
+            true => BoothEvent::ScratchBegin,
+            false => BoothEvent::ScratchEnd,
+        });
+    }
+}
+
+impl Processable for TrackpadController {}
+
+impl InputSource for TrackpadController {
+    fn on_window_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::ModifiersChanged(modifiers) => self.modifiers = modifiers.state(),
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(key),
+                        state,
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } => {
+                self.pressed.retain(|pressed| pressed != key);
+                if *state == ElementState::Pressed {
+                    self.pressed.push(*key);
+                }
+            }
+            // keys released outside the window are never seen
+            WindowEvent::Focused(false) => self.pressed.clear(),
+            // without a lock the cursor moves, its moves are accumulated
+            // instead of the device motion
+            WindowEvent::CursorMoved { position, .. } => {
+                let last = self.cursor.replace(*position);
+                if let (Some(last), false, true) = (last, self.locked, self.grabs_pointer()) {
+                    self.push_force(position.y - last.y);
+                }
+                return;
+            }
+            _ => return,
+        }
+
+        self.update_scratching();
+    }
+
+    fn on_device_event(&mut self, event: &DeviceEvent) {
+        if let (DeviceEvent::MouseMotion { delta }, true, true) =
+            (event, self.locked, self.grabs_pointer())
+        {
+            self.push_force(delta.1);
+        }
+    }
+
+    fn poll(&mut self, events: &mut Vec<BoothEvent<'static>>) {
+        events.append(&mut self.events);
+    }
+
+    fn grabs_pointer(&self) -> bool {
+        self.scratching || self.is_seeking()
+    }
+
+    fn set_pointer_locked(&mut self, locked: bool) {
+        self.locked = locked;
+    }
+
+    fn reload_mapping(&mut self, _settings: &Settings) {
+        self.bindings = KeyMap::load().pointer;
+        self.update_scratching();
+    }
+}
@@ -0,0 +1,180 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::Instant,
+};
+
+use crate::controller::TurntableFocus;
+
+/// Largest tempo difference in BPM counted as matched
+pub const TEMPO_TOLERANCE: f64 = 0.1;
+/// Largest phase difference in beats counted as matched
+pub const PHASE_TOLERANCE: f64 = 0.05;
+/// Seconds the decks must stay matched to end a round
+const MATCH_HOLD_SECONDS: f64 = 2.0;
+/// Smallest pitch offset given to the trained deck, so that there is always
+/// something to match
+const MIN_PITCH_OFFSET: f64 = 0.01;
+const MAX_PITCH_OFFSET: f64 = 0.06;
+
+/// Result of a round
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Score {
+    /// seconds taken to match the decks
+    pub seconds: f64,
+    /// mean tempo difference in BPM while the match was held
+    pub tempo_error: f64,
+    /// mean phase difference in beats while the match was held
+    pub phase_error: f64,
+}
+
+impl Score {
+    /// Points out of 100, lost with time and with the remaining differences
+    pub fn points(&self) -> u32 {
+        let time = (60.0 - self.seconds).clamp(0.0, 60.0) / 60.0;
+        let tempo = 1.0 - self.tempo_error / TEMPO_TOLERANCE;
+        let phase = 1.0 - self.phase_error / PHASE_TOLERANCE;
+
+        (100.0 * (0.5 * time + 0.25 * tempo + 0.25 * phase)).round() as u32
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Round {
+    /// deck whose pitch was randomized, to be matched to the other one
+    deck: TurntableFocus,
+    started_at: Instant,
+    /// start of the current match and its summed errors
+    matched_since: Option<Instant>,
+    errors: (f64, f64, u32),
+}
+
+/// Practice mode where one deck starts at a random pitch and the user is
+/// scored on how quickly and closely they match it to the other one by ear
+#[derive(Debug, Default)]
+pub struct Trainer {
+    round: Option<Round>,
+    last_score: Option<Score>,
+    best_score: Option<Score>,
+}
+
+impl Trainer {
+    /// Whether a round is running, hiding the tempo readouts
+    pub fn is_active(&self) -> bool {
+        self.round.is_some()
+    }
+
+    /// Deck to match in the running round
+    pub fn deck(&self) -> Option<TurntableFocus> {
+        self.round.map(|round| round.deck)
+    }
+
+    pub fn last_score(&self) -> Option<Score> {
+        self.last_score
+    }
+
+    pub fn best_score(&self) -> Option<Score> {
+        self.best_score
+    }
+
+    /// Start a round on `deck`, returning the random pitch to give it
+    /// around `pitch`
+    pub fn start(&mut self, deck: TurntableFocus, pitch: f64, now: Instant) -> f64 {
+        self.round = Some(Round {
+            deck,
+            started_at: now,
+            matched_since: None,
+            errors: (0.0, 0.0, 0),
+        });
+
+        let random = RandomState::new().build_hasher().finish();
+        let unit = (random >> 11) as f64 / (1u64 << 53) as f64;
+        let offset = MIN_PITCH_OFFSET + unit * (MAX_PITCH_OFFSET - MIN_PITCH_OFFSET);
+        match random & 1 {
+            0 => pitch + offset,
+            _ => pitch - offset,
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.round = None;
+    }
+
+    /// Follow the differences between the decks, ending the round with a
+    /// score once they stayed matched long enough
+    pub fn update(&mut self, tempo_error: f64, phase_error: f64, now: Instant) {
+        let Some(round) = self.round.as_mut() else {
+            return;
+        };
+
+        if tempo_error > TEMPO_TOLERANCE || phase_error > PHASE_TOLERANCE {
+            round.matched_since = None;
+            round.errors = (0.0, 0.0, 0);
+            return;
+        }
+
+        let matched_since = *round.matched_since.get_or_insert(now);
+        let (tempo_sum, phase_sum, count) = round.errors;
+        round.errors = (tempo_sum + tempo_error, phase_sum + phase_error, count + 1);
+
+        if now.duration_since(matched_since).as_secs_f64() < MATCH_HOLD_SECONDS {
+            return;
+        }
+
+        let (tempo_sum, phase_sum, count) = round.errors;
+        let score = Score {
+            seconds: matched_since.duration_since(round.started_at).as_secs_f64(),
+            tempo_error: tempo_sum / count as f64,
+            phase_error: phase_sum / count as f64,
+        };
+        self.round = None;
+        self.last_score = Some(score);
+        if self
+            .best_score
+            .is_none_or(|best| score.points() > best.points())
+        {
+            self.best_score = Some(score);
+        }
+    }
+}
+
+/// Distance in beats between two beat phases, at most half a beat
+pub fn phase_error(a: f64, b: f64) -> f64 {
+    let difference = (a - b).rem_euclid(1.0);
+    difference.min(1.0 - difference)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_round() {
+        assert!((phase_error(0.95, 0.05) - 0.1).abs() < 1e-9);
+        assert!((phase_error(0.25, 0.75) - 0.5).abs() < 1e-9);
+
+        let start = Instant::now();
+        let at = |seconds: f64| start + Duration::from_secs_f64(seconds);
+        let mut trainer = Trainer::default();
+
+        let pitch = trainer.start(TurntableFocus::Two, 1.0, start);
+        assert!((pitch - 1.0).abs() >= MIN_PITCH_OFFSET - 1e-9);
+        assert_eq!(trainer.deck(), Some(TurntableFocus::Two));
+
+        trainer.update(0.5, 0.0, at(5.0));
+        trainer.update(0.05, 0.02, at(10.0));
+        // a slip restarts the match
+        trainer.update(0.05, 0.2, at(11.0));
+        trainer.update(0.0, 0.0, at(20.0));
+        assert!(trainer.is_active());
+        trainer.update(0.0, 0.0, at(22.0));
+        assert!(!trainer.is_active());
+
+        let score = trainer.last_score().unwrap();
+        assert_eq!(score.seconds, 20.0);
+        assert_eq!(score.points(), 83);
+        assert_eq!(trainer.best_score(), Some(score));
+    }
+}
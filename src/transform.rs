@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{controller::TurntableFocus, processable::Processable};
+
+/// Note value chopping a channel while its transform button is held
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum TransformGate {
+    /// cut for as long as the button is held
+    #[default]
+    Off,
+    Quarter,
+    Eighth,
+    Sixteenth,
+    ThirtySecond,
+}
+
+impl TransformGate {
+    pub const ALL: [TransformGate; 5] = [
+        TransformGate::Off,
+        TransformGate::Quarter,
+        TransformGate::Eighth,
+        TransformGate::Sixteenth,
+        TransformGate::ThirtySecond,
+    ];
+
+    /// Length of a note in beats
+    fn beats(&self) -> Option<f64> {
+        match self {
+            TransformGate::Off => None,
+            TransformGate::Quarter => Some(1.0),
+            TransformGate::Eighth => Some(0.5),
+            TransformGate::Sixteenth => Some(0.25),
+            TransformGate::ThirtySecond => Some(0.125),
+        }
+    }
+}
+
+/// Momentary transform buttons of the channels, cutting a channel off the
+/// master while held
+#[derive(Debug, Default)]
+pub struct Transform {
+    /// seconds each button has been held, none when released
+    held: [Option<f64>; 2],
+}
+
+impl Transform {
+    fn index(deck: TurntableFocus) -> usize {
+        match deck {
+            TurntableFocus::One => 0,
+            TurntableFocus::Two => 1,
+        }
+    }
+
+    pub fn is_held(&self, deck: TurntableFocus) -> bool {
+        self.held[Self::index(deck)].is_some()
+    }
+
+    pub fn set_held(&mut self, deck: TurntableFocus, held: bool) {
+        let button = &mut self.held[Self::index(deck)];
+        *button = match (held, *button) {
+            (true, None) => Some(0.0),
+            (true, time) => time,
+            (false, _) => None,
+        };
+    }
+
+    /// Whether `deck` is cut off the master. When gated, the channel plays
+    /// the first half of each note and is cut for the second half, the notes
+    /// following `beat_phase`, the position of the deck within the beat, or
+    /// else starting on the press at `bpm`
+    pub fn is_cut(
+        &self,
+        deck: TurntableFocus,
+        gate: TransformGate,
+        beat_phase: Option<f64>,
+        bpm: f64,
+    ) -> bool {
+        let Some(time) = self.held[Self::index(deck)] else {
+            return false;
+        };
+        let Some(note) = gate.beats() else {
+            return true;
+        };
+
+        let beats = beat_phase.unwrap_or(time * bpm / 60.0);
+        (beats / note).rem_euclid(1.0) >= 0.5
+    }
+}
+
+impl Processable for Transform {
+    fn process(&mut self, delta: f64) {
+        for time in self.held.iter_mut().flatten() {
+            *time += delta;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_cut() {
+        let mut transform = Transform::default();
+        let deck = TurntableFocus::One;
+        assert!(!transform.is_cut(deck, TransformGate::Off, None, 120.0));
+
+        transform.set_held(deck, true);
+        assert!(transform.is_cut(deck, TransformGate::Off, None, 120.0));
+        assert!(!transform.is_cut(TurntableFocus::Two, TransformGate::Off, None, 120.0));
+
+        // eighths at 120 BPM last a quarter of a second, open then cut
+        assert!(!transform.is_cut(deck, TransformGate::Eighth, None, 120.0));
+        transform.process(0.2);
+        assert!(transform.is_cut(deck, TransformGate::Eighth, None, 120.0));
+        transform.process(0.1);
+        assert!(!transform.is_cut(deck, TransformGate::Eighth, None, 120.0));
+
+        // the beat grid of the deck wins
+        assert!(transform.is_cut(deck, TransformGate::Quarter, Some(0.7), 120.0));
+        assert!(!transform.is_cut(deck, TransformGate::Sixteenth, Some(0.6), 120.0));
+
+        transform.set_held(deck, false);
+        assert!(!transform.is_cut(deck, TransformGate::Quarter, Some(0.7), 120.0));
+    }
+}
@@ -1,25 +1,108 @@
 use std::{
-    path::Path,
+    path::{Path, PathBuf},
     sync::{Arc, Mutex},
 };
 
 use kira::{
-    manager::{error::PlaySoundError, AudioManager},
+    manager::error::PlaySoundError,
     sound::{
         static_sound::{StaticSoundData, StaticSoundHandle, StaticSoundSettings},
-        FromFileError,
+        streaming::{StreamingSoundData, StreamingSoundHandle, StreamingSoundSettings},
+        FromFileError, IntoOptionalRegion, PlaybackState,
     },
     track::TrackHandle,
     tween::Tween,
 };
 
-use crate::{processable::Processable, utils::lerp};
+use crate::{
+    audio_backend::Manager,
+    duration,
+    processable::Processable,
+    stems::{self, Stem, StemTracks},
+    utils::lerp,
+};
+
+/// Speed lost per second by a braking platter
+const BRAKE_DECELERATION: f64 = 1.0;
+/// Range of the pitch, +-8%
+pub const MIN_PITCH: f64 = 0.92;
+pub const MAX_PITCH: f64 = 1.08;
+/// Smallest pitch change of the pitch buttons, 0.1%
+const PITCH_STEP: f64 = 0.001;
+/// Longest time in seconds the displayed position moves on without the
+/// sound reporting it, e.g. while the audio is stalled
+const MAX_INTERPOLATION: f64 = 0.1;
+
+/// Audio of the loaded track
+enum Source {
+    /// decoded at once into memory
+    Static(Box<StaticSoundData>),
+    /// decoded from the file while playing, opened again on each play
+    Streaming { path: PathBuf, duration: f64 },
+}
+
+/// Sound playing the loaded track or one of its stems
+enum DeckSound {
+    Static(StaticSoundHandle),
+    /// cannot play backwards, its playback rate stopping at zero
+    Streaming(StreamingSoundHandle<FromFileError>),
+}
+
+impl DeckSound {
+    fn state(&self) -> PlaybackState {
+        match self {
+            DeckSound::Static(sound) => sound.state(),
+            DeckSound::Streaming(sound) => sound.state(),
+        }
+    }
+
+    fn position(&self) -> f64 {
+        match self {
+            DeckSound::Static(sound) => sound.position(),
+            DeckSound::Streaming(sound) => sound.position(),
+        }
+    }
+
+    fn seek_to(&mut self, position: f64) {
+        match self {
+            DeckSound::Static(sound) => sound.seek_to(position),
+            DeckSound::Streaming(sound) => sound.seek_to(position),
+        }
+    }
+
+    fn set_loop_region(&mut self, loop_region: impl IntoOptionalRegion) {
+        let loop_region = loop_region.into_optional_region();
+        match self {
+            DeckSound::Static(sound) => sound.set_loop_region(loop_region),
+            DeckSound::Streaming(sound) => sound.set_loop_region(loop_region),
+        }
+    }
+
+    fn set_playback_rate(&mut self, playback_rate: f64, tween: Tween) {
+        match self {
+            DeckSound::Static(sound) => sound.set_playback_rate(playback_rate, tween),
+            DeckSound::Streaming(sound) => sound.set_playback_rate(playback_rate, tween),
+        }
+    }
+
+    fn stop(&mut self, tween: Tween) {
+        match self {
+            DeckSound::Static(sound) => sound.stop(tween),
+            DeckSound::Streaming(sound) => sound.stop(tween),
+        }
+    }
+}
 
 /// A struct that simulates a turntable from a digital file.
 pub struct Turntable {
-    sound_data: Option<StaticSoundData>,
-    sound: Option<StaticSoundHandle>,
-    audio_manager: Arc<Mutex<AudioManager>>,
+    source: Option<Source>,
+    sound: Option<DeckSound>,
+    /// separated parts of the loaded track, played in sync with its sound
+    /// which is then kept silent
+    stems_data: Vec<(Stem, StaticSoundData)>,
+    stem_sounds: Vec<DeckSound>,
+    stem_tracks: StemTracks,
+    audio_manager: Arc<Mutex<Manager>>,
     output_destination: Arc<Mutex<TrackHandle>>,
     /// the virtual speed of the vinyl
     pitch_true: f64,
@@ -27,9 +110,22 @@ pub struct Turntable {
     pitch_target: f64,
     is_playing: bool,
     is_scratching: bool,
+    /// whether the platter is slowing down at [`BRAKE_DECELERATION`]
+    is_braking: bool,
+    /// stop and return to the start when the sound ends
+    auto_pause: bool,
+    /// tracks longer than this in seconds are streamed from their file
+    /// rather than decoded into memory
+    stream_over: Option<f64>,
     /// the current force on the vinyl (to be consumed into pitch variation)
     force: f64,
     currently_loaded: Option<String>,
+    /// start and end in seconds of the region playing in a loop
+    loop_region: Option<(f64, f64)>,
+    /// start of the loop being set, until its end is set
+    loop_in: Option<f64>,
+    /// last position reported by the sound, and the seconds since it was
+    reported_position: (f64, f64),
 }
 
 #[derive(Debug)]
@@ -60,20 +156,34 @@ pub enum SeekError {
 impl Turntable {
     /// Creates a new instance of a turntable
     pub fn new(
-        audio_manager: Arc<Mutex<AudioManager>>,
+        audio_manager: Arc<Mutex<Manager>>,
         output_destination: Arc<Mutex<TrackHandle>>,
     ) -> Self {
+        let stem_tracks = StemTracks::new(
+            &mut audio_manager.lock().unwrap(),
+            &output_destination.lock().unwrap(),
+        );
+
         Self {
-            sound_data: None,
+            source: None,
             sound: None,
+            stems_data: Vec::new(),
+            stem_sounds: Vec::new(),
+            stem_tracks,
             audio_manager: audio_manager,
             output_destination: output_destination,
             pitch_true: 0.0,
             pitch_target: 1.0,
             is_playing: false,
             is_scratching: false,
+            is_braking: false,
+            auto_pause: false,
+            stream_over: None,
             force: 0.0,
             currently_loaded: None,
+            loop_region: None,
+            loop_in: None,
+            reported_position: (0.0, 0.0),
         }
     }
 
@@ -83,35 +193,99 @@ impl Turntable {
             return Err(LoadError::IsPlaying);
         }
 
-        self.sound_data = match StaticSoundData::from_file(path) {
-            Ok(sound_data) => Some(sound_data),
-            Err(e) => return Err(LoadError::FromFile(e)),
-        };
+        let track = decode(path, self.stream_over)?;
+        self.load_decoded(path, track)
+    }
 
-        if let Some(sound) = &mut self.sound {
-            sound.stop(Tween::default());
+    /// Load a track decoded beforehand, e.g. on a background thread
+    pub fn load_decoded(&mut self, path: &Path, track: DecodedTrack) -> Result<(), LoadError> {
+        if self.is_playing {
+            return Err(LoadError::IsPlaying);
         }
 
-        let settings = StaticSoundSettings::new()
-            .output_destination(&*self.output_destination.lock().unwrap());
+        self.source = Some(track.source);
+        self.stems_data = track.stems;
+        self.stem_tracks.reset();
 
-        if let Some(sound_data) = &mut self.sound_data {
-            self.sound = match self
-                .audio_manager
-                .lock()
-                .unwrap()
-                .play(sound_data.with_settings(settings))
-            {
-                Ok(sound) => Some(sound),
-                Err(e) => return Err(LoadError::Play(e)),
-            };
+        self.play_from_start()?;
+        self.currently_loaded = Some(path.to_string_lossy().to_string());
+
+        Ok(())
+    }
+
+    /// Replace the sound by a new one playing the loaded data from the start
+    fn play_from_start(&mut self) -> Result<(), LoadError> {
+        for sound in self.sounds_mut() {
+            sound.stop(Tween::default());
         }
+        self.stem_sounds.clear();
+        self.loop_region = None;
+        self.loop_in = None;
 
-        self.currently_loaded = Some(path.to_string_lossy().to_string());
+        let volume = match self.has_stems() {
+            true => 0.0,
+            false => 1.0,
+        };
+        match &self.source {
+            Some(Source::Static(sound_data)) => {
+                let settings = StaticSoundSettings::new()
+                    .output_destination(&*self.output_destination.lock().unwrap())
+                    .playback_rate(self.pitch_true)
+                    .volume(volume);
+                let sound = self
+                    .audio_manager
+                    .lock()
+                    .unwrap()
+                    .play(sound_data.with_settings(settings))?;
+                self.sound = Some(DeckSound::Static(sound));
+            }
+            Some(Source::Streaming { path, .. }) => {
+                let settings = StreamingSoundSettings::new()
+                    .output_destination(&*self.output_destination.lock().unwrap())
+                    .playback_rate(self.pitch_true)
+                    .volume(volume);
+                let sound_data = StreamingSoundData::from_file(path)?.with_settings(settings);
+                let sound = self
+                    .audio_manager
+                    .lock()
+                    .unwrap()
+                    .play(sound_data)
+                    .map_err(|e| match e {
+                        PlaySoundError::SoundLimitReached => {
+                            LoadError::Play(PlaySoundError::SoundLimitReached)
+                        }
+                        PlaySoundError::IntoSoundError(e) => LoadError::FromFile(e),
+                    })?;
+                self.sound = Some(DeckSound::Streaming(sound));
+            }
+            None => (),
+        }
+
+        let mut audio_manager = self.audio_manager.lock().unwrap();
+        for (stem, sound_data) in self.stems_data.iter() {
+            let settings = StaticSoundSettings::new()
+                .output_destination(self.stem_tracks.track(*stem))
+                .playback_rate(self.pitch_true);
+            let sound = audio_manager.play(sound_data.with_settings(settings))?;
+            self.stem_sounds.push(DeckSound::Static(sound));
+        }
 
         Ok(())
     }
 
+    /// The sound of the track, then the sounds of its stems
+    fn sounds_mut(&mut self) -> impl Iterator<Item = &mut DeckSound> {
+        self.sound.iter_mut().chain(self.stem_sounds.iter_mut())
+    }
+
+    /// Whether the sound reached its end, after which it cannot be played
+    /// or seeked anymore
+    fn has_ended(&self) -> bool {
+        self.sound
+            .as_ref()
+            .is_some_and(|sound| sound.state() == PlaybackState::Stopped)
+    }
+
     pub fn currently_loaded(&self) -> Option<String> {
         self.currently_loaded.clone()
     }
@@ -120,6 +294,11 @@ impl Turntable {
         self.pitch_target
     }
 
+    /// Current speed of the vinyl, as a playback rate
+    pub fn speed(&self) -> f64 {
+        self.pitch_true
+    }
+
     pub fn position(&self) -> Option<f64> {
         match &self.sound {
             Some(sound) => Some(sound.position()),
@@ -127,25 +306,88 @@ impl Turntable {
         }
     }
 
+    /// Position for drawing, moved on at the speed of the vinyl since the
+    /// sound last reported it, which only happens at the rate of the audio
+    /// commands
+    pub fn display_position(&self) -> Option<f64> {
+        let position = self.position()?;
+        let (reported, elapsed) = self.reported_position;
+        if position != reported {
+            return Some(position);
+        }
+
+        let mut position = position + self.pitch_true * elapsed.min(MAX_INTERPOLATION);
+        if let Some((start, end)) = self.loop_region.filter(|(start, end)| end > start) {
+            if reported < end && position >= end {
+                position = start + (position - end) % (end - start);
+            }
+        }
+        Some(position.clamp(0.0, self.duration().unwrap_or(f64::MAX)))
+    }
+
     pub fn duration(&self) -> Option<f64> {
-        match &self.sound_data {
-            Some(sound_data) => Some(sound_data.duration().as_secs_f64()),
+        match &self.source {
+            Some(Source::Static(sound_data)) => Some(sound_data.duration().as_secs_f64()),
+            Some(Source::Streaming { duration, .. }) => Some(*duration),
             None => None,
         }
     }
 
+    /// Whether the loaded track is streamed from its file
+    pub fn is_streaming(&self) -> bool {
+        matches!(self.source, Some(Source::Streaming { .. }))
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.is_playing
+    }
+
+    /// Whether the loaded track has separated stems, played instead of its
+    /// mix
+    pub fn has_stems(&self) -> bool {
+        !self.stems_data.is_empty()
+    }
+
+    pub fn has_stem(&self, stem: Stem) -> bool {
+        self.stems_data.iter().any(|(loaded, _)| *loaded == stem)
+    }
+
+    /// Levels and mutes of the stems
+    pub fn stems(&self) -> &StemTracks {
+        &self.stem_tracks
+    }
+
+    pub fn stems_mut(&mut self) -> &mut StemTracks {
+        &mut self.stem_tracks
+    }
+
     pub fn toggle_start_stop(&mut self) {
         self.is_playing = !self.is_playing;
+        self.is_braking = false;
+    }
+
+    /// Stop the platter slowly, like a turntable with the brake turned down
+    pub fn brake(&mut self) {
+        self.is_playing = false;
+        self.is_braking = true;
     }
 
     /// Set the pitch of the turntable.
     /// The value is clamped in the range [0.92, 1.08], i.e. +-8%
     pub fn set_pitch(&mut self, pitch: f64) {
-        self.pitch_target = pitch.clamp(0.92, 1.08)
+        self.pitch_target = pitch.clamp(MIN_PITCH, MAX_PITCH)
+    }
+
+    /// Raise the pitch by `steps` of 0.1%, or lower it when negative, landing
+    /// on a whole step
+    pub fn step_pitch(&mut self, steps: f64) {
+        let pitch = ((self.pitch_target - 1.0) / PITCH_STEP).round() + steps;
+        self.set_pitch(1.0 + pitch * PITCH_STEP);
     }
 
     pub fn start_scratching(&mut self) {
         self.is_scratching = true;
+        self.is_braking = false;
     }
 
     pub fn end_scratching(&mut self) {
@@ -156,18 +398,188 @@ impl Turntable {
         self.force += force;
     }
 
+    pub fn set_auto_pause(&mut self, enabled: bool) {
+        self.auto_pause = enabled;
+    }
+
+    /// Length in seconds over which the tracks loaded next are streamed
+    pub fn stream_over(&self) -> Option<f64> {
+        self.stream_over
+    }
+
+    /// Stream the tracks loaded next that are longer than `over` seconds,
+    /// or none
+    pub fn set_stream_over(&mut self, over: Option<f64>) {
+        self.stream_over = over;
+    }
+
+    pub fn loop_region(&self) -> Option<(f64, f64)> {
+        self.loop_region
+    }
+
+    /// Play from `start` to `end` in a loop, jumping to `start` unless the
+    /// position is already in the loop
+    pub fn set_loop(&mut self, start: f64, end: f64) {
+        if end <= start || self.has_ended() {
+            return;
+        }
+        let Some(position) = self.position() else {
+            return;
+        };
+
+        let jump = !(start..end).contains(&position);
+        for sound in self.sounds_mut() {
+            sound.set_loop_region(start..end);
+            if jump {
+                sound.seek_to(start);
+            }
+        }
+        self.loop_region = Some((start, end));
+        self.loop_in = None;
+    }
+
+    /// Loop from `start` to `end` once the position reaches the loop, playing
+    /// on until then, or at once if already past its start
+    pub fn queue_loop(&mut self, start: f64, end: f64) {
+        if self.position().is_none_or(|position| position > start) {
+            return self.set_loop(start, end);
+        }
+        if end <= start || self.has_ended() || self.sound.is_none() {
+            return;
+        }
+
+        for sound in self.sounds_mut() {
+            sound.set_loop_region(start..end);
+        }
+        self.loop_region = Some((start, end));
+        self.loop_in = None;
+    }
+
+    /// Replace the loop by the one from `start` to `end`, keeping the place
+    /// of the position in the loop rather than jumping to its start
+    pub fn change_loop(&mut self, start: f64, end: f64) {
+        let (Some((old_start, _)), Some(position)) = (self.loop_region, self.position()) else {
+            return;
+        };
+        if start < 0.0 || self.duration().is_some_and(|duration| end > duration) {
+            return;
+        }
+
+        self.set_loop(start, end);
+        if self.loop_region != Some((start, end)) {
+            return;
+        }
+        let position = start + (position - old_start).rem_euclid(end - start);
+        for sound in self.sounds_mut() {
+            sound.seek_to(position);
+        }
+    }
+
+    /// Keep playing past the end of the loop
+    pub fn exit_loop(&mut self) {
+        for sound in self.sounds_mut() {
+            sound.set_loop_region(None);
+        }
+        self.loop_region = None;
+    }
+
+    pub fn loop_in(&self) -> Option<f64> {
+        self.loop_in
+    }
+
+    /// Mark the start of a loop, its end being set by [`Turntable::loop_out`]
+    pub fn set_loop_in(&mut self) {
+        self.loop_in = self.position();
+    }
+
+    /// Loop from the marked start to the current position
+    pub fn loop_out(&mut self) {
+        if let (Some(start), Some(end)) = (self.loop_in, self.position()) {
+            self.set_loop(start, end);
+        }
+    }
+
+    /// Go back to the start of the track, even once it has ended, leaving
+    /// the loop
+    pub fn return_to_start(&mut self) -> Result<(), LoadError> {
+        self.exit_loop();
+        if self.has_ended() {
+            return self.play_from_start();
+        }
+
+        for sound in self.sounds_mut() {
+            sound.seek_to(0.0);
+        }
+        Ok(())
+    }
+
+    /// Jump to a part of the track, leaving the loop
     pub fn seek(&mut self, percent: f64) -> Result<(), SeekError> {
         let duration = self.duration().ok_or(SeekError::EmptyDuration)?;
-        let sound = self.sound.as_mut().ok_or(SeekError::EmptySound)?;
+        self.exit_loop();
+        if self.sound.is_none() {
+            return Err(SeekError::EmptySound);
+        }
 
-        sound.seek_to(percent * duration);
+        for sound in self.sounds_mut() {
+            sound.seek_to(percent * duration);
+        }
 
         Ok(())
     }
 }
 
+/// Audio of a track and of its stems, ready to be loaded on a deck
+pub struct DecodedTrack {
+    source: Source,
+    stems: Vec<(Stem, StaticSoundData)>,
+}
+
+/// Decode a track and its stems, or only open the track when longer than
+/// `stream_over` seconds. This takes long enough to be done off the UI
+/// thread
+pub fn decode(path: &Path, stream_over: Option<f64>) -> Result<DecodedTrack, FromFileError> {
+    Ok(DecodedTrack {
+        source: open(path, stream_over)?,
+        stems: stems::load(path),
+    })
+}
+
+/// Decode a track, or only open it when longer than the streaming threshold
+fn open(path: &Path, stream_over: Option<f64>) -> Result<Source, FromFileError> {
+    if let Some(over) = stream_over {
+        // opening the stream only reads the header, giving the duration
+        let duration = StreamingSoundData::from_file(path)?
+            .duration()
+            .as_secs_f64();
+        if duration > over {
+            // the header may be wrong for variable bitrate tracks, while a
+            // decoded track is measured from its frames
+            let duration = duration::measure(path).unwrap_or_else(|e| {
+                log::warn!("Cannot measure {}: {}", path.display(), e);
+                duration
+            });
+            return Ok(Source::Streaming {
+                path: path.to_path_buf(),
+                duration,
+            });
+        }
+    }
+
+    Ok(Source::Static(Box::new(StaticSoundData::from_file(path)?)))
+}
+
 impl Processable for Turntable {
     fn process(&mut self, delta: f64) {
+        if self.auto_pause && self.has_ended() {
+            self.is_playing = false;
+            self.is_braking = false;
+            self.pitch_true = 0.0;
+            if let Err(e) = self.play_from_start() {
+                log::error!("Cannot reset the deck: {:?}", e);
+            }
+        }
+
         let force = self.force * 0.02 / delta;
 
         let pitch_per_state = match (self.is_playing, self.is_scratching) {
@@ -176,13 +588,31 @@ impl Processable for Turntable {
             (_, true) => 0.1 * force,
         };
 
-        self.pitch_true = lerp(self.pitch_true, pitch_per_state, 0.8 * 0.02 / delta);
+        if self.is_braking {
+            let step = BRAKE_DECELERATION * delta;
+            self.pitch_true = pitch_per_state.clamp(self.pitch_true - step, self.pitch_true + step);
+            self.is_braking = self.pitch_true != pitch_per_state;
+        } else {
+            // the weight is capped so that short frames cannot overshoot
+            self.pitch_true = lerp(
+                self.pitch_true,
+                pitch_per_state,
+                (0.8 * 0.02 / delta).min(1.0),
+            );
+        }
 
-        if let Some(sound) = &mut self.sound {
-            sound.set_playback_rate(self.pitch_true, Tween::default());
+        let pitch_true = self.pitch_true;
+        for sound in self.sounds_mut() {
+            sound.set_playback_rate(pitch_true, Tween::default());
         }
 
         self.force = 0.0;
+
+        let (reported, elapsed) = self.reported_position;
+        self.reported_position = match self.position() {
+            Some(position) if position != reported => (position, 0.0),
+            _ => (reported, elapsed + delta),
+        };
     }
 }
 
@@ -190,18 +620,14 @@ impl Processable for Turntable {
 mod tests {
     use std::path::Path;
 
-    use kira::{
-        manager::{AudioManager, AudioManagerSettings, DefaultBackend},
-        track::TrackBuilder,
-    };
+    use kira::track::TrackBuilder;
 
     use super::*;
+    use crate::audio_backend::{new_manager, testing::constant_wav};
 
     #[test]
     fn test_load() {
-        let audio_manager = Arc::new(Mutex::new(
-            AudioManager::<DefaultBackend>::new(AudioManagerSettings::default()).unwrap(),
-        ));
+        let audio_manager = Arc::new(Mutex::new(new_manager().unwrap()));
 
         let track = Arc::new(Mutex::new(
             audio_manager
@@ -220,9 +646,7 @@ mod tests {
 
     #[test]
     fn test_duration() {
-        let audio_manager = Arc::new(Mutex::new(
-            AudioManager::<DefaultBackend>::new(AudioManagerSettings::default()).unwrap(),
-        ));
+        let audio_manager = Arc::new(Mutex::new(new_manager().unwrap()));
 
         let track = Arc::new(Mutex::new(
             audio_manager
@@ -241,9 +665,7 @@ mod tests {
 
     #[test]
     fn test_position() {
-        let audio_manager = Arc::new(Mutex::new(
-            AudioManager::<DefaultBackend>::new(AudioManagerSettings::default()).unwrap(),
-        ));
+        let audio_manager = Arc::new(Mutex::new(new_manager().unwrap()));
 
         let track = Arc::new(Mutex::new(
             audio_manager
@@ -261,11 +683,37 @@ mod tests {
     }
 
     #[test]
-    fn test_start_scratching() {
-        let audio_manager = Arc::new(Mutex::new(
-            AudioManager::<DefaultBackend>::new(AudioManagerSettings::default()).unwrap(),
+    fn test_display_position() {
+        let audio_manager = Arc::new(Mutex::new(new_manager().unwrap()));
+
+        let track = Arc::new(Mutex::new(
+            audio_manager
+                .lock()
+                .unwrap()
+                .add_sub_track(TrackBuilder::new())
+                .unwrap(),
         ));
 
+        let mut turntable = Turntable::new(audio_manager, track);
+
+        let path = constant_wav("display_position", 1.0, 0.5);
+        turntable.load(&path).unwrap();
+        // the mock backend is not rendered, so the sound stays at the start
+        turntable.process(0.02);
+        turntable.pitch_true = 1.0;
+        assert_eq!(turntable.display_position(), Some(0.02));
+
+        turntable.process(1.0);
+        turntable.pitch_true = 1.0;
+        assert_eq!(turntable.display_position(), Some(MAX_INTERPOLATION));
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_start_scratching() {
+        let audio_manager = Arc::new(Mutex::new(new_manager().unwrap()));
+
         let track = Arc::new(Mutex::new(
             audio_manager
                 .lock()
@@ -282,9 +730,7 @@ mod tests {
 
     #[test]
     fn test_toggle_start_stop() {
-        let audio_manager = Arc::new(Mutex::new(
-            AudioManager::<DefaultBackend>::new(AudioManagerSettings::default()).unwrap(),
-        ));
+        let audio_manager = Arc::new(Mutex::new(new_manager().unwrap()));
 
         let track = Arc::new(Mutex::new(
             audio_manager
@@ -306,9 +752,7 @@ mod tests {
 
     #[test]
     fn test_end_scratching() {
-        let audio_manager = Arc::new(Mutex::new(
-            AudioManager::<DefaultBackend>::new(AudioManagerSettings::default()).unwrap(),
-        ));
+        let audio_manager = Arc::new(Mutex::new(new_manager().unwrap()));
 
         let track = Arc::new(Mutex::new(
             audio_manager
@@ -326,9 +770,7 @@ mod tests {
 
     #[test]
     fn test_apply_force() {
-        let audio_manager = Arc::new(Mutex::new(
-            AudioManager::<DefaultBackend>::new(AudioManagerSettings::default()).unwrap(),
-        ));
+        let audio_manager = Arc::new(Mutex::new(new_manager().unwrap()));
 
         let track = Arc::new(Mutex::new(
             audio_manager
@@ -0,0 +1,220 @@
+//! Simulations of the turntable physics: [`Turntable::process`] is driven
+//! with scripted inputs at several frame rates while the mock backend
+//! renders the audio of each frame, and the speed and position of the vinyl
+//! are checked against their expected trajectories.
+
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use kira::track::TrackBuilder;
+
+use crate::{
+    audio_backend::{
+        new_manager,
+        testing::{constant_wav, render, SAMPLE_RATE},
+        Manager,
+    },
+    processable::Processable,
+    turntable::Turntable,
+};
+
+/// Frame rates the app may run at, from the default 24 fps to fast displays
+const FRAME_RATES: [f64; 5] = [24.0, 30.0, 60.0, 120.0, 240.0];
+const TRACK_SECONDS: f64 = 20.0;
+/// Position of the vinyl when a simulation starts, so that it can go back
+const START_POSITION: f64 = 10.0;
+/// Allowed position drift, mostly due to the tweens of the playback rate
+const POSITION_TOLERANCE: f64 = 0.02;
+
+fn track_file() -> &'static PathBuf {
+    static TRACK: OnceLock<PathBuf> = OnceLock::new();
+    TRACK.get_or_init(|| constant_wav("simulation", TRACK_SECONDS, 0.1))
+}
+
+struct Simulation {
+    manager: Arc<Mutex<Manager>>,
+    turntable: Turntable,
+    delta: f64,
+    /// position integrated from the speed of every frame
+    expected_position: f64,
+}
+
+impl Simulation {
+    fn new(fps: f64) -> Self {
+        let manager = Arc::new(Mutex::new(new_manager().unwrap()));
+        let track = manager
+            .lock()
+            .unwrap()
+            .add_sub_track(TrackBuilder::new())
+            .unwrap();
+
+        let mut turntable = Turntable::new(Arc::clone(&manager), Arc::new(Mutex::new(track)));
+        turntable.load(track_file()).unwrap();
+        turntable.seek(START_POSITION / TRACK_SECONDS).unwrap();
+
+        let mut simulation = Self {
+            manager,
+            turntable,
+            delta: 1.0 / fps,
+            expected_position: START_POSITION,
+        };
+        simulation.run(0.0, |_| ());
+        simulation
+    }
+
+    /// Run frames for `seconds`, calling `input` before processing each one
+    fn run(&mut self, seconds: f64, mut input: impl FnMut(&mut Turntable)) {
+        let frames = (seconds / self.delta).round() as usize;
+
+        for _ in 0..=frames {
+            input(&mut self.turntable);
+            self.turntable.process(self.delta);
+
+            let mut manager = self.manager.lock().unwrap();
+            render(&mut manager, (self.delta * SAMPLE_RATE as f64) as usize);
+            self.expected_position += self.turntable.speed() * self.delta;
+        }
+
+        // positions are published at the start of the next render
+        render(&mut self.manager.lock().unwrap(), 1);
+    }
+
+    fn speed(&self) -> f64 {
+        self.turntable.speed()
+    }
+
+    fn assert_position(&self, fps: f64) {
+        let position = self.turntable.position().unwrap();
+        assert!(
+            (position - self.expected_position).abs() < POSITION_TOLERANCE,
+            "at {} fps, position {} instead of {}",
+            fps,
+            position,
+            self.expected_position
+        );
+    }
+}
+
+fn assert_speed(fps: f64, speed: f64, expected: f64, tolerance: f64) {
+    assert!(
+        (speed - expected).abs() < tolerance,
+        "at {} fps, speed {} instead of {}",
+        fps,
+        speed,
+        expected
+    );
+}
+
+#[test]
+fn test_start_stop() {
+    for fps in FRAME_RATES {
+        let mut simulation = Simulation::new(fps);
+
+        simulation.turntable.toggle_start_stop();
+        simulation.run(0.25, |_| ());
+        assert_speed(fps, simulation.speed(), 1.0, 0.1);
+        simulation.run(0.75, |_| ());
+        assert_speed(fps, simulation.speed(), 1.0, 1e-3);
+        simulation.assert_position(fps);
+
+        simulation.turntable.toggle_start_stop();
+        simulation.run(1.0, |_| ());
+        assert_speed(fps, simulation.speed(), 0.0, 1e-3);
+        simulation.assert_position(fps);
+    }
+}
+
+#[test]
+fn test_brake() {
+    for fps in FRAME_RATES {
+        let mut simulation = Simulation::new(fps);
+
+        simulation.turntable.toggle_start_stop();
+        simulation.run(1.0, |_| ());
+
+        // the platter loses speed linearly for a second
+        simulation.turntable.brake();
+        simulation.run(0.5, |_| ());
+        assert_speed(fps, simulation.speed(), 0.5, 0.05);
+        simulation.run(0.6, |_| ());
+        assert_speed(fps, simulation.speed(), 0.0, 1e-3);
+        simulation.assert_position(fps);
+    }
+}
+
+#[test]
+fn test_auto_pause() {
+    for fps in FRAME_RATES {
+        let mut simulation = Simulation::new(fps);
+
+        simulation.turntable.set_auto_pause(true);
+        simulation.turntable.seek(0.99).unwrap();
+        simulation.turntable.toggle_start_stop();
+        simulation.run(1.0, |_| ());
+
+        // the deck stopped at the start, ready to play again
+        assert!(!simulation.turntable.is_playing());
+        assert_speed(fps, simulation.speed(), 0.0, 1e-3);
+        assert!(simulation.turntable.position().unwrap() < 1e-3);
+        simulation.turntable.load(track_file()).unwrap();
+    }
+}
+
+#[test]
+fn test_pitch() {
+    for fps in FRAME_RATES {
+        let mut simulation = Simulation::new(fps);
+
+        simulation.turntable.set_pitch(1.08);
+        simulation.turntable.toggle_start_stop();
+        simulation.run(2.0, |_| ());
+        assert_speed(fps, simulation.speed(), 1.08, 1e-3);
+        simulation.assert_position(fps);
+    }
+}
+
+#[test]
+fn test_nudge() {
+    for fps in FRAME_RATES {
+        let mut simulation = Simulation::new(fps);
+        let delta = simulation.delta;
+
+        // the same push per second speeds the vinyl up by the same amount
+        // whatever the frame rate
+        simulation.turntable.toggle_start_stop();
+        simulation.run(1.0, |turntable| turntable.apply_force(50.0 * delta));
+        assert_speed(fps, simulation.speed(), 1.01, 1e-3);
+        simulation.assert_position(fps);
+
+        simulation.run(1.0, |_| ());
+        assert_speed(fps, simulation.speed(), 1.0, 1e-3);
+    }
+}
+
+#[test]
+fn test_scratch() {
+    for fps in FRAME_RATES {
+        let mut simulation = Simulation::new(fps);
+        let delta = simulation.delta;
+
+        simulation.turntable.toggle_start_stop();
+        simulation.run(1.0, |_| ());
+
+        // holding the vinyl stops it even though the platter turns
+        simulation.turntable.start_scratching();
+        simulation.run(1.0, |_| ());
+        assert_speed(fps, simulation.speed(), 0.0, 1e-3);
+
+        // pulling it back plays backwards
+        simulation.run(1.0, |turntable| turntable.apply_force(-500.0 * delta));
+        assert_speed(fps, simulation.speed(), -1.0, 1e-3);
+        simulation.assert_position(fps);
+
+        simulation.turntable.end_scratching();
+        simulation.run(1.0, |_| ());
+        assert_speed(fps, simulation.speed(), 1.0, 1e-3);
+        simulation.assert_position(fps);
+    }
+}
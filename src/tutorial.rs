@@ -0,0 +1,208 @@
+use std::{collections::HashMap, fs};
+
+use egui::{Align2, Color32, Context, Id, LayerId, Order, Rect, Stroke, Vec2};
+
+use crate::config::data_dir;
+
+/// Marker file written once the tutorial has been completed or skipped
+const DONE_FILE: &str = "tutorial_done";
+
+/// Parts of the UI the tutorial points at
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum TutorialArea {
+    Browser,
+    DeckOne,
+    DeckTwo,
+    Mixer,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TutorialStep {
+    Welcome,
+    Browser,
+    Decks,
+    Mixer,
+    Scratch,
+    Keyboard,
+    Done,
+}
+
+impl TutorialStep {
+    fn next(self) -> Self {
+        match self {
+            TutorialStep::Welcome => TutorialStep::Browser,
+            TutorialStep::Browser => TutorialStep::Decks,
+            TutorialStep::Decks => TutorialStep::Mixer,
+            TutorialStep::Mixer => TutorialStep::Scratch,
+            TutorialStep::Scratch => TutorialStep::Keyboard,
+            TutorialStep::Keyboard | TutorialStep::Done => TutorialStep::Done,
+        }
+    }
+
+    fn previous(self) -> Self {
+        match self {
+            TutorialStep::Welcome | TutorialStep::Browser => TutorialStep::Welcome,
+            TutorialStep::Decks => TutorialStep::Browser,
+            TutorialStep::Mixer => TutorialStep::Decks,
+            TutorialStep::Scratch => TutorialStep::Mixer,
+            TutorialStep::Keyboard => TutorialStep::Scratch,
+            TutorialStep::Done => TutorialStep::Keyboard,
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        match self {
+            TutorialStep::Welcome => "Welcome!",
+            TutorialStep::Browser => "File browser",
+            TutorialStep::Decks => "Decks",
+            TutorialStep::Mixer => "Mixer",
+            TutorialStep::Scratch => "Scratching",
+            TutorialStep::Keyboard => "Keyboard",
+            TutorialStep::Done => "",
+        }
+    }
+
+    fn text(&self) -> &'static str {
+        match self {
+            TutorialStep::Welcome => {
+                "This short tour shows the main parts of the booth. It can be skipped at any time."
+            }
+            TutorialStep::Browser => {
+                "Browse the ROOT_DIR folder with the arrow keys. Right loads the selected track \
+                 into the focused deck, left goes back to the parent folder. Files can also be \
+                 dropped on the window."
+            }
+            TutorialStep::Decks => {
+                "Each deck has its own volume, pitch and EQ. Click the progress bar to seek and \
+                 use FOCUS to choose the deck controlled by the keyboard and trackpad."
+            }
+            TutorialStep::Mixer => {
+                "Cue sends a channel to the headphones, CUE MIX blends the cue and master \
+                 outputs and LINE IN plays the audio input on a channel."
+            }
+            TutorialStep::Scratch => {
+                "Hold Super (Cmd) and move on the trackpad to scratch the focused deck. Hold Alt \
+                 and move to nudge it while it plays."
+            }
+            TutorialStep::Keyboard => {
+                "Press ? to list the keyboard shortcuts. Every key, MIDI, HID and gamepad \
+                 binding can be changed from the configuration folder, see the README."
+            }
+            TutorialStep::Done => "",
+        }
+    }
+
+    fn areas(&self) -> &'static [TutorialArea] {
+        match self {
+            TutorialStep::Browser => &[TutorialArea::Browser],
+            TutorialStep::Decks | TutorialStep::Scratch => {
+                &[TutorialArea::DeckOne, TutorialArea::DeckTwo]
+            }
+            TutorialStep::Mixer => &[TutorialArea::Mixer],
+            _ => &[],
+        }
+    }
+}
+
+/// First-run guided tour drawn over the booth
+pub struct Tutorial {
+    step: TutorialStep,
+    areas: HashMap<TutorialArea, Rect>,
+}
+
+impl Tutorial {
+    /// Start the tutorial unless it was already completed
+    pub fn new() -> Self {
+        let done = data_dir().join(DONE_FILE).exists();
+
+        Self {
+            step: if done {
+                TutorialStep::Done
+            } else {
+                TutorialStep::Welcome
+            },
+            areas: HashMap::new(),
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.step != TutorialStep::Done
+    }
+
+    pub fn restart(&mut self) {
+        self.step = TutorialStep::Welcome;
+    }
+
+    pub fn next(&mut self) {
+        self.step = self.step.next();
+        if self.step == TutorialStep::Done {
+            self.finish();
+        }
+    }
+
+    pub fn previous(&mut self) {
+        self.step = self.step.previous();
+    }
+
+    pub fn skip(&mut self) {
+        self.step = TutorialStep::Done;
+        self.finish();
+    }
+
+    fn finish(&self) {
+        let dir = data_dir();
+        match fs::create_dir_all(&dir).and_then(|_| fs::write(dir.join(DONE_FILE), "")) {
+            Ok(()) => (),
+            Err(e) => log::error!("Cannot save tutorial state: {}", e),
+        }
+    }
+
+    /// Remember where an area was drawn during this frame
+    pub fn set_area(&mut self, area: TutorialArea, rect: Rect) {
+        self.areas.insert(area, rect);
+    }
+
+    /// Draw the current step: the highlighted areas and the step window
+    pub fn show(&mut self, ctx: &Context) {
+        if !self.is_active() {
+            return;
+        }
+
+        let painter = ctx.layer_painter(LayerId::new(Order::Foreground, Id::new("tutorial")));
+        for area in self.step.areas() {
+            if let Some(rect) = self.areas.get(area) {
+                painter.rect_stroke(
+                    rect.expand(4.0),
+                    6.0,
+                    Stroke::new(3.0, Color32::from_rgb(255, 140, 0)),
+                );
+            }
+        }
+
+        egui::Window::new(self.step.title())
+            .id(Id::new("tutorial_window"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(Align2::RIGHT_TOP, Vec2::new(-10.0, 10.0))
+            .show(ctx, |ui| {
+                ui.set_max_width(260.0);
+                ui.label(self.step.text());
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if self.step != TutorialStep::Welcome && ui.button("Back").clicked() {
+                        self.previous();
+                    }
+                    let next = match self.step {
+                        TutorialStep::Keyboard => "Finish",
+                        _ => "Next",
+                    };
+                    if ui.button(next).clicked() {
+                        self.next();
+                    }
+                    if ui.button("Skip").clicked() {
+                        self.skip();
+                    }
+                });
+            });
+    }
+}
@@ -23,6 +23,27 @@ pub fn to_min_sec_millis_str(time_sec: f64) -> String {
     format!("{:02}:{:02}:{:03}", minutes, seconds, millis)
 }
 
+/// Format a duration as hours, minutes and seconds, for lengths that can
+/// exceed an hour such as a whole set
+pub fn to_hour_min_sec_str(time_sec: f64) -> String {
+    let seconds = time_sec.max(0.0) as u64;
+
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        (seconds % 3600) / 60,
+        seconds % 60
+    )
+}
+
+pub fn is_image_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            ["jpg", "jpeg", "png"].contains(&extension.to_lowercase().as_str())
+        })
+}
+
 pub fn to_cover_path(audio_file_path: &String) -> String {
     let path = Path::new(&audio_file_path);
     let mut cover_path = PathBuf::from(path);
@@ -30,3 +51,76 @@ pub fn to_cover_path(audio_file_path: &String) -> String {
 
     cover_path.to_string_lossy().to_string()
 }
+
+const BASE64_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Standard base64 with padding
+pub fn to_base64(data: &[u8]) -> String {
+    let mut encoded = String::new();
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let bits = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => {
+                    encoded.push(BASE64_ALPHABET[((bits >> (18 - 6 * i)) & 0x3F) as usize] as char)
+                }
+                false => encoded.push('='),
+            }
+        }
+    }
+    encoded
+}
+
+/// Bytes of standard base64 with padding, `None` if `text` is not
+pub fn from_base64(text: &str) -> Option<Vec<u8>> {
+    let chunks = text.len() / 4;
+    if chunks * 4 != text.len() {
+        return None;
+    }
+
+    let mut decoded = Vec::with_capacity(chunks * 3);
+    for (index, chunk) in text.as_bytes().chunks(4).enumerate() {
+        let padding = chunk.iter().rev().take_while(|c| **c == b'=').count();
+        // only the last chunk is padded
+        if padding > 2 || (padding > 0 && index + 1 < chunks) {
+            return None;
+        }
+        let mut bits = 0u32;
+        for c in &chunk[..4 - padding] {
+            let value = BASE64_ALPHABET.iter().position(|a| a == c)?;
+            bits = bits << 6 | value as u32;
+        }
+        bits <<= 6 * padding;
+        decoded.extend(&bits.to_be_bytes()[1..4 - padding]);
+    }
+    Some(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64() {
+        // vectors of RFC 4648
+        assert_eq!(to_base64(b""), "");
+        assert_eq!(to_base64(b"f"), "Zg==");
+        assert_eq!(to_base64(b"fo"), "Zm8=");
+        assert_eq!(to_base64(b"foo"), "Zm9v");
+        assert_eq!(to_base64(b"foob"), "Zm9vYg==");
+        assert_eq!(to_base64(b"fooba"), "Zm9vYmE=");
+        assert_eq!(to_base64(b"foobar"), "Zm9vYmFy");
+
+        for data in [&b""[..], b"f", b"fo", b"foo", &[0xff, 0x00, 0x80, 0x7f]] {
+            assert_eq!(from_base64(&to_base64(data)).as_deref(), Some(data));
+        }
+        assert_eq!(from_base64("Zg="), None);
+        assert_eq!(from_base64("Z!=="), None);
+        assert_eq!(from_base64("Zg==Zm9v"), None);
+    }
+}
@@ -0,0 +1,58 @@
+/// Watches for dead air, when no deck is heard on the master for a while,
+/// e.g. on an unattended radio or stream
+#[derive(Debug, Default)]
+pub struct Watchdog {
+    /// seconds since a deck was last heard
+    silent_for: f64,
+    dead_air: bool,
+}
+
+impl Watchdog {
+    pub fn is_dead_air(&self) -> bool {
+        self.dead_air
+    }
+
+    /// Count `delta` seconds of silence unless a deck is `audible`, returning
+    /// `Some(true)` once the silence reaches `limit` seconds and
+    /// `Some(false)` once a deck is heard again. A `limit` of 0 disables the
+    /// watchdog
+    pub fn update(&mut self, delta: f64, audible: bool, limit: f64) -> Option<bool> {
+        self.silent_for = match audible || limit <= 0.0 {
+            true => 0.0,
+            false => self.silent_for + delta,
+        };
+
+        let dead_air = limit > 0.0 && self.silent_for >= limit;
+        if dead_air == self.dead_air {
+            return None;
+        }
+        self.dead_air = dead_air;
+        Some(dead_air)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dead_air() {
+        let mut watchdog = Watchdog::default();
+
+        assert_eq!(watchdog.update(50.0, false, 60.0), None);
+        assert_eq!(watchdog.update(10.0, false, 60.0), Some(true));
+        assert_eq!(watchdog.update(10.0, false, 60.0), None);
+        assert!(watchdog.is_dead_air());
+
+        assert_eq!(watchdog.update(0.1, true, 60.0), Some(false));
+        assert_eq!(watchdog.update(50.0, false, 60.0), None);
+    }
+
+    #[test]
+    fn test_disabled() {
+        let mut watchdog = Watchdog::default();
+
+        assert_eq!(watchdog.update(1000.0, false, 0.0), None);
+        assert!(!watchdog.is_dead_air());
+    }
+}
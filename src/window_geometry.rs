@@ -0,0 +1,126 @@
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    monitor::MonitorHandle,
+    window::{Window, WindowBuilder},
+};
+
+use crate::config::data_dir;
+
+const GEOMETRY_FILE: &str = "window.toml";
+
+/// Size and position of the window, restored on the next launch
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    /// outer position in physical pixels, on the desktop spanning all the
+    /// monitors
+    pub x: i32,
+    pub y: i32,
+    /// inner size in physical pixels
+    pub width: u32,
+    pub height: u32,
+    pub maximized: bool,
+}
+
+impl WindowGeometry {
+    pub fn capture(window: &Window) -> Option<Self> {
+        let position = window.outer_position().ok()?;
+        let size = window.inner_size();
+        if size.width == 0 || size.height == 0 {
+            return None;
+        }
+
+        Some(Self {
+            x: position.x,
+            y: position.y,
+            width: size.width,
+            height: size.height,
+            maximized: window.is_maximized(),
+        })
+    }
+
+    /// The saved geometry, if any
+    pub fn load() -> Option<Self> {
+        let path = data_dir().join(GEOMETRY_FILE);
+        let content = fs::read_to_string(&path).ok()?;
+
+        match toml::from_str(&content) {
+            Ok(geometry) => Some(geometry),
+            Err(e) => {
+                log::error!("Cannot read '{}': {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        let dir = data_dir();
+        let result = toml::to_string(self)
+            .map_err(|e| e.to_string())
+            .and_then(|content| {
+                fs::create_dir_all(&dir)
+                    .and_then(|_| fs::write(dir.join(GEOMETRY_FILE), content))
+                    .map_err(|e| e.to_string())
+            });
+
+        if let Err(e) = result {
+            log::error!("Cannot save window geometry: {}", e);
+        }
+    }
+
+    /// Give the geometry to the window being built. The position is only
+    /// restored if it lies on one of the `monitors`, e.g. not on a monitor
+    /// unplugged since
+    pub fn apply(
+        &self,
+        builder: WindowBuilder,
+        monitors: impl Iterator<Item = MonitorHandle>,
+    ) -> WindowBuilder {
+        let areas: Vec<_> = monitors
+            .map(|monitor| (monitor.position(), monitor.size()))
+            .collect();
+
+        let builder = builder
+            .with_inner_size(PhysicalSize::new(self.width, self.height))
+            .with_maximized(self.maximized);
+        match self.is_on(&areas) {
+            true => builder.with_position(PhysicalPosition::new(self.x, self.y)),
+            false => builder,
+        }
+    }
+
+    /// Whether the top left corner of the window lies on one of the monitor
+    /// `areas`, given as their position and size
+    fn is_on(&self, areas: &[(PhysicalPosition<i32>, PhysicalSize<u32>)]) -> bool {
+        areas.iter().any(|(position, size)| {
+            (position.x..position.x + size.width as i32).contains(&self.x)
+                && (position.y..position.y + size.height as i32).contains(&self.y)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_on() {
+        let geometry = WindowGeometry {
+            x: 2000,
+            y: 100,
+            width: 800,
+            height: 600,
+            maximized: false,
+        };
+        let laptop = (PhysicalPosition::new(0, 0), PhysicalSize::new(1920, 1080));
+        let external = (
+            PhysicalPosition::new(1920, 0),
+            PhysicalSize::new(2560, 1440),
+        );
+
+        assert!(geometry.is_on(&[laptop, external]));
+        assert!(!geometry.is_on(&[laptop]));
+    }
+}